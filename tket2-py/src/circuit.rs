@@ -31,6 +31,7 @@ pub fn module(py: Python) -> PyResult<&PyModule> {
 
     m.add_function(wrap_pyfunction!(validate_hugr, m)?)?;
     m.add_function(wrap_pyfunction!(to_hugr_dot, m)?)?;
+    m.add_function(wrap_pyfunction!(supported_ops, m)?)?;
 
     m.add("HugrError", py.get_type::<PyHugrError>())?;
     m.add("BuildError", py.get_type::<PyBuildError>())?;
@@ -86,6 +87,18 @@ pub fn to_hugr_dot(c: &PyAny) -> PyResult<String> {
     with_hugr(c, |hugr, _| hugr.dot_string())
 }
 
+/// List the operations supported by tket2.
+///
+/// Returns the name of each operation along with its number of inputs and
+/// outputs, including classical parameters.
+#[pyfunction]
+pub fn supported_ops() -> Vec<(&'static str, usize, usize)> {
+    tket2::supported_ops()
+        .into_iter()
+        .map(|(op, sig)| (op.into(), sig.input.len(), sig.output.len()))
+        .collect()
+}
+
 /// A [`hugr::Node`] wrapper for Python.
 #[pyclass]
 #[pyo3(name = "Node")]
@@ -125,6 +125,15 @@ impl Tk2Circuit {
         Ok(circ_cost.cost.into_ref(py))
     }
 
+    /// Returns `True` if the circuit implements the identity on all its qubits,
+    /// up to a global phase.
+    ///
+    /// Circuits with parametric or non-unitary operations are never reported
+    /// as the identity.
+    pub fn is_identity(&self) -> bool {
+        tket2::circuit::is_identity(&self.hugr)
+    }
+
     /// Returns a hash of the circuit.
     pub fn hash(&self) -> u64 {
         self.hugr.circuit_hash().unwrap()
@@ -1,14 +1,16 @@
 //! PyO3 wrapper for the Badger circuit optimiser.
 
 use std::io::BufWriter;
+use std::sync::{Arc, Mutex};
 use std::{fs, num::NonZeroUsize, path::PathBuf};
 
 use hugr::Hugr;
 use pyo3::prelude::*;
 use tket2::optimiser::badger::BadgerOptions;
-use tket2::optimiser::{BadgerLogger, DefaultBadgerOptimiser};
+use tket2::optimiser::{BadgerLogger, BadgerOptimiser, DefaultBadgerOptimiser};
+use tket2::rewrite::strategy::CircuitCostStrategy;
 
-use crate::circuit::update_hugr;
+use crate::circuit::{try_update_hugr, update_hugr, Tk2Circuit};
 
 /// The module definition
 pub fn module(py: Python) -> PyResult<&PyModule> {
@@ -77,6 +79,20 @@ impl PyBadgerOptimiser {
     ///
     /// * `log_progress`: The path to a CSV file to log progress to.
     ///
+    /// * `cost_fn`: An optional function `Callable[[Tk2Circuit], int]`
+    ///     returning the cost of a circuit, to be minimised. If `None`, the
+    ///     optimiser minimises the CX count and then the total gate count.
+    ///
+    ///     The function is called once for every new circuit found by the
+    ///     optimiser, each time acquiring the GIL and copying the circuit into
+    ///     a new `Tk2Circuit`. This is considerably slower than the default
+    ///     cost function, and prevents worker threads from running in parallel
+    ///     while the cost is being computed.
+    ///
+    ///     If the function raises an exception, the optimisation is completed
+    ///     treating the circuit as having a very large cost, and the first
+    ///     exception raised is then propagated to the caller.
+    ///
     #[pyo3(name = "optimise")]
     #[allow(clippy::too_many_arguments)]
     pub fn py_optimise<'py>(
@@ -88,6 +104,7 @@ impl PyBadgerOptimiser {
         split_circ: Option<bool>,
         queue_size: Option<usize>,
         log_progress: Option<PathBuf>,
+        cost_fn: Option<PyObject>,
     ) -> PyResult<&'py PyAny> {
         let options = BadgerOptions {
             timeout,
@@ -95,8 +112,17 @@ impl PyBadgerOptimiser {
             n_threads: n_threads.unwrap_or(NonZeroUsize::new(1).unwrap()),
             split_circuit: split_circ.unwrap_or(false),
             queue_size: queue_size.unwrap_or(100),
+            ..Default::default()
         };
-        update_hugr(circ, |circ, _| self.optimise(circ, log_progress, options))
+        match cost_fn {
+            None => update_hugr(circ, |circ, _| self.optimise(circ, log_progress, options)),
+            Some(cost_fn) => {
+                let py = circ.py();
+                try_update_hugr(circ, |circ, _| {
+                    self.optimise_with_cost_fn(py, circ, log_progress, options, cost_fn)
+                })
+            }
+        }
     }
 }
 
@@ -117,4 +143,51 @@ impl PyBadgerOptimiser {
             .unwrap_or_default();
         self.0.optimise_with_log(&circ, badger_logger, options)
     }
+
+    /// The Python optimise method with a Python cost function, on Hugrs.
+    ///
+    /// Releases the GIL while optimising, so that the worker threads can
+    /// evaluate the cost function.
+    pub(super) fn optimise_with_cost_fn(
+        &self,
+        py: Python,
+        circ: Hugr,
+        log_progress: Option<PathBuf>,
+        options: BadgerOptions,
+        cost_fn: PyObject,
+    ) -> PyResult<Hugr> {
+        // The first error raised by the cost function, if any.
+        let error: Arc<Mutex<Option<PyErr>>> = Default::default();
+        let cost_fn = {
+            let error = error.clone();
+            move |circ: &Hugr| -> usize {
+                Python::with_gil(|py| {
+                    Py::new(py, Tk2Circuit { hugr: circ.clone() })
+                        .and_then(|circ| cost_fn.call1(py, (circ,)))
+                        .and_then(|cost| cost.extract::<usize>(py))
+                        .unwrap_or_else(|e| {
+                            error.lock().unwrap().get_or_insert(e);
+                            // Avoid overflows when computing cost deltas.
+                            isize::MAX as usize
+                        })
+                })
+            }
+        };
+        let strategy = CircuitCostStrategy::new(self.0.strategy().clone(), cost_fn);
+        let optimiser = BadgerOptimiser::new(self.0.rewriter().clone(), strategy);
+
+        let badger_logger = log_progress
+            .map(|file_name| {
+                let log_file = fs::File::create(file_name).unwrap();
+                let log_file = BufWriter::new(log_file);
+                BadgerLogger::new(log_file)
+            })
+            .unwrap_or_default();
+        let circ = py.allow_threads(|| optimiser.optimise_with_log(&circ, badger_logger, options));
+
+        match error.lock().unwrap().take() {
+            Some(err) => Err(err),
+            None => Ok(circ),
+        }
+    }
 }
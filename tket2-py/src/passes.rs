@@ -5,12 +5,13 @@ pub mod chunks;
 use std::{cmp::min, convert::TryInto, fs, num::NonZeroUsize, path::PathBuf};
 
 use pyo3::{prelude::*, types::IntoPyDict};
-use tket2::optimiser::badger::BadgerOptions;
+use tket2::circuit::cost::{is_cx, is_quantum};
+use tket2::optimiser::badger::{badger_schedule, BadgerOptions};
 use tket2::{op_matches, passes::apply_greedy_commutation, Circuit, Tk2Op};
 
 use crate::utils::{create_py_exception, ConvertPyErr};
 use crate::{
-    circuit::{try_update_hugr, try_with_hugr},
+    circuit::{try_update_hugr, try_with_hugr, with_hugr},
     optimiser::PyBadgerOptimiser,
 };
 
@@ -21,14 +22,27 @@ pub fn module(py: Python) -> PyResult<&PyModule> {
     let m = PyModule::new(py, "_passes")?;
     m.add_function(wrap_pyfunction!(greedy_depth_reduce, m)?)?;
     m.add_function(wrap_pyfunction!(badger_optimise, m)?)?;
+    m.add_function(wrap_pyfunction!(circuit_cx_count, m)?)?;
+    m.add_function(wrap_pyfunction!(circuit_gate_count, m)?)?;
+    m.add_function(wrap_pyfunction!(circuit_depth, m)?)?;
     m.add_class::<self::chunks::PyCircuitChunks>()?;
     m.add_function(wrap_pyfunction!(self::chunks::chunks, m)?)?;
     m.add("PullForwardError", py.get_type::<PyPullForwardError>())?;
+    m.add(
+        "ChunkReassembleError",
+        py.get_type::<PyChunkReassembleError>(),
+    )?;
     Ok(m)
 }
 
 create_py_exception!(tket2::passes::PullForwardError, PyPullForwardError, "");
 
+create_py_exception!(
+    tket2::passes::ChunkReassembleError,
+    PyChunkReassembleError,
+    "Errors that can occur while reassembling circuit chunks."
+);
+
 #[pyfunction]
 fn greedy_depth_reduce(circ: &PyAny) -> PyResult<(&PyAny, u32)> {
     let py = circ.py();
@@ -39,6 +53,30 @@ fn greedy_depth_reduce(circ: &PyAny) -> PyResult<(&PyAny, u32)> {
     })
 }
 
+/// The number of CX gates in a circuit.
+#[pyfunction]
+fn circuit_cx_count(circ: &PyAny) -> PyResult<usize> {
+    with_hugr(circ, |h, _| h.circuit_cost(|op| is_cx(op) as usize))
+}
+
+/// The number of quantum gates in a circuit.
+///
+/// Constants, parameter arithmetic, and other classical operations are not
+/// counted.
+#[pyfunction]
+fn circuit_gate_count(circ: &PyAny) -> PyResult<usize> {
+    with_hugr(circ, |h, _| h.circuit_cost(|op| is_quantum(op) as usize))
+}
+
+/// The depth of a circuit.
+///
+/// Operations acting on a common qubit or bit are placed in successive
+/// layers, and the depth is the number of layers.
+#[pyfunction]
+fn circuit_depth(circ: &PyAny) -> PyResult<usize> {
+    with_hugr(circ, |h, _| h.depth())
+}
+
 /// Rebase a circuit to the Nam gate set (CX, Rz, H) using TKET1.
 ///
 /// Equivalent to running the following code:
@@ -95,20 +133,6 @@ fn badger_optimise<'py>(
     if rebase {
         rebase_nam(circ)?;
     }
-    // Logic to choose how to split the circuit
-    let badger_splits = |n_threads: NonZeroUsize| match n_threads.get() {
-        n if n >= 7 => (
-            vec![n, 3, 1],
-            vec![timeout / 2, timeout / 10 * 3, timeout / 10 * 2],
-        ),
-        n if n >= 4 => (
-            vec![n, 2, 1],
-            vec![timeout / 2, timeout / 10 * 3, timeout / 10 * 2],
-        ),
-        n if n > 1 => (vec![n, 1], vec![timeout / 2, timeout / 2]),
-        1 => (vec![1], vec![timeout]),
-        _ => unreachable!(),
-    };
     // Optimise
     try_update_hugr(circ, |mut circ, _| {
         let n_cx = circ
@@ -119,8 +143,8 @@ fn badger_optimise<'py>(
             (n_cx / 50).try_into().unwrap_or(1.try_into().unwrap()),
             max_threads,
         );
-        let (split_threads, split_timeouts) = badger_splits(n_threads);
-        for (i, (n_threads, timeout)) in split_threads.into_iter().zip(split_timeouts).enumerate() {
+        for (i, (n_threads, timeout)) in badger_schedule(n_threads, timeout).into_iter().enumerate()
+        {
             let log_file = log_dir.as_ref().map(|log_dir| {
                 let mut log_file = log_dir.clone();
                 log_file.push(format!("cycle-{i}.log"));
@@ -129,7 +153,7 @@ fn badger_optimise<'py>(
             let options = BadgerOptions {
                 timeout: Some(timeout),
                 progress_timeout,
-                n_threads: n_threads.try_into().unwrap(),
+                n_threads,
                 split_circuit: true,
                 ..Default::default()
             };
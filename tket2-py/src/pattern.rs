@@ -27,12 +27,16 @@ pub fn module(py: Python) -> PyResult<&PyModule> {
         "InvalidReplacementError",
         py.get_type::<PyInvalidReplacementError>(),
     )?;
+    m.add(
+        "MatcherSerialisationError",
+        py.get_type::<PyMatcherSerialisationError>(),
+    )?;
 
     Ok(m)
 }
 
 create_py_exception!(
-    hugr::hugr::views::sibling_subgraph::InvalidReplacement,
+    tket2::rewrite::InvalidReplacement,
     PyInvalidReplacementError,
     "Errors that can occur while constructing a HUGR replacement."
 );
@@ -43,6 +47,12 @@ create_py_exception!(
     "Conversion error from circuit to pattern."
 );
 
+create_py_exception!(
+    tket2::portmatching::matcher::MatcherSerialisationError,
+    PyMatcherSerialisationError,
+    "Errors that can occur when saving or loading a pattern matcher."
+);
+
 #[derive(Clone)]
 #[pyclass]
 /// A rewrite rule defined by a left hand side and right hand side of an equation.
@@ -72,7 +82,8 @@ impl RuleMatcher {
             rules.into_iter().map(|Rule([l, r])| (l, r)).unzip();
         let patterns: Result<Vec<CircuitPattern>, _> =
             lefts.iter().map(CircuitPattern::try_from_circuit).collect();
-        let matcher = PatternMatcher::from_patterns(patterns.convert_pyerrs()?);
+        let matcher =
+            PatternMatcher::try_from_patterns(patterns.convert_pyerrs()?).convert_pyerrs()?;
 
         Ok(Self { matcher, rights })
     }
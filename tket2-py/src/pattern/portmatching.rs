@@ -1,15 +1,18 @@
 //! Python bindings for portmatching features
 
 use std::fmt;
+use std::path::PathBuf;
 
 use derive_more::{From, Into};
 use itertools::Itertools;
 use portmatching::PatternID;
 use pyo3::{prelude::*, types::PyIterator};
 
+use tket2::extension::TKET2_EXTENSION_ID;
 use tket2::portmatching::{CircuitPattern, PatternMatch, PatternMatcher};
 
 use crate::circuit::{try_with_hugr, with_hugr, PyNode};
+use crate::utils::ConvertPyErr;
 
 /// A pattern that match a circuit exactly
 ///
@@ -38,6 +41,33 @@ impl PyCircuitPattern {
     pub fn __repr__(&self) -> String {
         format!("{:?}", self.pattern)
     }
+
+    /// The number of gates in the pattern.
+    pub fn n_gates(&self) -> usize {
+        self.pattern.n_gates()
+    }
+
+    /// The number of qubits the pattern acts on.
+    pub fn n_qubits(&self) -> usize {
+        self.pattern.n_qubits()
+    }
+
+    /// The names of the gates in the pattern, in topological order.
+    ///
+    /// The names of tket2 operations are given without their extension
+    /// prefix, e.g. `"CX"`.
+    pub fn gate_names(&self) -> Vec<String> {
+        let prefix = format!("{TKET2_EXTENSION_ID}.");
+        self.pattern
+            .gates()
+            .iter()
+            .map(|name| {
+                name.strip_prefix(&prefix)
+                    .unwrap_or(name.as_str())
+                    .to_string()
+            })
+            .collect()
+    }
 }
 
 /// A matcher object for fast pattern matching on circuits.
@@ -62,7 +92,7 @@ impl PyPatternMatcher {
     /// Construct a matcher from a list of patterns.
     #[new]
     pub fn py_from_patterns(patterns: &PyIterator) -> PyResult<Self> {
-        Ok(PatternMatcher::from_patterns(
+        Ok(PatternMatcher::try_from_patterns(
             patterns
                 .iter()?
                 .map(|p| {
@@ -71,6 +101,7 @@ impl PyPatternMatcher {
                 })
                 .collect::<PyResult<Vec<_>>>()?,
         )
+        .convert_pyerrs()?
         .into())
     }
     /// A string representation of the pattern.
@@ -78,6 +109,20 @@ impl PyPatternMatcher {
         Ok(format!("{:?}", self.matcher))
     }
 
+    /// Save the matcher as a binary file.
+    ///
+    /// The extension of the file name is set to `.bin`. Returns the path of
+    /// the written file.
+    pub fn save_binary(&self, path: PathBuf) -> PyResult<PathBuf> {
+        self.matcher.save_binary(path).convert_pyerrs()
+    }
+
+    /// Load a matcher saved with `save_binary`.
+    #[staticmethod]
+    pub fn load_binary(path: PathBuf) -> PyResult<Self> {
+        Ok(PatternMatcher::load_binary(path).convert_pyerrs()?.into())
+    }
+
     /// Find all convex matches in a circuit.
     pub fn find_matches(&self, circ: &PyAny) -> PyResult<Vec<PyPatternMatch>> {
         with_hugr(circ, |circ, _| {
@@ -114,6 +159,11 @@ impl PyPatternMatch {
         self.pmatch.root().into()
     }
 
+    /// Returns the circuit nodes matched by the pattern.
+    pub fn matched_nodes(&self) -> Vec<PyNode> {
+        self.pmatch.nodes().iter().copied().map_into().collect()
+    }
+
     /// A string representation of the pattern.
     pub fn __repr__(&self) -> String {
         format!("{:?}", self.pmatch)
@@ -1,7 +1,7 @@
 from dataclasses import dataclass
 from pytket.circuit import Circuit
 
-from tket2.circuit import Tk2Circuit, Tk2Op, to_hugr_dot
+from tket2.circuit import Tk2Circuit, Tk2Op, to_hugr_dot, supported_ops
 
 
 @dataclass
@@ -35,6 +35,12 @@ def test_hash():
     assert hash(circA) == hash(circC)
 
 
+def test_is_identity():
+    assert Tk2Circuit(Circuit(1)).is_identity()
+    assert Tk2Circuit(Circuit(1).H(0).H(0)).is_identity()
+    assert not Tk2Circuit(Circuit(1).H(0).X(0)).is_identity()
+
+
 def test_conversion():
     tk1 = Circuit(4).CX(0, 2).CX(1, 2).CX(1, 3)
     tk1_dot = to_hugr_dot(tk1)
@@ -49,3 +55,12 @@ def test_conversion():
 
     assert tk1_back == tk1
     assert type(tk1_back) == Circuit
+
+
+def test_supported_ops():
+    ops = {name: (n_in, n_out) for name, n_in, n_out in supported_ops()}
+
+    assert ops["H"] == (1, 1)
+    assert ops["CX"] == (2, 2)
+    assert ops["Measure"] == (1, 2)
+    assert ops["RzF64"] == (2, 1)
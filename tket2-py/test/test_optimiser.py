@@ -1,3 +1,4 @@
+import pytest
 from pytket import Circuit
 from tket2.optimiser import BadgerOptimiser
 
@@ -12,3 +13,26 @@ def test_simple_optimiser():
     exp_c = Circuit(3).CX(1, 2)
 
     assert cc == exp_c
+
+
+def test_optimiser_cost_fn():
+    c = Circuit(3).CX(0, 1).CX(0, 1).CX(1, 2)
+    opt = BadgerOptimiser.compile_eccs("test_files/cx_cx_eccs.json")
+
+    def n_gates(circ) -> int:
+        return circ.to_tket1().n_gates
+
+    cc = opt.optimise(c, 3, cost_fn=n_gates)
+
+    assert cc == Circuit(3).CX(1, 2)
+
+
+def test_optimiser_cost_fn_error():
+    c = Circuit(3).CX(0, 1).CX(0, 1).CX(1, 2)
+    opt = BadgerOptimiser.compile_eccs("test_files/cx_cx_eccs.json")
+
+    def bad_cost(circ) -> int:
+        raise ValueError("bad cost")
+
+    with pytest.raises(ValueError, match="bad cost"):
+        opt.optimise(c, 3, cost_fn=bad_cost)
@@ -1,7 +1,14 @@
 from pytket import Circuit, OpType
 from dataclasses import dataclass
 from typing import Callable, Any
-from tket2.passes import badger_pass, greedy_depth_reduce, chunks
+from tket2.passes import (
+    badger_pass,
+    greedy_depth_reduce,
+    chunks,
+    circuit_cx_count,
+    circuit_gate_count,
+    circuit_depth,
+)
 from tket2.circuit import Tk2Circuit
 from tket2.pattern import Rule, RuleMatcher
 import hypothesis.strategies as st
@@ -133,3 +140,11 @@ def test_multiple_rules():
 
     out = circ.to_tket1()
     assert out == Circuit(3).CX(0, 1).X(0)
+
+
+def test_circuit_metrics():
+    c = Circuit(3).H(0).CX(0, 1).Rz(0.5, 2).CX(1, 2).H(0)
+    for circ in [c, Tk2Circuit(c)]:
+        assert circuit_cx_count(circ) == 2
+        assert circuit_gate_count(circ) == 5
+        assert circuit_depth(circ) == 3
@@ -15,6 +15,42 @@ def test_simple_matching():
     assert len(matcher.find_matches(c)) == 2
 
 
+def test_save_load_matcher(tmp_path):
+    """a saved matcher finds the same matches once loaded"""
+    c = Circuit(2).CX(0, 1).H(1).CX(0, 1)
+
+    p1 = CircuitPattern(Circuit(2).CX(0, 1).H(1))
+    p2 = CircuitPattern(Circuit(2).H(0).CX(1, 0))
+    matcher = PatternMatcher(iter([p1, p2]))
+
+    path = matcher.save_binary(tmp_path / "matcher")
+    assert str(path).endswith(".bin")
+    loaded = PatternMatcher.load_binary(path)
+
+    assert len(loaded.find_matches(c)) == len(matcher.find_matches(c))
+
+
+def test_pattern_gates():
+    """the gates of a pattern are listed in topological order"""
+    p = CircuitPattern(Circuit(2).H(0).CX(0, 1))
+
+    assert p.n_gates() == 2
+    assert p.n_qubits() == 2
+    assert p.gate_names() == ["H", "CX"]
+
+
+def test_matched_nodes():
+    """the matched nodes of a match correspond to the pattern gates"""
+    c = Circuit(2).CX(0, 1).H(1).CX(0, 1)
+
+    p1 = CircuitPattern(Circuit(2).CX(0, 1).H(1))
+    matcher = PatternMatcher(iter([p1]))
+
+    [match] = matcher.find_matches(c)
+    assert len(match.matched_nodes()) == 2
+    assert repr(match.root()) in map(repr, match.matched_nodes())
+
+
 def test_non_convex_pattern():
     """two-qubit circuits can't match three-qb ones"""
     p1 = CircuitPattern(Circuit(3).CX(0, 1).CX(1, 2))
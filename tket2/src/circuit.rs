@@ -1,22 +1,42 @@
 //! Quantum circuit representation and operations.
 
+pub mod angle;
 pub mod command;
+mod convex;
 pub mod cost;
+mod diff;
+mod dot;
 mod hash;
+mod identity;
+mod tableau;
+pub(crate) mod unitary;
 pub mod units;
+mod validate;
 
+use std::collections::HashMap;
 use std::iter::Sum;
 
 pub use command::{Command, CommandIterator};
-pub use hash::CircuitHash;
+pub use convex::{check_convex_with_checker, is_convex};
+pub use diff::{
+    circuit_diff, circuits_equal_up_to_qubit_permutation, CircuitDiff, DiffGate, DiffInput,
+    GateDiff, MAX_PERMUTATION_QUBITS,
+};
+pub use dot::circuit_to_dot;
+pub use hash::{CircuitHash, HashError};
+pub use identity::is_identity;
 use itertools::Either::{Left, Right};
+pub use validate::{validate_tk2_circuit, CircuitValidationError};
 
 use derive_more::From;
+use hugr::builder::BuildError;
+use hugr::extension::prelude::{BOOL_T, QB_T};
 use hugr::hugr::hugrmut::HugrMut;
 use hugr::hugr::NodeType;
 use hugr::ops::dataflow::IOTrait;
 use hugr::ops::{Input, Output, DFG};
 use hugr::types::FunctionType;
+use hugr::Hugr;
 use hugr::PortIndex;
 use hugr::{HugrView, OutgoingPort};
 use itertools::Itertools;
@@ -27,6 +47,9 @@ pub use hugr::types::{EdgeKind, Type, TypeRow};
 pub use hugr::{Node, Port, Wire};
 
 use self::units::{filter, LinearUnit, Units};
+use crate::extension::LINEAR_BIT;
+use crate::utils::build_simple_circuit;
+use crate::Tk2Op;
 
 /// An object behaving like a quantum circuit.
 //
@@ -73,6 +96,35 @@ pub trait Circuit: HugrView {
         self.children(self.root()).count() - 2
     }
 
+    /// The depth of the circuit.
+    ///
+    /// Operations acting on a common linear unit (e.g. a qubit) are placed in
+    /// successive layers, and the depth is the number of layers. Operations
+    /// without linear inputs, such as constants or classical arithmetic, do
+    /// not contribute to the depth.
+    fn depth(&self) -> usize
+    where
+        Self: Sized,
+    {
+        let mut unit_depth: HashMap<usize, usize> = HashMap::new();
+        let mut depth = 0;
+        for cmd in self.commands() {
+            let units = cmd.linear_inputs().map(|(u, _, _)| u.index()).collect_vec();
+            let Some(layer) = units
+                .iter()
+                .map(|u| unit_depth.get(u).copied().unwrap_or(0) + 1)
+                .max()
+            else {
+                continue;
+            };
+            for u in units {
+                unit_depth.insert(u, layer);
+            }
+            depth = depth.max(layer);
+        }
+        depth
+    }
+
     /// Count the number of qubits in the circuit.
     #[inline]
     fn qubit_count(&self) -> usize
@@ -82,6 +134,31 @@ pub trait Circuit: HugrView {
         self.qubits().count()
     }
 
+    /// The number of qubit inputs of the circuit.
+    ///
+    /// Unlike [`Circuit::qubit_count`], this only reads the circuit signature.
+    #[inline]
+    fn num_qubits(&self) -> usize {
+        self.circuit_signature()
+            .input()
+            .iter()
+            .filter(|ty| **ty == QB_T)
+            .count()
+    }
+
+    /// The number of classical bit inputs of the circuit.
+    ///
+    /// Both linear bits, as used by the TKET1 decoder, and boolean inputs are
+    /// counted.
+    #[inline]
+    fn num_bits(&self) -> usize {
+        self.circuit_signature()
+            .input()
+            .iter()
+            .filter(|ty| **ty == *LINEAR_BIT || **ty == BOOL_T)
+            .count()
+    }
+
     /// Get the input units of the circuit and their types.
     #[inline]
     fn units(&self) -> Units<OutgoingPort>
@@ -130,6 +207,21 @@ pub trait Circuit: HugrView {
         CommandIterator::new(self)
     }
 
+    /// Returns all the commands in the circuit, in the reverse of the order
+    /// given by [`Circuit::commands`].
+    ///
+    /// Every command is yielded before the commands it depends on, which is
+    /// useful for passes that traverse the circuit backwards.
+    ///
+    /// Ignores the Input and Output nodes.
+    #[inline]
+    fn commands_reverse_topological(&self) -> impl Iterator<Item = Command<'_, Self>> + '_
+    where
+        Self: Sized,
+    {
+        self.commands().collect_vec().into_iter().rev()
+    }
+
     /// Compute the cost of the circuit based on a per-operation cost function.
     #[inline]
     fn circuit_cost<F, C>(&self, op_cost: F) -> C
@@ -202,6 +294,66 @@ pub(crate) fn remove_empty_wire(
     Ok(())
 }
 
+/// Build a qubit-only circuit from a list of operations.
+///
+/// Each operation is appended in order, acting on the qubits at the given
+/// indices. Returns an error if an index is out of range or repeated within
+/// an operation.
+pub fn circuit_from_ops(
+    n_qubits: usize,
+    ops: &[(Tk2Op, Vec<usize>)],
+) -> Result<Hugr, CircuitBuildError> {
+    for (op, qubits) in ops {
+        if let Some(&index) = qubits.iter().find(|&&q| q >= n_qubits) {
+            return Err(CircuitBuildError::InvalidQubit {
+                op: *op,
+                index,
+                n_qubits,
+            });
+        }
+        if !qubits.iter().all_unique() {
+            return Err(CircuitBuildError::RepeatedQubit {
+                op: *op,
+                qubits: qubits.clone(),
+            });
+        }
+    }
+    let circ = build_simple_circuit(n_qubits, |circ| {
+        for (op, qubits) in ops {
+            circ.append(*op, qubits.clone())?;
+        }
+        Ok(())
+    })?;
+    Ok(circ)
+}
+
+/// Errors that can occur when building a circuit from a list of operations.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum CircuitBuildError {
+    /// An operation acts on a qubit that does not exist.
+    #[error("{op:?} acts on qubit {index}, but the circuit has {n_qubits} qubits")]
+    InvalidQubit {
+        /// The operation.
+        op: Tk2Op,
+        /// The invalid qubit index.
+        index: usize,
+        /// The number of qubits in the circuit.
+        n_qubits: usize,
+    },
+    /// An operation acts on the same qubit more than once.
+    #[error("{op:?} acts on qubits {qubits:?} with repetitions")]
+    RepeatedQubit {
+        /// The operation.
+        op: Tk2Op,
+        /// The qubit indices of the operation.
+        qubits: Vec<usize>,
+    },
+    /// The circuit could not be built.
+    #[error("Build error: {0}")]
+    BuildError(#[from] BuildError),
+}
+
 /// Errors that can occur when mutating a circuit.
 #[derive(Debug, Clone, Error, PartialEq, Eq, From)]
 pub enum CircuitMutError {
@@ -310,7 +462,7 @@ impl<T> Circuit for T where T: HugrView {}
 mod tests {
     use hugr::{
         builder::{DFGBuilder, DataflowHugr},
-        extension::{prelude::BOOL_T, PRELUDE_REGISTRY},
+        extension::PRELUDE_REGISTRY,
         Hugr,
     };
 
@@ -341,6 +493,8 @@ mod tests {
         assert_eq!(circ.circuit_signature().input_count(), 3);
         assert_eq!(circ.circuit_signature().output_count(), 3);
         assert_eq!(circ.qubit_count(), 2);
+        assert_eq!(circ.num_qubits(), 2);
+        assert_eq!(circ.num_bits(), 1);
         assert_eq!(circ.num_gates(), 3);
 
         assert_eq!(circ.units().count(), 3);
@@ -349,6 +503,62 @@ mod tests {
         assert_eq!(circ.qubits().count(), 2);
     }
 
+    #[test]
+    fn from_ops() {
+        let bell = circuit_from_ops(2, &[(Tk2Op::H, vec![0]), (Tk2Op::CX, vec![0, 1])]).unwrap();
+        assert_eq!(bell.commands().count(), 2);
+        assert_eq!(bell.qubit_count(), 2);
+
+        assert!(matches!(
+            circuit_from_ops(2, &[(Tk2Op::CX, vec![0, 2])]),
+            Err(CircuitBuildError::InvalidQubit { index: 2, .. })
+        ));
+        assert!(matches!(
+            circuit_from_ops(2, &[(Tk2Op::CX, vec![1, 1])]),
+            Err(CircuitBuildError::RepeatedQubit { .. })
+        ));
+    }
+
+    #[test]
+    fn circuit_depth() {
+        let circ = build_simple_circuit(3, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::H, [1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::T, [2])?;
+            circ.append(Tk2Op::CX, [1, 2])?;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(circ.depth(), 3);
+        assert_eq!(test_circuit().depth(), 3);
+        assert_eq!(build_simple_circuit(2, |_| Ok(())).unwrap().depth(), 0);
+    }
+
+    #[test]
+    fn topological_commands() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        let ops = |cmds: Vec<Command<'_, Hugr>>| {
+            cmds.iter()
+                .map(|cmd| Tk2Op::try_from(cmd.optype()).unwrap())
+                .collect_vec()
+        };
+
+        let forward = ops(circ.commands().collect());
+        assert_eq!(forward, [Tk2Op::H, Tk2Op::CX]);
+        let backward = ops(circ.commands_reverse_topological().collect());
+        assert_eq!(backward, [Tk2Op::CX, Tk2Op::H]);
+
+        // The order is stable for identical circuits.
+        let nodes = |c: &Hugr| c.commands().map(|cmd| cmd.node()).collect_vec();
+        assert_eq!(nodes(&circ), nodes(&circ.clone()));
+    }
+
     #[test]
     fn remove_qubit() {
         let mut circ = build_simple_circuit(2, |circ| {
@@ -0,0 +1,173 @@
+//! Rotation angles, expressed as multiples of π.
+
+use std::f64::consts::PI;
+
+use num_rational::Rational64;
+
+/// Absolute tolerance used when comparing floating point angles.
+pub const ANGLE_TOLERANCE: f64 = 1e-9;
+
+/// A rotation angle, expressed as a multiple of π (i.e. in half-turns).
+///
+/// Angles are stored either as a raw floating point value or as an exact
+/// rational number.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AngleValue {
+    /// A floating point multiple of π.
+    F64(f64),
+    /// An exact rational multiple of π.
+    Rational(Rational64),
+}
+
+impl AngleValue {
+    /// Returns the angle as a floating point multiple of π.
+    pub fn to_f64(&self) -> f64 {
+        match self {
+            AngleValue::F64(f) => *f,
+            AngleValue::Rational(r) => *r.numer() as f64 / *r.denom() as f64,
+        }
+    }
+
+    /// Returns the angle in radians.
+    pub fn radians(&self) -> f64 {
+        self.to_f64() * PI
+    }
+
+    /// Returns an equivalent angle reduced into the range `[0, 2)`.
+    ///
+    /// Rational angles are kept exact.
+    pub fn normalised(&self) -> AngleValue {
+        match self {
+            AngleValue::F64(f) => {
+                let f = f.rem_euclid(2.);
+                // `rem_euclid` may round small negative values up to 2.
+                AngleValue::F64(if f >= 2. { 0. } else { f })
+            }
+            AngleValue::Rational(r) => {
+                let (numer, denom) = (*r.numer(), *r.denom());
+                let numer = numer.rem_euclid(2 * denom);
+                AngleValue::Rational(Rational64::new(numer, denom))
+            }
+        }
+    }
+
+    /// Returns `true` if the two angles describe the same rotation, up to
+    /// multiples of 2π and a tolerance of [`ANGLE_TOLERANCE`].
+    pub fn approx_eq_mod_2pi(&self, other: &AngleValue) -> bool {
+        if let (AngleValue::Rational(_), AngleValue::Rational(_)) = (self, other) {
+            return self.normalised() == other.normalised();
+        }
+        let diff = AngleValue::F64(self.to_f64() - other.to_f64()).normalised();
+        // The difference may be close to either end of the range.
+        approx_eq(diff.to_f64(), 0.) || approx_eq(diff.to_f64(), 2.)
+    }
+
+    /// Returns a hashable and totally ordered key identifying the angle.
+    ///
+    /// Angles with equal values have equal keys, so the key can be used to
+    /// deduplicate constants. Floating point angles are keyed by their bit
+    /// pattern, with all `NaN`s mapped to the same key and `-0.0` mapped to
+    /// `+0.0`. Rational angles are keyed by their reduced fraction. Angles are
+    /// not normalised, so `0` and `2` have different keys.
+    pub fn canonical_key(&self) -> AngleKey {
+        match self {
+            AngleValue::F64(f) => {
+                let f = match f {
+                    f if f.is_nan() => f64::NAN,
+                    // Also matches `-0.0`.
+                    f if *f == 0. => 0.,
+                    f => *f,
+                };
+                AngleKey::F64(f.to_bits())
+            }
+            AngleValue::Rational(r) => {
+                let r = r.reduced();
+                AngleKey::Rational(*r.numer(), *r.denom())
+            }
+        }
+    }
+}
+
+/// A hashable and totally ordered representation of an [`AngleValue`].
+///
+/// See [`AngleValue::canonical_key`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum AngleKey {
+    /// The bit pattern of a floating point angle.
+    F64(u64),
+    /// The numerator and denominator of a reduced rational angle.
+    Rational(i64, i64),
+}
+
+impl From<f64> for AngleValue {
+    fn from(f: f64) -> Self {
+        AngleValue::F64(f)
+    }
+}
+
+impl From<Rational64> for AngleValue {
+    fn from(r: Rational64) -> Self {
+        AngleValue::Rational(r)
+    }
+}
+
+/// Returns `true` if the two values are within [`ANGLE_TOLERANCE`] of each
+/// other.
+pub fn approx_eq(a: f64, b: f64) -> bool {
+    (a - b).abs() < ANGLE_TOLERANCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0., 0.)]
+    #[case(2., 0.)]
+    #[case(2.5, 0.5)]
+    #[case(-0.5, 1.5)]
+    #[case(-4.25, 1.75)]
+    fn normalise_f64(#[case] angle: f64, #[case] expected: f64) {
+        let AngleValue::F64(f) = AngleValue::F64(angle).normalised() else {
+            panic!("Normalisation changed the angle representation");
+        };
+        assert!(approx_eq(f, expected));
+    }
+
+    #[rstest]
+    #[case((4, 1), (0, 1))]
+    #[case((5, 2), (1, 2))]
+    #[case((-1, 3), (5, 3))]
+    #[case((-9, 4), (7, 4))]
+    fn normalise_rational(#[case] angle: (i64, i64), #[case] expected: (i64, i64)) {
+        let angle = AngleValue::Rational(Rational64::new(angle.0, angle.1));
+        let expected = AngleValue::Rational(Rational64::new(expected.0, expected.1));
+        assert_eq!(angle.normalised(), expected);
+    }
+
+    #[test]
+    fn canonical_key() {
+        let key = |a: AngleValue| a.canonical_key();
+        assert_eq!(key(0.25.into()), key(0.25.into()));
+        assert_ne!(key(0.25.into()), key(0.5.into()));
+        assert_eq!(key(0.0.into()), key((-0.0).into()));
+        assert_eq!(key(f64::NAN.into()), key((-f64::NAN).into()));
+        assert_eq!(
+            key(Rational64::new_raw(2, 4).into()),
+            key(Rational64::new(1, 2).into())
+        );
+        // Different representations are not identified.
+        assert_ne!(key(0.5.into()), key(Rational64::new(1, 2).into()));
+    }
+
+    #[test]
+    fn approx_eq_mod_2pi() {
+        let half = AngleValue::Rational(Rational64::new(1, 2));
+        assert!(AngleValue::F64(2.0).approx_eq_mod_2pi(&AngleValue::F64(0.0)));
+        assert!(AngleValue::F64(-1.5).approx_eq_mod_2pi(&half));
+        assert!(AngleValue::F64(1e-12).approx_eq_mod_2pi(&AngleValue::F64(-1e-12)));
+        assert!(half.approx_eq_mod_2pi(&AngleValue::Rational(Rational64::new(-3, 2))));
+        assert!(!half.approx_eq_mod_2pi(&AngleValue::F64(1.5)));
+    }
+}
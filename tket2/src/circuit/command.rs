@@ -6,7 +6,8 @@ use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::iter::FusedIterator;
 
-use hugr::hugr::NodeType;
+use hugr::extension::prelude;
+use hugr::hugr::{NodeMetadata, NodeType};
 use hugr::ops::{OpTag, OpTrait};
 use hugr::{IncomingPort, OutgoingPort};
 use itertools::Either::{self, Left, Right};
@@ -51,6 +52,12 @@ impl<'circ, Circ: Circuit> Command<'circ, Circ> {
         self.circ.get_optype(self.node)
     }
 
+    /// Returns the metadata of the command's node for a given key.
+    #[inline]
+    pub fn get_metadata(&self, key: impl AsRef<str>) -> Option<&NodeMetadata> {
+        self.circ.get_metadata(self.node, key)
+    }
+
     /// Returns the units of this command in a given direction.
     #[inline]
     pub fn units(
@@ -112,6 +119,21 @@ impl<'circ, Circ: Circuit> Command<'circ, Circ> {
         })
     }
 
+    /// Returns the classical outputs of this command, with their output port
+    /// offsets.
+    ///
+    /// These are the non-qubit dataflow outputs, such as the boolean result of
+    /// a measurement. Constant edges are not included.
+    pub fn classical_outputs(&self) -> Vec<(usize, CircuitUnit)> {
+        self.outputs()
+            .filter(|(_, port, typ)| {
+                *typ != prelude::QB_T
+                    && matches!(self.optype().port_kind(*port), Some(EdgeKind::Value(_)))
+            })
+            .map(|(unit, port, _)| (port.index(), unit))
+            .collect()
+    }
+
     /// Returns the output units of this command.
     #[inline]
     pub fn inputs(&self) -> Units<IncomingPort, &'_ Self> {
@@ -469,14 +491,16 @@ impl<'circ, Circ: Circuit> std::fmt::Debug for CommandIterator<'circ, Circ> {
 #[cfg(test)]
 mod test {
     use hugr::builder::{Container, DFGBuilder, Dataflow, DataflowHugr};
-    use hugr::extension::prelude::QB_T;
+    use hugr::extension::prelude::{BOOL_T, QB_T};
     use hugr::ops::handle::NodeHandle;
     use hugr::ops::OpName;
     use hugr::std_extensions::arithmetic::float_ops::FLOAT_OPS_REGISTRY;
     use hugr::std_extensions::arithmetic::float_types::ConstF64;
+    use hugr::type_row;
     use hugr::types::FunctionType;
     use itertools::Itertools;
 
+    use crate::extension::REGISTRY;
     use crate::utils::build_simple_circuit;
     use crate::Tk2Op;
 
@@ -602,4 +626,26 @@ mod test {
             [CircuitUnit::Linear(0)],
         );
     }
+
+    #[test]
+    fn measure_classical_outputs() {
+        let qb_row = vec![QB_T; 1];
+        let mut h = DFGBuilder::new(FunctionType::new(qb_row, type_row![QB_T, BOOL_T])).unwrap();
+        let [q_in] = h.input_wires_arr();
+        let h_gate = h.add_dataflow_op(Tk2Op::H, [q_in]).unwrap();
+        let measure = h.add_dataflow_op(Tk2Op::Measure, h_gate.outputs()).unwrap();
+        let circ = h
+            .finish_hugr_with_outputs(measure.outputs(), &REGISTRY)
+            .unwrap();
+
+        let mut commands = CommandIterator::new(&circ);
+        let h_cmd = commands.next().unwrap();
+        assert_eq!(h_cmd.classical_outputs(), vec![]);
+
+        let measure_cmd = commands.next().unwrap();
+        assert_eq!(
+            measure_cmd.classical_outputs(),
+            vec![(1, CircuitUnit::Wire(Wire::new(measure.node(), 1)))]
+        );
+    }
 }
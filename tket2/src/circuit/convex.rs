@@ -0,0 +1,70 @@
+//! Convexity checks for sets of circuit nodes.
+
+use hugr::hugr::views::sibling_subgraph::TopoConvexChecker;
+use hugr::hugr::NodeIndex;
+use hugr::{HugrView, Node};
+use itertools::Itertools;
+use portgraph::algorithms::ConvexChecker;
+
+/// Returns `true` if the set of nodes forms a convex region of the circuit.
+///
+/// A set of nodes is convex if every path between two nodes in the set only
+/// goes through nodes in the set. All the nodes must share the same parent.
+/// The empty set is trivially convex.
+///
+/// This has runtime linear in the size of the circuit. For repeated checks
+/// on the same circuit, use [`check_convex_with_checker`] instead.
+pub fn is_convex(circ: &impl HugrView, nodes: &[Node]) -> bool {
+    let checker = TopoConvexChecker::new(circ);
+    check_convex_with_checker(circ, nodes, &checker)
+}
+
+/// Returns `true` if the set of nodes forms a convex region of the circuit,
+/// using a pre-computed convexity checker.
+///
+/// See [`is_convex`] for more details. Only convexity is checked: unlike
+/// [`SiblingSubgraph::try_from_nodes_with_checker`], no subgraph is built and
+/// the boundary of the region is not validated.
+///
+/// [`SiblingSubgraph::try_from_nodes_with_checker`]: hugr::hugr::views::SiblingSubgraph::try_from_nodes_with_checker
+pub fn check_convex_with_checker(
+    circ: &impl HugrView,
+    nodes: &[Node],
+    checker: &impl ConvexChecker,
+) -> bool {
+    if !nodes.iter().map(|&n| circ.get_parent(n)).all_equal() {
+        return false;
+    }
+    checker.is_node_convex(nodes.iter().map(|n| portgraph::NodeIndex::new(n.index())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::build_simple_circuit;
+    use crate::{Circuit, Tk2Op};
+
+    #[test]
+    fn convex_nodes() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::T, [1])?;
+            Ok(())
+        })
+        .unwrap();
+        let nodes: Vec<Node> = circ.commands().map(|cmd| cmd.node()).collect();
+        let (cx1, h, cx2, t) = (nodes[0], nodes[1], nodes[2], nodes[3]);
+
+        assert!(is_convex(&circ, &[cx1, h]));
+        assert!(is_convex(&circ, &[cx2, t]));
+        assert!(is_convex(&circ, &[]));
+        // The H gate lies on a path between the two CX gates.
+        assert!(!is_convex(&circ, &[cx1, cx2]));
+
+        let checker = TopoConvexChecker::new(&circ);
+        assert!(check_convex_with_checker(&circ, &[h, cx2, t], &checker));
+        assert!(!check_convex_with_checker(&circ, &[cx1, t], &checker));
+    }
+}
@@ -1,13 +1,18 @@
 //! Cost definitions for a circuit.
 
 use hugr::ops::OpType;
-use itertools::izip;
+use hugr::{Hugr, HugrView, IncomingPort, Node};
+use itertools::{izip, Itertools};
+use std::collections::{HashMap, VecDeque};
 use std::fmt::Debug;
 use std::iter::Sum;
 use std::num::NonZeroUsize;
 use std::ops::{Add, AddAssign};
 
+use crate::circuit::angle::approx_eq;
+use crate::circuit::Circuit;
 use crate::ops::op_matches;
+use crate::utils::constant_f64_input;
 use crate::Tk2Op;
 
 /// The cost for a group of operations in a circuit, each with cost `OpCost`.
@@ -202,9 +207,258 @@ pub fn is_quantum(op: &OpType) -> bool {
     op.is_quantum()
 }
 
+/// Returns a circuit cost function summing a weight for each operation type.
+///
+/// Operations not present in `weights` do not contribute to the cost.
+pub fn weighted_gate_cost(weights: HashMap<Tk2Op, usize>) -> impl Fn(&Hugr) -> usize {
+    move |circ: &Hugr| {
+        circ.circuit_cost(|op| {
+            Tk2Op::try_from(op)
+                .ok()
+                .and_then(|op| weights.get(&op).copied())
+                .unwrap_or(0)
+        })
+    }
+}
+
+/// Returns the number of T-like gates in a circuit.
+///
+/// See [`t_depth`] for the gates that are counted.
+pub fn t_count(circ: &Hugr) -> usize {
+    circ.commands()
+        .filter(|cmd| is_t_like(circ, cmd.node()))
+        .count()
+}
+
+/// Returns the T-depth of a circuit, the largest number of T-like gates on a
+/// path through the circuit.
+///
+/// T-like gates are the [`Tk2Op::T`] and [`Tk2Op::Tdg`] gates, and the
+/// [`Tk2Op::RzF64`] rotations that are not Clifford. [`Tk2Op::is_clifford`]
+/// does not inspect angles, so rotations by a constant multiple of π/2 are
+/// recognised here and not counted. Rotations by a non-constant angle are
+/// always counted.
+pub fn t_depth(circ: &Hugr) -> usize {
+    let mut depths: HashMap<Node, usize> = HashMap::new();
+    for cmd in circ.commands() {
+        let node = cmd.node();
+        let depth = circ
+            .input_neighbours(node)
+            .filter_map(|pred| depths.get(&pred).copied())
+            .max()
+            .unwrap_or(0);
+        depths.insert(node, depth + usize::from(is_t_like(circ, node)));
+    }
+    depths.into_values().max().unwrap_or(0)
+}
+
+/// Returns true if the command at `node` is a T-like gate, see [`t_depth`].
+fn is_t_like(circ: &Hugr, node: Node) -> bool {
+    let Ok(op) = Tk2Op::try_from(circ.get_optype(node)) else {
+        return false;
+    };
+    if op.is_clifford() {
+        return false;
+    }
+    match op {
+        Tk2Op::T | Tk2Op::Tdg => true,
+        Tk2Op::RzF64 => match constant_f64_input(circ, node, IncomingPort::from(1)) {
+            // Angles are expressed in half-turns.
+            Some(angle) => !approx_eq(2. * angle, (2. * angle).round()),
+            None => true,
+        },
+        _ => false,
+    }
+}
+
+/// The connectivity of a device, as an undirected graph between physical
+/// qubits.
+///
+/// The shortest-path distances between all pairs of qubits are computed on
+/// construction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CouplingMap {
+    /// The neighbours of each qubit.
+    adjacency: Vec<Vec<usize>>,
+    /// The distance between each pair of qubits, or `None` if they are not
+    /// connected.
+    distances: Vec<Vec<Option<usize>>>,
+}
+
+impl CouplingMap {
+    /// Create a coupling map from a list of connected qubit pairs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an edge refers to a qubit index not lower than `n_qubits`.
+    pub fn new(n_qubits: usize, edges: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        let mut adjacency = vec![Vec::new(); n_qubits];
+        for (a, b) in edges {
+            if a != b && !adjacency[a].contains(&b) {
+                adjacency[a].push(b);
+                adjacency[b].push(a);
+            }
+        }
+        let distances = (0..n_qubits)
+            .map(|source| {
+                let mut dist = vec![None; n_qubits];
+                dist[source] = Some(0);
+                let mut queue = VecDeque::from([source]);
+                while let Some(q) = queue.pop_front() {
+                    let d = dist[q].unwrap();
+                    for &n in &adjacency[q] {
+                        if dist[n].is_none() {
+                            dist[n] = Some(d + 1);
+                            queue.push_back(n);
+                        }
+                    }
+                }
+                dist
+            })
+            .collect();
+        Self {
+            adjacency,
+            distances,
+        }
+    }
+
+    /// A coupling map with qubits connected in a line.
+    pub fn linear(n_qubits: usize) -> Self {
+        Self::new(n_qubits, (1..n_qubits).map(|q| (q - 1, q)))
+    }
+
+    /// The number of qubits in the device.
+    pub fn n_qubits(&self) -> usize {
+        self.adjacency.len()
+    }
+
+    /// The qubits connected to `qubit`.
+    pub fn neighbours(&self, qubit: usize) -> &[usize] {
+        &self.adjacency[qubit]
+    }
+
+    /// The length of the shortest path between two qubits.
+    ///
+    /// Returns `None` if the qubits are not connected, or not in the device.
+    pub fn distance(&self, a: usize, b: usize) -> Option<usize> {
+        *self.distances.get(a)?.get(b)?
+    }
+}
+
+/// The number of CX gates needed to implement a SWAP.
+const SWAP_CX_COST: usize = 3;
+
+/// Returns a circuit cost function counting CX gates, penalised by the
+/// distance between their qubits on a device.
+///
+/// Each qubit of the circuit is identified with the physical qubit of the
+/// same index. A CX acting on qubits at distance `d` costs `1 + 3 * (d - 1)`,
+/// approximating the SWAP gates needed to make the qubits adjacent.
+/// Disconnected qubits, or qubits outside the coupling map, are treated as
+/// being at a distance equal to the number of qubits in the device.
+pub fn routing_cost(coupling: CouplingMap) -> impl Fn(&Hugr) -> usize {
+    move |circ: &Hugr| {
+        circ.commands()
+            .filter(|cmd| is_cx(cmd.optype()))
+            .map(|cmd| {
+                let (a, b) = cmd
+                    .linear_inputs()
+                    .map(|(unit, _, _)| unit.index())
+                    .collect_tuple()
+                    .expect("CX gates act on two qubits.");
+                let dist = coupling
+                    .distance(a, b)
+                    .unwrap_or(coupling.n_qubits())
+                    .max(1);
+                1 + SWAP_CX_COST * (dist - 1)
+            })
+            .sum()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::extension::REGISTRY;
+    use crate::utils::build_simple_circuit;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::QB_T;
+    use hugr::std_extensions::arithmetic::float_types::ConstF64;
+    use hugr::type_row;
+    use hugr::types::FunctionType;
+
+    #[test]
+    fn weighted_cost() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::T, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::T, [1])?;
+            circ.append(Tk2Op::CZ, [1, 0])?;
+            Ok(())
+        })
+        .unwrap();
+        let cost = weighted_gate_cost(HashMap::from([
+            (Tk2Op::CX, 10),
+            (Tk2Op::CZ, 10),
+            (Tk2Op::T, 3),
+        ]));
+        // Two 2-qubit gates, two T gates and an unweighted H.
+        assert_eq!(cost(&circ), 2 * 10 + 2 * 3);
+    }
+
+    #[test]
+    fn t_metrics() {
+        let mut h = DFGBuilder::new(FunctionType::new(
+            type_row![QB_T, QB_T],
+            type_row![QB_T, QB_T],
+        ))
+        .unwrap();
+        let [q0, q1] = h.input_wires_arr();
+        let [q0] = h.add_dataflow_op(Tk2Op::T, [q0]).unwrap().outputs_arr();
+        let [q1] = h.add_dataflow_op(Tk2Op::Tdg, [q1]).unwrap().outputs_arr();
+        let [q0, q1] = h
+            .add_dataflow_op(Tk2Op::CX, [q0, q1])
+            .unwrap()
+            .outputs_arr();
+        // A Clifford rotation, not counted.
+        let half = h.add_load_const(ConstF64::new(0.5)).unwrap();
+        let [q0] = h
+            .add_dataflow_op(Tk2Op::RzF64, [q0, half])
+            .unwrap()
+            .outputs_arr();
+        let quarter = h.add_load_const(ConstF64::new(0.25)).unwrap();
+        let [q1] = h
+            .add_dataflow_op(Tk2Op::RzF64, [q1, quarter])
+            .unwrap()
+            .outputs_arr();
+        let [q1] = h.add_dataflow_op(Tk2Op::T, [q1]).unwrap().outputs_arr();
+        let circ = h.finish_hugr_with_outputs([q0, q1], &REGISTRY).unwrap();
+
+        assert_eq!(t_count(&circ), 4);
+        // T/Tdg, then Rz(π/4) and T after the CX.
+        assert_eq!(t_depth(&circ), 3);
+    }
+
+    #[test]
+    fn linear_routing_cost() {
+        let coupling = CouplingMap::linear(4);
+        assert_eq!(coupling.distance(0, 3), Some(3));
+        assert_eq!(coupling.neighbours(1), [0, 2]);
+
+        let cost = routing_cost(coupling);
+        let cx = |a, b| {
+            build_simple_circuit(4, |circ| {
+                circ.append(Tk2Op::CX, [a, b])?;
+                Ok(())
+            })
+            .unwrap()
+        };
+        assert_eq!(cost(&cx(1, 2)), 1);
+        assert_eq!(cost(&cx(2, 1)), 1);
+        assert_eq!(cost(&cx(0, 3)), 1 + 3 * 2);
+        assert!(cost(&cx(0, 3)) > cost(&cx(0, 1)));
+    }
 
     #[test]
     fn major_minor() {
@@ -0,0 +1,601 @@
+//! Gate-by-gate comparison of circuits.
+
+use std::collections::HashMap;
+
+use hugr::ops::{OpName, OpType};
+use hugr::std_extensions::arithmetic::float_types::ConstF64;
+use hugr::values::Value;
+use hugr::{CircuitUnit, HugrView, Node};
+use itertools::Itertools;
+use smol_str::SmolStr;
+
+use super::angle::{AngleKey, AngleValue};
+use super::Circuit;
+
+/// A gate in a circuit, as compared by [`circuit_diff`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DiffGate {
+    /// The name of the operation.
+    pub op: SmolStr,
+    /// The indices of the qubits (and other linear units) the gate acts on.
+    pub qubits: Vec<usize>,
+    /// The sources of the non-linear inputs of the gate, such as its
+    /// parameters and classical controls.
+    pub inputs: Vec<DiffInput>,
+}
+
+/// The source of a non-linear input of a [`DiffGate`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum DiffInput {
+    /// A floating point constant.
+    Float(AngleKey),
+    /// Any other constant, by its debug representation.
+    Constant(String),
+    /// An input of the circuit, by port index.
+    CircuitInput(usize),
+    /// An output of a gate acting on linear units.
+    Gate {
+        /// The name of the operation.
+        op: SmolStr,
+        /// The linear units the gate acts on.
+        qubits: Vec<usize>,
+        /// The output port.
+        port: usize,
+    },
+    /// An output of a classical operation, which does not act on any linear
+    /// unit and is identified by its own inputs instead.
+    Classical {
+        /// The name of the operation.
+        op: SmolStr,
+        /// The sources of the operation's inputs.
+        inputs: Vec<DiffInput>,
+        /// The output port.
+        port: usize,
+    },
+}
+
+impl DiffInput {
+    /// Relabel the linear units the source refers to.
+    fn permuted(&self, perm: &[usize]) -> DiffInput {
+        match self {
+            DiffInput::Gate { op, qubits, port } => DiffInput::Gate {
+                op: op.clone(),
+                qubits: qubits.iter().map(|&q| perm[q]).collect(),
+                port: *port,
+            },
+            DiffInput::Classical { op, inputs, port } => DiffInput::Classical {
+                op: op.clone(),
+                inputs: inputs.iter().map(|i| i.permuted(perm)).collect(),
+                port: *port,
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+/// A single difference between two circuits.
+///
+/// Indices refer to the position of the gate in the topological order of the
+/// circuit it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GateDiff {
+    /// A gate of the second circuit that is not in the first one.
+    Added {
+        /// The index of the gate in the second circuit.
+        index: usize,
+        /// The added gate.
+        gate: DiffGate,
+    },
+    /// A gate of the first circuit that is not in the second one.
+    Removed {
+        /// The index of the gate in the first circuit.
+        index: usize,
+        /// The removed gate.
+        gate: DiffGate,
+    },
+    /// A gate replaced by a different operation on the same qubits.
+    Substituted {
+        /// The index of the gate in the first circuit.
+        old_index: usize,
+        /// The index of the gate in the second circuit.
+        new_index: usize,
+        /// The gate in the first circuit.
+        old: DiffGate,
+        /// The gate in the second circuit.
+        new: DiffGate,
+    },
+}
+
+/// The differences between two circuits, as computed by [`circuit_diff`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CircuitDiff {
+    /// The differences, ordered by their position in the circuits.
+    pub changes: Vec<GateDiff>,
+}
+
+impl CircuitDiff {
+    /// Returns `true` if the circuits have the same gates.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// The gates only present in the second circuit.
+    pub fn added(&self) -> impl Iterator<Item = &DiffGate> + '_ {
+        self.changes.iter().filter_map(|c| match c {
+            GateDiff::Added { gate, .. } => Some(gate),
+            _ => None,
+        })
+    }
+
+    /// The gates only present in the first circuit.
+    pub fn removed(&self) -> impl Iterator<Item = &DiffGate> + '_ {
+        self.changes.iter().filter_map(|c| match c {
+            GateDiff::Removed { gate, .. } => Some(gate),
+            _ => None,
+        })
+    }
+
+    /// The pairs of gates replaced by a different operation.
+    pub fn substituted(&self) -> impl Iterator<Item = (&DiffGate, &DiffGate)> + '_ {
+        self.changes.iter().filter_map(|c| match c {
+            GateDiff::Substituted { old, new, .. } => Some((old, new)),
+            _ => None,
+        })
+    }
+}
+
+/// Compare two circuits gate by gate.
+///
+/// The gates of each circuit are listed in topological order and aligned
+/// using a longest common subsequence. Unaligned gates are reported as
+/// removed from `a` or added in `b`, except where a removed and an added gate
+/// between the same aligned gates act on the same qubits, which are reported
+/// as a substitution.
+///
+/// Constant definitions and loads are not considered gates. The circuits may
+/// have different numbers of gates.
+pub fn circuit_diff(a: &impl Circuit, b: &impl Circuit) -> CircuitDiff {
+    let gates_a = diff_gates(a);
+    let gates_b = diff_gates(b);
+    let (n, m) = (gates_a.len(), gates_b.len());
+
+    let mut aligned = Vec::new();
+    align_gates(&gates_a, &gates_b, (0, 0), &mut aligned);
+
+    let mut changes = Vec::new();
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    for (next_i, next_j) in aligned.into_iter().chain([(n, m)]) {
+        removed.extend(i..next_i);
+        added.extend(j..next_j);
+        flush_gap(&mut changes, &mut removed, &mut added, &gates_a, &gates_b);
+        (i, j) = (next_i + 1, next_j + 1);
+    }
+    CircuitDiff { changes }
+}
+
+/// Collect the pairs of indices of a longest common subsequence of `a` and
+/// `b`, shifted by `offset`, in increasing order.
+///
+/// Uses Hirschberg's algorithm, which only needs space linear in the length
+/// of `b`.
+fn align_gates(
+    a: &[DiffGate],
+    b: &[DiffGate],
+    offset: (usize, usize),
+    aligned: &mut Vec<(usize, usize)>,
+) {
+    if a.is_empty() || b.is_empty() {
+        return;
+    }
+    if a.len() == 1 {
+        if let Some(j) = b.iter().position(|g| g == &a[0]) {
+            aligned.push((offset.0, offset.1 + j));
+        }
+        return;
+    }
+    // Split `b` where the common subsequences of both halves of `a` are the
+    // longest.
+    let mid = a.len() / 2;
+    let forward = lcs_lengths(&a[..mid], b, false);
+    let backward = lcs_lengths(&a[mid..], b, true);
+    let m = b.len();
+    let split = (0..=m)
+        .max_by_key(|&j| forward[j] + backward[m - j])
+        .unwrap();
+    align_gates(&a[..mid], &b[..split], offset, aligned);
+    align_gates(
+        &a[mid..],
+        &b[split..],
+        (offset.0 + mid, offset.1 + split),
+        aligned,
+    );
+}
+
+/// The lengths of the longest common subsequences of `a` and each prefix of
+/// `b`, indexed by the length of the prefix.
+///
+/// If `reversed` is set, both sequences are read backwards, so the lengths
+/// are those for each suffix of `b`.
+fn lcs_lengths(a: &[DiffGate], b: &[DiffGate], reversed: bool) -> Vec<usize> {
+    let nth = |s: &'_ [DiffGate], k: usize| match reversed {
+        true => s.len() - 1 - k,
+        false => k,
+    };
+    let m = b.len();
+    let mut row = vec![0usize; m + 1];
+    for i in 0..a.len() {
+        let x = &a[nth(a, i)];
+        let mut diagonal = 0;
+        for j in 1..=m {
+            let above = row[j];
+            row[j] = match x == &b[nth(b, j - 1)] {
+                true => diagonal + 1,
+                false => above.max(row[j - 1]),
+            };
+            diagonal = above;
+        }
+    }
+    row
+}
+
+/// The maximum number of qubits for which
+/// [`circuits_equal_up_to_qubit_permutation`] searches for a permutation.
+pub const MAX_PERMUTATION_QUBITS: usize = 8;
+
+/// Returns `true` if the circuits are identical up to a relabelling of their
+/// qubits.
+///
+/// The circuits are identical if they have the same gates, as compared by
+/// [`circuit_diff`], connected in the same way. The order in which
+/// independent gates are listed does not matter. Other linear units, such as
+/// linear bits, can only be relabelled to units of the same type.
+///
+/// The permutations are searched exhaustively, only pairing qubits with the
+/// same sequence of gates on their wire. Circuits with more than
+/// [`MAX_PERMUTATION_QUBITS`] qubits are reported as different.
+pub fn circuits_equal_up_to_qubit_permutation(a: &impl Circuit, b: &impl Circuit) -> bool {
+    let types_a = a.linear_units().map(|(_, _, typ)| typ).collect_vec();
+    let types_b = b.linear_units().map(|(_, _, typ)| typ).collect_vec();
+    let n = types_a.len();
+    if n != types_b.len() || n > MAX_PERMUTATION_QUBITS {
+        return false;
+    }
+    let gates_a = diff_gates(a);
+    let gates_b = diff_gates(b);
+    if gates_a.len() != gates_b.len() {
+        return false;
+    }
+    let wires_a = unit_wires(&gates_a, n);
+    let wires_b = unit_wires(&gates_b, n);
+
+    // The qubits of `b` that qubit `q` of `a` may be mapped to.
+    let candidates = (0..n)
+        .map(|q| {
+            let profile = wire_profile(&gates_a, &wires_a[q], q);
+            (0..n)
+                .filter(|&r| types_a[q] == types_b[r])
+                .filter(|&r| wire_profile(&gates_b, &wires_b[r], r) == profile)
+                .collect_vec()
+        })
+        .collect_vec();
+
+    let is_match = |perm: &[usize]| {
+        (0..n).all(|q| {
+            wires_a[q].iter().zip(&wires_b[perm[q]]).all(|(&ga, &gb)| {
+                let (ga, gb) = (&gates_a[ga], &gates_b[gb]);
+                ga.op == gb.op
+                    && ga
+                        .qubits
+                        .iter()
+                        .map(|&x| perm[x])
+                        .eq(gb.qubits.iter().copied())
+                    && ga
+                        .inputs
+                        .iter()
+                        .map(|i| i.permuted(perm))
+                        .eq(gb.inputs.iter().cloned())
+            })
+        })
+    };
+    let mut perm = Vec::with_capacity(n);
+    let mut used = vec![false; n];
+    find_permutation(&candidates, &mut perm, &mut used, &is_match)
+}
+
+/// Extend a partial permutation with the candidates of the next qubit, until
+/// a complete one satisfies `is_match`.
+fn find_permutation(
+    candidates: &[Vec<usize>],
+    perm: &mut Vec<usize>,
+    used: &mut [bool],
+    is_match: &impl Fn(&[usize]) -> bool,
+) -> bool {
+    let Some(options) = candidates.get(perm.len()) else {
+        return is_match(perm);
+    };
+    for &r in options {
+        if used[r] {
+            continue;
+        }
+        used[r] = true;
+        perm.push(r);
+        if find_permutation(candidates, perm, used, is_match) {
+            return true;
+        }
+        perm.pop();
+        used[r] = false;
+    }
+    false
+}
+
+/// The indices of the gates acting on each unit, in topological order.
+fn unit_wires(gates: &[DiffGate], n_units: usize) -> Vec<Vec<usize>> {
+    let mut wires = vec![Vec::new(); n_units];
+    for (i, gate) in gates.iter().enumerate() {
+        for &q in &gate.qubits {
+            wires[q].push(i);
+        }
+    }
+    wires
+}
+
+/// The operations along the wire of unit `q`, with the position of `q` in
+/// their arguments.
+fn wire_profile<'g>(gates: &'g [DiffGate], wire: &[usize], q: usize) -> Vec<(&'g SmolStr, usize)> {
+    wire.iter()
+        .map(|&g| {
+            let gate = &gates[g];
+            let pos = gate.qubits.iter().position(|&x| x == q).unwrap();
+            (&gate.op, pos)
+        })
+        .collect()
+}
+
+/// The gates of a circuit in topological order.
+fn diff_gates(circ: &impl Circuit) -> Vec<DiffGate> {
+    let mut gates = Vec::new();
+    let mut gate_index: HashMap<Node, usize> = HashMap::new();
+    for cmd in circ.commands() {
+        if matches!(cmd.optype(), OpType::Const(_) | OpType::LoadConstant(_)) {
+            continue;
+        }
+        let inputs = cmd
+            .inputs()
+            .filter_map(|(unit, _, _)| match unit {
+                CircuitUnit::Wire(wire) => Some(input_source(
+                    circ,
+                    &gates,
+                    &gate_index,
+                    wire.node(),
+                    wire.source().index(),
+                )),
+                CircuitUnit::Linear(_) => None,
+            })
+            .collect();
+        gate_index.insert(cmd.node(), gates.len());
+        gates.push(DiffGate {
+            op: cmd.optype().name(),
+            qubits: cmd.linear_inputs().map(|(u, _, _)| u.index()).collect(),
+            inputs,
+        });
+    }
+    gates
+}
+
+/// Describe the source of a non-linear input, given the gates preceding it.
+fn input_source(
+    circ: &impl Circuit,
+    gates: &[DiffGate],
+    gate_index: &HashMap<Node, usize>,
+    node: Node,
+    port: usize,
+) -> DiffInput {
+    if node == circ.input() {
+        return DiffInput::CircuitInput(port);
+    }
+    let const_node = match circ.get_optype(node) {
+        OpType::LoadConstant(_) => circ.input_neighbours(node).next(),
+        OpType::Const(_) => Some(node),
+        _ => None,
+    };
+    if let Some(OpType::Const(c)) = const_node.map(|n| circ.get_optype(n)) {
+        return match c.value() {
+            Value::Extension { c: (val,) } => match val.downcast_ref::<ConstF64>() {
+                Some(f) => DiffInput::Float(AngleValue::F64(f.value()).canonical_key()),
+                None => DiffInput::Constant(format!("{:?}", c.value())),
+            },
+            value => DiffInput::Constant(format!("{value:?}")),
+        };
+    }
+    let gate = &gates[gate_index[&node]];
+    match gate.qubits.is_empty() {
+        true => DiffInput::Classical {
+            op: gate.op.clone(),
+            inputs: gate.inputs.clone(),
+            port,
+        },
+        false => DiffInput::Gate {
+            op: gate.op.clone(),
+            qubits: gate.qubits.clone(),
+            port,
+        },
+    }
+}
+
+/// Record the unaligned gates between two aligned ones, pairing removed and
+/// added gates on the same qubits as substitutions.
+fn flush_gap(
+    changes: &mut Vec<GateDiff>,
+    removed: &mut Vec<usize>,
+    added: &mut Vec<usize>,
+    gates_a: &[DiffGate],
+    gates_b: &[DiffGate],
+) {
+    let mut unpaired = added.drain(..).collect_vec();
+    for old_index in removed.drain(..) {
+        let old = &gates_a[old_index];
+        match unpaired
+            .iter()
+            .position(|&j| gates_b[j].qubits == old.qubits)
+        {
+            Some(pos) => {
+                let new_index = unpaired.remove(pos);
+                changes.push(GateDiff::Substituted {
+                    old_index,
+                    new_index,
+                    old: old.clone(),
+                    new: gates_b[new_index].clone(),
+                });
+            }
+            None => changes.push(GateDiff::Removed {
+                index: old_index,
+                gate: old.clone(),
+            }),
+        }
+    }
+    changes.extend(unpaired.into_iter().map(|index| GateDiff::Added {
+        index,
+        gate: gates_b[index].clone(),
+    }));
+}
+
+#[cfg(test)]
+mod tests {
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::QB_T;
+    use hugr::types::FunctionType;
+    use hugr::Hugr;
+
+    use super::*;
+    use crate::extension::REGISTRY;
+    use crate::utils::build_simple_circuit;
+    use crate::Tk2Op;
+
+    fn rz_const(angle: f64) -> Hugr {
+        let mut h = DFGBuilder::new(FunctionType::new(vec![QB_T], vec![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let angle = h.add_load_const(ConstF64::new(angle)).unwrap();
+        let q = h
+            .add_dataflow_op(Tk2Op::RzF64, [q, angle])
+            .unwrap()
+            .out_wire(0);
+        h.finish_hugr_with_outputs([q], &REGISTRY).unwrap()
+    }
+
+    #[test]
+    fn cx_cx_removal() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::T, [0])?;
+            Ok(())
+        })
+        .unwrap();
+        let reduced = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::T, [0])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let diff = circuit_diff(&circ, &reduced);
+
+        let cx = DiffGate {
+            op: Tk2Op::CX.exposed_name(),
+            qubits: vec![0, 1],
+            inputs: vec![],
+        };
+        assert_eq!(diff.removed().collect_vec(), [&cx, &cx]);
+        assert_eq!(diff.added().count(), 0);
+        assert_eq!(diff.substituted().count(), 0);
+        assert!(circuit_diff(&reduced, &reduced).is_empty());
+    }
+
+    #[test]
+    fn substitution() {
+        let a = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::T, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        let b = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::Tdg, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::H, [1])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let diff = circuit_diff(&a, &b);
+
+        let [(old, new)] = diff.substituted().collect_vec()[..] else {
+            panic!("Expected a single substitution.");
+        };
+        assert_eq!(old.op, Tk2Op::T.exposed_name());
+        assert_eq!(new.op, Tk2Op::Tdg.exposed_name());
+        assert_eq!(
+            diff.added().map(|g| g.op.clone()).collect_vec(),
+            [Tk2Op::H.exposed_name()]
+        );
+    }
+
+    #[test]
+    fn equal_up_to_permutation() {
+        let circ = |gates: &[(Tk2Op, &[usize])]| {
+            build_simple_circuit(2, |circ| {
+                for (op, qubits) in gates {
+                    circ.append(*op, qubits.iter().copied())?;
+                }
+                Ok(())
+            })
+            .unwrap()
+        };
+        let cx_01 = circ(&[(Tk2Op::CX, &[0, 1])]);
+        let cx_10 = circ(&[(Tk2Op::CX, &[1, 0])]);
+        assert!(circuits_equal_up_to_qubit_permutation(&cx_01, &cx_10));
+        assert!(circuits_equal_up_to_qubit_permutation(&cx_01, &cx_01));
+
+        let cx_h = circ(&[(Tk2Op::CX, &[0, 1]), (Tk2Op::H, &[0])]);
+        let swapped = circ(&[(Tk2Op::CX, &[1, 0]), (Tk2Op::H, &[1])]);
+        let on_target = circ(&[(Tk2Op::CX, &[1, 0]), (Tk2Op::H, &[0])]);
+        assert!(circuits_equal_up_to_qubit_permutation(&cx_h, &swapped));
+        assert!(!circuits_equal_up_to_qubit_permutation(&cx_h, &on_target));
+        assert!(!circuits_equal_up_to_qubit_permutation(&cx_h, &cx_01));
+
+        // Independent gates may be listed in any order.
+        let h_x = circ(&[(Tk2Op::H, &[0]), (Tk2Op::X, &[1])]);
+        let x_h = circ(&[(Tk2Op::X, &[0]), (Tk2Op::H, &[1])]);
+        assert!(circuits_equal_up_to_qubit_permutation(&h_x, &x_h));
+    }
+
+    #[test]
+    fn parameters() {
+        let diff = circuit_diff(&rz_const(0.1), &rz_const(0.2));
+        let [(old, new)] = diff.substituted().collect_vec()[..] else {
+            panic!("Expected a single substitution.");
+        };
+        assert_eq!(
+            old.inputs,
+            [DiffInput::Float(AngleValue::F64(0.1).canonical_key())]
+        );
+        assert_eq!(
+            new.inputs,
+            [DiffInput::Float(AngleValue::F64(0.2).canonical_key())]
+        );
+        assert!(circuit_diff(&rz_const(0.1), &rz_const(0.1)).is_empty());
+
+        assert!(!circuits_equal_up_to_qubit_permutation(
+            &rz_const(0.1),
+            &rz_const(0.2)
+        ));
+        assert!(circuits_equal_up_to_qubit_permutation(
+            &rz_const(0.1),
+            &rz_const(0.1)
+        ));
+    }
+}
@@ -0,0 +1,139 @@
+//! GraphViz export of circuits.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use hugr::ops::{OpName, OpType};
+use hugr::types::EdgeKind;
+use hugr::{HugrView, IncomingPort, Node};
+use itertools::Itertools;
+
+use super::Circuit;
+use crate::utils::constant_f64_input;
+use crate::Tk2Op;
+
+/// Render a circuit in the GraphViz dot format.
+///
+/// Each operation is a node labelled with its name, filled with a colour
+/// depending on its type. Rotations with constant angles also show the angle
+/// values, in half-turns. The input and output boundary nodes are drawn as
+/// grey boxes.
+///
+/// Linear wires (e.g. qubits) are drawn as solid edges, classical wires as
+/// dashed edges, and constant and ordering edges as dotted edges.
+pub fn circuit_to_dot(circ: &impl Circuit) -> String {
+    let nodes = circ.children(circ.root()).collect_vec();
+    let ids: HashMap<Node, usize> = nodes.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+
+    let mut dot = String::from("digraph {\n    node [style=filled];\n");
+    for &node in &nodes {
+        let (label, attrs) = node_style(circ, node);
+        writeln!(dot, "    {} [label=\"{label}\", {attrs}];", ids[&node]).unwrap();
+    }
+    for &node in &nodes {
+        let optype = circ.get_optype(node);
+        for port in circ.node_outputs(node) {
+            let style = match optype.port_kind(port) {
+                Some(kind @ EdgeKind::Value(_)) if kind.is_linear() => "solid",
+                Some(EdgeKind::Value(_)) => "dashed",
+                _ => "dotted",
+            };
+            for (target, _) in circ.linked_inputs(node, port) {
+                let Some(target) = ids.get(&target) else {
+                    continue;
+                };
+                writeln!(dot, "    {} -> {target} [style={style}];", ids[&node]).unwrap();
+            }
+        }
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// The label and style attributes of a node.
+fn node_style(circ: &impl Circuit, node: Node) -> (String, &'static str) {
+    let optype = circ.get_optype(node);
+    match optype {
+        OpType::Input(_) => ("Input".to_string(), "shape=box, fillcolor=grey"),
+        OpType::Output(_) => ("Output".to_string(), "shape=box, fillcolor=grey"),
+        OpType::Const(_) | OpType::LoadConstant(_) => {
+            (optype.name().to_string(), "shape=note, fillcolor=white")
+        }
+        _ => match Tk2Op::try_from(optype) {
+            Ok(op) => (gate_label(circ, node, op), gate_colour(op)),
+            Err(_) => (optype.name().to_string(), "fillcolor=white"),
+        },
+    }
+}
+
+/// The name of a gate, followed by its constant parameters if any.
+fn gate_label(circ: &impl Circuit, node: Node, op: Tk2Op) -> String {
+    let name: &'static str = op.into();
+    let n_params = op.n_params();
+    if n_params == 0 {
+        return name.to_string();
+    }
+    let params = (0..n_params)
+        .map(|i| {
+            let port = IncomingPort::from(op.n_qubits() + i);
+            constant_f64_input(circ, node, port).map_or("?".to_string(), |f| f.to_string())
+        })
+        .join(", ");
+    format!("{name}({params})")
+}
+
+/// The fill colour of a gate.
+fn gate_colour(op: Tk2Op) -> &'static str {
+    use Tk2Op::*;
+    match op {
+        CX | CY | CZ => "fillcolor=red",
+        H => "fillcolor=lightblue",
+        T | Tdg | S | Sdg => "fillcolor=yellow",
+        X | Y | Z => "fillcolor=orange",
+        RzF64 | RxF64 | PhasedX | TK1 => "fillcolor=green",
+        ZZMax | ZZPhase | SWAP => "fillcolor=pink",
+        Measure | Reset | QAlloc | QFree => "fillcolor=lightgrey",
+        _ => "fillcolor=white",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::REGISTRY;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::{BOOL_T, QB_T};
+    use hugr::types::FunctionType;
+
+    #[test]
+    fn coloured_dot() {
+        let mut h = DFGBuilder::new(FunctionType::new(
+            vec![QB_T, QB_T],
+            vec![QB_T, QB_T, BOOL_T],
+        ))
+        .unwrap();
+        let [q0, q1] = h.input_wires_arr();
+        let q0 = h.add_dataflow_op(Tk2Op::H, [q0]).unwrap().out_wire(0);
+        let [q0, q1] = h
+            .add_dataflow_op(Tk2Op::CX, [q0, q1])
+            .unwrap()
+            .outputs_arr();
+        let [q0, bit] = h
+            .add_dataflow_op(Tk2Op::Measure, [q0])
+            .unwrap()
+            .outputs_arr();
+        let circ = h
+            .finish_hugr_with_outputs([q0, q1, bit], &REGISTRY)
+            .unwrap();
+
+        let dot = circuit_to_dot(&circ);
+
+        assert!(dot.starts_with("digraph {"));
+        assert!(dot.contains("[label=\"CX\", fillcolor=red]"));
+        assert!(dot.contains("[label=\"H\", fillcolor=lightblue]"));
+        assert!(dot.contains("[label=\"Input\", shape=box, fillcolor=grey]"));
+        assert!(dot.contains("[style=solid]"));
+        // The measurement result is a classical wire.
+        assert_eq!(dot.matches("[style=dashed]").count(), 1);
+    }
+}
@@ -2,14 +2,19 @@
 
 use std::hash::{Hash, Hasher};
 
-use fxhash::{FxHashMap, FxHasher64};
+use fxhash::{FxHashMap, FxHashSet, FxHasher64};
 use hugr::hugr::views::{HierarchyView, SiblingGraph};
 use hugr::ops::{LeafOp, OpName, OpType};
-use hugr::{HugrView, Node};
+use hugr::std_extensions::arithmetic::float_types::ConstF64;
+use hugr::values::Value;
+use hugr::{HugrView, Node, OutgoingPort, PortIndex};
+use itertools::Itertools;
 use petgraph::visit::{self as pg, Walker};
 use thiserror::Error;
 
+use super::angle::AngleValue;
 use super::Circuit;
+use crate::{Pauli, Tk2Op};
 
 /// Circuit hashing utilities.
 pub trait CircuitHash<'circ>: HugrView {
@@ -24,6 +29,19 @@ pub trait CircuitHash<'circ>: HugrView {
     /// Adapted from Quartz (Apache 2.0)
     /// <https://github.com/quantum-compiler/quartz/blob/2e13eb7ffb3c5c5fe96cf5b4246f4fd7512e111e/src/quartz/tasograph/tasograph.cpp#L410>
     fn circuit_hash(&'circ self) -> Result<u64, HashError>;
+
+    /// Compute a hash of a circuit that is invariant under reordering of
+    /// commuting gates.
+    ///
+    /// Along each qubit wire, consecutive gates acting on the wire with the
+    /// same Pauli commutation basis form a commuting run. Each gate is hashed
+    /// using the combined hashes of the runs preceding it, and the hashes
+    /// within a run are combined in an order-independent way. Circuits that
+    /// only differ by the order of gates commuting on all their shared qubits
+    /// thus have the same canonical hash.
+    ///
+    /// This is more expensive to compute than [`CircuitHash::circuit_hash`].
+    fn canonical_hash(&'circ self) -> Result<u64, HashError>;
 }
 
 impl<'circ, T> CircuitHash<'circ> for T
@@ -48,6 +66,23 @@ where
             .node_hash(self.output())
             .ok_or(HashError::CyclicCircuit)
     }
+
+    fn canonical_hash(&'circ self) -> Result<u64, HashError> {
+        let mut state = CanonicalHashState::default();
+
+        for node in pg::Topo::new(&self.as_petgraph())
+            .iter(&self.as_petgraph())
+            .filter(|&n| n != self.root())
+        {
+            canonical_hash_node(self, node, &mut state)?;
+        }
+
+        // If the output node has no hash, the topological sort failed due to a cycle.
+        state
+            .hashes
+            .node_hash(self.output())
+            .ok_or(HashError::CyclicCircuit)
+    }
 }
 
 /// Auxiliary data for circuit hashing.
@@ -57,6 +92,8 @@ where
 struct HashState {
     /// Computed node hashes.
     pub hashes: FxHashMap<Node, u64>,
+    /// Symmetric gates whose qubit ports were exchanged to canonicalise them.
+    pub swapped: FxHashSet<Node>,
 }
 
 impl HashState {
@@ -73,6 +110,17 @@ impl HashState {
     fn set_hash(&mut self, node: Node, hash: u64) -> Option<u64> {
         self.hashes.insert(node, hash)
     }
+
+    /// Return the canonical offset of an output port.
+    ///
+    /// The qubit ports of canonicalised symmetric gates are exchanged.
+    #[inline]
+    fn canonical_port(&self, node: Node, port: OutgoingPort) -> usize {
+        match port.index() {
+            i @ (0 | 1) if self.swapped.contains(&node) => 1 - i,
+            i => i,
+        }
+    }
 }
 
 /// Returns a hashable representation of an operation.
@@ -86,6 +134,13 @@ fn hashable_op(op: &OpType) -> impl Hash {
                 serde_json::to_string(op.args()).unwrap()
             )
         }
+        OpType::Const(const_op) => match const_op.value() {
+            Value::Extension { c: (val,) } => match val.downcast_ref::<ConstF64>() {
+                Some(f) => format!("const:{:?}", AngleValue::F64(f.value()).canonical_key()),
+                None => op.name().to_string(),
+            },
+            _ => op.name().to_string(),
+        },
         _ => op.name().to_string(),
     }
 }
@@ -110,22 +165,143 @@ fn hash_node(circ: &impl HugrView, node: Node, state: &mut HashState) -> Result<
     // Hash the node operation
     hashable_op(op).hash(&mut hasher);
 
-    // Add each each input neighbour hash, including the connected ports.
+    // Compute each input neighbour hash, including the connected ports.
     // TODO: Ignore state edges?
-    for input in circ.node_inputs(node) {
-        // Combine the hash for each subport, ignoring their order.
-        let input_hash = circ
-            .linked_ports(node, input)
-            .map(|(pred_node, pred_port)| {
-                let pred_node_hash = state.node_hash(pred_node);
-                fxhash::hash64(&(pred_node_hash, pred_port, input))
-            })
-            .fold(0, |total, hash| hash ^ total);
-        input_hash.hash(&mut hasher);
+    let mut input_hashes = circ
+        .node_inputs(node)
+        .map(|input| {
+            // Combine the hash for each subport, ignoring their order.
+            circ.linked_outputs(node, input)
+                .map(|(pred_node, pred_port)| {
+                    let pred_node_hash = state.node_hash(pred_node);
+                    let pred_port = state.canonical_port(pred_node, pred_port);
+                    fxhash::hash64(&(pred_node_hash, pred_port))
+                })
+                .fold(0, |total, hash| hash ^ total)
+        })
+        .collect_vec();
+
+    // Order the qubit inputs of symmetric gates deterministically, so that
+    // exchanging their arguments does not change the hash.
+    let symmetric = Tk2Op::try_from(op).map_or(false, |op| op.is_symmetric());
+    if symmetric && input_hashes[1] < input_hashes[0] {
+        input_hashes.swap(0, 1);
+        state.swapped.insert(node);
+    }
+
+    for (input, input_hash) in input_hashes.into_iter().enumerate() {
+        (input, input_hash).hash(&mut hasher);
     }
     Ok(hasher.finish())
 }
 
+/// Auxiliary data for canonical circuit hashing.
+#[derive(Clone, Default, Debug)]
+struct CanonicalHashState {
+    /// Computed node hashes.
+    hashes: HashState,
+    /// The commuting runs ending at each qubit output port.
+    runs: FxHashMap<(Node, OutgoingPort), CommutingRun>,
+}
+
+impl CanonicalHashState {
+    /// The combined hash of the run ending at an output port.
+    ///
+    /// Ports that do not end a commuting run are hashed individually.
+    fn run_sum(&self, node: Node, port: OutgoingPort) -> u64 {
+        match self.runs.get(&(node, port)) {
+            Some(run) => run.sum,
+            None => fxhash::hash64(&(self.hashes.node_hash(node), port)),
+        }
+    }
+}
+
+/// A sequence of gates acting on a wire with the same Pauli commutation basis.
+#[derive(Clone, Copy, Debug)]
+struct CommutingRun {
+    /// The commutation basis of the gates in the run.
+    pauli: Pauli,
+    /// The hash of the wire entering the run.
+    key: u64,
+    /// The order-independent combination of the gate hashes in the run.
+    sum: u64,
+}
+
+/// Compute the canonical hash of a circuit command and register the
+/// commuting runs it belongs to.
+///
+/// See [`CircuitHash::canonical_hash`].
+fn canonical_hash_node(
+    circ: &impl HugrView,
+    node: Node,
+    state: &mut CanonicalHashState,
+) -> Result<(), HashError> {
+    let op = circ.get_optype(node);
+    let paulis = Tk2Op::try_from(op)
+        .map(|op| op.qubit_commutation())
+        .unwrap_or_default();
+    let pauli_at = |index: usize| {
+        paulis
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map(|&(_, pauli)| pauli)
+    };
+    let mut hasher = FxHasher64::default();
+
+    // Hash the node children
+    if circ.children(node).count() > 0 {
+        let container: SiblingGraph = SiblingGraph::try_new(circ, node).unwrap();
+        container.canonical_hash()?.hash(&mut hasher);
+    }
+
+    // Hash the node operation
+    hashable_op(op).hash(&mut hasher);
+
+    // Hash each input wire, continuing the commuting run of the predecessor
+    // if it shares the same commutation basis.
+    let mut entering_runs: FxHashMap<usize, (u64, Option<CommutingRun>)> = FxHashMap::default();
+    for input in circ.node_inputs(node) {
+        let pauli = pauli_at(input.index());
+        let mut input_hash = 0;
+        let mut continued = None;
+        for (pred_node, pred_port) in circ.linked_ports(node, input) {
+            let pred_port = pred_port.as_outgoing().unwrap();
+            match state.runs.get(&(pred_node, pred_port)) {
+                Some(run) if Some(run.pauli) == pauli => {
+                    input_hash ^= run.key;
+                    continued = Some(*run);
+                }
+                _ => input_hash ^= state.run_sum(pred_node, pred_port),
+            }
+        }
+        entering_runs.insert(input.index(), (input_hash, continued));
+        (input, input_hash).hash(&mut hasher);
+    }
+    let hash = hasher.finish();
+    if state.hashes.set_hash(node, hash).is_some() {
+        panic!("Hash already set for node {node}");
+    }
+
+    // Register the runs on the qubit outputs.
+    for output in circ.node_outputs(node) {
+        let Some(pauli) = pauli_at(output.index()) else {
+            continue;
+        };
+        let Some(&(key, continued)) = entering_runs.get(&output.index()) else {
+            continue;
+        };
+        let gate_hash = fxhash::hash64(&(Some(hash), output));
+        let sum = match continued {
+            Some(run) => run.sum.wrapping_add(gate_hash),
+            None => gate_hash,
+        };
+        state
+            .runs
+            .insert((node, output), CommutingRun { pauli, key, sum });
+    }
+    Ok(())
+}
+
 /// Errors that can occur while hashing a hugr.
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum HashError {
@@ -136,15 +312,39 @@ pub enum HashError {
 
 #[cfg(test)]
 mod test {
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::QB_T;
+    use hugr::types::FunctionType;
     use hugr::Hugr;
     use tket_json_rs::circuit_json;
 
+    use crate::extension::REGISTRY;
     use crate::json::TKETDecode;
     use crate::utils::build_simple_circuit;
     use crate::Tk2Op;
 
     use super::*;
 
+    fn rz_const(angle: f64) -> Hugr {
+        let mut h = DFGBuilder::new(FunctionType::new(vec![QB_T], vec![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let angle = h.add_load_const(ConstF64::new(angle)).unwrap();
+        let q = h
+            .add_dataflow_op(Tk2Op::RzF64, [q, angle])
+            .unwrap()
+            .out_wire(0);
+        h.finish_hugr_with_outputs([q], &REGISTRY).unwrap()
+    }
+
+    #[test]
+    fn hash_float_constants() {
+        let hash = |angle: f64| rz_const(angle).circuit_hash().unwrap();
+        assert_eq!(hash(0.5), hash(0.5));
+        assert_ne!(hash(0.5), hash(0.25));
+        assert_eq!(hash(0.), hash(-0.));
+        assert_eq!(hash(f64::NAN), hash(f64::from_bits(0x7ff8_0000_0000_0001)));
+    }
+
     #[test]
     fn hash_equality() {
         let circ1 = build_simple_circuit(2, |circ| {
@@ -181,6 +381,69 @@ mod test {
         assert_ne!(hash1, hash3);
     }
 
+    #[test]
+    fn hash_symmetric_gates() {
+        let cz = |control, target| {
+            build_simple_circuit(2, |circ| {
+                circ.append(Tk2Op::H, [0])?;
+                circ.append(Tk2Op::CZ, [control, target])?;
+                circ.append(Tk2Op::T, [1])?;
+                Ok(())
+            })
+            .unwrap()
+        };
+        assert_eq!(cz(0, 1).circuit_hash(), cz(1, 0).circuit_hash());
+
+        // Asymmetric gates are not canonicalised.
+        let cx = |control, target| {
+            build_simple_circuit(2, |circ| {
+                circ.append(Tk2Op::CX, [control, target])?;
+                Ok(())
+            })
+            .unwrap()
+        };
+        assert_ne!(cx(0, 1).circuit_hash(), cx(1, 0).circuit_hash());
+    }
+
+    #[test]
+    fn canonical_hash_commutation() {
+        let z_cx = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::Z, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::X, [1])?;
+            Ok(())
+        })
+        .unwrap();
+        // Z commutes with the CX control, and X with its target.
+        let cx_z = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::X, [1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::Z, [0])?;
+            Ok(())
+        })
+        .unwrap();
+        // X does not commute with the CX control.
+        let x_cx = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::X, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::Z, [1])?;
+            Ok(())
+        })
+        .unwrap();
+        let cx_x = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::Z, [1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::X, [0])?;
+            Ok(())
+        })
+        .unwrap();
+
+        assert_ne!(z_cx.circuit_hash(), cx_z.circuit_hash());
+        assert_eq!(z_cx.canonical_hash(), cx_z.canonical_hash());
+        assert_ne!(x_cx.canonical_hash(), cx_x.canonical_hash());
+        assert_ne!(z_cx.canonical_hash(), x_cx.canonical_hash());
+    }
+
     #[test]
     fn hash_constants() {
         let c_str = r#"{"bits": [], "commands": [{"args": [["q", [0]]], "op": {"params": ["0.5"], "type": "Rz"}}], "created_qubits": [], "discarded_qubits": [], "implicit_permutation": [[["q", [0]], ["q", [0]]]], "phase": "0.0", "qubits": [["q", [0]]]}"#;
@@ -0,0 +1,132 @@
+//! Detection of circuits implementing the identity operation.
+
+use hugr::{HugrView, OutgoingPort};
+use itertools::Itertools;
+
+use super::tableau::CliffordTableau;
+use super::unitary::{Unitary, MAX_UNITARY_QUBITS};
+use super::Circuit;
+use crate::Tk2Op;
+
+/// Absolute tolerance used when comparing unitaries to the identity.
+const UNITARY_TOLERANCE: f64 = 1e-8;
+
+/// Returns `true` if the circuit implements the identity on all its qubits,
+/// up to a global phase.
+///
+/// Empty circuits are trivially the identity. Clifford circuits are checked
+/// using a stabiliser tableau, and small circuits with fixed gates (at most
+/// 8 qubits) by computing their unitary.
+///
+/// This check is conservative: circuits with parametric or non-unitary
+/// operations, or that are too large to simulate, are reported as not being
+/// the identity.
+pub fn is_identity(circ: &impl Circuit) -> bool {
+    let n_qubits = circ.qubit_count();
+    if !wires_preserved(circ, n_qubits) {
+        return false;
+    }
+
+    let Some(commands) = circ
+        .commands()
+        .map(|cmd| {
+            let op = Tk2Op::try_from(cmd.optype()).ok()?;
+            let qubits = cmd.linear_inputs().map(|(u, _, _)| u.index()).collect_vec();
+            // Only accept operations acting purely on qubits.
+            (cmd.input_count() == qubits.len()).then_some((op, qubits))
+        })
+        .collect::<Option<Vec<_>>>()
+    else {
+        return false;
+    };
+
+    if commands.is_empty() {
+        return true;
+    }
+
+    if commands
+        .iter()
+        .all(|(op, _)| CliffordTableau::is_clifford(*op))
+    {
+        let mut tableau = CliffordTableau::new(n_qubits);
+        return commands.iter().all(|(op, qbs)| tableau.apply_op(*op, qbs))
+            && tableau.is_identity();
+    }
+
+    if n_qubits <= MAX_UNITARY_QUBITS {
+        let mut unitary = Unitary::identity(n_qubits);
+        return commands.iter().all(|(op, qbs)| unitary.apply_op(*op, qbs))
+            && unitary.is_identity_up_to_phase(UNITARY_TOLERANCE);
+    }
+
+    false
+}
+
+/// Check that the circuit only has qubit inputs and outputs, and that each
+/// qubit input is connected to the output at the same position.
+fn wires_preserved(circ: &impl Circuit, n_qubits: usize) -> bool {
+    let sig = circ.circuit_signature();
+    if sig.input.len() != n_qubits || sig.output.len() != n_qubits {
+        return false;
+    }
+    let output = circ.output();
+    (0..n_qubits).all(|i| {
+        let mut node = circ.input();
+        let mut port = OutgoingPort::from(i);
+        loop {
+            let Ok((next, next_port)) = circ.linked_inputs(node, port).exactly_one() else {
+                return false;
+            };
+            if next == output {
+                return next_port.index() == i;
+            }
+            // Follow the qubit through the operation.
+            node = next;
+            port = OutgoingPort::from(next_port.index());
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::build_simple_circuit;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(vec![], true)]
+    #[case(vec![Tk2Op::H, Tk2Op::H], true)]
+    #[case(vec![Tk2Op::H, Tk2Op::X], false)]
+    #[case(vec![Tk2Op::T, Tk2Op::H, Tk2Op::H, Tk2Op::Tdg], true)]
+    #[case(vec![Tk2Op::T, Tk2Op::T], false)]
+    fn single_qubit(#[case] ops: Vec<Tk2Op>, #[case] expected: bool) {
+        let circ = build_simple_circuit(1, |circ| {
+            for op in ops {
+                circ.append(op, [0])?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(is_identity(&circ), expected);
+    }
+
+    #[test]
+    fn two_qubit() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::T, [1])?;
+            circ.append(Tk2Op::Tdg, [1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        assert!(is_identity(&circ));
+
+        let swap = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::SWAP, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        assert!(!is_identity(&swap));
+    }
+}
@@ -0,0 +1,172 @@
+//! Stabiliser tableau simulation of Clifford circuits.
+//!
+//! Uses the representation from Aaronson and Gottesman,
+//! "Improved Simulation of Stabilizer Circuits" (2004),
+//! <https://arxiv.org/abs/quant-ph/0406196>, dropping the scratch row.
+
+use crate::Tk2Op;
+
+/// A stabiliser tableau tracking the conjugation of the Pauli X and Z
+/// operators on each qubit by a Clifford circuit, up to global phase.
+///
+/// Rows `0..n` are the destabilisers (images of `X_i`), rows `n..2n` are the
+/// stabilisers (images of `Z_i`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CliffordTableau {
+    n_qubits: usize,
+    /// X components of each row.
+    x: Vec<Vec<bool>>,
+    /// Z components of each row.
+    z: Vec<Vec<bool>>,
+    /// Sign of each row.
+    r: Vec<bool>,
+}
+
+impl CliffordTableau {
+    /// The tableau of the identity circuit on `n_qubits` qubits.
+    pub fn new(n_qubits: usize) -> Self {
+        let mut x = vec![vec![false; n_qubits]; 2 * n_qubits];
+        let mut z = vec![vec![false; n_qubits]; 2 * n_qubits];
+        for i in 0..n_qubits {
+            x[i][i] = true;
+            z[n_qubits + i][i] = true;
+        }
+        Self {
+            n_qubits,
+            x,
+            z,
+            r: vec![false; 2 * n_qubits],
+        }
+    }
+
+    /// Returns `true` if the tableau corresponds to the identity operation.
+    pub fn is_identity(&self) -> bool {
+        *self == Self::new(self.n_qubits)
+    }
+
+    /// Whether an operation is a Clifford gate that can be applied to the
+    /// tableau.
+    pub fn is_clifford(op: Tk2Op) -> bool {
+        matches!(
+            op,
+            Tk2Op::H
+                | Tk2Op::S
+                | Tk2Op::Sdg
+                | Tk2Op::X
+                | Tk2Op::Y
+                | Tk2Op::Z
+                | Tk2Op::CX
+                | Tk2Op::CZ
+                | Tk2Op::CY
+                | Tk2Op::SWAP
+                | Tk2Op::ZZMax
+        )
+    }
+
+    /// Apply a Clifford gate to the given qubits.
+    ///
+    /// Returns `false` if the operation is not a supported Clifford gate, in
+    /// which case the tableau is left unchanged.
+    pub fn apply_op(&mut self, op: Tk2Op, qubits: &[usize]) -> bool {
+        match (op, qubits) {
+            (Tk2Op::H, &[a]) => self.h(a),
+            (Tk2Op::S, &[a]) => self.s(a),
+            (Tk2Op::Sdg, &[a]) => {
+                self.s(a);
+                self.s(a);
+                self.s(a);
+            }
+            (Tk2Op::Z, &[a]) => {
+                self.s(a);
+                self.s(a);
+            }
+            (Tk2Op::X, &[a]) => {
+                self.h(a);
+                self.s(a);
+                self.s(a);
+                self.h(a);
+            }
+            (Tk2Op::Y, &[a]) => {
+                // Y = iXZ
+                self.s(a);
+                self.s(a);
+                self.h(a);
+                self.s(a);
+                self.s(a);
+                self.h(a);
+            }
+            (Tk2Op::CX, &[a, b]) => self.cx(a, b),
+            (Tk2Op::CZ, &[a, b]) => {
+                self.h(b);
+                self.cx(a, b);
+                self.h(b);
+            }
+            (Tk2Op::CY, &[a, b]) => {
+                // CY = S_b CX S_b^†
+                self.apply_op(Tk2Op::Sdg, &[b]);
+                self.cx(a, b);
+                self.s(b);
+            }
+            (Tk2Op::SWAP, &[a, b]) => {
+                self.cx(a, b);
+                self.cx(b, a);
+                self.cx(a, b);
+            }
+            (Tk2Op::ZZMax, &[a, b]) => {
+                self.cx(a, b);
+                self.s(b);
+                self.cx(a, b);
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    fn h(&mut self, a: usize) {
+        for i in 0..2 * self.n_qubits {
+            self.r[i] ^= self.x[i][a] & self.z[i][a];
+            std::mem::swap(&mut self.x[i][a], &mut self.z[i][a]);
+        }
+    }
+
+    fn s(&mut self, a: usize) {
+        for i in 0..2 * self.n_qubits {
+            self.r[i] ^= self.x[i][a] & self.z[i][a];
+            self.z[i][a] ^= self.x[i][a];
+        }
+    }
+
+    fn cx(&mut self, a: usize, b: usize) {
+        for i in 0..2 * self.n_qubits {
+            let (xa, za, xb, zb) = (self.x[i][a], self.z[i][a], self.x[i][b], self.z[i][b]);
+            self.r[i] ^= xa & zb & !(xb ^ za);
+            self.x[i][b] = xb ^ xa;
+            self.z[i][a] = za ^ zb;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(&[(Tk2Op::H, &[0]), (Tk2Op::H, &[0])], true)]
+    #[case(&[(Tk2Op::S, &[0]), (Tk2Op::Sdg, &[0])], true)]
+    #[case(&[(Tk2Op::X, &[0]), (Tk2Op::Y, &[0]), (Tk2Op::Z, &[0])], true)]
+    #[case(&[(Tk2Op::Z, &[0])], false)]
+    #[case(&[(Tk2Op::S, &[0]), (Tk2Op::S, &[0])], false)]
+    #[case(&[(Tk2Op::CX, &[0, 1]), (Tk2Op::CX, &[0, 1])], true)]
+    #[case(&[(Tk2Op::CY, &[0, 1]), (Tk2Op::CY, &[0, 1])], true)]
+    #[case(&[(Tk2Op::SWAP, &[0, 1]), (Tk2Op::SWAP, &[0, 1])], true)]
+    #[case(&[(Tk2Op::CZ, &[0, 1]), (Tk2Op::H, &[1]), (Tk2Op::CX, &[0, 1]), (Tk2Op::H, &[1])], true)]
+    #[case(&[(Tk2Op::CX, &[0, 1]), (Tk2Op::CX, &[1, 0])], false)]
+    fn tableau_identity(#[case] ops: &[(Tk2Op, &[usize])], #[case] expected: bool) {
+        let mut tab = CliffordTableau::new(2);
+        for &(op, qbs) in ops {
+            assert!(tab.apply_op(op, qbs));
+        }
+        assert_eq!(tab.is_identity(), expected);
+    }
+}
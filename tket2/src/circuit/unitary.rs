@@ -0,0 +1,267 @@
+//! Dense unitary simulation of small circuits.
+
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use num_complex::Complex64;
+
+use crate::Tk2Op;
+
+/// The maximum number of qubits for which we compute dense unitaries.
+pub(crate) const MAX_UNITARY_QUBITS: usize = 8;
+
+/// Complex constants, for building gate matrices.
+pub(crate) const ZERO: Complex64 = Complex64::new(0., 0.);
+pub(crate) const ONE: Complex64 = Complex64::new(1., 0.);
+pub(crate) const I: Complex64 = Complex64::new(0., 1.);
+
+/// A dense unitary matrix acting on `n_qubits` qubits.
+///
+/// Basis states are indexed by integers, with the `i`-th qubit corresponding
+/// to the `i`-th bit of the index.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Unitary {
+    n_qubits: usize,
+    /// Column-major matrix entries.
+    data: Vec<Complex64>,
+}
+
+impl Unitary {
+    /// The identity on `n_qubits` qubits.
+    pub fn identity(n_qubits: usize) -> Self {
+        let dim = 1 << n_qubits;
+        let mut data = vec![ZERO; dim * dim];
+        for i in 0..dim {
+            data[i * dim + i] = ONE;
+        }
+        Self { n_qubits, data }
+    }
+
+    /// The dimension of the matrix.
+    pub fn dim(&self) -> usize {
+        1 << self.n_qubits
+    }
+
+    /// The matrix entry at `(row, col)`.
+    pub fn get(&self, row: usize, col: usize) -> Complex64 {
+        self.data[col * self.dim() + row]
+    }
+
+    /// Returns `true` if the matrix is `e^{iφ}·I` for some global phase `φ`,
+    /// up to an absolute tolerance on each entry.
+    pub fn is_identity_up_to_phase(&self, tolerance: f64) -> bool {
+        let phase = self.get(0, 0);
+        if (phase.norm() - 1.).abs() > tolerance {
+            return false;
+        }
+        let dim = self.dim();
+        (0..dim).all(|col| {
+            (0..dim).all(|row| {
+                let expected = if row == col { phase } else { ZERO };
+                let entry = self.get(row, col);
+                (entry.re - expected.re).abs() <= tolerance
+                    && (entry.im - expected.im).abs() <= tolerance
+            })
+        })
+    }
+
+    /// Left-multiply the unitary by a gate acting on the given qubits.
+    ///
+    /// Returns `false` if the operation does not have a fixed unitary, in
+    /// which case the matrix is left unchanged.
+    pub fn apply_op(&mut self, op: Tk2Op, qubits: &[usize]) -> bool {
+        let Some(gate) = gate_matrix(op) else {
+            return false;
+        };
+        self.apply_matrix(&gate, qubits)
+    }
+
+    /// Left-multiply the unitary by a row-major gate matrix acting on the
+    /// given qubits.
+    ///
+    /// Returns `false` if the matrix size does not match the number of qubits,
+    /// in which case the unitary is left unchanged.
+    pub fn apply_matrix(&mut self, gate: &[Complex64], qubits: &[usize]) -> bool {
+        if gate.len() != 1 << (2 * qubits.len()) {
+            return false;
+        }
+        let dim = self.dim();
+        let gate_dim = 1 << qubits.len();
+        for col in self.data.chunks_mut(dim) {
+            // Iterate over the basis states with all the target qubits set to 0.
+            for base in 0..dim {
+                if qubits.iter().any(|&q| base & (1 << q) != 0) {
+                    continue;
+                }
+                let index = |k: usize| {
+                    qubits
+                        .iter()
+                        .enumerate()
+                        .fold(base, |idx, (i, &q)| idx | (((k >> i) & 1) << q))
+                };
+                let amps: Vec<Complex64> = (0..gate_dim).map(|k| col[index(k)]).collect();
+                for row in 0..gate_dim {
+                    col[index(row)] = (0..gate_dim)
+                        .map(|k| gate[row * gate_dim + k] * amps[k])
+                        .fold(ZERO, |acc, x| acc + x);
+                }
+            }
+        }
+        true
+    }
+}
+
+/// The row-major matrix of a gate with a fixed unitary.
+///
+/// For multi-qubit gates, the `i`-th qubit argument corresponds to the `i`-th
+/// bit of the row and column indices.
+fn gate_matrix(op: Tk2Op) -> Option<Vec<Complex64>> {
+    let c = Complex64::new;
+    let (o, l, i) = (ZERO, ONE, I);
+    let h = FRAC_1_SQRT_2;
+    let t = Complex64::from_polar(1., std::f64::consts::FRAC_PI_4);
+    Some(match op {
+        Tk2Op::H => vec![c(h, 0.), c(h, 0.), c(h, 0.), c(-h, 0.)],
+        Tk2Op::X => vec![o, l, l, o],
+        Tk2Op::Y => vec![o, -i, i, o],
+        Tk2Op::Z => vec![l, o, o, -l],
+        Tk2Op::S => vec![l, o, o, i],
+        Tk2Op::Sdg => vec![l, o, o, -i],
+        Tk2Op::T => vec![l, o, o, t],
+        Tk2Op::Tdg => vec![l, o, o, t.conj()],
+        Tk2Op::CX => controlled([o, l, l, o]),
+        Tk2Op::CY => controlled([o, -i, i, o]),
+        Tk2Op::CZ => controlled([l, o, o, -l]),
+        Tk2Op::SWAP => vec![
+            l, o, o, o, //
+            o, o, l, o, //
+            o, l, o, o, //
+            o, o, o, l, //
+        ],
+        Tk2Op::ZZMax => {
+            let (a, b) = (t.conj(), t);
+            vec![
+                a, o, o, o, //
+                o, b, o, o, //
+                o, o, b, o, //
+                o, o, o, a, //
+            ]
+        }
+        _ => return None,
+    })
+}
+
+/// The row-major matrix of an Rz rotation, with the angle in half-turns.
+#[cfg(test)]
+pub(crate) fn rz_matrix(angle: f64) -> Vec<Complex64> {
+    let theta = angle * std::f64::consts::FRAC_PI_2;
+    let (o, phase) = (ZERO, Complex64::from_polar(1., theta));
+    vec![phase.conj(), o, o, phase]
+}
+
+/// The row-major matrix of an Rx rotation, with the angle in half-turns.
+#[cfg(test)]
+pub(crate) fn rx_matrix(angle: f64) -> Vec<Complex64> {
+    let theta = angle * std::f64::consts::FRAC_PI_2;
+    let (cos, sin) = (
+        Complex64::new(theta.cos(), 0.),
+        Complex64::new(0., -theta.sin()),
+    );
+    vec![cos, sin, sin, cos]
+}
+
+/// The row-major matrix of a controlled single-qubit gate.
+///
+/// The control qubit corresponds to bit 0 of the indices, and the target to
+/// bit 1.
+fn controlled(u: [Complex64; 4]) -> Vec<Complex64> {
+    let mut m = vec![ZERO; 16];
+    m[0] = ONE;
+    m[2 * 4 + 2] = ONE;
+    m[4 + 1] = u[0];
+    m[4 + 3] = u[1];
+    m[3 * 4 + 1] = u[2];
+    m[3 * 4 + 3] = u[3];
+    m
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+    use rstest::rstest;
+    use strum::IntoEnumIterator;
+
+    #[rstest]
+    #[case(&[(Tk2Op::H, &[0]), (Tk2Op::H, &[0])], true)]
+    #[case(&[(Tk2Op::T, &[0]), (Tk2Op::Tdg, &[0])], true)]
+    #[case(&[(Tk2Op::T, &[0]), (Tk2Op::T, &[0]), (Tk2Op::Sdg, &[0])], true)]
+    #[case(&[(Tk2Op::H, &[0]), (Tk2Op::X, &[0])], false)]
+    #[case(&[(Tk2Op::CY, &[0, 1]), (Tk2Op::CY, &[0, 1])], true)]
+    #[case(&[(Tk2Op::CX, &[1, 0]), (Tk2Op::CX, &[1, 0])], true)]
+    #[case(&[(Tk2Op::CX, &[0, 1]), (Tk2Op::CX, &[1, 0])], false)]
+    #[case(&[(Tk2Op::ZZMax, &[0, 1]), (Tk2Op::ZZMax, &[0, 1]), (Tk2Op::Z, &[0]), (Tk2Op::Z, &[1])], true)]
+    fn unitary_identity(#[case] ops: &[(Tk2Op, &[usize])], #[case] expected: bool) {
+        let mut u = Unitary::identity(2);
+        for &(op, qbs) in ops {
+            assert!(u.apply_op(op, qbs));
+        }
+        assert_eq!(u.is_identity_up_to_phase(1e-10), expected);
+    }
+
+    /// Whether conjugating the single-qubit Paulis by the gate results in
+    /// Pauli strings, up to phase.
+    ///
+    /// Returns `None` if the operation does not have a fixed unitary.
+    fn maps_paulis_to_paulis(op: Tk2Op) -> Option<bool> {
+        let gate = gate_matrix(op)?;
+        let n = op.n_qubits();
+        let dim = 1 << n;
+        let dagger = (0..dim * dim)
+            .map(|k| gate[(k % dim) * dim + k / dim].conj())
+            .collect_vec();
+        let qubits = (0..n).collect_vec();
+        let pauli_strings = (0..n)
+            .map(|_| [None, Some(Tk2Op::X), Some(Tk2Op::Y), Some(Tk2Op::Z)])
+            .multi_cartesian_product()
+            .collect_vec();
+        let conjugates_to_pauli = |pauli: Tk2Op, q: usize| {
+            pauli_strings.iter().any(|string| {
+                let mut u = Unitary::identity(n);
+                u.apply_matrix(&dagger, &qubits);
+                u.apply_op(pauli, &[q]);
+                u.apply_matrix(&gate, &qubits);
+                for (q, p) in string.iter().enumerate() {
+                    if let Some(p) = p {
+                        u.apply_op(*p, &[q]);
+                    }
+                }
+                u.is_identity_up_to_phase(1e-10)
+            })
+        };
+        Some(
+            qubits
+                .iter()
+                .all(|&q| conjugates_to_pauli(Tk2Op::X, q) && conjugates_to_pauli(Tk2Op::Z, q)),
+        )
+    }
+
+    #[test]
+    fn clifford_gates() {
+        for op in Tk2Op::iter() {
+            // Operations without a fixed unitary are never Clifford gates.
+            let clifford = maps_paulis_to_paulis(op).unwrap_or(false);
+            assert_eq!(op.is_clifford(), clifford, "{op:?}");
+        }
+    }
+
+    #[test]
+    fn pauli_gates() {
+        for op in Tk2Op::iter().filter(|op| op.n_qubits() == 1) {
+            let is_pauli = [Tk2Op::X, Tk2Op::Y, Tk2Op::Z].into_iter().any(|p| {
+                let mut u = Unitary::identity(1);
+                u.apply_op(op, &[0]) && u.apply_op(p, &[0]) && u.is_identity_up_to_phase(1e-10)
+            });
+            assert_eq!(op.is_pauli(), is_pauli, "{op:?}");
+        }
+    }
+}
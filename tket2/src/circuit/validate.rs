@@ -0,0 +1,140 @@
+//! Well-formedness checks for tket2 circuits.
+
+use hugr::extension::prelude::QB_T;
+use hugr::ops::{OpName, OpType};
+use hugr::std_extensions::arithmetic::float_types::FLOAT64_TYPE;
+use hugr::types::Type;
+use hugr::{HugrView, Node, Port};
+use smol_str::SmolStr;
+use thiserror::Error;
+
+use super::Circuit;
+use crate::extension::LINEAR_BIT;
+use crate::ops::{is_barrier, match_symb_const_op};
+use crate::Tk2Op;
+
+/// Check that a circuit only contains tket2 operations, and that its linear
+/// wires are well formed.
+///
+/// The following conditions are checked:
+///  - The circuit's inputs and outputs are qubits, linear bits, or floats.
+///  - Every operation is either a [`Tk2Op`], a barrier, a symbolic constant,
+///    or one of the HUGR input, output, and constant operations.
+///  - Every linear port is connected to exactly one other port, so that no
+///    qubit is dropped or duplicated.
+pub fn validate_tk2_circuit(circ: &impl Circuit) -> Result<(), CircuitValidationError> {
+    let sig = circ.circuit_signature();
+    if let Some(ty) = sig
+        .input
+        .iter()
+        .chain(sig.output.iter())
+        .find(|ty| !is_boundary_type(ty))
+    {
+        return Err(CircuitValidationError::InvalidBoundaryType(ty.clone()));
+    }
+
+    for node in circ.children(circ.root()) {
+        let optype = circ.get_optype(node);
+        if !is_allowed_op(optype) {
+            return Err(CircuitValidationError::UnsupportedOp {
+                op: optype.name(),
+                node,
+            });
+        }
+        for port in circ.all_node_ports(node) {
+            if !optype.port_kind(port).is_some_and(|k| k.is_linear()) {
+                continue;
+            }
+            match circ.linked_ports(node, port).count() {
+                1 => {}
+                0 => return Err(CircuitValidationError::DroppedLinearWire { node, port }),
+                _ => return Err(CircuitValidationError::DuplicatedLinearWire { node, port }),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Whether a type is allowed in the circuit's signature.
+fn is_boundary_type(ty: &Type) -> bool {
+    ty == &QB_T || ty == &*LINEAR_BIT || ty == &FLOAT64_TYPE
+}
+
+/// Whether an operation is allowed in a tket2 circuit.
+fn is_allowed_op(op: &OpType) -> bool {
+    matches!(
+        op,
+        OpType::Input(_) | OpType::Output(_) | OpType::Const(_) | OpType::LoadConstant(_)
+    ) || Tk2Op::try_from(op).is_ok()
+        || is_barrier(op)
+        || match_symb_const_op(op).is_some()
+}
+
+/// Error type for [`validate_tk2_circuit`].
+#[derive(Debug, Clone, PartialEq, Error)]
+#[non_exhaustive]
+pub enum CircuitValidationError {
+    /// The circuit's signature contains an unsupported type.
+    #[error("Unsupported type {0:?} in the circuit signature.")]
+    InvalidBoundaryType(Type),
+    /// The circuit contains an operation that is not a tket2 operation.
+    #[error("Unsupported operation {op} at node {node:?}.")]
+    UnsupportedOp {
+        /// The name of the operation.
+        op: SmolStr,
+        /// The node of the operation.
+        node: Node,
+    },
+    /// A linear port is not connected.
+    #[error("The linear port {port:?} of node {node:?} is not connected.")]
+    DroppedLinearWire {
+        /// The node with the disconnected port.
+        node: Node,
+        /// The disconnected port.
+        port: Port,
+    },
+    /// A linear port is connected to more than one port.
+    #[error("The linear port {port:?} of node {node:?} has multiple connections.")]
+    DuplicatedLinearWire {
+        /// The node with the duplicated port.
+        node: Node,
+        /// The duplicated port.
+        port: Port,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::test::t2_bell_circuit;
+    use crate::utils::build_simple_circuit;
+    use hugr::hugr::hugrmut::HugrMut;
+    use hugr::{Hugr, OutgoingPort};
+    use rstest::rstest;
+
+    #[rstest]
+    fn valid_bell_circuit(t2_bell_circuit: Hugr) {
+        assert_eq!(validate_tk2_circuit(&t2_bell_circuit), Ok(()));
+    }
+
+    #[test]
+    fn dangling_qubit() {
+        let mut circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        let h = circ.commands().next().unwrap().node();
+        let out_port: Port = OutgoingPort::from(0).into();
+        circ.disconnect(h, out_port).unwrap();
+
+        assert_eq!(
+            validate_tk2_circuit(&circ),
+            Err(CircuitValidationError::DroppedLinearWire {
+                node: h,
+                port: out_port
+            })
+        );
+    }
+}
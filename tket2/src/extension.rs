@@ -4,11 +4,14 @@
 
 use super::json::op::JsonOp;
 use crate::Tk2Op;
-use hugr::extension::prelude::PRELUDE;
+use hugr::extension::prelude::{PRELUDE, QB_T};
 use hugr::extension::simple_op::MakeOpDef;
-use hugr::extension::{CustomSignatureFunc, ExtensionId, ExtensionRegistry, SignatureError};
+use hugr::extension::{
+    CustomSignatureFunc, ExtensionId, ExtensionRegistry, SignatureError, SignatureFromArgs,
+};
 use hugr::hugr::IdentList;
 use hugr::ops::custom::{ExternalOp, OpaqueOp};
+use hugr::std_extensions::arithmetic::float_ops::EXTENSION as FLOAT_OPS_EXTENSION;
 use hugr::std_extensions::arithmetic::float_types::{EXTENSION as FLOAT_EXTENSION, FLOAT64_TYPE};
 use hugr::types::type_param::{CustomTypeArg, TypeArg, TypeParam};
 use hugr::types::{CustomType, FunctionType, PolyFuncType, Type, TypeBound};
@@ -62,12 +65,14 @@ pub static ref LINEAR_BIT: Type = {
         .unwrap())
     };
 
-/// Extension registry including the prelude, TKET1 and Tk2Ops extensions.
+/// Extension registry including the prelude, TKET1, Tk2Ops and floating point
+/// extensions.
 pub static ref REGISTRY: ExtensionRegistry = ExtensionRegistry::try_new([
     TKET1_EXTENSION.clone(),
     PRELUDE.clone(),
     TKET2_EXTENSION.clone(),
     FLOAT_EXTENSION.clone(),
+    FLOAT_OPS_EXTENSION.clone(),
 ]).unwrap();
 
 
@@ -150,6 +155,9 @@ pub const SYM_EXPR_NAME: SmolStr = SmolStr::new_inline("SymExpr");
 /// The name of the symbolic expression opaque type arg.
 pub const SYM_OP_ID: SmolStr = SmolStr::new_inline("symbolic_float");
 
+/// The name of the barrier operation.
+pub const BARRIER_OP_ID: SmolStr = SmolStr::new_inline("Barrier");
+
 lazy_static! {
 /// The type of the symbolic expression opaque type arg.
 pub static ref SYM_EXPR_T: CustomType =
@@ -176,7 +184,31 @@ pub static ref TKET2_EXTENSION: Extension = {
     )
     .unwrap();
 
+    e.add_op(
+        BARRIER_OP_ID,
+        "An ordering fence over a number of qubits, acting as the identity.".to_string(),
+        BarrierSignature([TypeParam::max_nat()]),
+    )
+    .unwrap();
+
     angle::add_to_extension(&mut e);
     e
 };
 }
+
+/// The signature of a barrier, parametrised by its number of qubits.
+struct BarrierSignature([TypeParam; 1]);
+
+impl SignatureFromArgs for BarrierSignature {
+    fn compute_signature(&self, arg_values: &[TypeArg]) -> Result<PolyFuncType, SignatureError> {
+        let [TypeArg::BoundedNat { n }] = arg_values else {
+            // This should have already been checked.
+            panic!("Wrong number of arguments");
+        };
+        Ok(FunctionType::new_endo(vec![QB_T; *n as usize]).into())
+    }
+
+    fn static_params(&self) -> &[TypeParam] {
+        &self.0
+    }
+}
@@ -3,29 +3,37 @@
 mod decoder;
 mod encoder;
 pub mod op;
+pub mod param;
+mod stream;
 
 #[cfg(test)]
 mod tests;
 
 use hugr::CircuitUnit;
 
+use std::collections::BTreeMap;
 use std::path::Path;
 use std::{fs, io};
 
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::hugr::NodeMetadata;
 use hugr::ops::OpType;
 use hugr::std_extensions::arithmetic::float_types::{ConstF64, FLOAT64_TYPE};
 use hugr::values::Value;
-use hugr::Hugr;
+use hugr::{Hugr, HugrView, Node};
 
+use serde::Deserializer;
 use stringreader::StringReader;
 use thiserror::Error;
-use tket_json_rs::circuit_json::SerialCircuit;
+use tket_json_rs::circuit_json::{Register, SerialCircuit};
 use tket_json_rs::optype::OpType as JsonOpType;
 
+use crate::circuit::angle::AngleValue;
 use crate::circuit::Circuit;
 
 use self::decoder::JsonDecoder;
 use self::encoder::JsonEncoder;
+use self::param::LinearParam;
 
 /// Prefix used for storing metadata in the hugr nodes.
 pub const METADATA_PREFIX: &str = "TKET1_JSON";
@@ -34,9 +42,20 @@ const METADATA_PHASE: &str = "TKET1_JSON.phase";
 /// The implicit permutation of qubits.
 const METADATA_IMPLICIT_PERM: &str = "TKET1_JSON.implicit_permutation";
 /// Explicit names for the input qubit registers.
-const METADATA_Q_REGISTERS: &str = "TKET1_JSON.qubit_registers";
+pub(crate) const METADATA_Q_REGISTERS: &str = "TKET1_JSON.qubit_registers";
 /// Explicit names for the input bit registers.
-const METADATA_B_REGISTERS: &str = "TKET1_JSON.bit_registers";
+pub(crate) const METADATA_B_REGISTERS: &str = "TKET1_JSON.bit_registers";
+/// The TKET1 operation group of a command, stored in the command's node.
+const METADATA_OPGROUP: &str = "TKET1_JSON.opgroup";
+/// The TKET1 register of each input wire, stored in the input node.
+const METADATA_INPUT_REGISTERS: &str = "TKET1_JSON.input_registers";
+/// The exact rational value of a constant parameter, stored in the constant's node.
+const METADATA_RATIONAL: &str = "TKET1_JSON.rational";
+/// The original expression of a constant parameter folded from a non-literal
+/// expression, such as `"pi/2"`, stored in the constant's node.
+const METADATA_PARAM_EXPR: &str = "TKET1_JSON.param_expr";
+/// The parsed form of a symbolic parameter, stored in the symbolic operation's node.
+const METADATA_LINEAR_PARAM: &str = "TKET1_JSON.linear_param";
 
 /// A JSON-serialized circuit that can be converted to a [`Hugr`].
 pub trait TKETDecode: Sized {
@@ -105,13 +124,14 @@ pub enum OpConvertError {
 /// Load a TKET1 circuit from a JSON file.
 pub fn load_tk1_json_file(path: impl AsRef<Path>) -> Result<Hugr, TK1ConvertError> {
     let file = fs::File::open(path)?;
-    let reader = io::BufReader::new(file);
-    load_tk1_json_reader(reader)
+    load_tk1_json_reader(file)
 }
 
 /// Load a TKET1 circuit from a JSON reader.
+///
+/// The reader is buffered internally.
 pub fn load_tk1_json_reader(json: impl io::Read) -> Result<Hugr, TK1ConvertError> {
-    let ser: SerialCircuit = serde_json::from_reader(json)?;
+    let ser: SerialCircuit = serde_json::from_reader(io::BufReader::new(json))?;
     Ok(ser.decode()?)
 }
 
@@ -121,6 +141,22 @@ pub fn load_tk1_json_str(json: &str) -> Result<Hugr, TK1ConvertError> {
     load_tk1_json_reader(reader)
 }
 
+/// Load a TKET1 circuit from a JSON reader, decoding its commands as they
+/// are read.
+///
+/// Unlike [`load_tk1_json_reader`], this does not hold the full list of
+/// commands in memory when the qubit and bit registers precede the commands
+/// in the JSON object. Otherwise, the commands are buffered until the
+/// registers have been read.
+///
+/// The reader is buffered internally.
+pub fn load_tk1_json_stream(json: impl io::Read) -> Result<Hugr, TK1ConvertError> {
+    let mut deserializer = serde_json::Deserializer::from_reader(io::BufReader::new(json));
+    let hugr = deserializer.deserialize_map(stream::CircuitVisitor)?;
+    deserializer.end()?;
+    Ok(hugr)
+}
+
 /// Save a circuit to file in TK1 JSON format.
 pub fn save_tk1_json_file(
     circ: &impl Circuit,
@@ -146,6 +182,16 @@ pub fn save_tk1_json_str(circ: &impl Circuit) -> Result<String, TK1ConvertError>
     String::from_utf8(bytes).map_err(|_| TK1ConvertError::InvalidJson)
 }
 
+/// Returns the TKET1 register associated to each input wire of a circuit
+/// decoded from TKET1, indexed by the input port offset.
+///
+/// The mapping is stored in the metadata of the circuit's input node, and
+/// is preserved by any operation that does not replace the input node.
+pub fn input_registers(circ: &impl Circuit) -> Option<BTreeMap<usize, Register>> {
+    let regs = circ.get_metadata(circ.input(), METADATA_INPUT_REGISTERS)?;
+    serde_json::from_value(regs.clone()).ok()
+}
+
 /// Error type for conversion between `Op` and `OpType`.
 #[derive(Debug, Error)]
 pub enum TK1ConvertError {
@@ -188,22 +234,86 @@ impl From<OpConvertError> for TK1ConvertError {
     }
 }
 
-#[inline]
-fn parse_val(n: &str) -> Option<f64> {
-    n.parse::<f64>().ok()
-}
 /// Try to interpret a TKET1 parameter as a constant value.
+///
+/// See [`param::parse_param`] for the supported expressions.
 #[inline]
 fn try_param_to_constant(param: &str) -> Option<Value> {
-    if let Some(f) = parse_val(param) {
-        Some(ConstF64::new(f).into())
-    } else if param.split('/').count() == 2 {
-        // TODO: Use the rational types from `Hugr::extensions::rotation`
-        let (n, d) = param.split_once('/').unwrap();
-        let n = parse_val(n)?;
-        let d = parse_val(d)?;
-        Some(ConstF64::new(n / d).into())
-    } else {
-        None
+    let value = param::parse_param(param)?.as_const()?;
+    Some(ConstF64::new(value).into())
+}
+
+/// Returns the angle defined by a constant node, in half-turns.
+///
+/// Constants decoded from exact rational TKET1 parameters, such as `"1/3"`,
+/// return an [`AngleValue::Rational`]. Other floating point constants return
+/// an [`AngleValue::F64`]. Returns `None` if the node is not a floating point
+/// constant.
+pub fn constant_angle(circ: &impl Circuit, node: Node) -> Option<AngleValue> {
+    let OpType::Const(const_op) = circ.get_optype(node) else {
+        return None;
+    };
+    let Value::Extension { c: (val,) } = const_op.value() else {
+        return None;
+    };
+    let f = val.downcast_ref::<ConstF64>()?;
+    let rational = circ
+        .get_metadata(node, METADATA_RATIONAL)
+        .and_then(|r| r.as_str())
+        .and_then(param::parse_rational);
+    Some(match rational {
+        Some(r) => AngleValue::Rational(r),
+        None => AngleValue::F64(f.value()),
+    })
+}
+
+/// Returns the parsed form of a symbolic TKET1 parameter, such as `"2*alpha"`.
+///
+/// Returns `None` if the node is not a symbolic parameter decoded from TKET1,
+/// or if its expression is not linear in its free symbols.
+pub fn symbolic_param(circ: &impl Circuit, node: Node) -> Option<LinearParam> {
+    let expr = circ.get_metadata(node, METADATA_LINEAR_PARAM)?;
+    serde_json::from_value(expr.clone()).ok()
+}
+
+/// Returns the global phase of a circuit decoded from TKET1, in half-turns.
+///
+/// The phase is stored in the metadata of the circuit's root. Passes that
+/// only preserve the circuit up to a global phase, such as
+/// [`crate::passes::rebase_to_nam`], discard it, and
+/// [`crate::passes::invert_circuit`] negates it. Other transformations do not
+/// update it, so the value is stale after any other operation that changes
+/// the global phase.
+///
+/// Returns `None` if the circuit has no recorded phase, or if the phase is a
+/// symbolic expression. See [`param::parse_param`] for the supported
+/// expressions.
+pub fn circuit_global_phase(circ: &impl Circuit) -> Option<f64> {
+    let phase = circ.get_metadata(circ.root(), METADATA_PHASE)?.as_str()?;
+    param::parse_param(phase)?.as_const()
+}
+
+/// Discard the recorded global phase of a circuit, after a transformation
+/// that changed it.
+pub(crate) fn discard_global_phase(circ: &mut Hugr) {
+    let root = circ.root();
+    if circ.get_metadata(root, METADATA_PHASE).is_some() {
+        circ.set_metadata(root, METADATA_PHASE, NodeMetadata::Null);
     }
 }
+
+/// Record the negated global phase of `circ` in its inverse.
+pub(crate) fn invert_global_phase(circ: &Hugr, inverse: &mut Hugr) {
+    let Some(phase) = circ
+        .get_metadata(circ.root(), METADATA_PHASE)
+        .and_then(|p| p.as_str())
+    else {
+        return;
+    };
+    let negated = match param::parse_param(phase).and_then(|p| p.as_const()) {
+        Some(value) => (0. - value).to_string(),
+        None => format!("-({phase})"),
+    };
+    let root = inverse.root();
+    inverse.set_metadata(root, METADATA_PHASE, serde_json::json!(negated));
+}
@@ -1,13 +1,15 @@
 //! Intermediate structure for converting decoding [`SerialCircuit`]s into [`Hugr`]s.
 
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::hash::{Hash, Hasher};
 use std::mem;
 
 use hugr::builder::{CircuitBuilder, Container, DFGBuilder, Dataflow, DataflowHugr};
 use hugr::extension::prelude::QB_T;
+use hugr::hugr::hugrmut::HugrMut;
 
+use hugr::ops::handle::NodeHandle;
 use hugr::ops::Const;
 use hugr::std_extensions::arithmetic::float_types::FLOAT64_TYPE;
 use hugr::types::FunctionType;
@@ -19,7 +21,11 @@ use tket_json_rs::circuit_json;
 use tket_json_rs::circuit_json::SerialCircuit;
 
 use super::op::JsonOp;
-use super::{try_param_to_constant, METADATA_IMPLICIT_PERM, METADATA_PHASE};
+use super::param::{parse_param, parse_rational};
+use super::{
+    try_param_to_constant, METADATA_IMPLICIT_PERM, METADATA_INPUT_REGISTERS, METADATA_LINEAR_PARAM,
+    METADATA_OPGROUP, METADATA_PARAM_EXPR, METADATA_PHASE, METADATA_RATIONAL,
+};
 use crate::extension::{LINEAR_BIT, REGISTRY};
 use crate::json::{METADATA_B_REGISTERS, METADATA_Q_REGISTERS};
 use crate::symbolic_constant_op;
@@ -69,26 +75,45 @@ impl JsonDecoder {
         // .with_extension_delta(&ExtensionSet::singleton(&TKET1_EXTENSION_ID));
 
         let mut dfg = DFGBuilder::new(sig).unwrap();
-
-        // Metadata. The circuit requires "name", and we store other things that
-        // should pass through the serialization roundtrip.
-        dfg.set_metadata("name", json!(serialcirc.name));
-        dfg.set_metadata(METADATA_PHASE, json!(serialcirc.phase));
-        dfg.set_metadata(
-            METADATA_IMPLICIT_PERM,
-            json!(serialcirc.implicit_permutation),
-        );
         dfg.set_metadata(METADATA_Q_REGISTERS, json!(serialcirc.qubits));
         dfg.set_metadata(METADATA_B_REGISTERS, json!(serialcirc.bits));
 
+        // The register of each input wire, kept in the input node.
+        let input_registers: BTreeMap<usize, &circuit_json::Register> = serialcirc
+            .qubits
+            .iter()
+            .chain(serialcirc.bits.iter())
+            .enumerate()
+            .collect();
+        let [input, _] = dfg.io();
+        dfg.hugr_mut()
+            .set_metadata(input, METADATA_INPUT_REGISTERS, json!(input_registers));
+
         let dangling_wires = dfg.input_wires().collect::<Vec<_>>();
-        JsonDecoder {
+        let mut decoder = JsonDecoder {
             hugr: dfg,
             dangling_wires,
             register_wire: wire_map,
             num_qubits,
             num_bits,
-        }
+        };
+        decoder.set_circuit_metadata(serialcirc);
+        decoder
+    }
+
+    /// Store the name, global phase, and implicit permutation of a
+    /// [`SerialCircuit`] as metadata of the circuit.
+    ///
+    /// The circuit requires "name", and we store other things that should
+    /// pass through the serialization roundtrip.
+    pub fn set_circuit_metadata(&mut self, serialcirc: &SerialCircuit) {
+        self.hugr.set_metadata("name", json!(serialcirc.name));
+        self.hugr
+            .set_metadata(METADATA_PHASE, json!(serialcirc.phase));
+        self.hugr.set_metadata(
+            METADATA_IMPLICIT_PERM,
+            json!(serialcirc.implicit_permutation),
+        );
     }
 
     /// Finish building the [`Hugr`].
@@ -103,8 +128,7 @@ impl JsonDecoder {
     ///
     /// - [`Command`]: circuit_json::Command
     pub fn add_command(&mut self, command: circuit_json::Command) {
-        // TODO Store the command's `opgroup` in the metadata.
-        let circuit_json::Command { op, args, .. } = command;
+        let circuit_json::Command { op, args, opgroup } = command;
         let num_qubits = args
             .iter()
             .take_while(|&arg| self.reg_wire(arg, 0) < self.num_qubits)
@@ -113,6 +137,7 @@ impl JsonDecoder {
         let op = JsonOp::new_from_op(op, num_qubits, num_bits);
 
         let args: Vec<_> = args.into_iter().map(|reg| self.reg_wire(&reg, 0)).collect();
+        let first_arg = args.first().copied();
 
         let param_wires: Vec<Wire> = op
             .param_inputs()
@@ -127,6 +152,15 @@ impl JsonDecoder {
         self.with_circ_builder(|circ| {
             circ.append_and_consume(&op, append_wires).unwrap();
         });
+
+        // Store the opgroup in the metadata of the new node, which now
+        // produces the dangling wire of its first argument.
+        if let (Some(opgroup), Some(arg)) = (opgroup, first_arg) {
+            let node = self.dangling_wires[arg].node();
+            self.hugr
+                .hugr_mut()
+                .set_metadata(node, METADATA_OPGROUP, json!(opgroup));
+        }
     }
 
     /// Apply a function to the internal hugr builder viewed as a [`CircuitBuilder`].
@@ -139,6 +173,14 @@ impl JsonDecoder {
     /// Returns the wire carrying a parameter.
     ///
     /// If the parameter is a constant, a constant definition is added to the Hugr.
+    /// Exact rational parameters such as `"1/3"` are also recorded in the
+    /// constant's metadata, see [`super::constant_angle`]. Other expressions
+    /// that are not plain numbers, such as `"pi/2"`, are recorded as written so
+    /// that they are encoded back unchanged.
+    ///
+    /// Other parameters are stored as strings in a symbolic operation. If they
+    /// are linear combinations of free symbols, their parsed form is recorded
+    /// in the operation's metadata, see [`super::symbolic_param`].
     ///
     /// TODO: If the parameter is a variable, returns the corresponding wire from the input.
     fn create_param_wire(&mut self, param: &str) -> Wire {
@@ -146,12 +188,34 @@ impl JsonDecoder {
             Some(c) => {
                 let const_type = FLOAT64_TYPE;
                 let const_op = Const::new(c, const_type).unwrap();
-                self.hugr.add_load_const(const_op).unwrap()
+                let const_id = self.hugr.add_constant(const_op).unwrap();
+                if let Some(r) = parse_rational(param) {
+                    // Keep the exact value alongside the floating point constant.
+                    self.hugr.hugr_mut().set_metadata(
+                        const_id.node(),
+                        METADATA_RATIONAL,
+                        json!(r.to_string()),
+                    );
+                } else if param.trim().parse::<f64>().is_err() {
+                    // Keep the source of the folded expression.
+                    self.hugr.hugr_mut().set_metadata(
+                        const_id.node(),
+                        METADATA_PARAM_EXPR,
+                        json!(param),
+                    );
+                }
+                self.hugr.load_const(&const_id).unwrap()
             }
             None => {
                 // store string in custom op.
                 let symb_op = symbolic_constant_op(param);
                 let o = self.hugr.add_dataflow_op(symb_op, []).unwrap();
+                if let Some(expr) = parse_param(param) {
+                    // Keep the parsed expression alongside the opaque string.
+                    self.hugr
+                        .hugr_mut()
+                        .set_metadata(o.node(), METADATA_LINEAR_PARAM, json!(expr));
+                }
                 o.out_wire(0)
             }
         }
@@ -7,7 +7,7 @@ use hugr::extension::prelude::QB_T;
 use hugr::ops::{OpName, OpType};
 use hugr::std_extensions::arithmetic::float_types::ConstF64;
 use hugr::values::Value;
-use hugr::Wire;
+use hugr::{PortIndex, Wire};
 use itertools::{Either, Itertools};
 use tket_json_rs::circuit_json::{self, Permutation, Register, SerialCircuit};
 
@@ -19,8 +19,8 @@ use crate::Tk2Op;
 
 use super::op::JsonOp;
 use super::{
-    OpConvertError, METADATA_B_REGISTERS, METADATA_IMPLICIT_PERM, METADATA_PHASE,
-    METADATA_Q_REGISTERS,
+    input_registers, OpConvertError, METADATA_B_REGISTERS, METADATA_IMPLICIT_PERM,
+    METADATA_OPGROUP, METADATA_PARAM_EXPR, METADATA_PHASE, METADATA_Q_REGISTERS, METADATA_RATIONAL,
 };
 
 /// The state of an in-progress [`SerialCircuit`] being built from a [`Circuit`].
@@ -60,8 +60,12 @@ impl JsonEncoder {
         // Recover other parameters stored in the metadata
         // TODO: Check for invalid encoded metadata
         let root = circ.root();
-        if let Some(p) = circ.get_metadata(root, METADATA_PHASE) {
-            phase = p.as_str().unwrap().to_string();
+        if let Some(p) = circ
+            .get_metadata(root, METADATA_PHASE)
+            .and_then(|p| p.as_str())
+        {
+            // A discarded phase is encoded as zero.
+            phase = p.to_string();
         }
         if let Some(perm) = circ.get_metadata(root, METADATA_IMPLICIT_PERM) {
             implicit_permutation = serde_json::from_value(perm.clone()).unwrap();
@@ -84,14 +88,19 @@ impl JsonEncoder {
                 r
             })
         };
-        for (unit, _, ty) in circ.units() {
+        // The registers stored in the input node take precedence, as they
+        // are attached to specific wires.
+        let input_regs = input_registers(circ).unwrap_or_default();
+        for (unit, port, ty) in circ.units() {
+            let input_reg = input_regs.get(&port.index()).cloned();
             if ty == QB_T {
                 let index = qubit_to_reg.len();
-                let reg = get_register(&mut qubit_registers, "q", index);
+                let reg =
+                    input_reg.unwrap_or_else(|| get_register(&mut qubit_registers, "q", index));
                 qubit_to_reg.insert(unit, reg);
             } else if ty == *LINEAR_BIT {
                 let index = bit_to_reg.len();
-                let reg = get_register(&mut bit_registers, "b", index);
+                let reg = input_reg.unwrap_or_else(|| get_register(&mut bit_registers, "b", index));
                 bit_to_reg.insert(unit, reg.clone());
             }
         }
@@ -135,8 +144,10 @@ impl JsonEncoder {
                     },
                 });
 
-        // TODO Restore the opgroup (once the decoding supports it)
-        let opgroup = None;
+        let opgroup = command
+            .get_metadata(METADATA_OPGROUP)
+            .and_then(|g| g.as_str())
+            .map(str::to_string);
         let op: JsonOp = optype.try_into()?;
         let mut op: circuit_json::Operation = op.into_operation();
         if !params.is_empty() {
@@ -197,7 +208,16 @@ impl JsonEncoder {
                 match const_op.value() {
                     Value::Extension { c: (val,) } => {
                         if let Some(f) = val.downcast_ref::<ConstF64>() {
-                            f.to_string()
+                            // Prefer the exact rational value or the source
+                            // expression when they were recorded.
+                            match command
+                                .get_metadata(METADATA_RATIONAL)
+                                .or_else(|| command.get_metadata(METADATA_PARAM_EXPR))
+                                .and_then(|r| r.as_str())
+                            {
+                                Some(r) => r.to_string(),
+                                None => f.to_string(),
+                            }
                         } else {
                             return false;
                         }
@@ -19,6 +19,7 @@ use tket_json_rs::optype::OpType as JsonOpType;
 
 use super::OpConvertError;
 use crate::extension::{try_unwrap_json_op, LINEAR_BIT};
+use crate::ops::{barrier_op, is_barrier};
 use crate::Tk2Op;
 
 /// A serialized operation, containing the operation type and all its attributes.
@@ -174,6 +175,7 @@ impl From<&JsonOp> for OpType {
     /// Convert the operation into a HUGR operation.
     ///
     /// We only translate operations that have a 1:1 mapping between TKET and HUGR.
+    /// Barriers acting only on qubits are translated to [`barrier_op`]s.
     /// Any other operation is wrapped in an `OpaqueOp`.
     fn from(json_op: &JsonOp) -> Self {
         match json_op.op.op_type {
@@ -192,8 +194,11 @@ impl From<&JsonOp> for OpType {
             JsonOpType::ZZMax => Tk2Op::ZZMax.into(),
             JsonOpType::ZZPhase => Tk2Op::ZZPhase.into(),
             JsonOpType::CZ => Tk2Op::CZ.into(),
+            JsonOpType::CY => Tk2Op::CY.into(),
+            JsonOpType::SWAP => Tk2Op::SWAP.into(),
             JsonOpType::Reset => Tk2Op::Reset.into(),
             JsonOpType::noop => LeafOp::Noop { ty: QB_T }.into(),
+            JsonOpType::Barrier if json_op.num_bits == 0 => barrier_op(json_op.num_qubits),
             _ => LeafOp::CustomOp(Box::new(json_op.as_opaque_op())).into(),
         }
     }
@@ -236,11 +241,15 @@ impl TryFrom<&OpType> for JsonOp {
                 Tk2Op::PhasedX => JsonOpType::PhasedX,
                 Tk2Op::ZZPhase => JsonOpType::ZZPhase,
                 Tk2Op::CZ => JsonOpType::CZ,
+                Tk2Op::CY => JsonOpType::CY,
+                Tk2Op::SWAP => JsonOpType::SWAP,
                 Tk2Op::Reset => JsonOpType::Reset,
                 Tk2Op::QAlloc | Tk2Op::QFree => {
                     unimplemented!("TKET1 does not support dynamic qubit allocation/discarding.")
                 }
             }
+        } else if is_barrier(op) {
+            JsonOpType::Barrier
         } else if let LeafOp::CustomOp(b) = leaf {
             let ext = (*b).as_ref();
             return try_unwrap_json_op(ext).ok_or_else(err);
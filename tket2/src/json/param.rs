@@ -0,0 +1,264 @@
+//! Parsing of TKET1 parameter expressions.
+//!
+//! TKET1 stores the parameters of its operations as strings, which may be
+//! numbers or symbolic expressions. This module recognises the expressions
+//! that are linear combinations of free symbols, such as `"pi/2"`, `"1/4"`,
+//! or `"2*alpha + 0.5"`.
+
+use std::collections::BTreeMap;
+use std::f64::consts::PI;
+use std::iter::Peekable;
+use std::str::Chars;
+
+use num_rational::Rational64;
+use serde::{Deserialize, Serialize};
+use smol_str::SmolStr;
+
+/// A linear combination of free symbols, with a constant term.
+///
+/// The decoder stores the parsed form of symbolic parameters in the metadata
+/// of their nodes, see [`super::symbolic_param`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LinearParam {
+    /// The constant term.
+    pub constant: f64,
+    /// The coefficient of each free symbol.
+    ///
+    /// Symbols with a zero coefficient are not stored.
+    pub coeffs: BTreeMap<SmolStr, f64>,
+}
+
+impl LinearParam {
+    /// A constant expression.
+    pub fn new_const(value: f64) -> Self {
+        Self {
+            constant: value,
+            coeffs: BTreeMap::new(),
+        }
+    }
+
+    /// A single free symbol.
+    pub fn new_symbol(name: impl Into<SmolStr>) -> Self {
+        Self {
+            constant: 0.,
+            coeffs: [(name.into(), 1.)].into(),
+        }
+    }
+
+    /// Returns the value of the expression if it has no free symbols.
+    pub fn as_const(&self) -> Option<f64> {
+        self.coeffs.is_empty().then_some(self.constant)
+    }
+
+    /// The free symbols of the expression.
+    pub fn free_symbols(&self) -> impl Iterator<Item = &SmolStr> + '_ {
+        self.coeffs.keys()
+    }
+
+    fn add(mut self, other: Self, sign: f64) -> Self {
+        self.constant += sign * other.constant;
+        for (sym, c) in other.coeffs {
+            let coeff = self.coeffs.entry(sym.clone()).or_default();
+            *coeff += sign * c;
+            if *coeff == 0. {
+                self.coeffs.remove(&sym);
+            }
+        }
+        self
+    }
+
+    fn scale(mut self, factor: f64) -> Self {
+        self.constant *= factor;
+        self.coeffs.retain(|_, c| {
+            *c *= factor;
+            *c != 0.
+        });
+        self
+    }
+}
+
+/// Parse a TKET1 parameter as an exact rational number.
+///
+/// Only integers and fractions of integers are recognised, such as `"3"`,
+/// `"-1/3"`, or `"2 / 5"`. Returns `None` for any other expression, including
+/// decimal numbers and fractions with a zero denominator.
+pub fn parse_rational(param: &str) -> Option<Rational64> {
+    let (numer, denom) = match param.split_once('/') {
+        Some((numer, denom)) => (numer, denom),
+        None => (param, "1"),
+    };
+    let numer: i64 = numer.trim().parse().ok()?;
+    let denom: i64 = denom.trim().parse().ok()?;
+    (denom != 0).then(|| Rational64::new(numer, denom))
+}
+
+/// Parse a TKET1 parameter into a linear combination of free symbols.
+///
+/// Supports numbers, the constant `pi`, symbol names, parentheses, and the
+/// `+`, `-`, `*`, and `/` operators. Returns `None` if the expression cannot be
+/// parsed, or is not linear in its free symbols (e.g. `"a*b"` or `"1/a"`).
+pub fn parse_param(param: &str) -> Option<LinearParam> {
+    if let Ok(f) = param.parse::<f64>() {
+        return Some(LinearParam::new_const(f));
+    }
+    let mut parser = Parser {
+        chars: param.chars().peekable(),
+    };
+    let expr = parser.expr()?;
+    parser.skip_whitespace();
+    parser.chars.peek().is_none().then_some(expr)
+}
+
+/// A recursive descent parser for parameter expressions.
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    /// expr := term (('+' | '-') term)*
+    fn expr(&mut self) -> Option<LinearParam> {
+        let mut expr = self.term()?;
+        loop {
+            self.skip_whitespace();
+            let sign = match self.chars.peek() {
+                Some('+') => 1.,
+                Some('-') => -1.,
+                _ => return Some(expr),
+            };
+            self.chars.next();
+            expr = expr.add(self.term()?, sign);
+        }
+    }
+
+    /// term := factor (('*' | '/') factor)*
+    fn term(&mut self) -> Option<LinearParam> {
+        let mut term = self.factor()?;
+        loop {
+            self.skip_whitespace();
+            let op = match self.chars.peek() {
+                Some(&op @ ('*' | '/')) => op,
+                _ => return Some(term),
+            };
+            self.chars.next();
+            let rhs = self.factor()?;
+            term = match (op, term.as_const(), rhs.as_const()) {
+                ('*', Some(c), _) => rhs.scale(c),
+                ('*', None, Some(c)) => term.scale(c),
+                ('/', _, Some(c)) => term.scale(c.recip()),
+                _ => return None,
+            };
+        }
+    }
+
+    /// factor := ('+' | '-') factor | number | 'pi' | symbol | '(' expr ')'
+    fn factor(&mut self) -> Option<LinearParam> {
+        self.skip_whitespace();
+        match *self.chars.peek()? {
+            '+' => {
+                self.chars.next();
+                self.factor()
+            }
+            '-' => {
+                self.chars.next();
+                Some(self.factor()?.scale(-1.))
+            }
+            '(' => {
+                self.chars.next();
+                let expr = self.expr()?;
+                self.skip_whitespace();
+                (self.chars.next()? == ')').then_some(expr)
+            }
+            c if c.is_ascii_digit() || c == '.' => self.number().map(LinearParam::new_const),
+            c if c.is_alphabetic() || c == '_' => {
+                let name = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                Some(match name.as_str() {
+                    "pi" => LinearParam::new_const(PI),
+                    _ => LinearParam::new_symbol(name),
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// A decimal number, with an optional exponent.
+    fn number(&mut self) -> Option<f64> {
+        let mut num = self.take_while(|c| c.is_ascii_digit() || c == '.');
+        if matches!(self.chars.peek(), Some('e' | 'E')) {
+            num.push(self.chars.next()?);
+            if let Some(&sign @ ('+' | '-')) = self.chars.peek() {
+                num.push(sign);
+                self.chars.next();
+            }
+            num.push_str(&self.take_while(|c| c.is_ascii_digit()));
+        }
+        num.parse().ok()
+    }
+
+    fn take_while(&mut self, pred: impl Fn(char) -> bool) -> String {
+        let mut s = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if !pred(c) {
+                break;
+            }
+            s.push(c);
+            self.chars.next();
+        }
+        s
+    }
+
+    fn skip_whitespace(&mut self) {
+        self.take_while(char::is_whitespace);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("0.25", 0.25)]
+    #[case("1/4", 0.25)]
+    #[case("pi/2", PI / 2.)]
+    #[case("0.5*pi", PI / 2.)]
+    #[case("-(1 + 2) * 3", -9.)]
+    #[case("1e-3", 0.001)]
+    fn parse_constant(#[case] param: &str, #[case] expected: f64) {
+        let value = parse_param(param).unwrap().as_const().unwrap();
+        assert!((value - expected).abs() < 1e-12);
+    }
+
+    #[test]
+    fn parse_symbolic() {
+        let expr = parse_param("2*alpha").unwrap();
+        assert_eq!(expr.as_const(), None);
+        assert_eq!(expr.coeffs, [("alpha".into(), 2.)].into());
+
+        let expr = parse_param("a + 0.5 - a/2").unwrap();
+        assert_eq!(expr.constant, 0.5);
+        assert_eq!(expr.coeffs, [("a".into(), 0.5)].into());
+
+        assert_eq!(parse_param("a - a"), Some(LinearParam::new_const(0.)));
+    }
+
+    #[rstest]
+    #[case("3", Some(Rational64::from_integer(3)))]
+    #[case("1/3", Some(Rational64::new(1, 3)))]
+    #[case(" -2 / 6 ", Some(Rational64::new(-1, 3)))]
+    #[case("1/0", None)]
+    #[case("0.5", None)]
+    #[case("pi/2", None)]
+    fn parse_exact_rational(#[case] param: &str, #[case] expected: Option<Rational64>) {
+        assert_eq!(parse_rational(param), expected);
+    }
+
+    #[rstest]
+    #[case("a*b")]
+    #[case("1/a")]
+    #[case("(1 + 2")]
+    #[case("2**3")]
+    #[case("")]
+    fn parse_invalid(#[case] param: &str) {
+        assert_eq!(parse_param(param), None);
+    }
+}
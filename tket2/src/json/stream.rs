@@ -0,0 +1,114 @@
+//! Incremental decoding of TKET1 JSON circuits.
+//!
+//! The commands of a serialized circuit are added to the [`JsonDecoder`] as
+//! they are parsed, instead of first collecting them into a
+//! [`SerialCircuit`].
+
+use std::fmt;
+
+use hugr::Hugr;
+use serde::de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor};
+use serde_json::{Map, Value};
+use tket_json_rs::circuit_json::{Command, SerialCircuit};
+
+use super::decoder::JsonDecoder;
+
+/// The key of the command list in a serialized circuit.
+const COMMANDS_KEY: &str = "commands";
+
+/// A visitor decoding a serialized circuit into a [`Hugr`].
+///
+/// The header fields of the circuit are collected first. If the qubit and bit
+/// registers precede the command list, each command is decoded as soon as it
+/// is parsed. Otherwise, the commands are buffered until the registers are
+/// known.
+pub(super) struct CircuitVisitor;
+
+impl<'de> Visitor<'de> for CircuitVisitor {
+    type Value = Hugr;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a serialized TKET1 circuit")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Hugr, A::Error> {
+        let mut header = Map::new();
+        let mut decoder = None;
+        let mut buffered: Vec<Command> = Vec::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if key != COMMANDS_KEY {
+                header.insert(key, map.next_value()?);
+                continue;
+            }
+            match partial_header(&header) {
+                Some(partial) => {
+                    let mut dec = JsonDecoder::new(&partial);
+                    map.next_value_seed(CommandsSeed(&mut dec))?;
+                    decoder = Some(dec);
+                }
+                None => buffered = map.next_value()?,
+            }
+        }
+
+        header.insert(COMMANDS_KEY.to_string(), Value::Array(vec![]));
+        let header: SerialCircuit =
+            serde_json::from_value(Value::Object(header)).map_err(de::Error::custom)?;
+        let mut decoder = match decoder {
+            Some(mut decoder) => {
+                decoder.set_circuit_metadata(&header);
+                decoder
+            }
+            None => JsonDecoder::new(&header),
+        };
+        for command in buffered {
+            decoder.add_command(command);
+        }
+        Ok(decoder.finish())
+    }
+}
+
+/// The header of a circuit with the fields read so far, if its qubit and bit
+/// registers are known.
+///
+/// Missing fields are filled with defaults. They are set on the decoder once
+/// the whole circuit has been read.
+fn partial_header(header: &Map<String, Value>) -> Option<SerialCircuit> {
+    if !header.contains_key("qubits") || !header.contains_key("bits") {
+        return None;
+    }
+    let mut partial = header.clone();
+    partial
+        .entry("phase")
+        .or_insert_with(|| Value::String("0".to_string()));
+    partial
+        .entry("implicit_permutation")
+        .or_insert_with(|| Value::Array(vec![]));
+    partial.insert(COMMANDS_KEY.to_string(), Value::Array(vec![]));
+    serde_json::from_value(Value::Object(partial)).ok()
+}
+
+/// Decodes a list of commands, adding each one to the decoder.
+struct CommandsSeed<'a>(&'a mut JsonDecoder);
+
+impl<'de, 'a> DeserializeSeed<'de> for CommandsSeed<'a> {
+    type Value = ();
+
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for CommandsSeed<'a> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list of commands")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(command) = seq.next_element::<Command>()? {
+            self.0.add_command(command);
+        }
+        Ok(())
+    }
+}
@@ -7,14 +7,23 @@ use hugr::extension::prelude::QB_T;
 
 use hugr::std_extensions::arithmetic::float_types::{ConstF64, FLOAT64_TYPE};
 use hugr::types::FunctionType;
-use hugr::Hugr;
+use hugr::{Hugr, HugrView};
+use itertools::Itertools;
+use num_rational::Rational64;
 use rstest::{fixture, rstest};
-use tket_json_rs::circuit_json::{self, SerialCircuit};
+use tket_json_rs::circuit_json::{self, Register, SerialCircuit};
 use tket_json_rs::optype;
 
+use crate::circuit::angle::AngleValue;
 use crate::circuit::Circuit;
+use crate::circuit::CircuitHash;
 use crate::extension::REGISTRY;
-use crate::json::TKETDecode;
+use crate::json::{
+    circuit_global_phase, constant_angle, input_registers, load_tk1_json_str, load_tk1_json_stream,
+    symbolic_param, TKETDecode,
+};
+use crate::ops::is_barrier;
+use crate::passes::{cancel_inverse_pairs, invert_circuit, rebase_to_nam, CircuitChunks};
 use crate::Tk2Op;
 
 const SIMPLE_JSON: &str = r#"{
@@ -56,6 +65,18 @@ const PARAMETRIZED: &str = r#"{
         "implicit_permutation": [[["q", [0]], ["q", [0]]], [["q", [1]], ["q", [1]]]]
     }"#;
 
+const BARRIER_OPGROUP: &str = r#"{
+        "phase": "0",
+        "bits": [],
+        "qubits": [["q", [0]], ["q", [1]]],
+        "commands": [
+            {"args": [["q", [0]]], "op": {"type": "H"}, "opgroup": "prep"},
+            {"args": [["q", [0]], ["q", [1]]], "op": {"type": "Barrier", "signature": ["Q", "Q"]}},
+            {"args": [["q", [0]], ["q", [1]]], "op": {"type": "CX"}}
+        ],
+        "implicit_permutation": [[["q", [0]], ["q", [0]]], [["q", [1]], ["q", [1]]]]
+    }"#;
+
 #[rstest]
 #[case::simple(SIMPLE_JSON, 2, 2)]
 #[case::unknown_op(UNKNOWN_OP, 2, 3)]
@@ -72,6 +93,53 @@ fn json_roundtrip(#[case] circ_s: &str, #[case] num_commands: usize, #[case] num
     compare_serial_circs(&ser, &reser);
 }
 
+#[test]
+fn json_roundtrip_barrier_opgroup() {
+    let ser: SerialCircuit = serde_json::from_str(BARRIER_OPGROUP).unwrap();
+    let circ: Hugr = ser.clone().decode().unwrap();
+    assert_eq!(
+        circ.commands()
+            .filter(|cmd| is_barrier(cmd.optype()))
+            .count(),
+        1
+    );
+
+    let reser = SerialCircuit::encode(&circ).unwrap();
+    compare_serial_circs(&ser, &reser);
+    let opgroups = reser
+        .commands
+        .iter()
+        .map(|c| c.opgroup.as_deref())
+        .collect_vec();
+    assert_eq!(opgroups, vec![Some("prep"), None, None]);
+}
+
+#[test]
+fn json_input_registers() {
+    let json = r#"{
+        "phase": "0",
+        "bits": [],
+        "qubits": [["a", [0]], ["b", [0]], ["a", [1]]],
+        "commands": [
+            {"args": [["a", [1]], ["b", [0]]], "op": {"type": "CX"}},
+            {"args": [["a", [0]]], "op": {"type": "H"}}
+        ],
+        "implicit_permutation": [[["a", [0]], ["a", [0]]], [["b", [0]], ["b", [0]]], [["a", [1]], ["a", [1]]]]
+    }"#;
+    let ser: SerialCircuit = serde_json::from_str(json).unwrap();
+    let circ: Hugr = ser.clone().decode().unwrap();
+
+    let regs = input_registers(&circ).unwrap();
+    let expected = [("a", 0), ("b", 0), ("a", 1)];
+    assert_eq!(regs.len(), expected.len());
+    for (i, (name, index)) in expected.into_iter().enumerate() {
+        assert_eq!(regs[&i], Register(name.to_string(), vec![index]));
+    }
+
+    let reser = SerialCircuit::encode(&circ).unwrap();
+    compare_serial_circs(&ser, &reser);
+}
+
 #[rstest]
 #[cfg_attr(miri, ignore)] // Opening files is not supported in (isolated) miri
 #[case::barenco_tof_10("../test_files/barenco_tof_10.json")]
@@ -165,3 +233,161 @@ fn compare_serial_circs(a: &SerialCircuit, b: &SerialCircuit) {
     }
     // TODO: Check commands equality (they only implement PartialEq)
 }
+
+/// A large serialized circuit, with the registers before or after the
+/// commands.
+fn large_circuit_json(n_layers: usize, registers_first: bool) -> String {
+    let commands = (0..n_layers)
+        .flat_map(|i| {
+            [
+                r#"{"args": [["q", [0]]], "op": {"type": "H"}}"#.to_string(),
+                r#"{"args": [["q", [0]], ["q", [1]]], "op": {"type": "CX"}}"#.to_string(),
+                format!(
+                    r#"{{"args": [["q", [1]]], "op": {{"params": ["{}"], "type": "Rz"}}}}"#,
+                    i % 7
+                ),
+            ]
+        })
+        .join(", ");
+    let registers = r#""qubits": [["q", [0]], ["q", [1]]], "bits": []"#;
+    let commands = format!(r#""commands": [{commands}]"#);
+    let others = r#""phase": "0.5", "implicit_permutation": [[["q", [0]], ["q", [0]]], [["q", [1]], ["q", [1]]]]"#;
+    if registers_first {
+        format!("{{{registers}, {commands}, {others}}}")
+    } else {
+        format!("{{{commands}, {others}, {registers}}}")
+    }
+}
+
+#[rstest]
+#[case::streamed(true)]
+#[case::buffered(false)]
+fn streaming_decode(#[case] registers_first: bool) {
+    let json = large_circuit_json(2000, registers_first);
+    let streamed = load_tk1_json_stream(json.as_bytes()).unwrap();
+    let expected = load_tk1_json_str(&json).unwrap();
+
+    assert_eq!(streamed.num_gates(), expected.num_gates());
+    assert_eq!(streamed.circuit_hash(), expected.circuit_hash());
+    assert_eq!(
+        streamed.get_metadata(streamed.root(), "TKET1_JSON.phase"),
+        Some(&"0.5".into())
+    );
+}
+
+#[rstest]
+#[case::zero(SIMPLE_JSON, Some(0.))]
+#[case::fraction(UNKNOWN_OP, Some(0.5))]
+#[case::expression(
+    r#"{"phase": "(1 + 2)/4", "bits": [], "qubits": [], "commands": [], "implicit_permutation": []}"#,
+    Some(0.75)
+)]
+#[case::symbolic(
+    r#"{"phase": "a", "bits": [], "qubits": [], "commands": [], "implicit_permutation": []}"#,
+    None
+)]
+fn global_phase(#[case] json: &str, #[case] phase: Option<f64>) {
+    let circ = load_tk1_json_str(json).unwrap();
+    assert_eq!(circuit_global_phase(&circ), phase);
+}
+
+#[test]
+fn global_phase_preserved() {
+    let json = r#"{
+        "phase": "0.25",
+        "bits": [],
+        "qubits": [["q", [0]], ["q", [1]]],
+        "commands": [
+            {"args": [["q", [0]]], "op": {"type": "H"}},
+            {"args": [["q", [0]]], "op": {"type": "H"}},
+            {"args": [["q", [0]], ["q", [1]]], "op": {"type": "CX"}}
+        ],
+        "implicit_permutation": [[["q", [0]], ["q", [0]]], [["q", [1]], ["q", [1]]]]
+    }"#;
+    let mut circ = load_tk1_json_str(json).unwrap();
+    assert_eq!(cancel_inverse_pairs(&mut circ), 2);
+    assert_eq!(circuit_global_phase(&circ), Some(0.25));
+
+    // Circuits are split into chunks when optimised in parallel.
+    let circ = CircuitChunks::split(&circ, 1).reassemble().unwrap();
+    assert_eq!(circuit_global_phase(&circ), Some(0.25));
+}
+
+#[test]
+fn global_phase_updated() {
+    let json = r#"{
+        "phase": "0.25",
+        "bits": [],
+        "qubits": [["q", [0]]],
+        "commands": [
+            {"args": [["q", [0]]], "op": {"type": "T"}}
+        ],
+        "implicit_permutation": [[["q", [0]], ["q", [0]]]]
+    }"#;
+    let mut circ = load_tk1_json_str(json).unwrap();
+
+    let inverse = invert_circuit(&circ).unwrap();
+    assert_eq!(circuit_global_phase(&inverse), Some(-0.25));
+
+    // The T gate is rebased up to a global phase.
+    rebase_to_nam(&mut circ).unwrap();
+    assert_eq!(circuit_global_phase(&circ), None);
+    let ser = SerialCircuit::encode(&circ).unwrap();
+    assert_eq!(ser.phase, "0");
+}
+
+#[test]
+fn rational_parameters() {
+    let json = r#"{
+        "phase": "0",
+        "bits": [],
+        "qubits": [["q", [0]]],
+        "commands": [
+            {"args": [["q", [0]]], "op": {"params": ["1/3"], "type": "Rz"}},
+            {"args": [["q", [0]]], "op": {"params": ["0.1"], "type": "Rz"}},
+            {"args": [["q", [0]]], "op": {"params": ["pi/2"], "type": "Rz"}}
+        ],
+        "implicit_permutation": [[["q", [0]], ["q", [0]]]]
+    }"#;
+    let circ = load_tk1_json_str(json).unwrap();
+    let angles = circ
+        .nodes()
+        .filter_map(|node| constant_angle(&circ, node))
+        .collect_vec();
+    assert_eq!(angles.len(), 3);
+    assert!(angles.contains(&AngleValue::Rational(Rational64::new(1, 3))));
+    assert!(angles.contains(&AngleValue::F64(0.1)));
+
+    // The exact values and folded expressions are kept when encoding back to
+    // TKET1.
+    let ser = SerialCircuit::encode(&circ).unwrap();
+    let params = ser
+        .commands
+        .iter()
+        .flat_map(|cmd| cmd.op.params.clone().unwrap_or_default())
+        .collect_vec();
+    assert_eq!(params, vec!["1/3", "0.1", "pi/2"]);
+}
+
+#[test]
+fn symbolic_parameters() {
+    let json = r#"{
+        "phase": "0",
+        "bits": [],
+        "qubits": [["q", [0]]],
+        "commands": [
+            {"args": [["q", [0]]], "op": {"params": ["2*alpha + 0.5"], "type": "Rz"}},
+            {"args": [["q", [0]]], "op": {"params": ["a*b"], "type": "Rz"}}
+        ],
+        "implicit_permutation": [[["q", [0]], ["q", [0]]]]
+    }"#;
+    let circ = load_tk1_json_str(json).unwrap();
+    let exprs = circ
+        .nodes()
+        .filter_map(|node| symbolic_param(&circ, node))
+        .collect_vec();
+    // Non-linear expressions are only stored as strings.
+    assert_eq!(exprs.len(), 1);
+    assert_eq!(exprs[0].constant, 0.5);
+    assert_eq!(exprs[0].coeffs, [("alpha".into(), 2.)].into());
+}
@@ -11,6 +11,7 @@ pub mod json;
 pub(crate) mod ops;
 pub mod optimiser;
 pub mod passes;
+pub mod qasm;
 pub mod rewrite;
 
 #[cfg(feature = "portmatching")]
@@ -19,4 +20,7 @@ pub mod portmatching;
 mod utils;
 
 pub use circuit::Circuit;
-pub use ops::{op_matches, symbolic_constant_op, Pauli, Tk2Op};
+pub use ops::{
+    barrier_op, commutation_table, is_barrier, op_matches, supported_ops, symbolic_constant_op,
+    Pauli, PauliString, Phase, Tk2Op,
+};
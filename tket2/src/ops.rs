@@ -1,7 +1,8 @@
 use crate::extension::{
-    SYM_EXPR_T, SYM_OP_ID, TKET2_EXTENSION as EXTENSION, TKET2_EXTENSION_ID as EXTENSION_ID,
+    BARRIER_OP_ID, SYM_EXPR_T, SYM_OP_ID, TKET2_EXTENSION as EXTENSION,
+    TKET2_EXTENSION_ID as EXTENSION_ID,
 };
-use hugr::ops::OpName;
+use hugr::ops::{OpName, OpTrait};
 use hugr::{
     extension::{
         prelude::{BOOL_T, QB_T},
@@ -17,8 +18,13 @@ use hugr::{
     },
 };
 
+use std::collections::HashMap;
+use std::ops::Mul;
+
+use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 
+use strum::IntoEnumIterator;
 use strum_macros::{Display, EnumIter, EnumString, IntoStaticStr};
 use thiserror::Error;
 
@@ -68,6 +74,8 @@ pub enum Tk2Op {
     QAlloc,
     QFree,
     Reset,
+    CY,
+    SWAP,
 }
 
 impl Tk2Op {
@@ -77,6 +85,19 @@ impl Tk2Op {
     }
 }
 
+/// Returns every operation supported by the tket2 extension, along with its
+/// signature.
+pub fn supported_ops() -> Vec<(Tk2Op, FunctionType)> {
+    Tk2Op::iter()
+        .map(|op| {
+            let sig = OpType::from(op)
+                .dataflow_signature()
+                .expect("Tk2Ops are dataflow operations.");
+            (op, sig)
+        })
+        .collect()
+}
+
 /// Whether an op is a given Tk2Op.
 pub fn op_matches(op: &OpType, tk2op: Tk2Op) -> bool {
     op.name() == tk2op.exposed_name()
@@ -102,6 +123,136 @@ impl Pauli {
     pub fn commutes_with(&self, other: Self) -> bool {
         *self == Pauli::I || other == Pauli::I || *self == other
     }
+
+    /// Multiply this pauli by another, returning the resulting pauli and
+    /// phase factor.
+    ///
+    /// For example, `X.multiply(Y)` returns `(Z, Phase::I)`, as `XY = iZ`.
+    pub fn multiply(self, other: Self) -> (Pauli, Phase) {
+        use Pauli::*;
+        match (self, other) {
+            (I, p) | (p, I) => (p, Phase::One),
+            (X, X) | (Y, Y) | (Z, Z) => (I, Phase::One),
+            (X, Y) => (Z, Phase::I),
+            (Y, X) => (Z, Phase::MinusI),
+            (Y, Z) => (X, Phase::I),
+            (Z, Y) => (X, Phase::MinusI),
+            (Z, X) => (Y, Phase::I),
+            (X, Z) => (Y, Phase::MinusI),
+        }
+    }
+}
+
+/// A phase factor from multiplying paulis, one of `±1` or `±i`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize, EnumIter)]
+pub enum Phase {
+    /// The phase `1`.
+    #[default]
+    One,
+    /// The phase `i`.
+    I,
+    /// The phase `-1`.
+    MinusOne,
+    /// The phase `-i`.
+    MinusI,
+}
+
+impl Phase {
+    /// The phase as a number of quarter turns, `i^k`.
+    fn quarter_turns(self) -> u8 {
+        match self {
+            Phase::One => 0,
+            Phase::I => 1,
+            Phase::MinusOne => 2,
+            Phase::MinusI => 3,
+        }
+    }
+
+    /// The phase `i^k`.
+    fn from_quarter_turns(k: u8) -> Self {
+        match k % 4 {
+            0 => Phase::One,
+            1 => Phase::I,
+            2 => Phase::MinusOne,
+            _ => Phase::MinusI,
+        }
+    }
+}
+
+impl Mul for Phase {
+    type Output = Phase;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Phase::from_quarter_turns(self.quarter_turns() + rhs.quarter_turns())
+    }
+}
+
+/// A tensor product of paulis on multiple qubits, with a global phase.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PauliString {
+    /// The pauli acting on each qubit.
+    pub paulis: Vec<Pauli>,
+    /// The global phase factor.
+    pub phase: Phase,
+}
+
+impl PauliString {
+    /// Create a new pauli string with a trivial phase.
+    pub fn new(paulis: impl Into<Vec<Pauli>>) -> Self {
+        Self {
+            paulis: paulis.into(),
+            phase: Phase::One,
+        }
+    }
+
+    /// The identity pauli string on `n_qubits` qubits.
+    pub fn identity(n_qubits: usize) -> Self {
+        Self::new(vec![Pauli::I; n_qubits])
+    }
+
+    /// Check if this pauli string commutes with another.
+    ///
+    /// Two strings commute if they anticommute on an even number of qubits.
+    pub fn commutes_with(&self, other: &Self) -> bool {
+        let anticommuting = self
+            .paulis
+            .iter()
+            .zip(&other.paulis)
+            .filter(|(p, q)| !p.commutes_with(**q))
+            .count();
+        anticommuting % 2 == 0
+    }
+}
+
+impl Mul for &PauliString {
+    type Output = PauliString;
+
+    /// Multiply two pauli strings qubit-wise.
+    ///
+    /// If the strings have different lengths, the shorter one is padded with
+    /// identities.
+    fn mul(self, rhs: Self) -> Self::Output {
+        let n_qubits = self.paulis.len().max(rhs.paulis.len());
+        let mut phase = self.phase * rhs.phase;
+        let paulis = (0..n_qubits)
+            .map(|i| {
+                let p = self.paulis.get(i).copied().unwrap_or(Pauli::I);
+                let q = rhs.paulis.get(i).copied().unwrap_or(Pauli::I);
+                let (pauli, factor) = p.multiply(q);
+                phase = phase * factor;
+                pauli
+            })
+            .collect();
+        PauliString { paulis, phase }
+    }
+}
+
+impl Mul for PauliString {
+    type Output = PauliString;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        &self * &rhs
+    }
 }
 impl MakeOpDef for Tk2Op {
     fn signature(&self) -> SignatureFunc {
@@ -112,7 +263,7 @@ impl MakeOpDef for Tk2Op {
             H | T | S | X | Y | Z | Tdg | Sdg | Reset => {
                 FunctionType::new(one_qb_row.clone(), one_qb_row)
             }
-            CX | ZZMax | CZ => FunctionType::new(two_qb_row.clone(), two_qb_row),
+            CX | ZZMax | CZ | CY | SWAP => FunctionType::new(two_qb_row.clone(), two_qb_row),
             ZZPhase => FunctionType::new(type_row![QB_T, QB_T, FLOAT64_TYPE], two_qb_row),
             Measure => FunctionType::new(one_qb_row, type_row![QB_T, BOOL_T]),
             RzF64 | RxF64 => FunctionType::new(type_row![QB_T, FLOAT64_TYPE], one_qb_row),
@@ -161,6 +312,7 @@ impl Tk2Op {
             X | RxF64 => vec![(0, Pauli::X)],
             T | Z | S | Tdg | Sdg | RzF64 | Measure => vec![(0, Pauli::Z)],
             CX => vec![(0, Pauli::Z), (1, Pauli::X)],
+            CY => vec![(0, Pauli::Z), (1, Pauli::Y)],
             ZZMax | ZZPhase | CZ => vec![(0, Pauli::Z), (1, Pauli::Z)],
             // by default, no commutation
             _ => vec![],
@@ -172,10 +324,120 @@ impl Tk2Op {
         use Tk2Op::*;
         match self {
             H | CX | T | S | X | Y | Z | Tdg | Sdg | ZZMax | RzF64 | RxF64 | PhasedX | ZZPhase
-            | CZ | TK1 => true,
+            | CZ | CY | SWAP | TK1 => true,
+            AngleAdd | Measure | QAlloc | QFree | Reset => false,
+        }
+    }
+
+    /// Check if this op is a Clifford gate.
+    ///
+    /// Parametric rotations are never considered Clifford, as their angles
+    /// are not inspected. Non-unitary operations are not Clifford gates.
+    pub fn is_clifford(&self) -> bool {
+        use Tk2Op::*;
+        match self {
+            H | CX | S | Sdg | X | Y | Z | ZZMax | CZ | CY | SWAP => true,
+            T | Tdg | RzF64 | RxF64 | PhasedX | ZZPhase | TK1 => false,
             AngleAdd | Measure | QAlloc | QFree | Reset => false,
         }
     }
+
+    /// Check if this op is a two-qubit gate that is symmetric in its qubits.
+    ///
+    /// Exchanging the qubit arguments of a symmetric gate, e.g.
+    /// `CZ(0, 1)` and `CZ(1, 0)`, results in the same operation.
+    pub fn is_symmetric(&self) -> bool {
+        use Tk2Op::*;
+        matches!(self, CZ | ZZMax | ZZPhase | SWAP)
+    }
+
+    /// The number of qubits the operation acts on.
+    ///
+    /// Qubits allocated or freed by the operation are counted, but classical
+    /// outputs such as measurement results are not.
+    pub fn n_qubits(&self) -> usize {
+        use Tk2Op::*;
+        match self {
+            H | T | S | X | Y | Z | Tdg | Sdg | Reset | Measure | QAlloc | QFree => 1,
+            RzF64 | RxF64 | PhasedX | TK1 => 1,
+            CX | ZZMax | CZ | CY | SWAP | ZZPhase => 2,
+            AngleAdd => 0,
+        }
+    }
+
+    /// The number of floating point parameters of the operation.
+    pub fn n_params(&self) -> usize {
+        use Tk2Op::*;
+        match self {
+            RzF64 | RxF64 | ZZPhase => 1,
+            PhasedX | AngleAdd => 2,
+            TK1 => 3,
+            H | T | S | X | Y | Z | Tdg | Sdg | Reset | Measure | QAlloc | QFree => 0,
+            CX | ZZMax | CZ | CY | SWAP => 0,
+        }
+    }
+
+    /// The inverse of this gate, if it is a unitary gate without parameters
+    /// whose inverse is also a parameter-free [`Tk2Op`].
+    ///
+    /// Returns `None` for parametric gates, [`Tk2Op::ZZMax`], and
+    /// non-unitary operations.
+    pub fn dagger(&self) -> Option<Tk2Op> {
+        use Tk2Op::*;
+        match self {
+            H | CX | X | Y | Z | CZ | CY | SWAP => Some(*self),
+            T => Some(Tdg),
+            Tdg => Some(T),
+            S => Some(Sdg),
+            Sdg => Some(S),
+            _ => None,
+        }
+    }
+
+    /// Check if this op is a Pauli gate.
+    pub fn is_pauli(&self) -> bool {
+        matches!(self, Tk2Op::X | Tk2Op::Y | Tk2Op::Z)
+    }
+
+    /// Check if this op commutes with `other` when they share a single qubit,
+    /// at qubit port `port` of this op and `other_port` of `other`.
+    ///
+    /// This uses the Pauli basis each op commutes with on the shared qubit,
+    /// so it is conservative: ops without commutation data on that qubit,
+    /// such as [`Tk2Op::H`], are never considered to commute.
+    pub fn commutes_on_qubit(&self, port: usize, other: Tk2Op, other_port: usize) -> bool {
+        let pauli_at = |op: &Tk2Op, port: usize| {
+            op.qubit_commutation()
+                .into_iter()
+                .find(|&(i, _)| i == port)
+                .map(|(_, pauli)| pauli)
+        };
+        match (pauli_at(self, port), pauli_at(&other, other_port)) {
+            (Some(p1), Some(p2)) => p1.commutes_with(p2),
+            _ => false,
+        }
+    }
+}
+
+/// The commutation relations of the quantum [`Tk2Op`] gates sharing a qubit.
+///
+/// Maps each pair `((op1, port1), (op2, port2))` to whether the gates commute
+/// when the qubit at port `port1` of `op1` is the qubit at port `port2` of
+/// `op2`, and their other qubits are distinct. Every qubit port of
+/// multi-qubit gates is included. See [`Tk2Op::commutes_on_qubit`].
+pub fn commutation_table() -> HashMap<((Tk2Op, usize), (Tk2Op, usize)), bool> {
+    let placements = Tk2Op::iter()
+        .filter(Tk2Op::is_quantum)
+        .flat_map(|op| (0..op.n_qubits()).map(move |port| (op, port)))
+        .collect_vec();
+    placements
+        .iter()
+        .cartesian_product(&placements)
+        .map(|(&(op1, port1), &(op2, port2))| {
+            let commutes = op1.commutes_on_qubit(port1, op2, port2);
+            (((op1, port1), (op2, port2)), commutes)
+        })
+        .collect()
 }
 
 /// Initialize a new custom symbolic expression constant op from a string.
@@ -194,6 +456,36 @@ pub fn symbolic_constant_op(s: &str) -> OpType {
     l.into()
 }
 
+/// Initialize a new barrier op acting on `n_qubits` qubits.
+///
+/// Barriers act as the identity on their qubits. They are opaque ordering
+/// fences: the pattern matcher never matches them, so no rewrite can move
+/// operations across a barrier.
+pub fn barrier_op(n_qubits: usize) -> OpType {
+    let l: LeafOp = EXTENSION
+        .instantiate_extension_op(
+            &BARRIER_OP_ID,
+            vec![TypeArg::BoundedNat { n: n_qubits as u64 }],
+            &REGISTRY,
+        )
+        .unwrap()
+        .into();
+    l.into()
+}
+
+/// Whether an op is a barrier, as created by [`barrier_op`].
+pub fn is_barrier(op: &OpType) -> bool {
+    let OpType::LeafOp(LeafOp::CustomOp(e)) = op else {
+        return false;
+    };
+    match e.as_ref() {
+        ExternalOp::Extension(e) => {
+            e.def().name() == &BARRIER_OP_ID && e.def().extension() == &EXTENSION_ID
+        }
+        ExternalOp::Opaque(e) => e.name() == &BARRIER_OP_ID && e.extension() == &EXTENSION_ID,
+    }
+}
+
 /// match against a symbolic constant
 pub(crate) fn match_symb_const_op(op: &OpType) -> Option<String> {
     // Extract the symbol for a symbolic operation node.
@@ -281,14 +573,18 @@ pub(crate) mod test {
 
     use std::sync::Arc;
 
+    use hugr::extension::prelude::QB_T;
     use hugr::extension::simple_op::MakeOpDef;
-    use hugr::ops::OpName;
+    use hugr::ops::{OpName, OpType};
+    use hugr::std_extensions::arithmetic::float_types::FLOAT64_TYPE;
+    use hugr::types::TypeRow;
     use hugr::CircuitUnit;
     use hugr::{extension::OpDef, Hugr};
     use rstest::{fixture, rstest};
     use strum::IntoEnumIterator;
 
-    use super::Tk2Op;
+    use super::{commutation_table, Pauli, PauliString, Phase, Tk2Op};
+    use crate::circuit::cost::is_cx;
     use crate::extension::{TKET2_EXTENSION as EXTENSION, TKET2_EXTENSION_ID as EXTENSION_ID};
     use crate::{circuit::Circuit, utils::build_simple_circuit};
     fn get_opdef(op: impl OpName) -> Option<&'static Arc<OpDef>> {
@@ -303,6 +599,133 @@ pub(crate) mod test {
         }
     }
 
+    #[rstest]
+    #[case(Tk2Op::CZ)]
+    #[case(Tk2Op::CY)]
+    #[case(Tk2Op::SWAP)]
+    fn two_qubit_gates_roundtrip(#[case] op: Tk2Op) {
+        let opdef = get_opdef(op).unwrap();
+        assert_eq!(Tk2Op::from_def(opdef), Ok(op));
+        assert_eq!(opdef.name(), &op.exposed_name());
+
+        let optype: OpType = op.into();
+        assert_eq!(Tk2Op::try_from(&optype), Ok(op));
+
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(op, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(circ.commands().count(), 1);
+    }
+
+    #[test]
+    fn supported_ops() {
+        let ops = super::supported_ops();
+        assert_eq!(ops.len(), Tk2Op::iter().count());
+        for (op, sig) in &ops {
+            assert!(
+                sig.input.len() + sig.output.len() > 0,
+                "{op:?} has an empty signature."
+            );
+        }
+
+        let arity = |op: Tk2Op| {
+            let (_, sig) = ops.iter().find(|(o, _)| *o == op).unwrap();
+            (sig.input.len(), sig.output.len())
+        };
+        assert_eq!(arity(Tk2Op::H), (1, 1));
+        assert_eq!(arity(Tk2Op::CX), (2, 2));
+        assert_eq!(arity(Tk2Op::Measure), (1, 2));
+        assert_eq!(arity(Tk2Op::TK1), (4, 1));
+        assert_eq!(arity(Tk2Op::QAlloc), (0, 1));
+    }
+
+    #[test]
+    fn clifford_classification() {
+        for op in Tk2Op::iter() {
+            // Paulis are Cliffords, and Cliffords are quantum gates.
+            assert!(!op.is_pauli() || op.is_clifford());
+            assert!(!op.is_clifford() || op.is_quantum());
+        }
+    }
+
+    #[test]
+    fn arities() {
+        for (op, sig) in super::supported_ops() {
+            let count = |row: &TypeRow, ty| row.iter().filter(|t| *t == ty).count();
+            let n_qubits = count(&sig.input, &QB_T).max(count(&sig.output, &QB_T));
+            assert_eq!(op.n_qubits(), n_qubits, "{op:?}");
+            assert_eq!(op.n_params(), count(&sig.input, &FLOAT64_TYPE), "{op:?}");
+        }
+    }
+
+    #[test]
+    fn barrier() {
+        let barrier = super::barrier_op(3);
+        let sig = barrier.dataflow_signature().unwrap();
+        assert_eq!((sig.input.len(), sig.output.len()), (3, 3));
+        assert!(super::is_barrier(&barrier));
+        assert!(!super::is_barrier(&Tk2Op::CX.into()));
+        assert!(Tk2Op::try_from(&barrier).is_err());
+
+        let circ = build_simple_circuit(3, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(super::barrier_op(3), [0, 1, 2])?;
+            Ok(())
+        })
+        .unwrap();
+        let n_cx = circ.commands().filter(|cmd| is_cx(cmd.optype())).count();
+        assert_eq!(n_cx, 1);
+    }
+
+    #[rstest]
+    #[case(Pauli::X, Pauli::Y, Pauli::Z, Phase::I)]
+    #[case(Pauli::Y, Pauli::X, Pauli::Z, Phase::MinusI)]
+    #[case(Pauli::Y, Pauli::Z, Pauli::X, Phase::I)]
+    #[case(Pauli::Z, Pauli::Y, Pauli::X, Phase::MinusI)]
+    #[case(Pauli::Z, Pauli::X, Pauli::Y, Phase::I)]
+    #[case(Pauli::X, Pauli::Z, Pauli::Y, Phase::MinusI)]
+    #[case(Pauli::Y, Pauli::Y, Pauli::I, Phase::One)]
+    #[case(Pauli::I, Pauli::Z, Pauli::Z, Phase::One)]
+    fn pauli_multiply(
+        #[case] p: Pauli,
+        #[case] q: Pauli,
+        #[case] expected: Pauli,
+        #[case] phase: Phase,
+    ) {
+        assert_eq!(p.multiply(q), (expected, phase));
+        // Anticommuting paulis pick up opposite phases.
+        if !p.commutes_with(q) {
+            assert_eq!(q.multiply(p), (expected, phase * Phase::MinusOne));
+        }
+    }
+
+    #[test]
+    fn pauli_string_multiply() {
+        let xz = PauliString::new([Pauli::X, Pauli::Z]);
+        let yx = PauliString::new([Pauli::Y, Pauli::X]);
+
+        // (X ⊗ Z)(Y ⊗ X) = (iZ) ⊗ (iY) = -(Z ⊗ Y)
+        let prod = &xz * &yx;
+        assert_eq!(prod.paulis, vec![Pauli::Z, Pauli::Y]);
+        assert_eq!(prod.phase, Phase::MinusOne);
+        assert!(xz.commutes_with(&yx));
+
+        let zz = PauliString::new([Pauli::Z, Pauli::Z]);
+        let xi = PauliString::new([Pauli::X]);
+        assert!(!zz.commutes_with(&xi));
+        assert_eq!(
+            &zz * &xi,
+            PauliString {
+                paulis: vec![Pauli::Y, Pauli::Z],
+                phase: Phase::I,
+            }
+        );
+
+        assert_eq!(&xz * &xz, PauliString::identity(2));
+    }
+
     #[fixture]
     pub(crate) fn t2_bell_circuit() -> Hugr {
         let h = build_simple_circuit(2, |circ| {
@@ -340,4 +763,21 @@ pub(crate) mod test {
         // 5 commands: alloc, reset, cx, measure, free
         assert_eq!(h.commands().count(), 5);
     }
+
+    #[test]
+    fn commutation() {
+        let table = commutation_table();
+        assert!(table[&((Tk2Op::Z, 0), (Tk2Op::Z, 0))]);
+        assert!(!table[&((Tk2Op::H, 0), (Tk2Op::Z, 0))]);
+        // Z commutes with the control of a CX, but not its target.
+        assert!(table[&((Tk2Op::Z, 0), (Tk2Op::CX, 0))]);
+        assert!(!table[&((Tk2Op::Z, 0), (Tk2Op::CX, 1))]);
+        assert!(table[&((Tk2Op::CX, 1), (Tk2Op::X, 0))]);
+        // The relation is symmetric.
+        for (&(a, b), &commutes) in &table {
+            assert_eq!(table[&(b, a)], commutes);
+        }
+        // Non-unitary operations are not included.
+        assert!(!table.contains_key(&((Tk2Op::Measure, 0), (Tk2Op::Z, 0))));
+    }
 }
@@ -6,4 +6,4 @@ pub mod badger;
 
 #[cfg(feature = "portmatching")]
 pub use badger::DefaultBadgerOptimiser;
-pub use badger::{BadgerLogger, BadgerOptimiser};
+pub use badger::{badger_schedule, BadgerLogger, BadgerOptimiser};
@@ -11,18 +11,22 @@
 //! detect and ignore duplicates. The priority queue is truncated whenever
 //! it gets too large.
 
+pub mod benchmark;
 mod eq_circ_class;
 mod hugr_pchannel;
 mod hugr_pqueue;
+mod hugr_spill;
 pub mod log;
+mod n_best;
 mod qtz_circuit;
 mod worker;
 
 use crossbeam_channel::select;
 pub use eq_circ_class::{load_eccs_json_file, EqCircClass};
 use fxhash::FxHashSet;
-use hugr::hugr::HugrError;
+pub use hugr_pqueue::{PriorityFn, TruncationStrategy};
 pub use log::BadgerLogger;
+pub use qtz_circuit::QtzLoadError;
 
 use std::num::NonZeroUsize;
 use std::time::{Duration, Instant};
@@ -31,18 +35,21 @@ use std::{mem, thread};
 use hugr::Hugr;
 
 use crate::circuit::cost::CircuitCost;
-use crate::circuit::CircuitHash;
-use crate::optimiser::badger::hugr_pchannel::{HugrPriorityChannel, PriorityChannelLog};
+use crate::circuit::{CircuitHash, HashError};
+use crate::optimiser::badger::hugr_pchannel::{
+    HugrPriorityChannel, PriorityChannelLog, WaterMarks,
+};
 use crate::optimiser::badger::hugr_pqueue::{Entry, HugrPQ};
+use crate::optimiser::badger::n_best::NBestCircuits;
 use crate::optimiser::badger::worker::BadgerWorker;
-use crate::passes::CircuitChunks;
+use crate::passes::{ChunkReassembleError, CircuitChunks};
 use crate::rewrite::strategy::RewriteStrategy;
 use crate::rewrite::trace::RewriteTracer;
 use crate::rewrite::Rewriter;
 use crate::Circuit;
 
 /// Configuration options for the Badger optimiser.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct BadgerOptions {
     /// The maximum time (in seconds) to run the optimiser.
     ///
@@ -71,6 +78,70 @@ pub struct BadgerOptions {
     ///
     /// Defaults to `20`.
     pub queue_size: usize,
+    /// The number of distinct best circuits to keep track of.
+    ///
+    /// See [`BadgerOptimiser::optimise_keep_best`]. Values lower than `1` are
+    /// treated as `1`.
+    ///
+    /// Defaults to `1`.
+    pub keep_n_best: usize,
+    /// Whether to identify seen circuits by their canonical hash instead of
+    /// their raw hash.
+    ///
+    /// Circuits that only differ by the order of commuting gates are then
+    /// explored only once, at the cost of computing the more expensive
+    /// [`CircuitHash::canonical_hash`] for every new circuit.
+    ///
+    /// Defaults to `false`.
+    #[serde(default)]
+    pub canonical_dedup: bool,
+    /// The maximum number of queued circuits to keep in memory.
+    ///
+    /// When set below `queue_size`, the lowest-priority circuits in the queue
+    /// are written to a temporary file and reloaded as the search progresses.
+    /// Only used when running with more than one thread.
+    ///
+    /// Defaults to `None`, which keeps the whole queue in memory.
+    #[serde(default)]
+    pub spill_threshold: Option<usize>,
+    /// The maximum number of newly generated circuits waiting to be added to
+    /// the queue.
+    ///
+    /// Workers producing circuits faster than they can be queued are blocked
+    /// when this limit is reached, and resume once half of the waiting
+    /// circuits have been queued. Only used when running with more than one
+    /// thread.
+    ///
+    /// Defaults to `None`, which never blocks the workers.
+    #[serde(default)]
+    pub max_pending_circuits: Option<usize>,
+    /// A known lower bound on the cost of the circuit.
+    ///
+    /// The optimiser stops as soon as it finds a circuit whose cost, as
+    /// returned by [`CircuitCost::as_usize`], is at most this value. The
+    /// input circuit is returned unchanged if it already meets the target.
+    ///
+    /// Defaults to `None`, which runs until the timeout or until the search
+    /// is exhausted.
+    #[serde(default)]
+    pub target_cost: Option<usize>,
+    /// How the priority queue makes room for new circuits when it is full.
+    ///
+    /// When circuits are spilled to disk (see
+    /// [`BadgerOptions::spill_threshold`]), the most expensive spilled
+    /// circuits are discarded instead.
+    ///
+    /// Defaults to [`TruncationStrategy::DropWorst`].
+    #[serde(default)]
+    pub truncation: TruncationStrategy,
+    /// The order in which queued circuits are processed.
+    ///
+    /// Only used when running with a single thread. This option is not
+    /// serialised.
+    ///
+    /// Defaults to `None`, which processes the cheapest circuits first.
+    #[serde(skip)]
+    pub priority: Option<PriorityFn>,
 }
 
 impl Default for BadgerOptions {
@@ -81,8 +152,32 @@ impl Default for BadgerOptions {
             n_threads: NonZeroUsize::new(1).unwrap(),
             split_circuit: Default::default(),
             queue_size: 20,
+            keep_n_best: 1,
+            canonical_dedup: false,
+            spill_threshold: None,
+            max_pending_circuits: None,
+            target_cost: None,
+            truncation: Default::default(),
+            priority: None,
+        }
+    }
+}
+
+impl BadgerOptions {
+    /// The hash used to identify already seen circuits.
+    ///
+    /// See [`BadgerOptions::canonical_dedup`].
+    fn dedup_hash(&self, circ: &Hugr) -> Result<u64, HashError> {
+        match self.canonical_dedup {
+            true => circ.canonical_hash(),
+            false => circ.circuit_hash(),
         }
     }
+
+    /// Whether a cost meets the [`BadgerOptions::target_cost`].
+    fn reached_target(&self, cost: &impl CircuitCost) -> bool {
+        self.target_cost.is_some_and(|t| cost.as_usize() <= t)
+    }
 }
 
 /// The Badger optimiser.
@@ -113,6 +208,16 @@ impl<R, S> BadgerOptimiser<R, S> {
         Self { rewriter, strategy }
     }
 
+    /// The rewriter used by the optimiser.
+    pub fn rewriter(&self) -> &R {
+        &self.rewriter
+    }
+
+    /// The rewrite strategy used by the optimiser.
+    pub fn strategy(&self) -> &S {
+        &self.strategy
+    }
+
     fn cost(&self, circ: &Hugr) -> S::Cost
     where
         S: RewriteStrategy,
@@ -134,6 +239,43 @@ where
         self.optimise_with_log(circ, Default::default(), options)
     }
 
+    /// Run the Badger optimiser on a circuit until a wall-clock deadline.
+    ///
+    /// The optimiser stops at the deadline, or earlier if `options.timeout`
+    /// is reached first. This can be used to bound the total time spent in
+    /// multiple optimiser invocations.
+    pub fn optimise_with_deadline(
+        &self,
+        circ: &Hugr,
+        deadline: Instant,
+        options: BadgerOptions,
+    ) -> Hugr {
+        self.optimise_keep_best_until(
+            circ,
+            &mut Default::default(),
+            options,
+            Some(deadline),
+            &mut |_, _| {},
+        )
+        .into_iter()
+        .next()
+        .expect("The optimiser always returns at least one circuit.")
+        .0
+    }
+
+    /// Run the Badger optimiser on a circuit with a timeout in milliseconds.
+    ///
+    /// See [`BadgerOptimiser::optimise_with_deadline`].
+    pub fn optimise_with_timeout_ms(
+        &self,
+        circ: &Hugr,
+        timeout_ms: u64,
+        options: BadgerOptions,
+    ) -> Hugr {
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
+        self.optimise_with_deadline(circ, deadline, options)
+    }
+
     /// Run the Badger optimiser on a circuit with logging activated.
     ///
     /// A timeout (in seconds) can be provided.
@@ -143,27 +285,118 @@ where
         log_config: BadgerLogger,
         options: BadgerOptions,
     ) -> Hugr {
+        let (best_circ, _) = self
+            .optimise_keep_best_with_log(circ, log_config, options)
+            .into_iter()
+            .next()
+            .expect("The optimiser always returns at least one circuit.");
+        best_circ
+    }
+
+    /// Run the Badger optimiser on a circuit, returning the best
+    /// `options.keep_n_best` distinct circuits found.
+    ///
+    /// The circuits are returned along with their costs, sorted by ascending
+    /// cost. The first circuit is the one returned by [`BadgerOptimiser::optimise`].
+    ///
+    /// When splitting the circuit into chunks, only the best circuit is returned.
+    pub fn optimise_keep_best(&self, circ: &Hugr, options: BadgerOptions) -> Vec<(Hugr, S::Cost)> {
+        self.optimise_keep_best_with_log(circ, Default::default(), options)
+    }
+
+    /// Run the Badger optimiser on a circuit with logging activated, returning
+    /// the best `options.keep_n_best` distinct circuits found.
+    ///
+    /// See [`BadgerOptimiser::optimise_keep_best`].
+    pub fn optimise_keep_best_with_log(
+        &self,
+        circ: &Hugr,
+        mut log_config: BadgerLogger,
+        options: BadgerOptions,
+    ) -> Vec<(Hugr, S::Cost)> {
+        self.optimise_keep_best_until(circ, &mut log_config, options, None, &mut |_, _| {})
+    }
+
+    /// Run the Badger optimiser on a circuit, reporting each improvement to a
+    /// callback.
+    ///
+    /// `on_improvement` is called on the calling thread every time a circuit
+    /// with a lower cost than all previous ones is found, with the new best
+    /// circuit and its cost. When splitting the circuit into chunks, it is
+    /// only called once with the reassembled circuit, if it improves on the
+    /// original.
+    pub fn optimise_with_callback(
+        &self,
+        circ: &Hugr,
+        options: BadgerOptions,
+        mut on_improvement: impl FnMut(&Hugr, &S::Cost),
+    ) -> Hugr {
+        self.optimise_keep_best_until(
+            circ,
+            &mut Default::default(),
+            options,
+            None,
+            &mut on_improvement,
+        )
+        .into_iter()
+        .next()
+        .expect("The optimiser always returns at least one circuit.")
+        .0
+    }
+
+    /// Run the Badger optimiser, stopping at the earliest of `deadline` and
+    /// the timeout in `options`.
+    ///
+    /// `on_improvement` is called with every new best circuit found.
+    fn optimise_keep_best_until(
+        &self,
+        circ: &Hugr,
+        logger: &mut BadgerLogger,
+        options: BadgerOptions,
+        deadline: Option<Instant>,
+        on_improvement: &mut dyn FnMut(&Hugr, &S::Cost),
+    ) -> Vec<(Hugr, S::Cost)> {
+        let cost = self.cost(circ);
+        if options.reached_target(&cost) {
+            return vec![(circ.clone(), cost)];
+        }
         if options.split_circuit && options.n_threads.get() > 1 {
-            return self.split_run(circ, log_config, options).unwrap();
+            let (best_circ, best_cost) = self
+                .split_run(circ, cost.clone(), logger, options, deadline)
+                .unwrap();
+            if best_cost < cost {
+                on_improvement(&best_circ, &best_cost);
+            }
+            return vec![(best_circ, best_cost)];
         }
         match options.n_threads.get() {
-            1 => self.badger(circ, log_config, options),
-            _ => self.badger_multithreaded(circ, log_config, options),
+            1 => self.badger(circ, cost, logger, options, deadline, on_improvement),
+            _ => self.badger_multithreaded(circ, cost, logger, options, deadline, on_improvement),
         }
     }
 
-    #[tracing::instrument(target = "badger::metrics", skip(self, circ, logger))]
-    fn badger(&self, circ: &Hugr, mut logger: BadgerLogger, opt: BadgerOptions) -> Hugr {
+    #[tracing::instrument(target = "badger::metrics", skip(self, circ, logger, on_improvement))]
+    fn badger(
+        &self,
+        circ: &Hugr,
+        circ_cost: S::Cost,
+        logger: &mut BadgerLogger,
+        opt: BadgerOptions,
+        deadline: Option<Instant>,
+        on_improvement: &mut dyn FnMut(&Hugr, &S::Cost),
+    ) -> Vec<(Hugr, S::Cost)> {
         let start_time = Instant::now();
         let mut last_best_time = Instant::now();
 
-        let mut best_circ = circ.clone();
-        let mut best_circ_cost = self.cost(circ);
-        let num_rewrites = best_circ.rewrite_trace().map(|rs| rs.len());
+        let mut best_circ_cost = circ_cost;
+        let num_rewrites = circ.rewrite_trace().map(|rs| rs.len());
         logger.log_best(&best_circ_cost, num_rewrites);
 
+        // The best circuits seen so far.
+        let mut n_best = NBestCircuits::new(opt.keep_n_best);
+
         // Hash of seen circuits. Dot not store circuits as this map gets huge
-        let hash = circ.circuit_hash().unwrap();
+        let hash = opt.dedup_hash(circ).unwrap();
         let mut seen_hashes = FxHashSet::default();
         seen_hashes.insert(hash);
 
@@ -172,22 +405,28 @@ where
             let strategy = self.strategy.clone();
             move |circ: &'_ Hugr| strategy.circuit_cost(circ)
         };
-        let cost = (cost_fn)(circ);
-
-        let mut pq = HugrPQ::new(cost_fn, opt.queue_size);
-        pq.push_unchecked(circ.clone(), hash, cost);
+        let mut pq = HugrPQ::new(cost_fn, opt.queue_size)
+            .with_truncation(opt.truncation)
+            .with_priority(opt.priority);
+        pq.push_unchecked(circ.clone(), hash, best_circ_cost.clone());
 
         let mut circ_cnt = 0;
         let mut timeout_flag = false;
-        while let Some(Entry { circ, cost, .. }) = pq.pop() {
+        while let Some(Entry { circ, cost, hash }) = pq.pop() {
             if cost < best_circ_cost {
-                best_circ = circ.clone();
                 best_circ_cost = cost.clone();
-                let num_rewrites = best_circ.rewrite_trace().map(|rs| rs.len());
+                let num_rewrites = circ.rewrite_trace().map(|rs| rs.len());
                 logger.log_best(&best_circ_cost, num_rewrites);
+                on_improvement(&circ, &best_circ_cost);
                 last_best_time = Instant::now();
             }
+            if n_best.check_accepted(hash, &cost) {
+                n_best.insert(circ.clone(), hash, cost.clone());
+            }
             circ_cnt += 1;
+            if opt.reached_target(&best_circ_cost) {
+                break;
+            }
 
             let rewrites = self.rewriter.get_rewrites(&circ);
 
@@ -199,11 +438,11 @@ where
             // - We haven't seen yet.
             for r in self.strategy.apply_rewrites(rewrites, &circ) {
                 let new_circ_cost = cost.add_delta(&r.cost_delta);
-                if !pq.check_accepted(&new_circ_cost) {
+                if !pq.check_accepted(&pq.priority(&r.circ, &new_circ_cost)) {
                     continue;
                 }
 
-                let Ok(new_circ_hash) = r.circ.circuit_hash() else {
+                let Ok(new_circ_hash) = opt.dedup_hash(&r.circ) else {
                     // The composed rewrites produced a loop.
                     //
                     // See [https://github.com/CQCL/tket2/discussions/242]
@@ -225,6 +464,10 @@ where
                     break;
                 }
             }
+            if deadline.is_some_and(|d| Instant::now() >= d) {
+                timeout_flag = true;
+                break;
+            }
             if let Some(p_timeout) = opt.progress_timeout {
                 if last_best_time.elapsed().as_secs() > p_timeout {
                     timeout_flag = true;
@@ -240,20 +483,23 @@ where
             false,
             timeout_flag,
         );
-        best_circ
+        n_best.into_sorted_vec()
     }
 
     /// Run the Badger optimiser on a circuit, using multiple threads.
     ///
     /// This is the multi-threaded version of [`badger`]. See [`BadgerOptimiser`] for
     /// more details.
-    #[tracing::instrument(target = "badger::metrics", skip(self, circ, logger))]
+    #[tracing::instrument(target = "badger::metrics", skip(self, circ, logger, on_improvement))]
     fn badger_multithreaded(
         &self,
         circ: &Hugr,
-        mut logger: BadgerLogger,
+        circ_cost: S::Cost,
+        logger: &mut BadgerLogger,
         opt: BadgerOptions,
-    ) -> Hugr {
+        deadline: Option<Instant>,
+        on_improvement: &mut dyn FnMut(&Hugr, &S::Cost),
+    ) -> Vec<(Hugr, S::Cost)> {
         let n_threads: usize = opt.n_threads.get();
 
         // multi-consumer priority channel for queuing circuits to be processed by the workers
@@ -261,11 +507,21 @@ where
             let strategy = self.strategy.clone();
             move |circ: &'_ Hugr| strategy.circuit_cost(circ)
         };
-        let (pq, rx_log) = HugrPriorityChannel::init(cost_fn.clone(), opt.queue_size);
+        let (pq, rx_log) = HugrPriorityChannel::init(
+            cost_fn.clone(),
+            opt.queue_size,
+            opt.truncation,
+            opt.keep_n_best,
+            opt.spill_threshold,
+            opt.max_pending_circuits.map(WaterMarks::new),
+        );
 
-        let initial_circ_hash = circ.circuit_hash().unwrap();
-        let mut best_circ = circ.clone();
-        let mut best_circ_cost = self.cost(&best_circ);
+        let initial_circ_hash = opt.dedup_hash(circ).unwrap();
+        let initial_circ_cost = circ_cost;
+        let mut best_circ_cost = initial_circ_cost.clone();
+
+        // The best circuits seen so far, as reported by the priority channel.
+        let mut n_best = NBestCircuits::new(opt.keep_n_best);
 
         // Initialise the work channels and send the initial circuit.
         pq.send(vec![Work {
@@ -279,14 +535,21 @@ where
         // patterns and sends the results back to main.
         let joins: Vec<_> = (0..n_threads)
             .map(|i| {
-                BadgerWorker::spawn(i, pq.clone(), self.rewriter.clone(), self.strategy.clone())
+                BadgerWorker::spawn(
+                    i,
+                    pq.clone(),
+                    self.rewriter.clone(),
+                    self.strategy.clone(),
+                    opt,
+                )
             })
             .collect();
 
         // Deadline for the optimisation timeout
-        let timeout_event = match opt.timeout {
+        let timeout = opt.timeout.map(|t| Instant::now() + Duration::from_secs(t));
+        let timeout_event = match timeout.into_iter().chain(deadline).min() {
             None => crossbeam_channel::never(),
-            Some(t) => crossbeam_channel::at(Instant::now() + Duration::from_secs(t)),
+            Some(t) => crossbeam_channel::at(t),
         };
 
         // Deadline for the timeout when no progress is made
@@ -296,7 +559,7 @@ where
         };
 
         // Main loop: log best circuits as they come in from the priority queue,
-        // until the timeout is reached.
+        // until the timeout is reached or the search space is exhausted.
         let mut timeout_flag = false;
         let mut processed_count = 0;
         let mut seen_count = 0;
@@ -304,22 +567,38 @@ where
             select! {
                 recv(rx_log) -> msg => {
                     match msg {
-                        Ok(PriorityChannelLog::NewBestCircuit(circ, cost)) => {
+                        Ok(PriorityChannelLog::NewBestCircuit(circ, cost, hash)) => {
                             if cost < best_circ_cost {
-                                best_circ = circ;
-                                best_circ_cost = cost;
-                                let num_rewrites = best_circ.rewrite_trace().map(|rs| rs.len());
+                                best_circ_cost = cost.clone();
+                                let num_rewrites = circ.rewrite_trace().map(|rs| rs.len());
                                 logger.log_best(&best_circ_cost, num_rewrites);
+                                on_improvement(&circ, &best_circ_cost);
                                 if let Some(t) = opt.progress_timeout {
                                     progress_timeout_event = crossbeam_channel::at(Instant::now() + Duration::from_secs(t));
                                 }
                             }
+                            n_best.insert(circ, hash, cost);
+                            if opt.reached_target(&best_circ_cost) {
+                                // Signal the workers to stop.
+                                let _ = pq.close();
+                                break;
+                            }
                         },
                         Ok(PriorityChannelLog::CircuitCount{processed_count: proc, seen_count: seen, queue_length}) => {
                             processed_count = proc;
                             seen_count = seen;
                             logger.log_progress(processed_count, Some(queue_length), seen_count);
                         }
+                        Ok(PriorityChannelLog::Exhausted) => {
+                            // No more circuits to process. The priority
+                            // channel has already stopped.
+                            break;
+                        }
+                        Ok(PriorityChannelLog::SpillFailed(e)) => {
+                            // The priority channel has already stopped.
+                            logger.log(format!("Could not spill circuits to disk: {e}. Stopping Badger optimisation."));
+                            break;
+                        }
                         Err(crossbeam_channel::RecvError) => {
                             logger.log("The priority channel panicked. Stopping Badger optimisation.");
                             let _ = pq.close();
@@ -345,13 +624,14 @@ where
         // Empty the log from the priority queue and store final circuit count.
         while let Ok(log) = rx_log.recv() {
             match log {
-                PriorityChannelLog::NewBestCircuit(circ, cost) => {
+                PriorityChannelLog::NewBestCircuit(circ, cost, hash) => {
                     if cost < best_circ_cost {
-                        best_circ = circ;
-                        best_circ_cost = cost;
-                        let num_rewrites = best_circ.rewrite_trace().map(|rs| rs.len());
+                        best_circ_cost = cost.clone();
+                        let num_rewrites = circ.rewrite_trace().map(|rs| rs.len());
                         logger.log_best(&best_circ_cost, num_rewrites);
+                        on_improvement(&circ, &best_circ_cost);
                     }
+                    n_best.insert(circ, hash, cost);
                 }
                 PriorityChannelLog::CircuitCount {
                     processed_count: proc,
@@ -362,6 +642,10 @@ where
                     seen_count = seen;
                     logger.log_progress(processed_count, Some(queue_length), seen_count);
                 }
+                PriorityChannelLog::Exhausted => {}
+                PriorityChannelLog::SpillFailed(e) => {
+                    logger.log(format!("Could not spill circuits to disk: {e}."));
+                }
             }
         }
         logger.log_processing_end(
@@ -374,7 +658,11 @@ where
 
         joins.into_iter().for_each(|j| j.join().unwrap());
 
-        best_circ
+        if n_best.is_empty() {
+            // The priority channel stopped before processing the initial circuit.
+            n_best.insert(circ.clone(), initial_circ_hash, initial_circ_cost);
+        }
+        n_best.into_sorted_vec()
     }
 
     /// Split the circuit into chunks and process each in a separate thread.
@@ -382,10 +670,11 @@ where
     fn split_run(
         &self,
         circ: &Hugr,
-        mut logger: BadgerLogger,
+        circ_cost: S::Cost,
+        logger: &mut BadgerLogger,
         opt: BadgerOptions,
-    ) -> Result<Hugr, HugrError> {
-        let circ_cost = self.cost(circ);
+        deadline: Option<Instant>,
+    ) -> Result<(Hugr, S::Cost), ChunkReassembleError> {
         let max_chunk_cost = circ_cost.clone().div_cost(opt.n_threads);
         logger.log(format!(
             "Splitting circuit with cost {:?} into chunks of at most {max_chunk_cost:?}.",
@@ -409,14 +698,21 @@ where
                 let join = thread::Builder::new()
                     .name(format!("chunk-{}", i))
                     .spawn(move || {
-                        let res = badger.optimise(
-                            &chunk,
-                            BadgerOptions {
-                                n_threads: NonZeroUsize::new(1).unwrap(),
-                                split_circuit: false,
-                                ..opt
-                            },
-                        );
+                        let (res, _) = badger
+                            .optimise_keep_best_until(
+                                &chunk,
+                                &mut Default::default(),
+                                BadgerOptions {
+                                    n_threads: NonZeroUsize::new(1).unwrap(),
+                                    split_circuit: false,
+                                    ..opt
+                                },
+                                deadline,
+                                &mut |_, _| {},
+                            )
+                            .into_iter()
+                            .next()
+                            .expect("The optimiser always returns at least one circuit.");
                         tx.send(res).unwrap();
                     })
                     .unwrap();
@@ -438,13 +734,45 @@ where
             logger.log_best(best_circ_cost.clone(), num_rewrites);
         }
 
-        logger.log_processing_end(opt.n_threads.get(), None, best_circ_cost, true, false);
+        logger.log_processing_end(
+            opt.n_threads.get(),
+            None,
+            best_circ_cost.clone(),
+            true,
+            false,
+        );
         joins.into_iter().for_each(|j| j.join().unwrap());
 
-        Ok(best_circ)
+        Ok((best_circ, best_circ_cost))
     }
 }
 
+/// Split a Badger run into stages with decreasing numbers of threads.
+///
+/// Returns the number of threads and the timeout (in seconds) of each stage.
+/// Early stages use many threads on small chunks of the circuit, later stages
+/// use fewer threads to optimise across the chunk boundaries.
+pub fn badger_schedule(n_threads: NonZeroUsize, timeout: u64) -> Vec<(NonZeroUsize, u64)> {
+    let stages = match n_threads.get() {
+        n if n >= 7 => vec![
+            (n, timeout / 2),
+            (3, timeout / 10 * 3),
+            (1, timeout / 10 * 2),
+        ],
+        n if n >= 4 => vec![
+            (n, timeout / 2),
+            (2, timeout / 10 * 3),
+            (1, timeout / 10 * 2),
+        ],
+        n if n > 1 => vec![(n, timeout / 2), (1, timeout / 2)],
+        _ => vec![(1, timeout)],
+    };
+    stages
+        .into_iter()
+        .map(|(n, timeout)| (n.try_into().unwrap(), timeout))
+        .collect()
+}
+
 #[cfg(feature = "portmatching")]
 mod badger_default {
     use std::io;
@@ -490,6 +818,7 @@ use self::hugr_pchannel::Work;
 #[cfg(test)]
 #[cfg(feature = "portmatching")]
 mod tests {
+    use hugr::ops::OpType;
     use hugr::{
         builder::{DFGBuilder, Dataflow, DataflowHugr},
         extension::prelude::QB_T,
@@ -497,13 +826,24 @@ mod tests {
         types::FunctionType,
         Hugr,
     };
+    use itertools::Itertools;
     use rstest::{fixture, rstest};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
 
+    use crate::circuit::CircuitHash;
     use crate::json::load_tk1_json_str;
     use crate::optimiser::badger::BadgerOptions;
+    use crate::rewrite::strategy::{
+        BoxedStrategy, LexicographicCostFunction, RewriteResult, RewriteStrategy,
+    };
+    use crate::rewrite::{BoxedRewriter, CircuitRewrite, ECCRewriter};
+    use crate::utils::build_simple_circuit;
     use crate::{extension::REGISTRY, Circuit, Tk2Op};
+    use std::path::Path;
 
-    use super::{BadgerOptimiser, DefaultBadgerOptimiser};
+    use super::{badger_schedule, BadgerOptimiser, DefaultBadgerOptimiser};
 
     /// Simplified description of the circuit's commands.
     fn gates(circ: &Hugr) -> Vec<Tk2Op> {
@@ -512,6 +852,34 @@ mod tests {
             .collect()
     }
 
+    /// A strategy counting the evaluations of its whole-circuit cost.
+    #[derive(Clone)]
+    struct CountingStrategy<S> {
+        strategy: S,
+        n_calls: Arc<AtomicUsize>,
+    }
+
+    impl<S: RewriteStrategy> RewriteStrategy for CountingStrategy<S> {
+        type Cost = S::Cost;
+
+        fn apply_rewrites(
+            &self,
+            rewrites: impl IntoIterator<Item = CircuitRewrite>,
+            circ: &Hugr,
+        ) -> impl Iterator<Item = RewriteResult<Self::Cost>> {
+            self.strategy.apply_rewrites(rewrites, circ)
+        }
+
+        fn op_cost(&self, op: &OpType) -> Self::Cost {
+            self.strategy.op_cost(op)
+        }
+
+        fn circuit_cost(&self, circ: &Hugr) -> Self::Cost {
+            self.n_calls.fetch_add(1, Ordering::Relaxed);
+            self.strategy.circuit_cost(circ)
+        }
+    }
+
     #[fixture]
     fn rz_rz() -> Hugr {
         let input_t = vec![QB_T, FLOAT64_TYPE, FLOAT64_TYPE];
@@ -583,6 +951,112 @@ mod tests {
         assert_eq!(gates(&opt_rz), vec![Tk2Op::AngleAdd, Tk2Op::RzF64]);
     }
 
+    #[rstest]
+    fn dyn_optimiser(rz_rz: Hugr) {
+        // The rewriter is selected at runtime, e.g. from a configuration file.
+        let rewriter_path = Path::new("../test_files/small_eccs.json");
+        let rewriter = match rewriter_path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => {
+                BoxedRewriter::new(ECCRewriter::try_from_eccs_json_file(rewriter_path).unwrap())
+            }
+            _ => BoxedRewriter::new(ECCRewriter::load_binary(rewriter_path).unwrap()),
+        };
+        let strategy = BoxedStrategy::new(LexicographicCostFunction::default_cx());
+        let badger_opt = BadgerOptimiser::new(rewriter, strategy);
+
+        let opt_rz = badger_opt.optimise(
+            &rz_rz,
+            BadgerOptions {
+                queue_size: 4,
+                ..Default::default()
+            },
+        );
+        assert_eq!(gates(&opt_rz), vec![Tk2Op::AngleAdd, Tk2Op::RzF64]);
+    }
+
+    #[rstest]
+    fn improvement_callback(rz_rz: Hugr, badger_opt: DefaultBadgerOptimiser) {
+        let mut improvements = Vec::new();
+        let opt_rz = badger_opt.optimise_with_callback(
+            &rz_rz,
+            BadgerOptions {
+                queue_size: 4,
+                ..Default::default()
+            },
+            |circ, cost| improvements.push((circ.circuit_hash().unwrap(), cost.clone())),
+        );
+
+        let (last_hash, last_cost) = improvements.last().expect("No improvement reported.");
+        assert_eq!(*last_hash, opt_rz.circuit_hash().unwrap());
+        assert_eq!(*last_cost, badger_opt.cost(&opt_rz));
+    }
+
+    #[rstest]
+    fn rz_rz_cancellation_priority(rz_rz: Hugr, badger_opt: DefaultBadgerOptimiser) {
+        // Explore the largest circuits first.
+        let opt_rz = badger_opt.optimise(
+            &rz_rz,
+            BadgerOptions {
+                queue_size: 4,
+                priority: Some(|circ, _| -(circ.num_gates() as isize)),
+                ..Default::default()
+            },
+        );
+        assert_eq!(gates(&opt_rz), vec![Tk2Op::AngleAdd, Tk2Op::RzF64]);
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    fn cost_evaluations(rz_rz: Hugr, badger_opt: DefaultBadgerOptimiser, #[case] n_threads: usize) {
+        let n_calls = Arc::new(AtomicUsize::new(0));
+        let strategy = CountingStrategy {
+            strategy: LexicographicCostFunction::default_cx(),
+            n_calls: n_calls.clone(),
+        };
+        let counting_opt = BadgerOptimiser::new(badger_opt.rewriter().clone(), strategy);
+        let opt_rz = counting_opt.optimise(
+            &rz_rz,
+            BadgerOptions {
+                n_threads: n_threads.try_into().unwrap(),
+                queue_size: 4,
+                ..Default::default()
+            },
+        );
+        assert_eq!(gates(&opt_rz), vec![Tk2Op::AngleAdd, Tk2Op::RzF64]);
+        // Only the cost of the input circuit is evaluated. The costs of the
+        // rewritten circuits are obtained from the rewrite cost deltas.
+        assert_eq!(n_calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[rstest]
+    fn rz_rz_cancellation_spilled(rz_rz: Hugr, badger_opt: DefaultBadgerOptimiser) {
+        let opt_rz = badger_opt.optimise(
+            &rz_rz,
+            BadgerOptions {
+                n_threads: 2.try_into().unwrap(),
+                queue_size: 4,
+                spill_threshold: Some(1),
+                ..Default::default()
+            },
+        );
+        assert_eq!(gates(&opt_rz), vec![Tk2Op::AngleAdd, Tk2Op::RzF64]);
+    }
+
+    #[rstest]
+    fn rz_rz_cancellation_backpressure(rz_rz: Hugr, badger_opt: DefaultBadgerOptimiser) {
+        let opt_rz = badger_opt.optimise(
+            &rz_rz,
+            BadgerOptions {
+                n_threads: 2.try_into().unwrap(),
+                queue_size: 4,
+                max_pending_circuits: Some(1),
+                ..Default::default()
+            },
+        );
+        assert_eq!(gates(&opt_rz), vec![Tk2Op::AngleAdd, Tk2Op::RzF64]);
+    }
+
     #[rstest]
     fn rz_rz_cancellation_parallel(rz_rz: Hugr, badger_opt: DefaultBadgerOptimiser) {
         let mut opt_rz = badger_opt.optimise(
@@ -597,6 +1071,105 @@ mod tests {
         opt_rz.update_validate(&REGISTRY).unwrap();
     }
 
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    fn keep_n_best(rz_rz: Hugr, badger_opt: DefaultBadgerOptimiser, #[case] n_threads: usize) {
+        let best = badger_opt.optimise_keep_best(
+            &rz_rz,
+            BadgerOptions {
+                n_threads: n_threads.try_into().unwrap(),
+                queue_size: 4,
+                keep_n_best: 3,
+                ..Default::default()
+            },
+        );
+        assert!(!best.is_empty() && best.len() <= 3);
+        // Sorted by ascending cost.
+        assert!(best.windows(2).all(|w| w[0].1 <= w[1].1));
+        // No duplicates.
+        let hashes: Vec<_> = best
+            .iter()
+            .map(|(circ, _)| circ.circuit_hash().unwrap())
+            .collect();
+        assert_eq!(hashes.iter().unique().count(), hashes.len());
+        // The first circuit is the best one.
+        assert_eq!(gates(&best[0].0), vec![Tk2Op::AngleAdd, Tk2Op::RzF64]);
+    }
+
+    #[rstest]
+    fn parallel_terminates_early(badger_opt: DefaultBadgerOptimiser) {
+        // No rewrite rule applies to this circuit.
+        let circ = build_simple_circuit(1, |circ| {
+            circ.append(Tk2Op::Z, [0])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let start = Instant::now();
+        let opt = badger_opt.optimise(
+            &circ,
+            BadgerOptions {
+                timeout: Some(60),
+                n_threads: 2.try_into().unwrap(),
+                queue_size: 4,
+                ..Default::default()
+            },
+        );
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert_eq!(gates(&opt), vec![Tk2Op::Z]);
+    }
+
+    #[rstest]
+    fn canonical_dedup(rz_rz: Hugr, badger_opt: DefaultBadgerOptimiser) {
+        let options = |canonical_dedup| BadgerOptions {
+            queue_size: 4,
+            canonical_dedup,
+            ..Default::default()
+        };
+
+        // The same circuit, up to commuting gates on the CX control.
+        let circs = [[Tk2Op::Z, Tk2Op::T], [Tk2Op::T, Tk2Op::Z]].map(|[a, b]| {
+            build_simple_circuit(2, |circ| {
+                circ.append(a, [0])?;
+                circ.append(Tk2Op::CX, [0, 1])?;
+                circ.append(b, [0])?;
+                circ.append(Tk2Op::CX, [0, 1])?;
+                Ok(())
+            })
+            .unwrap()
+        });
+        let n_seen = |canonical_dedup| {
+            circs
+                .iter()
+                .map(|circ| options(canonical_dedup).dedup_hash(circ).unwrap())
+                .unique()
+                .count()
+        };
+        assert_eq!(n_seen(false), 2);
+        assert_eq!(n_seen(true), 1);
+
+        // The optimiser skips circuits already seen up to commutation.
+        let optimise = |circ: &Hugr, canonical_dedup| {
+            let mut logger = BadgerLogger::default();
+            let opt = badger_opt.optimise_keep_best_until(
+                circ,
+                &mut logger,
+                options(canonical_dedup),
+                None,
+                &mut |_, _| {},
+            );
+            (opt[0].1.clone(), logger.circuits_seen().unwrap())
+        };
+        for circ in circs.iter().chain([&rz_rz]) {
+            let (cost, seen) = optimise(circ, false);
+            let (cost_canonical, seen_canonical) = optimise(circ, true);
+            assert!(seen_canonical >= 1);
+            assert!(seen_canonical <= seen);
+            assert!(cost_canonical <= cost);
+        }
+    }
+
     #[rstest]
     #[ignore = "Loading the ECC set is really slow (~5 seconds)"]
     fn non_composable_rewrites(
@@ -615,9 +1188,71 @@ mod tests {
         opt.update_validate(&REGISTRY).unwrap();
     }
 
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    fn deadline(
+        non_composable_rw_hugr: Hugr,
+        badger_opt: DefaultBadgerOptimiser,
+        #[case] n_threads: usize,
+    ) {
+        let start = Instant::now();
+        let mut opt = badger_opt.optimise_with_timeout_ms(
+            &non_composable_rw_hugr,
+            50,
+            BadgerOptions {
+                n_threads: n_threads.try_into().unwrap(),
+                queue_size: 4,
+                ..Default::default()
+            },
+        );
+        assert!(start.elapsed() < Duration::from_secs(1));
+        opt.update_validate(&REGISTRY).unwrap();
+    }
+
+    #[rstest]
+    #[case(1, vec![(1, 100)])]
+    #[case(4, vec![(4, 50), (2, 30), (1, 20)])]
+    #[case(7, vec![(7, 50), (3, 30), (1, 20)])]
+    #[case(16, vec![(16, 50), (3, 30), (1, 20)])]
+    fn schedule(#[case] n_threads: usize, #[case] expected: Vec<(usize, u64)>) {
+        let stages = badger_schedule(n_threads.try_into().unwrap(), 100)
+            .into_iter()
+            .map(|(n, timeout)| (n.get(), timeout))
+            .collect_vec();
+        assert_eq!(stages, expected);
+    }
+
     #[test]
     fn load_precompiled_bin() {
         let opt = BadgerOptimiser::default_with_rewriter_binary("../test_files/small_eccs.rwr");
         opt.unwrap();
     }
+
+    #[rstest]
+    #[case::single_thread(1)]
+    #[case::multithreaded(2)]
+    fn target_cost(#[case] n_threads: usize) {
+        let badger_opt =
+            BadgerOptimiser::default_with_eccs_json_file("../test_files/cx_cx_eccs.json").unwrap();
+        let cx_cx = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        let options = |target_cost| BadgerOptions {
+            n_threads: n_threads.try_into().unwrap(),
+            queue_size: 4,
+            target_cost,
+            ..Default::default()
+        };
+
+        let opt = badger_opt.optimise(&cx_cx, options(Some(0)));
+        assert_eq!(gates(&opt), vec![]);
+
+        // The input already meets the target, so the search stops immediately.
+        let opt = badger_opt.optimise(&cx_cx, options(Some(2)));
+        assert_eq!(gates(&opt), vec![Tk2Op::CX, Tk2Op::CX]);
+    }
 }
@@ -0,0 +1,169 @@
+//! Reproducible optimisation benchmark cases.
+//!
+//! A benchmark case captures an input circuit, the options used to optimise
+//! it, and the cost the optimiser is expected to reach. Cases are stored as
+//! JSON files and can be re-run to detect optimisation regressions.
+//!
+//! The rewriter and rewrite strategy are not part of the case, and must be
+//! provided when running it.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+
+use hugr::Hugr;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::circuit::cost::CircuitCost;
+use crate::rewrite::strategy::RewriteStrategy;
+use crate::rewrite::Rewriter;
+
+use super::{BadgerOptimiser, BadgerOptions};
+
+/// An input circuit with its optimisation options and expected cost.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BenchmarkCase {
+    /// The circuit to optimise.
+    pub circuit: Hugr,
+    /// The options passed to the optimiser.
+    pub options: BadgerOptions,
+    /// The maximum cost the optimised circuit may have, as returned by
+    /// [`CircuitCost::as_usize`].
+    pub expected_cost: usize,
+}
+
+/// Error type for running or storing benchmark cases.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BenchmarkFailure {
+    /// Could not read or write the benchmark case file.
+    #[error("Benchmark file error: {0}")]
+    Io(#[from] io::Error),
+    /// Could not encode or decode the benchmark case.
+    #[error("Benchmark serialisation error: {0}")]
+    Serialisation(#[from] serde_json::Error),
+    /// The optimised circuit has a higher cost than expected.
+    #[error("Optimised circuit has cost {achieved}, expected at most {expected}.")]
+    CostRegression {
+        /// The expected cost of the optimised circuit.
+        expected: usize,
+        /// The cost achieved by the optimiser.
+        achieved: usize,
+    },
+}
+
+/// Store a benchmark case to a JSON file.
+pub fn save_benchmark_case(
+    path: impl AsRef<Path>,
+    input: &Hugr,
+    options: &BadgerOptions,
+    expected_cost: usize,
+) -> Result<(), BenchmarkFailure> {
+    let case = BenchmarkCase {
+        circuit: input.clone(),
+        options: *options,
+        expected_cost,
+    };
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(writer, &case)?;
+    Ok(())
+}
+
+/// Load a benchmark case from a JSON file.
+pub fn load_benchmark_case(path: impl AsRef<Path>) -> Result<BenchmarkCase, BenchmarkFailure> {
+    let reader = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(reader)?)
+}
+
+/// Load a benchmark case and optimise its circuit with `optimiser`.
+///
+/// Fails with [`BenchmarkFailure::CostRegression`] if the optimised circuit
+/// is more expensive than the expected cost. Reaching a lower cost is
+/// accepted.
+pub fn run_benchmark_case<R, S>(
+    path: impl AsRef<Path>,
+    optimiser: &BadgerOptimiser<R, S>,
+) -> Result<(), BenchmarkFailure>
+where
+    R: Rewriter + Send + Clone + 'static,
+    S: RewriteStrategy + Send + Sync + Clone + 'static,
+    S::Cost: serde::Serialize + Send + Sync,
+{
+    let case = load_benchmark_case(path)?;
+    let opt = optimiser.optimise(&case.circuit, case.options);
+    let achieved = optimiser.cost(&opt).as_usize();
+    if achieved > case.expected_cost {
+        return Err(BenchmarkFailure::CostRegression {
+            expected: case.expected_cost,
+            achieved,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+#[cfg(feature = "portmatching")]
+mod tests {
+    use super::*;
+    use crate::optimiser::badger::DefaultBadgerOptimiser;
+    use crate::utils::build_simple_circuit;
+    use crate::utils::test::TempPath;
+    use crate::Tk2Op;
+    use rstest::{fixture, rstest};
+
+    #[fixture]
+    fn badger_opt() -> DefaultBadgerOptimiser {
+        BadgerOptimiser::default_with_eccs_json_file("../test_files/small_eccs.json").unwrap()
+    }
+
+    fn cx_cx() -> Hugr {
+        build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    #[rstest]
+    fn rerun_benchmark_case(badger_opt: DefaultBadgerOptimiser) {
+        let path = TempPath::new("benchmark_rerun.json");
+        let circ = cx_cx();
+        let options = BadgerOptions {
+            queue_size: 4,
+            ..Default::default()
+        };
+        let opt = badger_opt.optimise(&circ, options);
+        let cost = badger_opt.cost(&opt).as_usize();
+
+        save_benchmark_case(&path, &circ, &options, cost).unwrap();
+        assert!(run_benchmark_case(&path, &badger_opt).is_ok());
+    }
+
+    #[rstest]
+    fn benchmark_regression(badger_opt: DefaultBadgerOptimiser) {
+        let path = TempPath::new("benchmark_regression.json");
+        // A single CX cannot be removed.
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        let options = BadgerOptions {
+            queue_size: 4,
+            ..Default::default()
+        };
+        let achieved = badger_opt
+            .cost(&badger_opt.optimise(&circ, options))
+            .as_usize();
+        assert!(achieved > 0);
+
+        save_benchmark_case(&path, &circ, &options, 0).unwrap();
+        let res = run_benchmark_case(&path, &badger_opt);
+        assert!(matches!(
+            res,
+            Err(BenchmarkFailure::CostRegression { expected: 0, .. })
+        ));
+    }
+}
@@ -78,6 +78,8 @@ impl EqCircClass {
 }
 
 /// Load a set of equivalence classes from a JSON file.
+///
+/// Files with a `.gz` extension are decompressed with gzip before parsing.
 pub fn load_eccs_json_file(path: impl AsRef<Path>) -> io::Result<Vec<EqCircClass>> {
     let all_circs = load_ecc_set(path)?;
 
@@ -1,6 +1,7 @@
 //! A multi-producer multi-consumer min-priority channel of Hugrs.
 
-use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, RwLock};
 use std::thread;
 use std::time::Instant;
 
@@ -10,12 +11,26 @@ use hugr::Hugr;
 
 use crate::circuit::cost::CircuitCost;
 
-use super::hugr_pqueue::{Entry, HugrPQ};
+use super::hugr_pqueue::{Entry, HugrPQ, TruncationStrategy};
+use super::hugr_spill::{HugrSpill, SpillError};
+use super::n_best::NBestCircuits;
 
 /// A unit of work for a worker, consisting of a circuit to process, along its
 /// hash and cost.
 pub type Work<P> = Entry<Hugr, P, u64>;
 
+/// Messages sent to the priority channel.
+#[derive(Debug, Clone)]
+enum PriorityChannelMsg<P> {
+    /// Add a batch of new circuits to the queue.
+    Push(Vec<Work<P>>),
+    /// A worker finished processing a circuit popped from the queue, producing
+    /// a (possibly empty) batch of new circuits.
+    Processed(Vec<Work<P>>),
+    /// Stop the priority channel.
+    Close,
+}
+
 /// A priority channel for HUGRs.
 ///
 /// Queues hugrs using a cost function `C` that produces priority values `P`.
@@ -24,7 +39,7 @@ pub type Work<P> = Entry<Hugr, P, u64>;
 #[derive(Debug, Clone)]
 pub struct HugrPriorityChannel<C, P: Ord> {
     /// Channel to add circuits from the queue.
-    push: Receiver<Vec<Work<P>>>,
+    push: Receiver<PriorityChannelMsg<P>>,
     /// Channel to pop circuits from the queue.
     pop: Sender<Work<P>>,
     /// Outbound channel to log to main thread.
@@ -34,35 +49,55 @@ pub struct HugrPriorityChannel<C, P: Ord> {
     last_progress_log: Instant,
     /// The priority queue data structure.
     pq: HugrPQ<P, C>,
+    /// Temporary file holding the lowest-priority circuits, created once the
+    /// queue grows past `spill_threshold`.
+    spill: Option<HugrSpill<P>>,
+    /// The maximum number of circuits kept in memory, if spilling is enabled.
+    spill_threshold: Option<usize>,
+    /// The maximum number of circuits in the queue, in memory or spilled.
+    queue_capacity: usize,
     /// The set of hashes we've seen.
     seen_hashes: FxHashSet<u64>,
-    /// The minimum cost we've seen.
-    min_cost: Option<P>,
+    /// The costs and hashes of the best circuits we've seen.
+    n_best: NBestCircuits<(), P>,
     /// The number of circuits we've processed.
     circ_cnt: usize,
+    /// The number of circuits popped from the queue whose processing results
+    /// have not been received yet.
+    in_flight: usize,
     /// The maximum cost in the queue. Shared with the workers so they can cull
     /// the circuits they generate.
     max_cost: Arc<RwLock<Option<P>>>,
     /// Local copy of `max_cost`, used to avoid locking when checking the value.
     local_max_cost: Option<P>,
+    /// The number of circuits sent to the channel and not yet queued. Shared
+    /// with the senders, which block when it grows too large.
+    backpressure: Arc<Backpressure>,
 }
 
 /// Logging information from the priority channel.
 #[derive(Debug, Clone)]
 pub enum PriorityChannelLog<P> {
-    NewBestCircuit(Hugr, P),
+    /// A new circuit entered the set of best circuits seen so far, with its
+    /// cost and hash.
+    NewBestCircuit(Hugr, P, u64),
     CircuitCount {
         processed_count: usize,
         seen_count: usize,
         queue_length: usize,
     },
+    /// The queue is empty and no circuit is being processed, so no more
+    /// circuits will be produced. The priority channel has stopped.
+    Exhausted,
+    /// Spilling circuits to disk failed. The priority channel has stopped.
+    SpillFailed(Arc<SpillError>),
 }
 
 /// Channels for communication with the priority channel.
 #[derive(Clone)]
 pub struct PriorityChannelCommunication<P> {
     /// A channel to add batches of circuits to the queue.
-    push: Sender<Vec<Work<P>>>,
+    push: Sender<PriorityChannelMsg<P>>,
     /// A channel to remove the best candidate circuit from the queue.
     pop: Receiver<Work<P>>,
     /// A maximum accepted cost for the queue. Circuits with higher costs will
@@ -70,22 +105,163 @@ pub struct PriorityChannelCommunication<P> {
     ///
     /// Shared with the workers so they can cull the circuits they generate.
     max_cost: Arc<RwLock<Option<P>>>,
+    /// The number of circuits sent and not yet queued, used to block the
+    /// senders when the channel falls behind.
+    backpressure: Arc<Backpressure>,
+}
+
+/// The thresholds at which the senders to a priority channel are blocked and
+/// resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaterMarks {
+    /// Senders are blocked when the number of circuits waiting to be queued
+    /// would exceed this value.
+    pub high: usize,
+    /// Blocked senders resume when the number of circuits waiting to be
+    /// queued falls to this value.
+    pub low: usize,
+}
+
+impl WaterMarks {
+    /// Water marks with the given high mark, resuming the senders once half
+    /// of it has been queued.
+    pub fn new(high: usize) -> Self {
+        let high = high.max(1);
+        Self {
+            high,
+            low: high / 2,
+        }
+    }
+}
+
+/// Backpressure on the senders of circuits to a priority channel.
+///
+/// Counts the circuits sent to the channel that it has not received yet.
+/// When [`WaterMarks`] are set, senders block while the count would exceed
+/// the high-water mark, and resume once it falls to the low-water mark.
+#[derive(Debug, Default)]
+pub struct Backpressure {
+    /// The number of circuits sent and not yet received.
+    queued: AtomicUsize,
+    /// The blocking thresholds, if backpressure is enabled.
+    marks: Option<WaterMarks>,
+    /// Whether senders are blocked, and whether the channel has stopped.
+    state: Mutex<BackpressureState>,
+    /// Notified when blocked senders can resume.
+    resume: Condvar,
+}
+
+#[derive(Debug, Default)]
+struct BackpressureState {
+    throttled: bool,
+    closed: bool,
+}
+
+impl Backpressure {
+    /// Create a new backpressure counter, with optional blocking thresholds.
+    pub fn new(marks: Option<WaterMarks>) -> Self {
+        Self {
+            marks,
+            ..Default::default()
+        }
+    }
+
+    /// The number of circuits sent and not yet received.
+    pub fn queued_len(&self) -> usize {
+        self.queued.load(Ordering::Acquire)
+    }
+
+    /// Register `n` circuits about to be sent, blocking until there is room
+    /// for them.
+    ///
+    /// A batch larger than the high-water mark is let through once nothing
+    /// else is waiting, so that it cannot block forever.
+    fn acquire(&self, n: usize) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(marks) = self.marks {
+            loop {
+                let queued = self.queued_len();
+                if state.closed || queued == 0 {
+                    break;
+                }
+                if !state.throttled && queued + n <= marks.high {
+                    break;
+                }
+                state.throttled = true;
+                state = self.resume.wait(state).unwrap();
+            }
+        }
+        self.queued.fetch_add(n, Ordering::AcqRel);
+    }
+
+    /// Register `n` circuits as received, resuming the senders if the count
+    /// falls to the low-water mark.
+    fn release(&self, n: usize) {
+        let mut state = self.state.lock().unwrap();
+        let queued = self.queued.fetch_sub(n, Ordering::AcqRel) - n;
+        if state.throttled && self.marks.map_or(true, |marks| queued <= marks.low) {
+            state.throttled = false;
+            self.resume.notify_all();
+        }
+    }
+
+    /// Stop blocking the senders, as the channel will not receive any more
+    /// circuits.
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        self.resume.notify_all();
+    }
 }
 
 impl<P: CircuitCost> PriorityChannelCommunication<P> {
     /// Signal the priority channel to stop.
     ///
     /// This will in turn signal the workers to stop.
-    pub fn close(&self) -> Result<(), SendError<Vec<Work<P>>>> {
-        self.push.send(Vec::new())
+    pub fn close(&self) -> Result<(), SendError<()>> {
+        self.push
+            .send(PriorityChannelMsg::Close)
+            .map_err(|_| SendError(()))
     }
 
     /// Send a lot of circuits to the priority channel.
+    ///
+    /// Blocks while the channel has too many circuits waiting to be queued,
+    /// see [`WaterMarks`].
     pub fn send(&self, work: Vec<Work<P>>) -> Result<(), SendError<Vec<Work<P>>>> {
         if work.is_empty() {
             return Ok(());
         }
-        self.push.send(work)
+        self.send_msg(PriorityChannelMsg::Push(work))
+    }
+
+    /// Report the result of processing a circuit received from the priority
+    /// channel.
+    ///
+    /// This must be called exactly once for each circuit returned by
+    /// [`PriorityChannelCommunication::recv`], even if no new circuits were
+    /// produced, so that the channel can detect when the search is exhausted.
+    ///
+    /// Blocks while the channel has too many circuits waiting to be queued,
+    /// see [`WaterMarks`].
+    pub fn send_processed(&self, work: Vec<Work<P>>) -> Result<(), SendError<Vec<Work<P>>>> {
+        self.send_msg(PriorityChannelMsg::Processed(work))
+    }
+
+    /// Send a message carrying circuits, applying backpressure.
+    fn send_msg(&self, msg: PriorityChannelMsg<P>) -> Result<(), SendError<Vec<Work<P>>>> {
+        let n = msg.work_len();
+        self.backpressure.acquire(n);
+        self.push.send(msg).map_err(|SendError(msg)| {
+            self.backpressure.release(n);
+            SendError(msg.into_work())
+        })
+    }
+
+    /// The number of circuits sent to the priority channel that have not been
+    /// added to its queue yet.
+    pub fn queued_len(&self) -> usize {
+        self.backpressure.queued_len()
     }
 
     /// Receive a circuit from the priority channel.
@@ -104,6 +280,24 @@ impl<P: CircuitCost> PriorityChannelCommunication<P> {
     }
 }
 
+impl<P> PriorityChannelMsg<P> {
+    /// Extract the circuits carried by the message, if any.
+    fn into_work(self) -> Vec<Work<P>> {
+        match self {
+            PriorityChannelMsg::Push(work) | PriorityChannelMsg::Processed(work) => work,
+            PriorityChannelMsg::Close => Vec::new(),
+        }
+    }
+
+    /// The number of circuits carried by the message.
+    fn work_len(&self) -> usize {
+        match self {
+            PriorityChannelMsg::Push(work) | PriorityChannelMsg::Processed(work) => work.len(),
+            PriorityChannelMsg::Close => 0,
+        }
+    }
+}
+
 impl<C, P> HugrPriorityChannel<C, P>
 where
     C: Fn(&Hugr) -> P + Send + Sync + 'static,
@@ -115,15 +309,37 @@ where
     ///
     /// Get back a [`PriorityChannelCommunication`] for adding and removing circuits to/from the queue,
     /// and a channel receiver to receive logging information.
+    ///
+    /// The `keep_n_best` best distinct circuits are reported via
+    /// [`PriorityChannelLog::NewBestCircuit`] logs.
+    ///
+    /// If `spill_threshold` is set, at most that many circuits are kept in
+    /// memory. The lowest-priority circuits beyond it are written to a
+    /// temporary file, and reloaded as the in-memory queue empties.
+    ///
+    /// If `water_marks` are set, senders block while too many circuits are
+    /// waiting to be added to the queue. This bounds the memory used when
+    /// the workers produce circuits faster than the channel can queue them.
+    ///
+    /// The in-memory queue is truncated following the `truncation` strategy
+    /// when it is full. When circuits are spilled to disk, the in-memory queue
+    /// never fills up, and the most expensive spilled circuits are discarded
+    /// instead.
     pub fn init(
         cost_fn: C,
         queue_capacity: usize,
+        truncation: TruncationStrategy,
+        keep_n_best: usize,
+        spill_threshold: Option<usize>,
+        water_marks: Option<WaterMarks>,
     ) -> (
         PriorityChannelCommunication<P>,
         Receiver<PriorityChannelLog<P>>,
     ) {
         // Shared maximum cost in the queue.
         let max_cost = Arc::new(RwLock::new(None));
+        // Shared count of the circuits waiting to be queued.
+        let backpressure = Arc::new(Backpressure::new(water_marks));
         // Channels for pushing and popping circuits from pqueue
         let (tx_push, rx_push) = crossbeam_channel::unbounded();
         let (tx_pop, rx_pop) = crossbeam_channel::bounded(0);
@@ -135,8 +351,12 @@ where
             tx_pop,
             tx_log,
             max_cost.clone(),
+            backpressure.clone(),
             cost_fn,
             queue_capacity,
+            truncation,
+            keep_n_best,
+            spill_threshold,
         );
         pq.run();
         (
@@ -144,25 +364,36 @@ where
                 push: tx_push,
                 pop: rx_pop,
                 max_cost,
+                backpressure,
             },
             rx_log,
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
     fn new(
-        push: Receiver<Vec<Work<P>>>,
+        push: Receiver<PriorityChannelMsg<P>>,
         pop: Sender<Work<P>>,
         log: Sender<PriorityChannelLog<P>>,
         max_cost: Arc<RwLock<Option<P>>>,
+        backpressure: Arc<Backpressure>,
         cost_fn: C,
         queue_capacity: usize,
+        truncation: TruncationStrategy,
+        keep_n_best: usize,
+        spill_threshold: Option<usize>,
     ) -> Self {
         // The priority queue, local to this thread.
-        let pq = HugrPQ::new(cost_fn, queue_capacity);
+        let pq = HugrPQ::new(cost_fn, queue_capacity).with_truncation(truncation);
+        // The on-disk overflow of the queue is only needed if the threshold
+        // is below the queue capacity.
+        let spill_threshold = spill_threshold
+            .map(|t| t.max(1))
+            .filter(|&t| t < queue_capacity);
         // The set of hashes we've seen.
         let seen_hashes = FxHashSet::default();
-        // The minimum cost we've seen.
-        let min_cost = None;
+        // The best circuits we've seen.
+        let n_best = NBestCircuits::new(keep_n_best);
         // The number of circuits we've seen (for logging).
         let circ_cnt = 0;
 
@@ -173,11 +404,16 @@ where
             // Ensure we log the first progress.
             last_progress_log: Instant::now() - std::time::Duration::from_secs(60),
             pq,
+            spill: None,
+            spill_threshold,
+            queue_capacity,
             seen_hashes,
-            min_cost,
+            n_best,
             circ_cnt,
+            in_flight: 0,
             max_cost,
             local_max_cost: None,
+            backpressure,
         }
     }
 
@@ -187,54 +423,119 @@ where
         let _ = builder
             .name("priority-channel".into())
             .spawn(move || {
+                let mut exhausted = false;
+                let mut spill_error = None;
                 'main: loop {
+                    if let Err(e) = self.refill() {
+                        spill_error = Some(e);
+                        break 'main;
+                    }
                     while self.pq.is_empty() {
-                        let Ok(new_circs) = self.push.recv() else {
+                        if self.is_exhausted() {
+                            // No circuits are queued or being processed, so
+                            // no new ones can be produced.
+                            exhausted = true;
+                            break 'main;
+                        }
+                        let Ok(msg) = self.push.recv() else {
                             // Something went wrong
                             break 'main;
                         };
-                        if new_circs.is_empty() {
+                        match self.handle_msg(msg) {
+                            Ok(true) => {}
                             // The main thread signalled us to stop.
-                            break 'main;
+                            Ok(false) => break 'main,
+                            Err(e) => {
+                                spill_error = Some(e);
+                                break 'main;
+                            }
                         }
-                        self.enqueue_circs(new_circs);
                     }
                     select! {
                         recv(self.push) -> result => {
-                            let Ok(new_circs) = result else {
+                            let Ok(msg) = result else {
                                 // Something went wrong
                                 break 'main;
                             };
-                            if new_circs.is_empty() {
+                            match self.handle_msg(msg) {
+                                Ok(true) => {}
                                 // The main thread signalled us to stop.
-                                break 'main;
+                                Ok(false) => break 'main,
+                                Err(e) => {
+                                    spill_error = Some(e);
+                                    break 'main;
+                                }
                             }
-                            self.enqueue_circs(new_circs);
                         }
                         send(self.pop, self.pq.pop().unwrap()) -> result => {
                             if result.is_err() {
                                 // Something went wrong.
                                 break 'main;
                             }
+                            self.in_flight += 1;
                             self.update_max_cost();
                         }
                     }
                 }
+                // Unblock any sender, no more circuits will be received.
+                self.backpressure.close();
                 // Send a last set of logs before terminating.
                 self.log
                     .send(PriorityChannelLog::CircuitCount {
                         processed_count: self.circ_cnt,
                         seen_count: self.seen_hashes.len(),
-                        queue_length: self.pq.len(),
+                        queue_length: self.queue_len(),
                     })
                     .unwrap();
+                if exhausted {
+                    self.log.send(PriorityChannelLog::Exhausted).unwrap();
+                }
+                if let Some(e) = spill_error {
+                    self.log
+                        .send(PriorityChannelLog::SpillFailed(Arc::new(e)))
+                        .unwrap();
+                }
             })
             .unwrap();
     }
 
+    /// Handle a message received on the push channel.
+    ///
+    /// Returns `false` if the channel should stop.
+    fn handle_msg(&mut self, msg: PriorityChannelMsg<P>) -> Result<bool, SpillError> {
+        self.backpressure.release(msg.work_len());
+        match msg {
+            PriorityChannelMsg::Push(circs) => self.enqueue_circs(circs)?,
+            PriorityChannelMsg::Processed(circs) => {
+                self.in_flight = self.in_flight.saturating_sub(1);
+                // This is the result from processing a circuit. Add it to the count.
+                self.circ_cnt += 1;
+                self.enqueue_circs(circs)?;
+            }
+            PriorityChannelMsg::Close => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Whether the search has been exhausted.
+    ///
+    /// This is the case when at least one circuit has been processed, the
+    /// queue is empty, and no circuits are currently being processed by the
+    /// workers.
+    #[inline]
+    fn is_exhausted(&self) -> bool {
+        self.circ_cnt > 0 && self.in_flight == 0 && self.queue_len() == 0
+    }
+
+    /// The number of circuits in the queue, including spilled ones.
+    #[inline]
+    fn queue_len(&self) -> usize {
+        self.pq.len() + self.spill.as_ref().map_or(0, |spill| spill.len())
+    }
+
     /// Add circuits to queue.
     #[tracing::instrument(target = "badger::metrics", skip(self, circs))]
-    fn enqueue_circs(&mut self, circs: Vec<Work<P>>) {
+    fn enqueue_circs(&mut self, circs: Vec<Work<P>>) -> Result<(), SpillError> {
         for Work { cost, hash, circ } in circs {
             if !self.seen_hashes.insert(hash) {
                 // Ignore this circuit: we've seen it before.
@@ -242,31 +543,77 @@ where
             }
 
             // A new best circuit
-            if self.min_cost.is_none() || Some(&cost) < self.min_cost.as_ref() {
-                self.min_cost = Some(cost.clone());
+            if self.n_best.insert((), hash, cost.clone()) {
                 self.log
                     .send(PriorityChannelLog::NewBestCircuit(
                         circ.clone(),
                         cost.clone(),
+                        hash,
                     ))
                     .unwrap();
             }
 
             self.pq.push_unchecked(circ, hash, cost);
         }
+        self.spill_excess()?;
         self.update_max_cost();
 
-        // This is the result from processing a circuit. Add it to the count.
-        self.circ_cnt += 1;
         if Instant::now() - self.last_progress_log > std::time::Duration::from_millis(100) {
             self.log
                 .send(PriorityChannelLog::CircuitCount {
                     processed_count: self.circ_cnt,
                     seen_count: self.seen_hashes.len(),
-                    queue_length: self.pq.len(),
+                    queue_length: self.queue_len(),
                 })
                 .unwrap();
         }
+        Ok(())
+    }
+
+    /// Move the lowest-priority circuits to the spill file if the in-memory
+    /// queue exceeds the spill threshold.
+    ///
+    /// The most expensive spilled circuits are discarded if the total queue
+    /// size exceeds its capacity.
+    fn spill_excess(&mut self) -> Result<(), SpillError> {
+        let Some(threshold) = self.spill_threshold else {
+            return Ok(());
+        };
+        if self.spill.is_none() && self.pq.len() > threshold {
+            self.spill = Some(HugrSpill::new()?);
+        }
+        let Some(spill) = &mut self.spill else {
+            return Ok(());
+        };
+        while self.pq.len() > threshold {
+            let work = self.pq.pop_max().unwrap();
+            spill.push(work)?;
+        }
+        while self.pq.len() + spill.len() > self.queue_capacity {
+            spill.discard_max()?;
+        }
+        Ok(())
+    }
+
+    /// Reload spilled circuits into memory.
+    ///
+    /// Circuits are reloaded while the in-memory queue is below the spill
+    /// threshold, or while a spilled circuit is cheaper than the best one in
+    /// memory.
+    fn refill(&mut self) -> Result<(), SpillError> {
+        let (Some(spill), Some(threshold)) = (&mut self.spill, self.spill_threshold) else {
+            return Ok(());
+        };
+        while let Some(min_spilled) = spill.min_cost() {
+            let has_room = self.pq.len() < threshold;
+            let is_cheaper = self.pq.peek().is_some_and(|e| min_spilled < e.cost);
+            if !has_room && !is_cheaper {
+                break;
+            }
+            let Work { circ, cost, hash } = spill.pop_min()?.unwrap();
+            self.pq.push_unchecked(circ, hash, cost);
+        }
+        self.spill_excess()
     }
 
     /// Update the shared `max_cost` value.
@@ -286,3 +633,57 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn backpressure_high_water_mark() {
+        let marks = WaterMarks::new(4);
+        let backpressure = Arc::new(Backpressure::new(Some(marks)));
+        let max_seen = Arc::new(AtomicUsize::new(0));
+
+        // A flood of single-circuit sends from several producers.
+        let producers = (0..4)
+            .map(|_| {
+                let backpressure = backpressure.clone();
+                let max_seen = max_seen.clone();
+                thread::spawn(move || {
+                    for _ in 0..50 {
+                        backpressure.acquire(1);
+                        max_seen.fetch_max(backpressure.queued_len(), Ordering::AcqRel);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        // A slow consumer.
+        let mut received = 0;
+        while received < 200 {
+            if backpressure.queued_len() > 0 {
+                backpressure.release(1);
+                received += 1;
+            }
+            thread::sleep(Duration::from_micros(50));
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+
+        assert_eq!(backpressure.queued_len(), 0);
+        assert!(max_seen.load(Ordering::Acquire) <= marks.high);
+    }
+
+    #[test]
+    fn backpressure_closed() {
+        let backpressure = Backpressure::new(Some(WaterMarks::new(1)));
+        backpressure.acquire(1);
+        backpressure.close();
+        // Does not block once the channel is closed.
+        backpressure.acquire(1);
+        assert_eq!(backpressure.queued_len(), 2);
+    }
+}
@@ -1,20 +1,70 @@
+use std::collections::BTreeMap;
+
 use delegate::delegate;
 use fxhash::FxHashMap;
 use hugr::Hugr;
+use itertools::Itertools;
 use priority_queue::DoublePriorityQueue;
 
+use crate::circuit::cost::CircuitCost;
 use crate::circuit::CircuitHash;
 
+/// A function ordering the circuits of a priority queue.
+///
+/// Receives each circuit pushed into the queue along with its cost, as
+/// returned by [`CircuitCost::as_usize`]. Circuits with lower values are
+/// popped first, and ties are broken by cost.
+///
+/// This can be used e.g. to favour circuits that are expected to lead to
+/// further improvements, rather than the cheapest ones.
+pub type PriorityFn = fn(&Hugr, usize) -> isize;
+
+/// The key ordering the circuits in a [`HugrPQ`].
+///
+/// The value of the priority function, or `0` if there is none, followed by
+/// the cost of the circuit.
+pub type Priority<P> = (isize, P);
+
 /// A min-priority queue for Hugrs.
 ///
-/// The cost function provided will be used as the priority of the Hugrs.
+/// The cost function provided will be used as the priority of the Hugrs,
+/// unless a priority function is given with [`HugrPQ::with_priority`].
 /// Uses hashes internally to store the Hugrs.
 #[derive(Debug, Clone, Default)]
 pub struct HugrPQ<P: Ord, C> {
-    queue: DoublePriorityQueue<u64, P>,
-    hash_lookup: FxHashMap<u64, Hugr>,
+    queue: DoublePriorityQueue<u64, Priority<P>>,
+    hash_lookup: FxHashMap<u64, (Hugr, P)>,
+    /// The number of queued circuits with each cost.
+    costs: BTreeMap<P, usize>,
     cost_fn: C,
+    priority_fn: Option<PriorityFn>,
     max_size: usize,
+    truncation: TruncationStrategy,
+}
+
+/// How a full priority queue makes room for a new circuit.
+///
+/// The queue is truncated when a circuit is pushed while it holds `max_size`
+/// circuits. Strategies discarding more circuits at once trade exploration
+/// breadth for fewer truncations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+#[non_exhaustive]
+pub enum TruncationStrategy {
+    /// Discard the circuit with the highest priority value.
+    #[default]
+    DropWorst,
+    /// Discard the worst half of the queue.
+    HalveWorst,
+    /// Keep only the `n` best circuits.
+    ///
+    /// At least one circuit is always discarded.
+    KeepTopN(usize),
+    /// Keep the circuits whose cost is at most the given factor times the
+    /// lowest cost in the queue.
+    ///
+    /// Costs are compared using [`CircuitCost::as_usize`]. If every circuit is
+    /// within the threshold, the worst one is discarded.
+    CostThreshold(f64),
 }
 
 pub struct Entry<C, P, H> {
@@ -25,20 +75,38 @@ pub struct Entry<C, P, H> {
 
 impl<P: Ord, C> HugrPQ<P, C> {
     /// Create a new HugrPQ with a cost function and some initial capacity.
+    ///
+    /// The Hugrs are prioritised by their cost.
     pub fn new(cost_fn: C, max_size: usize) -> Self {
         Self {
             queue: DoublePriorityQueue::with_capacity(max_size),
             hash_lookup: Default::default(),
+            costs: Default::default(),
             cost_fn,
+            priority_fn: None,
             max_size,
+            truncation: Default::default(),
         }
     }
 
+    /// Order the queue with a priority function instead of the cost alone.
+    ///
+    /// See [`PriorityFn`].
+    pub fn with_priority(mut self, priority_fn: Option<PriorityFn>) -> Self {
+        self.priority_fn = priority_fn;
+        self
+    }
+
+    /// Set the strategy used to truncate the queue when it is full.
+    pub fn with_truncation(mut self, truncation: TruncationStrategy) -> Self {
+        self.truncation = truncation;
+        self
+    }
+
     /// Reference to the minimal Hugr in the queue.
-    #[allow(unused)]
     pub fn peek(&self) -> Option<Entry<&Hugr, &P, u64>> {
-        let (hash, cost) = self.queue.peek_min()?;
-        let circ = self.hash_lookup.get(hash)?;
+        let (hash, _) = self.queue.peek_min()?;
+        let (circ, cost) = self.hash_lookup.get(hash)?;
         Some(Entry {
             circ,
             cost,
@@ -52,6 +120,7 @@ impl<P: Ord, C> HugrPQ<P, C> {
     #[allow(unused)]
     pub fn push(&mut self, hugr: Hugr)
     where
+        P: CircuitCost,
         C: Fn(&Hugr) -> P,
     {
         let hash = hugr.circuit_hash().unwrap();
@@ -66,43 +135,102 @@ impl<P: Ord, C> HugrPQ<P, C> {
     ///
     /// This does not check that the hash is valid.
     ///
-    /// If the queue is full, the most last will be dropped.
+    /// If the queue is full, it is truncated according to its
+    /// [`TruncationStrategy`].
     pub fn push_unchecked(&mut self, hugr: Hugr, hash: u64, cost: P)
     where
-        C: Fn(&Hugr) -> P,
+        P: CircuitCost,
     {
-        if !self.check_accepted(&cost) {
+        let priority = self.priority(&hugr, &cost);
+        if !self.check_accepted(&priority) {
             return;
         }
         if self.len() >= self.max_size {
-            self.pop_max();
+            self.truncate_full();
         }
-        self.queue.push(hash, cost);
-        self.hash_lookup.insert(hash, hugr);
+        self.queue.push(hash, priority);
+        *self.costs.entry(cost.clone()).or_default() += 1;
+        self.hash_lookup.insert(hash, (hugr, cost));
+    }
+
+    /// The key ordering a Hugr with the given cost in the queue.
+    pub fn priority(&self, hugr: &Hugr, cost: &P) -> Priority<P>
+    where
+        P: CircuitCost,
+    {
+        let value = self.priority_fn.map_or(0, |f| f(hugr, cost.as_usize()));
+        (value, cost.clone())
     }
 
     /// Pop the minimal Hugr from the queue.
     pub fn pop(&mut self) -> Option<Entry<Hugr, P, u64>> {
-        let (hash, cost) = self.queue.pop_min()?;
-        let circ = self.hash_lookup.remove(&hash)?;
+        let (hash, _) = self.queue.pop_min()?;
+        let (circ, cost) = self.remove_lookup(hash)?;
         Some(Entry { circ, cost, hash })
     }
 
     /// Pop the maximal Hugr from the queue.
     pub fn pop_max(&mut self) -> Option<Entry<Hugr, P, u64>> {
-        let (hash, cost) = self.queue.pop_max()?;
-        let circ = self.hash_lookup.remove(&hash)?;
+        let (hash, _) = self.queue.pop_max()?;
+        let (circ, cost) = self.remove_lookup(hash)?;
         Some(Entry { circ, cost, hash })
     }
 
     /// Discard the largest elements of the queue.
     ///
     /// Only keep up to `max_size` elements.
-    #[allow(unused)]
     pub fn truncate(&mut self, max_size: usize) {
         while self.queue.len() > max_size {
             let (hash, _) = self.queue.pop_max().unwrap();
-            self.hash_lookup.remove(&hash);
+            self.remove_lookup(hash);
+        }
+    }
+
+    /// Remove a Hugr that is no longer in the queue from the lookup tables.
+    fn remove_lookup(&mut self, hash: u64) -> Option<(Hugr, P)> {
+        let (circ, cost) = self.hash_lookup.remove(&hash)?;
+        if let Some(count) = self.costs.get_mut(&cost) {
+            *count -= 1;
+            if *count == 0 {
+                self.costs.remove(&cost);
+            }
+        }
+        Some((circ, cost))
+    }
+
+    /// Make room in a full queue, following the truncation strategy.
+    fn truncate_full(&mut self)
+    where
+        P: CircuitCost,
+    {
+        match self.truncation {
+            TruncationStrategy::DropWorst => {}
+            TruncationStrategy::HalveWorst => self.truncate(self.max_size / 2),
+            TruncationStrategy::KeepTopN(n) => self.truncate(n),
+            TruncationStrategy::CostThreshold(factor) => {
+                let cost = |hash: &u64| self.hash_lookup[hash].1.as_usize() as f64;
+                let Some(best) = self
+                    .queue
+                    .iter()
+                    .map(|(hash, _)| cost(hash))
+                    .reduce(f64::min)
+                else {
+                    return;
+                };
+                let discarded = self
+                    .queue
+                    .iter()
+                    .map(|(&hash, _)| hash)
+                    .filter(|hash| cost(hash) > factor * best)
+                    .collect_vec();
+                for hash in discarded {
+                    self.queue.remove(&hash);
+                    self.remove_lookup(hash);
+                }
+            }
+        }
+        if self.len() >= self.max_size {
+            self.pop_max();
         }
     }
 
@@ -114,21 +242,28 @@ impl<P: Ord, C> HugrPQ<P, C> {
 
     /// The largest cost in the queue.
     pub fn max_cost(&self) -> Option<&P> {
-        self.queue.peek_max().map(|(_, cost)| cost)
+        self.costs.last_key_value().map(|(cost, _)| cost)
     }
 
-    /// Returns `true` if an element with the given cost would be accepted.
+    /// The largest priority in the queue.
+    ///
+    /// This is the element that is discarded first when the queue is full.
+    pub fn max_priority(&self) -> Option<&Priority<P>> {
+        self.queue.peek_max().map(|(_, priority)| priority)
+    }
+
+    /// Returns `true` if an element with the given priority would be accepted.
     ///
     /// If `false`, the element will be dropped if passed to [`HugrPQ::push`] or
-    /// [`HugrPQ::push_unchecked`].
-    pub fn check_accepted(&self, cost: &P) -> bool {
+    /// [`HugrPQ::push_unchecked`]. See [`HugrPQ::priority`].
+    pub fn check_accepted(&self, priority: &Priority<P>) -> bool {
         if self.max_size == 0 {
             return false;
         }
         if self.len() < self.max_size {
             return true;
         }
-        cost < self.max_cost().unwrap()
+        priority < self.max_priority().unwrap()
     }
 
     /// Returns `true` is the queue is at capacity.
@@ -143,3 +278,94 @@ impl<P: Ord, C> HugrPQ<P, C> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::build_simple_circuit;
+    use crate::utils::test::n_cx;
+    use crate::{Circuit, Tk2Op};
+
+    /// Pop all the elements of the queue, returning their costs.
+    fn pop_costs<C>(pq: &mut HugrPQ<usize, C>) -> Vec<usize> {
+        std::iter::from_fn(|| pq.pop().map(|e| e.cost)).collect()
+    }
+
+    #[test]
+    fn custom_priority() {
+        let cost_fn = |circ: &Hugr| circ.num_gates();
+        let mut pq = HugrPQ::new(cost_fn, 10);
+        // Prioritise the most expensive circuits.
+        let mut rev_pq =
+            HugrPQ::new(cost_fn, 10).with_priority(Some(|_: &Hugr, cost: usize| -(cost as isize)));
+        for n in [2, 1, 3] {
+            pq.push(n_cx(n));
+            rev_pq.push(n_cx(n));
+        }
+        assert_eq!(pq.max_cost(), Some(&3));
+        assert_eq!(rev_pq.max_cost(), Some(&3));
+        assert_eq!(rev_pq.max_priority(), Some(&(-1, 1)));
+
+        assert_eq!(pop_costs(&mut pq), vec![1, 2, 3]);
+        assert_eq!(pop_costs(&mut rev_pq), vec![3, 2, 1]);
+        assert_eq!(rev_pq.max_cost(), None);
+    }
+
+    /// Push circuits with costs 1 to `n` into a full queue of size `n`, then
+    /// one more circuit with cost 1 to trigger the truncation.
+    fn truncated_costs(truncation: TruncationStrategy) -> Vec<usize> {
+        let cost_fn = |circ: &Hugr| circ.num_gates();
+        let mut pq = HugrPQ::new(cost_fn, 6).with_truncation(truncation);
+        for n in 1..=6 {
+            pq.push(n_cx(n));
+        }
+        assert!(pq.is_full());
+        pq.push(
+            build_simple_circuit(2, |circ| {
+                circ.append(Tk2Op::H, [0])?;
+                Ok(())
+            })
+            .unwrap(),
+        );
+        pop_costs(&mut pq)
+    }
+
+    #[test]
+    fn truncate_drop_worst() {
+        assert_eq!(
+            truncated_costs(TruncationStrategy::DropWorst),
+            [1, 1, 2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn truncate_halve_worst() {
+        assert_eq!(
+            truncated_costs(TruncationStrategy::HalveWorst),
+            [1, 1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn truncate_keep_top_n() {
+        assert_eq!(truncated_costs(TruncationStrategy::KeepTopN(2)), [1, 1, 2]);
+        // At least one circuit is discarded.
+        assert_eq!(
+            truncated_costs(TruncationStrategy::KeepTopN(10)),
+            [1, 1, 2, 3, 4, 5]
+        );
+    }
+
+    #[test]
+    fn truncate_cost_threshold() {
+        assert_eq!(
+            truncated_costs(TruncationStrategy::CostThreshold(3.)),
+            [1, 1, 2, 3]
+        );
+        // Every circuit is within the threshold: drop the worst one.
+        assert_eq!(
+            truncated_costs(TruncationStrategy::CostThreshold(10.)),
+            [1, 1, 2, 3, 4, 5]
+        );
+    }
+}
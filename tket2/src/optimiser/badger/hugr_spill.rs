@@ -0,0 +1,225 @@
+//! Temporary on-disk storage for circuits evicted from the priority queue.
+
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use thiserror::Error;
+
+use super::hugr_pchannel::Work;
+
+/// Counter used to give a unique name to each spill file of the process.
+static SPILL_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// A min-priority store of Hugrs, kept in a temporary file.
+///
+/// Circuits are serialised with msgpack and appended to the file, while their
+/// costs, hashes, and positions in the file are kept in memory. The file is
+/// compacted once more than half of it is taken by removed circuits, and
+/// deleted on drop.
+#[derive(Debug)]
+pub struct HugrSpill<P> {
+    /// The temporary file holding the serialised circuits.
+    file: File,
+    /// The path of the temporary file.
+    path: PathBuf,
+    /// Offset and length of each stored circuit, ordered by cost and hash.
+    index: BTreeMap<(P, u64), (u64, usize)>,
+    /// The end of the written data in the file.
+    end: u64,
+    /// The total size of the stored circuits.
+    live: u64,
+}
+
+impl<P: Ord> HugrSpill<P> {
+    /// Create an empty store backed by a new temporary file.
+    pub fn new() -> io::Result<Self> {
+        let id = SPILL_FILE_COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!(
+            "tket2_badger_spill_{}_{id}.msgpack",
+            std::process::id()
+        ));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&path)?;
+        Ok(Self {
+            file,
+            path,
+            index: BTreeMap::new(),
+            end: 0,
+            live: 0,
+        })
+    }
+
+    /// Write a circuit to the store.
+    pub fn push(&mut self, work: Work<P>) -> Result<(), SpillError> {
+        let bytes = rmp_serde::encode::to_vec(&work.circ)?;
+        self.file.seek(SeekFrom::Start(self.end))?;
+        self.file.write_all(&bytes)?;
+        self.index
+            .insert((work.cost, work.hash), (self.end, bytes.len()));
+        self.end += bytes.len() as u64;
+        self.live += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Read back and remove the circuit with the lowest cost.
+    pub fn pop_min(&mut self) -> Result<Option<Work<P>>, SpillError> {
+        let Some(((cost, hash), (offset, len))) = self.index.pop_first() else {
+            return Ok(None);
+        };
+        let mut bytes = vec![0; len];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut bytes)?;
+        self.live -= len as u64;
+        self.reclaim()?;
+        let circ = rmp_serde::decode::from_slice(&bytes)?;
+        Ok(Some(Work { circ, cost, hash }))
+    }
+
+    /// Remove the circuit with the highest cost, without reading it.
+    ///
+    /// Returns `false` if the store was empty.
+    pub fn discard_max(&mut self) -> Result<bool, SpillError> {
+        let Some((_, (_, len))) = self.index.pop_last() else {
+            return Ok(false);
+        };
+        self.live -= len as u64;
+        self.reclaim()?;
+        Ok(true)
+    }
+
+    /// The lowest cost in the store.
+    pub fn min_cost(&self) -> Option<&P> {
+        self.index.first_key_value().map(|((cost, _), _)| cost)
+    }
+
+    /// The number of circuits in the store.
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    /// Free the space used by removed circuits once they take more than
+    /// half of the file.
+    ///
+    /// The stored circuits are moved to the start of the file in offset
+    /// order, so each one is written before the data of the next ones. An
+    /// error leaves the index consistent with the file contents.
+    fn reclaim(&mut self) -> io::Result<()> {
+        if self.end <= 2 * self.live {
+            return Ok(());
+        }
+        let mut entries: Vec<_> = self.index.values_mut().collect();
+        entries.sort_unstable_by_key(|(offset, _)| *offset);
+        let mut new_end = 0;
+        let mut bytes = Vec::new();
+        for (offset, len) in entries {
+            if *offset != new_end {
+                bytes.resize(*len, 0);
+                self.file.seek(SeekFrom::Start(*offset))?;
+                self.file.read_exact(&mut bytes)?;
+                self.file.seek(SeekFrom::Start(new_end))?;
+                self.file.write_all(&bytes)?;
+                *offset = new_end;
+            }
+            new_end += *len as u64;
+        }
+        self.file.set_len(new_end)?;
+        self.end = new_end;
+        Ok(())
+    }
+}
+
+impl<P> Drop for HugrSpill<P> {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Errors that can occur when spilling circuits to disk.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum SpillError {
+    /// An IO error occurred
+    #[error("IO error: {0}")]
+    Io(#[from] io::Error),
+    /// An error occurred during deserialisation
+    #[error("Deserialisation error: {0}")]
+    Deserialisation(#[from] rmp_serde::decode::Error),
+    /// An error occurred during serialisation
+    #[error("Serialisation error: {0}")]
+    Serialisation(#[from] rmp_serde::encode::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::CircuitHash;
+    use crate::utils::test::n_cx;
+    use crate::Circuit;
+
+    #[test]
+    fn spill_and_reload() {
+        let mut spill = HugrSpill::new().unwrap();
+        for n in [3, 1, 4, 2] {
+            let circ = n_cx(n);
+            let hash = circ.circuit_hash().unwrap();
+            spill
+                .push(Work {
+                    circ,
+                    cost: n,
+                    hash,
+                })
+                .unwrap();
+        }
+        assert_eq!(spill.len(), 4);
+        assert_eq!(spill.min_cost(), Some(&1));
+
+        assert!(spill.discard_max().unwrap());
+        let mut costs = Vec::new();
+        while let Some(Work { circ, cost, hash }) = spill.pop_min().unwrap() {
+            assert_eq!(circ.circuit_hash().unwrap(), hash);
+            assert_eq!(circ.num_gates(), cost);
+            costs.push(cost);
+        }
+        assert_eq!(costs, [1, 2, 3]);
+        assert_eq!(spill.len(), 0);
+        assert_eq!(spill.end, 0);
+    }
+
+    #[test]
+    fn spill_compaction() {
+        let mut spill = HugrSpill::new().unwrap();
+        for n in 1..=4 {
+            let circ = n_cx(n);
+            let hash = circ.circuit_hash().unwrap();
+            spill
+                .push(Work {
+                    circ,
+                    cost: n,
+                    hash,
+                })
+                .unwrap();
+        }
+        let full_size = spill.end;
+        // Removing the two smallest circuits does not free half of the file.
+        spill.pop_min().unwrap();
+        spill.pop_min().unwrap();
+        assert_eq!(spill.end, full_size);
+        // Once most of the file is unused, the remaining circuit is moved to
+        // its start.
+        spill.discard_max().unwrap();
+        assert_eq!(spill.end, spill.live);
+        assert_eq!(spill.file.metadata().unwrap().len(), spill.end);
+
+        let Work { circ, cost, hash } = spill.pop_min().unwrap().unwrap();
+        assert_eq!(cost, 3);
+        assert_eq!(circ.num_gates(), 3);
+        assert_eq!(circ.circuit_hash().unwrap(), hash);
+    }
+}
@@ -8,6 +8,7 @@ pub struct BadgerLogger<'w> {
     circ_candidates_csv: Option<csv::Writer<Box<dyn io::Write + 'w>>>,
     last_circ_processed: usize,
     last_progress_time: Instant,
+    circuits_seen: Option<usize>,
 }
 
 impl<'w> Default for BadgerLogger<'w> {
@@ -17,6 +18,7 @@ impl<'w> Default for BadgerLogger<'w> {
             last_circ_processed: Default::default(),
             // Ensure the first progress message is printed.
             last_progress_time: Instant::now() - Duration::from_secs(60),
+            circuits_seen: None,
         }
     }
 }
@@ -69,13 +71,14 @@ impl<'w> BadgerLogger<'w> {
     /// Log the final optimised circuit
     #[inline]
     pub fn log_processing_end<C: Debug>(
-        &self,
+        &mut self,
         circuits_processed: usize,
         circuits_seen: Option<usize>,
         best_cost: C,
         needs_joining: bool,
         timeout: bool,
     ) {
+        self.circuits_seen = circuits_seen;
         match timeout {
             true => self.log("Optimisation finished (timeout)."),
             false => self.log("Optimisation finished."),
@@ -92,6 +95,12 @@ impl<'w> BadgerLogger<'w> {
         }
     }
 
+    /// The number of distinct circuits seen by the last optimisation run, if
+    /// it was recorded.
+    pub fn circuits_seen(&self) -> Option<usize> {
+        self.circuits_seen
+    }
+
     /// Log the progress of the optimisation.
     #[inline(always)]
     pub fn log_progress(
@@ -0,0 +1,140 @@
+//! A bounded set of the best circuits found during an optimisation.
+
+use std::collections::BinaryHeap;
+
+use fxhash::FxHashSet;
+
+/// Keeps track of the `capacity` distinct circuits with the lowest cost.
+///
+/// Circuits are deduplicated using their hash. Ties between circuits with the
+/// same cost are broken by insertion order, preferring the earliest circuit.
+///
+/// The circuit type `T` is generic so that the set can also be used to keep
+/// track of costs and hashes only.
+#[derive(Debug, Clone)]
+pub(super) struct NBestCircuits<T, P: Ord> {
+    /// Max-heap keyed on the cost, with the worst retained circuit on top.
+    heap: BinaryHeap<NBestEntry<T, P>>,
+    /// The hashes of the circuits in the heap.
+    hashes: FxHashSet<u64>,
+    /// The maximum number of circuits to retain.
+    capacity: usize,
+    /// The number of circuits inserted so far, used to break ties.
+    insertions: usize,
+}
+
+#[derive(Debug, Clone)]
+struct NBestEntry<T, P> {
+    cost: P,
+    order: usize,
+    hash: u64,
+    circ: T,
+}
+
+impl<T, P: Ord> NBestCircuits<T, P> {
+    /// Create a new set retaining at most `capacity` circuits.
+    ///
+    /// At least one circuit is always retained.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            heap: BinaryHeap::with_capacity(capacity),
+            hashes: FxHashSet::default(),
+            capacity,
+            insertions: 0,
+        }
+    }
+
+    /// Check whether a circuit would be inserted in the set.
+    pub fn check_accepted(&self, hash: u64, cost: &P) -> bool {
+        if self.hashes.contains(&hash) {
+            return false;
+        }
+        match self.heap.peek() {
+            Some(worst) if self.heap.len() >= self.capacity => cost < &worst.cost,
+            _ => true,
+        }
+    }
+
+    /// Insert a circuit in the set, evicting the worst circuit if the set is
+    /// full.
+    ///
+    /// Returns `true` if the circuit was inserted.
+    pub fn insert(&mut self, circ: T, hash: u64, cost: P) -> bool {
+        if !self.check_accepted(hash, &cost) {
+            return false;
+        }
+        if self.heap.len() >= self.capacity {
+            let evicted = self.heap.pop().unwrap();
+            self.hashes.remove(&evicted.hash);
+        }
+        self.hashes.insert(hash);
+        self.heap.push(NBestEntry {
+            cost,
+            order: self.insertions,
+            hash,
+            circ,
+        });
+        self.insertions += 1;
+        true
+    }
+
+    /// The number of retained circuits.
+    #[allow(unused)]
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    /// Returns `true` if no circuits have been retained.
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    /// Returns the retained circuits with their costs, sorted by ascending
+    /// cost.
+    pub fn into_sorted_vec(self) -> Vec<(T, P)> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|entry| (entry.circ, entry.cost))
+            .collect()
+    }
+}
+
+impl<T, P: Ord> PartialEq for NBestEntry<T, P> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<T, P: Ord> Eq for NBestEntry<T, P> {}
+
+impl<T, P: Ord> PartialOrd for NBestEntry<T, P> {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, P: Ord> Ord for NBestEntry<T, P> {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (&self.cost, self.order).cmp(&(&other.cost, other.order))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_n_best() {
+        let mut n_best = NBestCircuits::new(3);
+        assert!(n_best.insert("a", 0, 5));
+        assert!(n_best.insert("b", 1, 3));
+        assert!(!n_best.insert("b", 1, 3));
+        assert!(n_best.insert("c", 2, 4));
+        assert!(n_best.insert("d", 3, 1));
+        assert!(!n_best.insert("e", 4, 4));
+        assert_eq!(n_best.len(), 3);
+        assert_eq!(n_best.into_sorted_vec(), vec![("d", 1), ("b", 3), ("c", 4)]);
+    }
+}
@@ -1,8 +1,11 @@
 use std::collections::HashMap;
-use std::io;
+use std::fs::File;
+use std::io::{self, Read};
 use std::path::Path;
 
-use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+use flate2::read::GzDecoder;
+
+use hugr::builder::{BuildError, DFGBuilder, Dataflow, DataflowHugr};
 use hugr::extension::prelude::QB_T;
 use hugr::ops::OpType as Op;
 use hugr::std_extensions::arithmetic::float_types::FLOAT64_TYPE;
@@ -11,6 +14,7 @@ use hugr::CircuitUnit;
 use hugr::Hugr as Circuit;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 use crate::Tk2Op;
 
@@ -40,11 +44,65 @@ struct RepCircData {
     circ: RepCirc,
 }
 
-fn map_op(opstr: &str) -> Op {
+/// Errors that can occur when loading a Quartz circuit.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum QtzLoadError {
+    /// The circuit contains an operation that is not supported.
+    #[error("unknown op '{op}' in circuit {circuit}")]
+    UnknownOp {
+        /// The Quartz name of the operation.
+        op: String,
+        /// The id of the circuit.
+        circuit: String,
+    },
+    /// An operation uses a wire that is not defined.
+    #[error("unknown wire '{wire}' in circuit {circuit}")]
+    UnknownWire {
+        /// The Quartz identifier of the wire.
+        wire: String,
+        /// The id of the circuit.
+        circuit: String,
+    },
+    /// The circuit could not be built.
+    #[error("could not build circuit {circuit}: {error}")]
+    Build {
+        /// The error raised by the builder.
+        error: BuildError,
+        /// The id of the circuit.
+        circuit: String,
+    },
+    /// The file could not be read.
+    #[error("could not read ECC file: {0}")]
+    Io(#[from] io::Error),
+    /// The file is not a valid Quartz ECC set.
+    #[error("invalid ECC file: {0}")]
+    InvalidJson(#[from] serde_json::Error),
+    /// An equivalence class in the file contains no circuits.
+    #[error("equivalence class {class} has no circuits")]
+    EmptyClass {
+        /// The key of the class in the file.
+        class: String,
+    },
+}
+
+impl From<QtzLoadError> for io::Error {
+    fn from(err: QtzLoadError) -> Self {
+        match err {
+            QtzLoadError::Io(err) => err,
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+fn map_op(opstr: &str) -> Option<Op> {
     // TODO, more
-    match opstr {
+    let op = match opstr {
         "h" => Tk2Op::H,
         "cx" => Tk2Op::CX,
+        "cz" => Tk2Op::CZ,
+        "cy" => Tk2Op::CY,
+        "swap" => Tk2Op::SWAP,
         "t" => Tk2Op::T,
         "s" => Tk2Op::S,
         "x" => Tk2Op::X,
@@ -54,21 +112,28 @@ fn map_op(opstr: &str) -> Op {
         "sdg" => Tk2Op::Sdg,
         "rz" => Tk2Op::RzF64,
         "add" => Tk2Op::AngleAdd,
-        x => panic!("unknown op {x}"),
-    }
-    .into()
+        _ => return None,
+    };
+    Some(op.into())
 }
 
-// TODO change to TryFrom
-impl From<RepCircData> for Circuit {
-    fn from(RepCircData { circ: rc, meta }: RepCircData) -> Self {
+impl TryFrom<RepCircData> for Circuit {
+    type Error = QtzLoadError;
+
+    fn try_from(RepCircData { circ: rc, meta }: RepCircData) -> Result<Self, QtzLoadError> {
+        let circuit_id = meta.id.first().cloned().unwrap_or_default();
+        let build_err = |error| QtzLoadError::Build {
+            error,
+            circuit: circuit_id.clone(),
+        };
+
         let qb_types: Vec<Type> = vec![QB_T; meta.n_qb];
         let param_types: Vec<Type> = vec![FLOAT64_TYPE; meta.n_input_param];
         let mut builder = DFGBuilder::new(FunctionType::new(
             [qb_types.clone(), param_types].concat(),
             qb_types,
         ))
-        .unwrap();
+        .map_err(build_err)?;
 
         // Current map between quartz qubit and parameter identifiers, and
         // circuit units. Since quartz defines output wires arbitrarily for each
@@ -92,11 +157,25 @@ impl From<RepCircData> for Circuit {
             outputs,
         } in rc.0
         {
-            let op = map_op(&opstr);
+            let op = map_op(&opstr).ok_or_else(|| QtzLoadError::UnknownOp {
+                op: opstr.clone(),
+                circuit: circuit_id.clone(),
+            })?;
 
             // Translate the quartz inputs into circuit units.
-            let inputs = inputs.iter().map(|inp| *input_units.get(inp).unwrap());
-            let hugr_outputs = circ.append_with_outputs(op, inputs).unwrap();
+            let inputs = inputs
+                .iter()
+                .map(|inp| {
+                    input_units
+                        .get(inp)
+                        .copied()
+                        .ok_or_else(|| QtzLoadError::UnknownWire {
+                            wire: inp.clone(),
+                            circuit: circuit_id.clone(),
+                        })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let hugr_outputs = circ.append_with_outputs(op, inputs).map_err(build_err)?;
 
             for (idx, wire) in outputs.iter().zip(hugr_outputs) {
                 input_units.insert(idx.to_string(), CircuitUnit::Wire(wire));
@@ -106,23 +185,41 @@ impl From<RepCircData> for Circuit {
         let circ_outputs = circ.finish();
         builder
             .finish_hugr_with_outputs(circ_outputs, &crate::extension::REGISTRY)
-            .unwrap()
+            .map_err(build_err)
     }
 }
 
-pub(super) fn load_ecc_set(path: impl AsRef<Path>) -> io::Result<HashMap<String, Vec<Circuit>>> {
-    let jsons = std::fs::read_to_string(path)?;
-    let (_, ecc_map): (Vec<()>, HashMap<String, Vec<RepCircData>>) =
-        serde_json::from_str(&jsons).unwrap();
-
-    Ok(ecc_map
-        .into_values()
-        .map(|datmap| {
-            let id = datmap[0].meta.id[0].clone();
-            let circs = datmap.into_iter().map(|rcd| rcd.into()).collect();
-            (id, circs)
+/// Load a set of equivalence classes from a Quartz JSON file.
+///
+/// Files with a `.gz` extension are decompressed before parsing.
+pub(super) fn load_ecc_set(
+    path: impl AsRef<Path>,
+) -> Result<HashMap<String, Vec<Circuit>>, QtzLoadError> {
+    let path = path.as_ref();
+    let jsons = if path.extension().is_some_and(|ext| ext == "gz") {
+        let mut jsons = String::new();
+        GzDecoder::new(File::open(path)?).read_to_string(&mut jsons)?;
+        jsons
+    } else {
+        std::fs::read_to_string(path)?
+    };
+    let (_, ecc_map): (Vec<()>, HashMap<String, Vec<RepCircData>>) = serde_json::from_str(&jsons)?;
+
+    ecc_map
+        .into_iter()
+        .map(|(class, datmap)| {
+            let id = datmap
+                .first()
+                .and_then(|dat| dat.meta.id.first())
+                .cloned()
+                .ok_or(QtzLoadError::EmptyClass { class })?;
+            let circs = datmap
+                .into_iter()
+                .map(Circuit::try_from)
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok((id, circs))
         })
-        .collect())
+        .collect()
 }
 
 #[cfg(test)]
@@ -132,12 +229,14 @@ mod tests {
     use hugr::HugrView;
 
     use super::*;
+    use crate::utils::test::TempPath;
+
     fn load_representative_set(path: &str) -> HashMap<String, Circuit> {
         let jsons = std::fs::read_to_string(path).unwrap();
         // read_rep_json(&jsons).unwrap();
         let st: Vec<RepCircData> = serde_json::from_str(&jsons).unwrap();
         st.into_iter()
-            .map(|mut rcd| (rcd.meta.id.remove(0), rcd.into()))
+            .map(|mut rcd| (rcd.meta.id.remove(0), rcd.try_into().unwrap()))
             .collect()
     }
 
@@ -152,6 +251,43 @@ mod tests {
         }
     }
 
+    #[test]
+    fn unknown_op() {
+        let rcd: RepCircData = serde_json::from_value(serde_json::json!({
+            "meta": {
+                "n_qb": 1,
+                "n_input_param": 0,
+                "n_total_param": 0,
+                "num_gates": 1,
+                "id": ["circ_42"],
+                "fingerprint": [0.0],
+            },
+            "circ": [{"opstr": "foobar", "outputs": ["Q0"], "inputs": ["Q0"]}],
+        }))
+        .unwrap();
+        let err = Circuit::try_from(rcd).unwrap_err();
+        assert!(matches!(err, QtzLoadError::UnknownOp { .. }));
+        let msg = err.to_string();
+        assert!(msg.contains("foobar"));
+        assert!(msg.contains("circ_42"));
+    }
+
+    #[test]
+    fn empty_class() {
+        let path = TempPath::new("empty_class.json");
+        std::fs::write(&path, r#"[[], {"class_0": []}]"#).unwrap();
+        let err = load_ecc_set(&path).unwrap_err();
+        assert!(matches!(err, QtzLoadError::EmptyClass { class } if class == "class_0"));
+    }
+
+    #[test]
+    fn invalid_json() {
+        let path = TempPath::new("invalid_ecc.json");
+        std::fs::write(&path, "not json").unwrap();
+        let err = load_ecc_set(&path).unwrap_err();
+        assert!(matches!(err, QtzLoadError::InvalidJson(_)));
+    }
+
     #[test]
     #[cfg_attr(miri, ignore)] // Opening files is not supported in (isolated) miri
     fn test_read_complete() {
@@ -162,4 +298,39 @@ mod tests {
         //     .flatten()
         //     .for_each(|c| check_soundness(c).unwrap());
     }
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // Opening files is not supported in (isolated) miri
+    fn test_read_gzipped() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let json_path = "../test_files/h_rz_cxcomplete_ECC_set.json";
+        let gz_path = TempPath::new("h_rz_cxcomplete_ECC_set.json.gz");
+        let mut encoder = GzEncoder::new(File::create(&gz_path).unwrap(), Compression::fast());
+        encoder
+            .write_all(&std::fs::read(json_path).unwrap())
+            .unwrap();
+        encoder.finish().unwrap();
+
+        let summary = |ecc: HashMap<String, Vec<Circuit>>| {
+            ecc.into_iter()
+                .map(|(id, circs)| {
+                    (
+                        id,
+                        circs
+                            .iter()
+                            .map(|c| serde_json::to_string(c).unwrap())
+                            .collect_vec(),
+                    )
+                })
+                .sorted()
+                .collect_vec()
+        };
+        let plain = summary(load_ecc_set(json_path).unwrap());
+        let gzipped = summary(load_ecc_set(&gz_path).unwrap());
+        assert!(!plain.is_empty());
+        assert_eq!(plain, gzipped);
+    }
 }
@@ -3,11 +3,11 @@
 use std::thread::{self, JoinHandle};
 
 use crate::circuit::cost::CircuitCost;
-use crate::circuit::CircuitHash;
 use crate::rewrite::strategy::RewriteStrategy;
 use crate::rewrite::Rewriter;
 
 use super::hugr_pchannel::{PriorityChannelCommunication, Work};
+use super::BadgerOptions;
 
 /// A worker that processes circuits for the Badger optimiser.
 pub struct BadgerWorker<R, S, P: Ord> {
@@ -20,6 +20,8 @@ pub struct BadgerWorker<R, S, P: Ord> {
     rewriter: R,
     /// The rewrite strategy to use.
     strategy: S,
+    /// The optimiser options, used to hash the new circuits.
+    options: BadgerOptions,
 }
 
 impl<R, S, P> BadgerWorker<R, S, P>
@@ -35,6 +37,7 @@ where
         priority_channel: PriorityChannelCommunication<P>,
         rewriter: R,
         strategy: S,
+        options: BadgerOptions,
     ) -> JoinHandle<()> {
         let name = format!("BadgerWorker-{id}");
         thread::Builder::new()
@@ -45,6 +48,7 @@ where
                     priority_channel,
                     rewriter,
                     strategy,
+                    options,
                 };
                 worker.run_loop()
             })
@@ -73,7 +77,7 @@ where
                         return None;
                     }
 
-                    let Ok(hash) = r.circ.circuit_hash() else {
+                    let Ok(hash) = self.options.dedup_hash(&r.circ) else {
                         // The composed rewrites were not valid.
                         //
                         // See [https://github.com/CQCL/tket2/discussions/242]
@@ -88,8 +92,13 @@ where
                 })
                 .collect();
 
-            let send = tracing::trace_span!(target: "badger::metrics", "BadgerWorker::send_result")
-                .in_scope(|| self.priority_channel.send(new_circs));
+            // Sending may block if the priority channel is falling behind.
+            let send = tracing::trace_span!(
+                target: "badger::metrics",
+                "BadgerWorker::send_result",
+                queued = self.priority_channel.queued_len()
+            )
+            .in_scope(|| self.priority_channel.send_processed(new_circs));
             if send.is_err() {
                 // Terminating
                 break;
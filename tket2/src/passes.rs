@@ -1,7 +1,45 @@
 //! Optimisation passes and related utilities for circuits.
 
 mod commutation;
-pub use commutation::{apply_greedy_commutation, PullForwardError};
+pub use commutation::{apply_greedy_commutation, commute_to_minimise_depth, PullForwardError};
+
+pub mod pauli;
+pub use pauli::{track_paulis, PauliFrame};
+
+pub mod rotations;
+pub use rotations::{merge_rotations_through_swaps, merge_rz_rotations};
+
+pub mod identities;
+pub use identities::remove_identities;
+
+pub mod cancel;
+pub use cancel::cancel_inverse_pairs;
+
+pub mod fold;
+pub use fold::fold_angle_constants;
+
+pub mod reset;
+pub use reset::simplify_before_reset;
+
+pub mod invert;
+pub use invert::{invert_circuit, UninvertibleOp};
+
+pub mod rebase;
+pub use rebase::{decompose_tk1, rebase_to_nam, RebaseError};
+
+pub mod clifford_t;
+pub use clifford_t::{approximate_rz, clifford_t_approximate};
+
+#[cfg(feature = "portmatching")]
+pub mod greedy;
+#[cfg(feature = "portmatching")]
+pub use greedy::greedy_rewrite;
 
 pub mod chunks;
-pub use chunks::CircuitChunks;
+pub use chunks::{ChunkReassembleError, CircuitChunks};
+
+pub mod layers;
+pub use layers::layerize;
+
+pub mod timing;
+pub use timing::{run_passes_timed, PassFn, PassTimer};
@@ -0,0 +1,128 @@
+//! Cancellation of adjacent inverse gates.
+
+use std::collections::HashSet;
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{Hugr, HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+
+use crate::circuit::Circuit;
+use crate::Tk2Op;
+
+/// Remove pairs of adjacent gates that are inverse of each other.
+///
+/// Two gates cancel when the second is the [`Tk2Op::dagger`] of the first and
+/// each qubit output of the first is connected to the same qubit input of the
+/// second. For instance, `H-H`, `S-Sdg` and `CX-CX` with the same control and
+/// target cancel, but `CX-CX` with the qubits swapped does not. Pairs are
+/// removed until none are left, so nested pairs such as `S-H-H-Sdg` are also
+/// removed.
+///
+/// Returns the number of gates removed.
+pub fn cancel_inverse_pairs(circ: &mut Hugr) -> usize {
+    let mut n_removed = 0;
+    loop {
+        let pairs = find_inverse_pairs(circ);
+        if pairs.is_empty() {
+            return n_removed;
+        }
+        for &(first, second, n_qubits) in &pairs {
+            remove_pair(circ, first, second, n_qubits);
+        }
+        n_removed += 2 * pairs.len();
+    }
+}
+
+/// Find disjoint pairs of adjacent inverse gates, along with their number of
+/// qubits.
+fn find_inverse_pairs(circ: &Hugr) -> Vec<(Node, Node, usize)> {
+    let mut pairs = Vec::new();
+    let mut paired = HashSet::new();
+    for cmd in circ.commands() {
+        let first = cmd.node();
+        if paired.contains(&first) {
+            continue;
+        }
+        let Some((second, n_qubits)) = inverse_successor(circ, first) else {
+            continue;
+        };
+        paired.extend([first, second]);
+        pairs.push((first, second, n_qubits));
+    }
+    pairs
+}
+
+/// The successor of `node` that cancels with it and the number of qubits of
+/// the two gates, if any.
+fn inverse_successor(circ: &Hugr, node: Node) -> Option<(Node, usize)> {
+    let op = Tk2Op::try_from(circ.get_optype(node)).ok()?;
+    let inverse = op.dagger()?;
+    let n_qubits = op.n_qubits();
+    let mut successors = (0..n_qubits).map(|i| {
+        let (next, port) = circ
+            .linked_inputs(node, OutgoingPort::from(i))
+            .exactly_one()
+            .ok()?;
+        (port.index() == i).then_some(next)
+    });
+    let next = successors.next()??;
+    if !successors.all(|n| n == Some(next)) {
+        return None;
+    }
+    (Tk2Op::try_from(circ.get_optype(next)).ok()? == inverse).then_some((next, n_qubits))
+}
+
+/// Remove two cancelling gates, reconnecting the qubit wires around them.
+fn remove_pair(circ: &mut Hugr, first: Node, second: Node, n_qubits: usize) {
+    let wires = (0..n_qubits)
+        .map(|i| {
+            let (src, src_port) = circ
+                .linked_outputs(first, IncomingPort::from(i))
+                .exactly_one()
+                .ok()
+                .expect("Invalid circuit: qubit input is not connected.");
+            let (dst, dst_port) = circ
+                .linked_inputs(second, OutgoingPort::from(i))
+                .exactly_one()
+                .ok()
+                .expect("Invalid circuit: qubit output is not connected.");
+            (src, src_port, dst, dst_port)
+        })
+        .collect_vec();
+    for node in [first, second] {
+        circ.remove_node(node)
+            .expect("Invalid circuit: could not remove node.");
+    }
+    for (src, src_port, dst, dst_port) in wires {
+        circ.connect(src, src_port.index(), dst, dst_port.index())
+            .expect("Invalid circuit: could not reconnect qubit.");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::utils::build_simple_circuit;
+
+    #[rstest]
+    #[case(vec![(Tk2Op::H, vec![0]), (Tk2Op::H, vec![0])], 2)]
+    #[case(vec![(Tk2Op::S, vec![0]), (Tk2Op::Sdg, vec![0])], 2)]
+    #[case(vec![(Tk2Op::H, vec![0]), (Tk2Op::X, vec![0])], 0)]
+    #[case(vec![(Tk2Op::CX, vec![0, 1]), (Tk2Op::CX, vec![0, 1])], 2)]
+    #[case(vec![(Tk2Op::CX, vec![0, 1]), (Tk2Op::CX, vec![1, 0])], 0)]
+    #[case(vec![(Tk2Op::T, vec![0]), (Tk2Op::H, vec![1]), (Tk2Op::H, vec![1]), (Tk2Op::Tdg, vec![0])], 4)]
+    #[case(vec![(Tk2Op::S, vec![0]), (Tk2Op::H, vec![0]), (Tk2Op::H, vec![0]), (Tk2Op::Sdg, vec![0])], 4)]
+    fn cancel_pairs(#[case] gates: Vec<(Tk2Op, Vec<usize>)>, #[case] expected: usize) {
+        let mut circ = build_simple_circuit(2, |circ| {
+            for (op, qubits) in &gates {
+                circ.append(*op, qubits.clone())?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(cancel_inverse_pairs(&mut circ), expected);
+        assert_eq!(circ.num_gates(), gates.len() - expected);
+    }
+}
@@ -18,6 +18,7 @@ use hugr::types::FunctionType;
 use hugr::{Hugr, HugrView, IncomingPort, Node, OutgoingPort, PortIndex, Wire};
 use itertools::Itertools;
 use portgraph::algorithms::ConvexChecker;
+use thiserror::Error;
 
 use crate::Circuit;
 
@@ -91,6 +92,24 @@ impl Chunk {
         }
     }
 
+    /// Check that the chunk's boundary still matches the wires it was
+    /// extracted from.
+    ///
+    /// The chunk circuit may have been replaced since the split, so its input
+    /// and output arities must be checked before reinserting it.
+    fn check_boundary(&self, index: usize) -> Result<(), ChunkReassembleError> {
+        let signature = self.circ.circuit_signature();
+        let (num_inputs, num_outputs) = (signature.input.len(), signature.output.len());
+        if num_inputs != self.inputs.len() || num_outputs != self.outputs.len() {
+            return Err(ChunkReassembleError::BoundaryMismatch {
+                index,
+                expected: (self.inputs.len(), self.outputs.len()),
+                actual: (num_inputs, num_outputs),
+            });
+        }
+        Ok(())
+    }
+
     /// Insert the chunk back into a circuit.
     pub(self) fn insert(&self, circ: &mut impl HugrMut, root: Node) -> ChunkInsertResult {
         if self.circ.children(self.circ.root()).nth(2).is_none() {
@@ -227,6 +246,37 @@ enum ConnectionTarget {
     TransitiveConnection(ChunkConnection),
 }
 
+/// Error from reassembling a [`CircuitChunks`] into a circuit.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChunkReassembleError {
+    /// A chunk was replaced by a circuit with a different number of inputs or
+    /// outputs than the original chunk.
+    #[error(
+        "Chunk {index} has {} inputs and {} outputs, but the original chunk had {} inputs and {} outputs.",
+        actual.0, actual.1, expected.0, expected.1
+    )]
+    BoundaryMismatch {
+        /// The index of the offending chunk.
+        index: usize,
+        /// The number of inputs and outputs of the original chunk.
+        expected: (usize, usize),
+        /// The number of inputs and outputs of the updated chunk.
+        actual: (usize, usize),
+    },
+    /// The reassembled circuit does not have the original signature.
+    #[error("The reassembled circuit has signature {actual}, but the original circuit had signature {expected}.")]
+    SignatureMismatch {
+        /// The signature of the original circuit.
+        expected: FunctionType,
+        /// The signature of the reassembled circuit.
+        actual: FunctionType,
+    },
+    /// Error in hugr mutation.
+    #[error("Hugr mutation error: {0:?}")]
+    HugrError(#[from] HugrError),
+}
+
 /// An utility for splitting a circuit into chunks, and reassembling them
 /// afterwards.
 ///
@@ -307,7 +357,18 @@ impl CircuitChunks {
     }
 
     /// Reassemble the chunks into a circuit.
-    pub fn reassemble(self) -> Result<Hugr, HugrError> {
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a chunk was replaced by a circuit with a different
+    /// number of inputs or outputs, or if the reassembled circuit does not
+    /// have the original circuit's signature.
+    pub fn reassemble(self) -> Result<Hugr, ChunkReassembleError> {
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            chunk.check_boundary(index)?;
+        }
+        let signature = self.signature.clone();
+
         let name = self
             .root_meta
             .as_ref()
@@ -427,6 +488,14 @@ impl CircuitChunks {
 
         reassembled.overwrite_node_metadata(root, self.root_meta)?;
 
+        let reassembled_signature = reassembled.circuit_signature();
+        if reassembled_signature != signature {
+            return Err(ChunkReassembleError::SignatureMismatch {
+                expected: signature,
+                actual: reassembled_signature,
+            });
+        }
+
         Ok(reassembled)
     }
 
@@ -535,4 +604,38 @@ mod test {
             &[h, out, out]
         );
     }
+
+    #[test]
+    fn reassemble_arity_mismatch() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let mut chunks = CircuitChunks::split(&circ, 1);
+        assert_eq!(chunks.len(), 2);
+
+        // Replace the two-qubit CX chunk with a single-qubit circuit.
+        chunks[1] = build_simple_circuit(1, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let err = chunks.reassemble().unwrap_err();
+        assert_eq!(
+            err,
+            ChunkReassembleError::BoundaryMismatch {
+                index: 1,
+                expected: (2, 2),
+                actual: (1, 1),
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Chunk 1 has 1 inputs and 1 outputs, but the original chunk had 2 inputs and 2 outputs."
+        );
+    }
 }
@@ -0,0 +1,313 @@
+//! Approximate decomposition of arbitrary Rz rotations into Clifford+T gates.
+//!
+//! Rotations are approximated by an exhaustive search over single-qubit
+//! Clifford+T circuits in Matsumoto-Amano normal form, in order of increasing
+//! T-count. This is far from the optimal T-counts achieved by number-theoretic
+//! methods (e.g. gridsynth), but is simple and exact for small precisions.
+
+use hugr::{Hugr, HugrView, IncomingPort};
+use itertools::Itertools;
+use num_complex::Complex64;
+
+use super::identities::remove_gate_with_params;
+use crate::circuit::unitary::{I, ONE, ZERO};
+use crate::circuit::Circuit;
+use crate::json::discard_global_phase;
+use crate::utils::{constant_f64_input, insert_1q_gate_before};
+use crate::Tk2Op;
+
+/// The maximum T-count of the approximations explored by [`approximate_rz`].
+pub const MAX_T_COUNT: usize = 16;
+
+/// Absolute tolerance used to identify exact Clifford+T angles.
+const EXACT_TOLERANCE: f64 = 1e-10;
+
+/// Distance under which two unitaries are considered equal up to phase.
+///
+/// This is larger than [`EXACT_TOLERANCE`], as [`distance`] takes a square
+/// root of the rounding errors.
+const UNITARY_TOLERANCE: f64 = 1e-6;
+
+/// A row-major 2x2 complex matrix.
+type Mat2 = [Complex64; 4];
+
+/// Returns a sequence of Clifford+T gates approximating `Rz(angle)` up to a
+/// global phase, within an operator-norm distance of `epsilon`.
+///
+/// The `angle` is given as a multiple of π, as for [`Tk2Op::RzF64`]. The
+/// gates are listed in the order they are applied.
+///
+/// Rotations by multiples of π/4 are decomposed exactly. Otherwise, the
+/// search is limited to circuits with at most [`MAX_T_COUNT`] T gates; if
+/// `epsilon` cannot be reached within that limit, the closest approximation
+/// found is returned instead.
+pub fn approximate_rz(angle: f64, epsilon: f64) -> Vec<Tk2Op> {
+    let quarters = angle * 4.;
+    if (quarters - quarters.round()).abs() < EXACT_TOLERANCE {
+        return exact_rz(quarters.round() as i64);
+    }
+
+    let target = rz_matrix(angle);
+    let cliffords = cliffords();
+    let t = gate_matrix(Tk2Op::T);
+
+    // The words in the current layer, as (syllable choices, matrix).
+    let mut layer: Vec<(u32, Mat2)> = vec![(0, identity())];
+    let mut best: Option<(f64, Vec<Tk2Op>)> = None;
+    for n_syllables in 0..=MAX_T_COUNT {
+        for leading_t in [false, true] {
+            if n_syllables + leading_t as usize > MAX_T_COUNT {
+                continue;
+            }
+            for &(syllables, ref mat) in &layer {
+                let mat = if leading_t { mul(&t, mat) } else { *mat };
+                for (cliff_gates, cliff) in &cliffords {
+                    let dist = distance(&mul(&mat, cliff), &target);
+                    if best.as_ref().map_or(true, |(d, _)| dist < *d) {
+                        let gates = word_gates(cliff_gates, syllables, n_syllables, leading_t);
+                        best = Some((dist, gates));
+                    }
+                }
+            }
+            if best.as_ref().is_some_and(|(d, _)| *d <= epsilon) {
+                return best.unwrap().1;
+            }
+        }
+        layer = next_layer(&layer, n_syllables);
+    }
+    best.unwrap().1
+}
+
+/// Replace every non-Clifford [`Tk2Op::RzF64`] with a constant angle by a
+/// Clifford+T approximation within operator-norm distance `epsilon`.
+///
+/// See [`approximate_rz`] for details on the approximation. Rotations with
+/// non-constant angles are left untouched. As the approximations are only
+/// correct up to a global phase, the recorded global phase of the circuit is
+/// discarded, see [`crate::json::circuit_global_phase`].
+///
+/// Returns the total number of T gates introduced.
+pub fn clifford_t_approximate(circ: &mut Hugr, epsilon: f64) -> usize {
+    let rotations = circ
+        .commands()
+        .filter(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(Tk2Op::RzF64))
+        .filter_map(|cmd| {
+            let angle = constant_f64_input(circ, cmd.node(), IncomingPort::from(1))?;
+            let quarters = angle * 2.;
+            let is_clifford = (quarters - quarters.round()).abs() < EXACT_TOLERANCE;
+            (!is_clifford).then_some((cmd.node(), angle))
+        })
+        .collect_vec();
+
+    if !rotations.is_empty() {
+        discard_global_phase(circ);
+    }
+    let mut t_count = 0;
+    for (node, angle) in rotations {
+        let gates = approximate_rz(angle, epsilon);
+        t_count += gates
+            .iter()
+            .filter(|op| matches!(op, Tk2Op::T | Tk2Op::Tdg))
+            .count();
+        for op in gates {
+            insert_1q_gate_before(circ, node, IncomingPort::from(0), op);
+        }
+        remove_gate_with_params(circ, node);
+    }
+    t_count
+}
+
+/// The exact decomposition of a rotation by `quarters`·π/4.
+fn exact_rz(quarters: i64) -> Vec<Tk2Op> {
+    match quarters.rem_euclid(8) {
+        0 => vec![],
+        1 => vec![Tk2Op::T],
+        2 => vec![Tk2Op::S],
+        3 => vec![Tk2Op::S, Tk2Op::T],
+        4 => vec![Tk2Op::Z],
+        5 => vec![Tk2Op::Z, Tk2Op::T],
+        6 => vec![Tk2Op::Sdg],
+        7 => vec![Tk2Op::Tdg],
+        _ => unreachable!(),
+    }
+}
+
+/// Extend every word in a layer by one `HT` or `SHT` syllable.
+///
+/// The syllable choices of a word with `n_syllables` are stored as bits,
+/// with bit `i` set if the `i`-th syllable is `SHT`.
+fn next_layer(layer: &[(u32, Mat2)], n_syllables: usize) -> Vec<(u32, Mat2)> {
+    let ht = mul(&gate_matrix(Tk2Op::H), &gate_matrix(Tk2Op::T));
+    let sht = mul(&gate_matrix(Tk2Op::S), &ht);
+    layer
+        .iter()
+        .flat_map(|(syllables, mat)| {
+            [
+                (*syllables, mul(mat, &ht)),
+                (syllables | 1 << n_syllables, mul(mat, &sht)),
+            ]
+        })
+        .collect()
+}
+
+/// The gates of a normal form word `(T|ε)(HT|SHT)*C`, in application order.
+fn word_gates(
+    clifford: &[Tk2Op],
+    syllables: u32,
+    n_syllables: usize,
+    leading_t: bool,
+) -> Vec<Tk2Op> {
+    let mut gates = clifford.to_vec();
+    // The syllables are applied right to left.
+    for i in (0..n_syllables).rev() {
+        gates.extend([Tk2Op::T, Tk2Op::H]);
+        if syllables & (1 << i) != 0 {
+            gates.push(Tk2Op::S);
+        }
+    }
+    if leading_t {
+        gates.push(Tk2Op::T);
+    }
+    gates
+}
+
+/// The 24 single-qubit Clifford operations up to global phase, with a gate
+/// sequence implementing each of them.
+fn cliffords() -> Vec<(Vec<Tk2Op>, Mat2)> {
+    let mut cliffords: Vec<(Vec<Tk2Op>, Mat2)> = vec![(vec![], identity())];
+    let mut i = 0;
+    while i < cliffords.len() {
+        for op in [Tk2Op::H, Tk2Op::S] {
+            let (gates, mat) = &cliffords[i];
+            let new_mat = mul(&gate_matrix(op), mat);
+            if cliffords
+                .iter()
+                .all(|(_, m)| distance(m, &new_mat) > UNITARY_TOLERANCE)
+            {
+                let mut new_gates = gates.clone();
+                new_gates.push(op);
+                cliffords.push((new_gates, new_mat));
+            }
+        }
+        i += 1;
+    }
+    cliffords
+}
+
+/// The operator-norm distance between two unitaries, minimised over global
+/// phases.
+fn distance(u: &Mat2, v: &Mat2) -> f64 {
+    let trace = (0..4)
+        .map(|i| u[i].conj() * v[i])
+        .fold(ZERO, |acc, x| acc + x);
+    (2. - trace.norm()).max(0.).sqrt()
+}
+
+fn identity() -> Mat2 {
+    [ONE, ZERO, ZERO, ONE]
+}
+
+fn mul(a: &Mat2, b: &Mat2) -> Mat2 {
+    [
+        a[0] * b[0] + a[1] * b[2],
+        a[0] * b[1] + a[1] * b[3],
+        a[2] * b[0] + a[3] * b[2],
+        a[2] * b[1] + a[3] * b[3],
+    ]
+}
+
+/// The matrix of `Rz(angle)`, with the angle given as a multiple of π.
+fn rz_matrix(angle: f64) -> Mat2 {
+    let half = angle * std::f64::consts::FRAC_PI_2;
+    [
+        Complex64::from_polar(1., -half),
+        ZERO,
+        ZERO,
+        Complex64::from_polar(1., half),
+    ]
+}
+
+fn gate_matrix(op: Tk2Op) -> Mat2 {
+    let h = std::f64::consts::FRAC_1_SQRT_2;
+    match op {
+        Tk2Op::H => [
+            Complex64::new(h, 0.),
+            Complex64::new(h, 0.),
+            Complex64::new(h, 0.),
+            Complex64::new(-h, 0.),
+        ],
+        Tk2Op::S => [ONE, ZERO, ZERO, I],
+        Tk2Op::T => [
+            ONE,
+            ZERO,
+            ZERO,
+            Complex64::from_polar(1., std::f64::consts::FRAC_PI_4),
+        ],
+        _ => panic!("Unsupported gate {op:?}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::unitary::Unitary;
+    use crate::extension::REGISTRY;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::QB_T;
+    use hugr::ops::OpType;
+    use hugr::std_extensions::arithmetic::float_types::ConstF64;
+    use hugr::types::FunctionType;
+    use rstest::rstest;
+
+    /// The matrix implemented by a sequence of single-qubit gates.
+    fn gates_matrix(gates: &[Tk2Op]) -> Mat2 {
+        let mut u = Unitary::identity(1);
+        for &op in gates {
+            assert!(u.apply_op(op, &[0]));
+        }
+        [u.get(0, 0), u.get(0, 1), u.get(1, 0), u.get(1, 1)]
+    }
+
+    #[test]
+    fn clifford_group() {
+        assert_eq!(cliffords().len(), 24);
+    }
+
+    #[rstest]
+    #[case(0.25, 0.)]
+    #[case(-1.5, 0.)]
+    #[case(0.1, 0.1)]
+    #[case(0.7, 0.05)]
+    #[case(-0.33, 0.05)]
+    fn approximation_within_epsilon(#[case] angle: f64, #[case] epsilon: f64) {
+        let gates = approximate_rz(angle, epsilon);
+        let dist = distance(&gates_matrix(&gates), &rz_matrix(angle));
+        assert!(dist <= epsilon + 1e-6, "distance {dist} > {epsilon}");
+    }
+
+    #[test]
+    fn approximate_circuit() {
+        let mut h = DFGBuilder::new(FunctionType::new(vec![QB_T], vec![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let angle = h.add_constant(ConstF64::new(0.1)).unwrap();
+        let angle = h.load_const(&angle).unwrap();
+        let rz = h.add_dataflow_op(Tk2Op::RzF64, [q, angle]).unwrap();
+        let mut circ = h.finish_hugr_with_outputs(rz.outputs(), &REGISTRY).unwrap();
+
+        let t_count = clifford_t_approximate(&mut circ, 0.1);
+
+        let gates: Vec<Tk2Op> = circ
+            .commands()
+            .filter_map(|cmd| Tk2Op::try_from(cmd.optype()).ok())
+            .collect();
+        assert!(!gates.contains(&Tk2Op::RzF64));
+        assert_eq!(gates.iter().filter(|&&op| op == Tk2Op::T).count(), t_count);
+        let dist = distance(&gates_matrix(&gates), &rz_matrix(0.1));
+        assert!(dist <= 0.1 + 1e-6);
+        // The angle constant is no longer used.
+        assert!(!circ.nodes().any(|n| matches!(
+            circ.get_optype(n),
+            OpType::Const(_) | OpType::LoadConstant(_)
+        )));
+    }
+}
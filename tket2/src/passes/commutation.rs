@@ -1,6 +1,7 @@
 use std::{collections::HashMap, rc::Rc};
 
 use hugr::hugr::{hugrmut::HugrMut, HugrError, Rewrite};
+use hugr::ops::OpType;
 use hugr::{CircuitUnit, Direction, Hugr, HugrView, Node, Port, PortIndex};
 use itertools::Itertools;
 use portgraph::PortOffset;
@@ -10,6 +11,8 @@ use crate::{
     ops::{Pauli, Tk2Op},
 };
 
+use super::layerize;
+
 use thiserror::Error;
 
 type Qb = crate::circuit::units::LinearUnit;
@@ -292,14 +295,25 @@ impl Rewrite for PullForward {
 
 /// Pass which greedily commutes operations forwards in order to reduce depth.
 pub fn apply_greedy_commutation(circ: &mut Hugr) -> Result<u32, PullForwardError> {
+    let slice_vec = load_slices(circ);
+    commute_slices(circ, slice_vec, |_| true)
+}
+
+/// Move each movable command to the earliest slice it can commute back to,
+/// returning the number of commands moved.
+fn commute_slices(
+    circ: &mut Hugr,
+    mut slice_vec: SliceVec,
+    movable: impl Fn(&ComCommand) -> bool,
+) -> Result<u32, PullForwardError> {
     let mut count = 0;
-    let mut slice_vec = load_slices(circ);
 
     for slice_index in 0..slice_vec.len() {
         let slice_commands: Vec<_> = slice_vec[slice_index]
             .iter()
             .flatten()
             .unique()
+            .filter(|command| movable(command))
             .cloned()
             .collect();
 
@@ -326,6 +340,71 @@ pub fn apply_greedy_commutation(circ: &mut Hugr) -> Result<u32, PullForwardError
     Ok(count)
 }
 
+/// Load the layers of a circuit, as given by [`layerize`], into slices.
+fn layer_slices(circ: &Hugr) -> SliceVec {
+    let n_qbs = circ.qubit_count();
+    let mut commands: HashMap<Node, ComCommand> =
+        circ.commands().map(|c| (c.node(), ComCommand::from(c))).collect();
+
+    layerize(circ)
+        .into_iter()
+        .map(|layer| {
+            let mut slice = vec![None; n_qbs];
+            for node in layer {
+                if let Some(command) = commands.remove(&node) {
+                    add_to_slice(&mut slice, Rc::new(command));
+                }
+            }
+            slice
+        })
+        .collect()
+}
+
+/// Whether a command can be moved to an earlier layer without breaking its
+/// classical dependencies, i.e. all its non-linear inputs are loaded
+/// constants or inputs to the circuit.
+fn has_independent_inputs(circ: &Hugr, command: &ComCommand) -> bool {
+    command.inputs.iter().all(|unit| match unit {
+        CircuitUnit::Wire(wire) => matches!(
+            circ.get_optype(wire.node()),
+            OpType::Input(_) | OpType::LoadConstant(_)
+        ),
+        CircuitUnit::Linear(_) => true,
+    })
+}
+
+/// Commute gates to earlier layers in order to reduce the depth of a circuit.
+///
+/// The circuit is partitioned into layers with [`layerize`], and each gate
+/// is moved to the earliest layer with free slots on its qubits such that it
+/// commutes with every gate on those qubits in the layers in between. This
+/// is repeated on the new layering for as long as the depth decreases. Gates
+/// depending on classical values computed in the circuit are never moved.
+///
+/// The circuit is only modified if its depth decreases; the gate count is
+/// never changed.
+///
+/// Returns the reduction in depth.
+pub fn commute_to_minimise_depth(circ: &mut Hugr) -> Result<usize, PullForwardError> {
+    let depth = layerize(circ).len();
+    let mut best_depth = depth;
+    loop {
+        let mut commuted = circ.clone();
+        let slices = layer_slices(&commuted);
+        let moves = {
+            let movable = |command: &ComCommand| has_independent_inputs(circ, command);
+            commute_slices(&mut commuted, slices, movable)?
+        };
+        let new_depth = layerize(&commuted).len();
+        if moves == 0 || new_depth >= best_depth {
+            break;
+        }
+        *circ = commuted;
+        best_depth = new_depth;
+    }
+    Ok(depth - best_depth)
+}
+
 #[cfg(test)]
 mod test {
 
@@ -342,6 +421,7 @@ mod test {
     use rstest::{fixture, rstest};
 
     use super::*;
+    use crate::circuit::CircuitHash;
 
     #[fixture]
     // example circuit from original task
@@ -628,4 +708,34 @@ mod test {
             "depth optimisation should not change the number of nodes."
         )
     }
+
+    #[rstest]
+    #[case(example_cx(), 1)]
+    #[case(cant_commute(), 0)]
+    #[case(commutes_but_same_depth(), 0)]
+    fn minimise_depth(#[case] mut case: Hugr, #[case] reduction: usize) {
+        let original = case.clone();
+        let depth_before = layerize(&case).len();
+        assert_eq!(commute_to_minimise_depth(&mut case).unwrap(), reduction);
+        case.update_validate(&REGISTRY).unwrap();
+        assert_eq!(layerize(&case).len(), depth_before - reduction);
+        assert_eq!(case.num_gates(), original.num_gates());
+        if reduction == 0 {
+            assert_eq!(
+                case.circuit_hash().unwrap(),
+                original.circuit_hash().unwrap()
+            );
+        }
+    }
+
+    #[rstest]
+    fn minimise_depth_layers(mut example_cx: Hugr, example_cx_better: Hugr) {
+        assert_eq!(layerize(&example_cx).len(), 3);
+        assert_eq!(commute_to_minimise_depth(&mut example_cx).unwrap(), 1);
+        assert_eq!(layerize(&example_cx).len(), 2);
+        assert_eq!(
+            example_cx.circuit_hash().unwrap(),
+            example_cx_better.circuit_hash().unwrap()
+        );
+    }
 }
@@ -0,0 +1,121 @@
+//! Constant folding of angle arithmetic.
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{Const, LoadConstant};
+use hugr::std_extensions::arithmetic::float_types::{ConstF64, FLOAT64_TYPE};
+use hugr::{Hugr, HugrView, IncomingPort, Node, OutgoingPort};
+use itertools::Itertools;
+
+use super::identities::remove_unused_constant;
+use crate::circuit::Circuit;
+use crate::utils::constant_f64_input;
+use crate::Tk2Op;
+
+/// Evaluate the [`Tk2Op::AngleAdd`] operations whose inputs are constants.
+///
+/// Each such operation is replaced by a load of a new constant holding the
+/// sum of its inputs. Operations are folded in topological order, so chains
+/// of additions of constants are folded into a single constant. The constants
+/// feeding the folded operations are removed if they become unused.
+///
+/// Returns the number of operations folded.
+pub fn fold_angle_constants(circ: &mut Hugr) -> usize {
+    let additions = circ
+        .commands()
+        .filter(|cmd| Tk2Op::try_from(cmd.optype()) == Ok(Tk2Op::AngleAdd))
+        .map(|cmd| cmd.node())
+        .collect_vec();
+    fold_additions(circ, additions)
+}
+
+/// Fold the given [`Tk2Op::AngleAdd`] operations whose inputs are constants.
+///
+/// The operations must be given in topological order. Returns the number of
+/// operations folded.
+pub(super) fn fold_additions(circ: &mut Hugr, additions: impl IntoIterator<Item = Node>) -> usize {
+    let mut n_folded = 0;
+    for add in additions {
+        let inputs = [0, 1].map(|i| constant_f64_input(circ, add, IncomingPort::from(i)));
+        let [Some(a), Some(b)] = inputs else {
+            continue;
+        };
+        replace_with_constant(circ, add, a + b);
+        n_folded += 1;
+    }
+    n_folded
+}
+
+/// Replace a node with a single float output by a load of a constant.
+fn replace_with_constant(circ: &mut Hugr, node: Node, value: f64) {
+    let parent = circ.get_parent(node).expect("Command has no parent.");
+    let const_op = Const::new(ConstF64::new(value).into(), FLOAT64_TYPE).unwrap();
+    let const_node = circ
+        .add_op_with_parent(parent, const_op)
+        .expect("Invalid circuit: could not insert constant.");
+    let load = circ
+        .add_op_with_parent(
+            parent,
+            LoadConstant {
+                datatype: FLOAT64_TYPE,
+            },
+        )
+        .expect("Invalid circuit: could not insert constant load.");
+    circ.connect(const_node, 0, load, 0)
+        .expect("Invalid circuit: could not connect constant.");
+
+    let targets = circ
+        .linked_inputs(node, OutgoingPort::from(0))
+        .collect_vec();
+    let sources = circ.input_neighbours(node).unique().collect_vec();
+    circ.remove_node(node)
+        .expect("Invalid circuit: could not remove node.");
+    for (target, port) in targets {
+        circ.connect(load, 0, target, port.index())
+            .expect("Invalid circuit: could not connect constant.");
+    }
+    for src in sources {
+        remove_unused_constant(circ, src);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::REGISTRY;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::QB_T;
+    use hugr::ops::OpType;
+    use hugr::types::FunctionType;
+
+    #[test]
+    fn fold_angle_add() {
+        let mut h = DFGBuilder::new(FunctionType::new_endo(vec![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let a = h.add_load_const(ConstF64::new(0.25)).unwrap();
+        let b = h.add_load_const(ConstF64::new(0.25)).unwrap();
+        let sum = h.add_dataflow_op(Tk2Op::AngleAdd, [a, b]).unwrap();
+        let rz = h
+            .add_dataflow_op(Tk2Op::RzF64, [q, sum.out_wire(0)])
+            .unwrap();
+        let mut circ = h.finish_hugr_with_outputs(rz.outputs(), &REGISTRY).unwrap();
+
+        assert_eq!(fold_angle_constants(&mut circ), 1);
+        circ.update_validate(&REGISTRY).unwrap();
+
+        let rz = circ
+            .commands()
+            .find(|cmd| Tk2Op::try_from(cmd.optype()) == Ok(Tk2Op::RzF64))
+            .unwrap()
+            .node();
+        assert_eq!(
+            constant_f64_input(&circ, rz, IncomingPort::from(1)),
+            Some(0.5)
+        );
+        let n_consts = circ
+            .children(circ.root())
+            .filter(|&n| matches!(circ.get_optype(n), OpType::Const(_)))
+            .count();
+        assert_eq!(n_consts, 1);
+        assert_eq!(fold_angle_constants(&mut circ), 0);
+    }
+}
@@ -0,0 +1,125 @@
+//! Greedy single-sweep rewriting using a pattern matcher.
+
+use std::collections::HashSet;
+
+use hugr::{Hugr, HugrView, Node};
+
+use crate::portmatching::{PatternMatch, PatternMatcher};
+use crate::rewrite::CircuitRewrite;
+
+/// Greedily apply non-overlapping cost-reducing rewrites in a single sweep.
+///
+/// The matches of `matcher` are considered in the order returned by
+/// [`PatternMatcher::find_matches`]. A match of the pattern with ID `i` is
+/// replaced by `repls[i]` if the replacement has a strictly lower `cost`
+/// than the matched subcircuit, and the match does not overlap with any
+/// rewrite accepted earlier in the sweep.
+///
+/// Two matches overlap if they share a node or are directly connected, as
+/// the replacement of one would invalidate the boundary of the other.
+///
+/// Patterns without a corresponding replacement are ignored.
+///
+/// Returns the number of rewrites applied.
+pub fn greedy_rewrite(
+    circ: &mut Hugr,
+    matcher: &PatternMatcher,
+    repls: &[Hugr],
+    cost: impl Fn(&Hugr) -> usize,
+) -> usize {
+    let mut consumed: HashSet<Node> = HashSet::new();
+    let mut rewrites: Vec<CircuitRewrite> = Vec::new();
+    for m in matcher.find_matches(circ) {
+        let Some(repl) = repls.get(m.pattern_id().0) else {
+            continue;
+        };
+        if m.nodes().iter().any(|n| consumed.contains(n)) {
+            continue;
+        }
+        if cost(repl) >= matched_cost(circ, &m, &cost) {
+            continue;
+        }
+        let Ok(rewrite) = m.to_rewrite(circ, repl.clone()) else {
+            continue;
+        };
+        for &node in m.nodes() {
+            consumed.insert(node);
+            consumed.extend(circ.all_neighbours(node));
+        }
+        rewrites.push(rewrite);
+    }
+
+    let n_rewrites = rewrites.len();
+    for rewrite in rewrites {
+        rewrite
+            .apply(circ)
+            .expect("Non-overlapping rewrites should be applicable.");
+    }
+    n_rewrites
+}
+
+/// The cost of the subcircuit matched by a pattern.
+fn matched_cost(circ: &Hugr, m: &PatternMatch, cost: impl Fn(&Hugr) -> usize) -> usize {
+    let matched = m
+        .subcircuit()
+        .subgraph
+        .extract_subgraph(circ, "")
+        .expect("Could not extract the matched subcircuit.");
+    cost(&matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portmatching::CircuitPattern;
+    use crate::utils::build_simple_circuit;
+    use crate::{Circuit, Tk2Op};
+
+    fn cx_cx() -> Hugr {
+        build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn cx_cx_to_identity() {
+        let mut circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        let matcher = PatternMatcher::from_patterns(vec![CircuitPattern::try_from_circuit(
+            &cx_cx(),
+        )
+        .unwrap()]);
+        let identity = build_simple_circuit(2, |_| Ok(())).unwrap();
+
+        let n_rewrites = greedy_rewrite(&mut circ, &matcher, &[identity], |c| c.num_gates());
+
+        assert_eq!(n_rewrites, 1);
+        let gates: Vec<Tk2Op> = circ
+            .commands()
+            .map(|cmd| cmd.optype().try_into().unwrap())
+            .collect();
+        assert_eq!(gates, vec![Tk2Op::H]);
+    }
+
+    #[test]
+    fn no_cost_reduction() {
+        let mut circ = cx_cx();
+        let matcher = PatternMatcher::from_patterns(vec![CircuitPattern::try_from_circuit(
+            &cx_cx(),
+        )
+        .unwrap()]);
+
+        let n_rewrites = greedy_rewrite(&mut circ, &matcher, &[cx_cx()], |c| c.num_gates());
+
+        assert_eq!(n_rewrites, 0);
+        assert_eq!(circ.num_gates(), 2);
+    }
+}
@@ -0,0 +1,156 @@
+//! Removal of operations acting as the identity.
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::{LeafOp, OpType};
+use hugr::{Hugr, HugrView, IncomingPort, Node};
+use itertools::Itertools;
+
+use crate::circuit::angle::AngleValue;
+use crate::circuit::Circuit;
+use crate::utils::{constant_f64_input, remove_1q_gate};
+use crate::Tk2Op;
+
+/// Remove the operations of a circuit that act as the identity.
+///
+/// The following nodes are removed, and their wires reconnected:
+///  - [`LeafOp::Noop`] operations.
+///  - [`Tk2Op::RzF64`] and [`Tk2Op::RxF64`] rotations by a constant angle of
+///    zero, modulo 2 half-turns.
+///  - [`Tk2Op::TK1`] gates with constant angles `(a, b, c)` where both `b` and
+///    `a + c` are zero, modulo 2 half-turns.
+///
+/// Angles are compared with [`AngleValue::approx_eq_mod_2pi`], so rotations
+/// equal to the identity up to a global phase are removed. The constants
+/// feeding the removed rotations are also removed if they become unused.
+///
+/// Returns the number of operations removed.
+pub fn remove_identities(circ: &mut Hugr) -> usize {
+    let identities = circ
+        .commands()
+        .filter(|cmd| is_identity_op(circ, cmd.node()))
+        .map(|cmd| cmd.node())
+        .collect_vec();
+
+    for &node in &identities {
+        remove_gate_with_params(circ, node);
+    }
+    identities.len()
+}
+
+/// Whether the node is an operation acting as the identity on its qubit.
+fn is_identity_op(circ: &Hugr, node: Node) -> bool {
+    let optype = circ.get_optype(node);
+    if matches!(optype, OpType::LeafOp(LeafOp::Noop { .. })) {
+        return true;
+    }
+    let is_zero = |angle: f64| AngleValue::F64(angle).approx_eq_mod_2pi(&AngleValue::F64(0.));
+    let param = |index: usize| constant_f64_input(circ, node, IncomingPort::from(index + 1));
+    match Tk2Op::try_from(optype) {
+        Ok(Tk2Op::RzF64 | Tk2Op::RxF64) => param(0).is_some_and(is_zero),
+        Ok(Tk2Op::TK1) => {
+            let (Some(a), Some(b), Some(c)) = (param(0), param(1), param(2)) else {
+                return false;
+            };
+            is_zero(b) && is_zero(a + c)
+        }
+        _ => false,
+    }
+}
+
+/// Remove a single-qubit gate, reconnecting its qubit wire, along with the
+/// constants feeding its parameters if they become unused.
+pub(super) fn remove_gate_with_params(circ: &mut Hugr, node: Node) {
+    let param_sources = (1..circ.num_inputs(node))
+        .filter_map(|i| {
+            let port = IncomingPort::from(i);
+            circ.linked_outputs(node, port).exactly_one().ok()
+        })
+        .map(|(src, _)| src)
+        .collect_vec();
+    remove_1q_gate(circ, node);
+    for load in param_sources {
+        remove_unused_constant(circ, load);
+    }
+}
+
+/// Remove a [`OpType::LoadConstant`] node with no remaining consumers, along
+/// with its constant if it has no other loads.
+pub(super) fn remove_unused_constant(circ: &mut Hugr, load: Node) {
+    if !matches!(circ.get_optype(load), OpType::LoadConstant(_))
+        || circ.output_neighbours(load).next().is_some()
+    {
+        return;
+    }
+    let const_node = circ.input_neighbours(load).exactly_one().ok();
+    circ.remove_node(load)
+        .expect("Invalid circuit: could not remove constant load.");
+    if let Some(const_node) = const_node {
+        if circ.output_neighbours(const_node).next().is_none() {
+            circ.remove_node(const_node)
+                .expect("Invalid circuit: could not remove constant.");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::REGISTRY;
+    use crate::utils::build_simple_circuit;
+    use crate::utils::test::gates;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::QB_T;
+    use hugr::std_extensions::arithmetic::float_types::ConstF64;
+    use hugr::types::FunctionType;
+    use rstest::rstest;
+
+    #[test]
+    fn remove_noop() {
+        let mut h = DFGBuilder::new(FunctionType::new_endo(vec![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let q = h.add_dataflow_op(Tk2Op::H, [q]).unwrap().out_wire(0);
+        let noop = h.add_dataflow_op(LeafOp::Noop { ty: QB_T }, [q]).unwrap();
+        let q = h.add_dataflow_op(Tk2Op::T, noop.outputs()).unwrap();
+        let mut circ = h.finish_hugr_with_outputs(q.outputs(), &REGISTRY).unwrap();
+
+        assert_eq!(remove_identities(&mut circ), 1);
+        assert_eq!(circ.num_gates(), 2);
+        assert_eq!(gates(&circ), [Tk2Op::H, Tk2Op::T]);
+        circ.update_validate(&REGISTRY).unwrap();
+    }
+
+    #[rstest]
+    #[case::zero(0., 1)]
+    #[case::full_turn(2., 1)]
+    #[case::tiny(1e-12, 1)]
+    #[case::quarter(0.5, 0)]
+    fn remove_zero_rz(#[case] angle: f64, #[case] expected: usize) {
+        let mut h = DFGBuilder::new(FunctionType::new_endo(vec![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let angle = h.add_load_const(ConstF64::new(angle)).unwrap();
+        let rz = h.add_dataflow_op(Tk2Op::RzF64, [q, angle]).unwrap();
+        let q = h.add_dataflow_op(Tk2Op::H, rz.outputs()).unwrap();
+        let mut circ = h.finish_hugr_with_outputs(q.outputs(), &REGISTRY).unwrap();
+        let n_nodes = circ.node_count();
+
+        assert_eq!(remove_identities(&mut circ), expected);
+        if expected == 1 {
+            // The rotation and its constant are gone.
+            assert_eq!(gates(&circ), [Tk2Op::H]);
+            assert_eq!(circ.node_count(), n_nodes - 3);
+        }
+        circ.update_validate(&REGISTRY).unwrap();
+    }
+
+    #[test]
+    fn keep_non_rotations() {
+        let mut circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(remove_identities(&mut circ), 0);
+        assert_eq!(gates(&circ), [Tk2Op::H, Tk2Op::CX]);
+    }
+}
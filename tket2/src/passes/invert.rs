@@ -0,0 +1,302 @@
+//! Inversion of whole circuits.
+
+use std::collections::HashMap;
+
+use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+use hugr::ops::{LeafOp, OpName, OpType};
+use hugr::std_extensions::arithmetic::float_ops::EXTENSION as FLOAT_OPS_EXTENSION;
+use hugr::std_extensions::arithmetic::float_types::ConstF64;
+use hugr::types::FunctionType;
+use hugr::{Hugr, HugrView, Node, Wire};
+use itertools::Itertools;
+use smol_str::SmolStr;
+use thiserror::Error;
+
+use crate::circuit::command::{CircuitUnit, Command};
+use crate::circuit::Circuit;
+use crate::extension::REGISTRY;
+use crate::json::invert_global_phase;
+use crate::Tk2Op;
+
+/// Returns the inverse of a circuit.
+///
+/// The gates are applied in reverse order, and each gate is replaced by its
+/// inverse. Non-parametric gates are replaced by their [`Tk2Op::dagger`], and
+/// the angles of parametric rotations are negated with a floating point
+/// negation. [`Tk2Op::ZZMax`] is inverted into a [`Tk2Op::ZZPhase`] rotation.
+///
+/// The classical operations computing the parameters, such as constants and
+/// [`Tk2Op::AngleAdd`], are copied to the inverse. The inverse circuit has
+/// the same inputs as the original circuit, and its linear outputs. The
+/// recorded global phase of the circuit is negated, see
+/// [`crate::json::circuit_global_phase`].
+pub fn invert_circuit(circ: &Hugr) -> Result<Hugr, UninvertibleOp> {
+    let (classical, gates): (Vec<_>, Vec<_>) = circ
+        .commands()
+        .filter(|cmd| !matches!(cmd.optype(), OpType::Const(_)))
+        .partition(|cmd| {
+            cmd.linear_inputs().next().is_none() && cmd.linear_outputs().next().is_none()
+        });
+    let gates = gates
+        .into_iter()
+        .map(|cmd| {
+            let no_inverse = || UninvertibleOp::NoInverse {
+                op: cmd.optype().name(),
+                node: cmd.node(),
+            };
+            let op = Tk2Op::try_from(cmd.optype()).map_err(|_| no_inverse())?;
+            let inverse = inverse_gate(op).ok_or_else(no_inverse)?;
+            Ok((cmd, inverse))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let inputs = circ.circuit_signature().input;
+    let linear_types = circ.linear_units().map(|(_, _, ty)| ty).collect_vec();
+    let mut builder = DFGBuilder::new(FunctionType::new(inputs, linear_types))
+        .expect("Could not create the inverse circuit.");
+    let input_wires = builder.input_wires().collect_vec();
+    let mut qubits: Vec<Wire> = circ
+        .linear_units()
+        .map(|(_, port, _)| input_wires[port.index()])
+        .collect();
+    let mut values: HashMap<Wire, Wire> = circ
+        .nonlinear_units()
+        .map(|(wire, port, _)| (wire, input_wires[port.index()]))
+        .collect();
+
+    // The classical operations only depend on the inputs and on each other,
+    // as the invertible gates have no classical outputs.
+    for cmd in classical {
+        copy_classical(circ, &mut builder, &cmd, &mut values);
+    }
+
+    for (cmd, (inv_op, inv_params)) in gates.into_iter().rev() {
+        let mut units = Vec::new();
+        let mut params = Vec::new();
+        for (unit, _, _) in cmd.inputs() {
+            match unit {
+                CircuitUnit::Linear(unit) => units.push(unit.index()),
+                CircuitUnit::Wire(wire) => params.push(values[&wire]),
+            }
+        }
+        let param_wires = inv_params
+            .into_iter()
+            .map(|p| match p {
+                InverseParam::Same(i) => params[i],
+                InverseParam::Negated(i) => builder
+                    .add_dataflow_op(fneg_op(), [params[i]])
+                    .expect("Could not negate the parameter.")
+                    .out_wire(0),
+                InverseParam::Const(value) => builder.add_load_const(ConstF64::new(value)).unwrap(),
+            })
+            .collect_vec();
+        let inputs = units.iter().map(|&q| qubits[q]).chain(param_wires);
+        let outputs = builder
+            .add_dataflow_op(inv_op, inputs)
+            .expect("Could not add the inverse gate.")
+            .outputs();
+        for (&q, wire) in units.iter().zip(outputs) {
+            qubits[q] = wire;
+        }
+    }
+
+    let mut inverse = builder
+        .finish_hugr_with_outputs(qubits, &REGISTRY)
+        .expect("Invalid inverse circuit.");
+    invert_global_phase(circ, &mut inverse);
+    Ok(inverse)
+}
+
+/// Copy a classical operation to the inverse circuit, recording the wires of
+/// its outputs.
+fn copy_classical(
+    circ: &Hugr,
+    builder: &mut DFGBuilder<Hugr>,
+    cmd: &Command<'_, Hugr>,
+    values: &mut HashMap<Wire, Wire>,
+) {
+    let node = cmd.node();
+    let outputs = match cmd.optype() {
+        OpType::LoadConstant(_) => {
+            let const_node = circ.input_neighbours(node).next().unwrap();
+            let OpType::Const(c) = circ.get_optype(const_node) else {
+                panic!("LoadConstant is not connected to a constant.");
+            };
+            vec![builder.add_load_const(c.clone()).unwrap()]
+        }
+        op => {
+            let inputs = cmd
+                .inputs()
+                .filter_map(|(unit, _, _)| match unit {
+                    CircuitUnit::Wire(wire) => Some(values[&wire]),
+                    CircuitUnit::Linear(_) => None,
+                })
+                .collect_vec();
+            builder
+                .add_dataflow_op(op.clone(), inputs)
+                .expect("Could not copy the classical operation.")
+                .outputs()
+                .collect()
+        }
+    };
+    for ((_, port, _), wire) in cmd.outputs().zip(outputs) {
+        values.insert(Wire::new(node, port), wire);
+    }
+}
+
+/// A parameter of an inverse gate, in terms of the parameters of the
+/// original gate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum InverseParam {
+    /// The `i`-th parameter of the original gate.
+    Same(usize),
+    /// The negation of the `i`-th parameter of the original gate.
+    Negated(usize),
+    /// A constant angle.
+    Const(f64),
+}
+
+/// The inverse of a gate, and the parameters of the inverse gate.
+fn inverse_gate(op: Tk2Op) -> Option<(Tk2Op, Vec<InverseParam>)> {
+    use InverseParam::*;
+    match op {
+        Tk2Op::RzF64 | Tk2Op::RxF64 | Tk2Op::ZZPhase => Some((op, vec![Negated(0)])),
+        // PhasedX(a, b) = Rz(b) Rx(a) Rz(-b)
+        Tk2Op::PhasedX => Some((op, vec![Negated(0), Same(1)])),
+        // TK1(a, b, c) = Rz(a) Rx(b) Rz(c)
+        Tk2Op::TK1 => Some((op, vec![Negated(2), Negated(1), Negated(0)])),
+        // ZZMax = ZZPhase(1/2)
+        Tk2Op::ZZMax => Some((Tk2Op::ZZPhase, vec![Const(-0.5)])),
+        _ => op.dagger().map(|inv| (inv, vec![])),
+    }
+}
+
+/// The floating point negation operation.
+fn fneg_op() -> OpType {
+    let l: LeafOp = FLOAT_OPS_EXTENSION
+        .instantiate_extension_op(&SmolStr::new_inline("fneg"), vec![], &REGISTRY)
+        .unwrap()
+        .into();
+    l.into()
+}
+
+/// Error type for [`invert_circuit`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum UninvertibleOp {
+    /// The operation is not unitary, or its inverse is not supported.
+    #[error("Cannot invert the {op} operation at node {node:?}.")]
+    NoInverse {
+        /// The name of the operation.
+        op: SmolStr,
+        /// The node of the operation.
+        node: Node,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{build_simple_circuit, constant_f64_input};
+    use hugr::extension::prelude::QB_T;
+    use hugr::std_extensions::arithmetic::float_types::FLOAT64_TYPE;
+
+    fn gates(circ: &Hugr) -> Vec<Tk2Op> {
+        circ.commands()
+            .filter_map(|cmd| Tk2Op::try_from(cmd.optype()).ok())
+            .collect()
+    }
+
+    #[test]
+    fn invert_h_cx() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::T, [1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let inv = invert_circuit(&circ).unwrap();
+
+        assert_eq!(inv.qubit_count(), 2);
+        let inv_gates = gates(&inv);
+        assert_eq!(inv_gates[0], Tk2Op::CX);
+        assert_eq!(
+            inv_gates[1..].iter().sorted().collect_vec(),
+            [Tk2Op::H, Tk2Op::Tdg].iter().sorted().collect_vec()
+        );
+    }
+
+    #[test]
+    fn invert_rotation() {
+        let mut h = DFGBuilder::new(FunctionType::new_endo(vec![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let angle = h.add_load_const(ConstF64::new(0.25)).unwrap();
+        let rz = h.add_dataflow_op(Tk2Op::RzF64, [q, angle]).unwrap();
+        let circ = h.finish_hugr_with_outputs(rz.outputs(), &REGISTRY).unwrap();
+
+        let inv = invert_circuit(&circ).unwrap();
+
+        let rz = inv
+            .commands()
+            .find(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(Tk2Op::RzF64))
+            .unwrap();
+        let (neg, _) = inv.single_linked_output(rz.node(), 1).unwrap();
+        assert_eq!(inv.get_optype(neg).name(), fneg_op().name());
+        assert_eq!(constant_f64_input(&inv, neg, 0.into()), Some(0.25));
+    }
+
+    #[test]
+    fn invert_input_rotation() {
+        let mut h =
+            DFGBuilder::new(FunctionType::new(vec![QB_T, FLOAT64_TYPE], vec![QB_T])).unwrap();
+        let [q, angle] = h.input_wires_arr();
+        let half = h.add_load_const(ConstF64::new(0.5)).unwrap();
+        let sum = h
+            .add_dataflow_op(Tk2Op::AngleAdd, [angle, half])
+            .unwrap()
+            .out_wire(0);
+        let rz = h.add_dataflow_op(Tk2Op::RzF64, [q, angle]).unwrap();
+        let [q] = rz.outputs_arr();
+        let rx = h.add_dataflow_op(Tk2Op::RxF64, [q, sum]).unwrap();
+        let circ = h.finish_hugr_with_outputs(rx.outputs(), &REGISTRY).unwrap();
+
+        let inv = invert_circuit(&circ).unwrap();
+
+        assert_eq!(
+            inv.circuit_signature().input,
+            circ.circuit_signature().input
+        );
+        assert_eq!(gates(&inv), [Tk2Op::AngleAdd, Tk2Op::RxF64, Tk2Op::RzF64]);
+        // Each rotation angle is the negation of the original one.
+        let angle_source = |gate: Tk2Op| {
+            let cmd = inv
+                .commands()
+                .find(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(gate))
+                .unwrap();
+            let (neg, _) = inv.single_linked_output(cmd.node(), 1).unwrap();
+            assert_eq!(inv.get_optype(neg).name(), fneg_op().name());
+            inv.single_linked_output(neg, 0).unwrap()
+        };
+        assert_eq!(angle_source(Tk2Op::RzF64), (inv.input(), 1.into()));
+        let (add, _) = angle_source(Tk2Op::RxF64);
+        assert_eq!(Tk2Op::try_from(inv.get_optype(add)), Ok(Tk2Op::AngleAdd));
+    }
+
+    #[test]
+    fn uninvertible() {
+        let mut h = DFGBuilder::new(FunctionType::new_endo(vec![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let measure = h.add_dataflow_op(Tk2Op::Measure, [q]).unwrap();
+        let [q, _] = measure.outputs_arr();
+        let circ = h.finish_hugr_with_outputs([q], &REGISTRY).unwrap();
+        assert_eq!(
+            invert_circuit(&circ).unwrap_err(),
+            UninvertibleOp::NoInverse {
+                op: Tk2Op::Measure.exposed_name(),
+                node: measure.node()
+            }
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Partitioning of circuits into layers of parallel gates.
+
+use std::collections::HashMap;
+
+use hugr::{Hugr, HugrView, Node};
+
+use crate::circuit::Circuit;
+
+/// Partition the gates of a circuit into layers of parallel gates.
+///
+/// Gates are placed greedily in the earliest layer after all the gates they
+/// depend on, so the gates in a layer act on disjoint qubits. Classical
+/// dependencies are also taken into account: a gate using the result of a
+/// measurement is placed in a later layer than the measurement, even if the
+/// value goes through classical operations in between.
+///
+/// Only the commands acting on linear units (e.g. qubits) are placed in a
+/// layer. [`Circuit::depth`] only follows linear units, so the number of
+/// layers is equal to the depth for circuits without classical dependencies
+/// between gates, and may be larger otherwise.
+pub fn layerize(circ: &Hugr) -> Vec<Vec<Node>> {
+    // The number of layers up to and including each node.
+    let mut levels: HashMap<Node, usize> = HashMap::new();
+    let mut layers: Vec<Vec<Node>> = Vec::new();
+    for cmd in circ.commands() {
+        let node = cmd.node();
+        let level = circ
+            .input_neighbours(node)
+            .filter_map(|pred| levels.get(&pred).copied())
+            .max()
+            .unwrap_or(0);
+        if cmd.linear_inputs().next().is_none() {
+            levels.insert(node, level);
+            continue;
+        }
+        levels.insert(node, level + 1);
+        if layers.len() <= level {
+            layers.resize_with(level + 1, Vec::new);
+        }
+        layers[level].push(node);
+    }
+    layers
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::*;
+    use crate::utils::build_simple_circuit;
+    use crate::Tk2Op;
+
+    #[rstest]
+    #[case::independent(vec![(Tk2Op::H, vec![0]), (Tk2Op::X, vec![1])], vec![2])]
+    #[case::dependent(vec![(Tk2Op::H, vec![0]), (Tk2Op::X, vec![0])], vec![1, 1])]
+    #[case::mixed(
+        vec![(Tk2Op::H, vec![0]), (Tk2Op::T, vec![1]), (Tk2Op::CX, vec![0, 1]), (Tk2Op::X, vec![1])],
+        vec![2, 1, 1]
+    )]
+    fn layers(#[case] gates: Vec<(Tk2Op, Vec<usize>)>, #[case] layer_sizes: Vec<usize>) {
+        let circ = build_simple_circuit(2, |circ| {
+            for (op, qubits) in &gates {
+                circ.append(*op, qubits.clone())?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        let layers = layerize(&circ);
+        let sizes = layers.iter().map(Vec::len).collect::<Vec<_>>();
+        assert_eq!(sizes, layer_sizes);
+        assert_eq!(layers.len(), circ.depth());
+    }
+}
@@ -0,0 +1,296 @@
+//! Pauli tracking pass.
+//!
+//! Commutes all Pauli gates in a circuit towards its end, accumulating them
+//! into a final [`PauliFrame`].
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::hugr::NodeType;
+use hugr::{Hugr, HugrView};
+use itertools::Itertools;
+
+use crate::circuit::Circuit;
+use crate::json::discard_global_phase;
+use crate::ops::{Pauli, Tk2Op};
+use crate::utils::{insert_1q_gate_before, remove_1q_gate};
+
+/// A Pauli operator on each linear unit of a circuit, up to a global phase.
+///
+/// Indexed by the linear unit index of each qubit.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct PauliFrame {
+    paulis: Vec<Pauli>,
+}
+
+impl PauliFrame {
+    /// Create a new identity frame for `n_qubits` qubits.
+    pub fn new(n_qubits: usize) -> Self {
+        Self {
+            paulis: vec![Pauli::I; n_qubits],
+        }
+    }
+
+    /// The Pauli acting on a qubit.
+    ///
+    /// Returns [`Pauli::I`] for qubits outside the frame.
+    pub fn get(&self, qubit: usize) -> Pauli {
+        self.paulis.get(qubit).copied().unwrap_or(Pauli::I)
+    }
+
+    /// The Paulis acting on each qubit.
+    pub fn paulis(&self) -> &[Pauli] {
+        &self.paulis
+    }
+
+    /// Whether the frame is the identity on every qubit.
+    pub fn is_identity(&self) -> bool {
+        self.paulis.iter().all(|&p| p == Pauli::I)
+    }
+
+    /// Set the Pauli acting on a qubit, extending the frame if necessary.
+    fn set(&mut self, qubit: usize, pauli: Pauli) {
+        if qubit >= self.paulis.len() {
+            self.paulis.resize(qubit + 1, Pauli::I);
+        }
+        self.paulis[qubit] = pauli;
+    }
+
+    /// Multiply a Pauli into the frame on a qubit, ignoring phases.
+    fn apply(&mut self, qubit: usize, pauli: Pauli) {
+        let (x1, z1) = pauli_bits(self.get(qubit));
+        let (x2, z2) = pauli_bits(pauli);
+        self.set(qubit, pauli_from_bits(x1 ^ x2, z1 ^ z2));
+    }
+
+    /// The X and Z components of the Pauli on a qubit.
+    fn bits(&self, qubit: usize) -> (bool, bool) {
+        pauli_bits(self.get(qubit))
+    }
+
+    /// Set the Pauli on a qubit from its X and Z components.
+    fn set_bits(&mut self, qubit: usize, x: bool, z: bool) {
+        self.set(qubit, pauli_from_bits(x, z));
+    }
+}
+
+/// Decompose a Pauli into its X and Z components.
+fn pauli_bits(pauli: Pauli) -> (bool, bool) {
+    match pauli {
+        Pauli::I => (false, false),
+        Pauli::X => (true, false),
+        Pauli::Y => (true, true),
+        Pauli::Z => (false, true),
+    }
+}
+
+/// Build a Pauli from its X and Z components.
+fn pauli_from_bits(x: bool, z: bool) -> Pauli {
+    match (x, z) {
+        (false, false) => Pauli::I,
+        (true, false) => Pauli::X,
+        (true, true) => Pauli::Y,
+        (false, true) => Pauli::Z,
+    }
+}
+
+/// The Pauli gate corresponding to a Pauli operator.
+fn pauli_gate(pauli: Pauli) -> Option<Tk2Op> {
+    match pauli {
+        Pauli::I => None,
+        Pauli::X => Some(Tk2Op::X),
+        Pauli::Y => Some(Tk2Op::Y),
+        Pauli::Z => Some(Tk2Op::Z),
+    }
+}
+
+/// Commute all Pauli gates (X, Y and Z) towards the end of the circuit.
+///
+/// The Pauli gates are removed from the circuit, and the gates they are
+/// commuted through are updated according to the Clifford commutation rules
+/// (e.g. an X on the control of a CX becomes an X on both qubits, and a T
+/// conjugated by an X becomes a Tdg). The net Pauli on each qubit is
+/// accumulated in the returned [`PauliFrame`], which must be applied at the
+/// end of the circuit to recover the original operation (up to a global
+/// phase). The recorded global phase of the circuit is discarded, see
+/// [`crate::json::circuit_global_phase`].
+///
+/// Whenever the frame cannot be commuted through an operation, the Paulis on
+/// the affected qubits are re-inserted in front of it.
+pub fn track_paulis(circ: &mut Hugr) -> PauliFrame {
+    let mut frame = PauliFrame::new(circ.qubit_count());
+
+    // Collect the commands first, as we'll be mutating the circuit.
+    let commands = circ
+        .commands()
+        .map(|cmd| {
+            let op = Tk2Op::try_from(cmd.optype()).ok();
+            let qubits = cmd
+                .linear_inputs()
+                .map(|(unit, port, _)| (unit.index(), port))
+                .collect_vec();
+            (cmd.node(), op, qubits)
+        })
+        .collect_vec();
+
+    for (node, op, qubits) in commands {
+        let qbs = qubits.iter().map(|&(qb, _)| qb).collect_vec();
+        match (op, qbs.as_slice()) {
+            (Some(op @ (Tk2Op::X | Tk2Op::Y | Tk2Op::Z)), &[q]) => {
+                frame.apply(q, pauli_from_op(op));
+                remove_1q_gate(circ, node);
+            }
+            (Some(Tk2Op::H), &[q]) => {
+                let (x, z) = frame.bits(q);
+                frame.set_bits(q, z, x);
+            }
+            (Some(Tk2Op::S | Tk2Op::Sdg), &[q]) => {
+                let (x, z) = frame.bits(q);
+                frame.set_bits(q, x, z ^ x);
+            }
+            (Some(op @ (Tk2Op::T | Tk2Op::Tdg)), &[q]) => {
+                let (x, _) = frame.bits(q);
+                if x {
+                    let new_op = match op {
+                        Tk2Op::T => Tk2Op::Tdg,
+                        _ => Tk2Op::T,
+                    };
+                    let exts = circ.get_nodetype(node).input_extensions().cloned();
+                    circ.replace_op(node, NodeType::new(new_op, exts))
+                        .expect("Invalid circuit: could not replace T gate.");
+                }
+            }
+            (Some(Tk2Op::CX), &[c, t]) => {
+                let (xc, zc) = frame.bits(c);
+                let (xt, zt) = frame.bits(t);
+                frame.set_bits(c, xc, zc ^ zt);
+                frame.set_bits(t, xt ^ xc, zt);
+            }
+            (Some(Tk2Op::CZ), &[a, b]) => {
+                let (xa, za) = frame.bits(a);
+                let (xb, zb) = frame.bits(b);
+                frame.set_bits(a, xa, za ^ xb);
+                frame.set_bits(b, xb, zb ^ xa);
+            }
+            (Some(Tk2Op::SWAP), &[a, b]) => {
+                let (pa, pb) = (frame.get(a), frame.get(b));
+                frame.set(a, pb);
+                frame.set(b, pa);
+            }
+            (Some(Tk2Op::ZZMax), &[a, b]) => {
+                let (xa, za) = frame.bits(a);
+                let (xb, zb) = frame.bits(b);
+                frame.set_bits(a, xa, za ^ xa ^ xb);
+                frame.set_bits(b, xb, zb ^ xa ^ xb);
+            }
+            _ => {
+                // Generic operation. Keep the Paulis that commute with it, and
+                // flush the rest in front of it.
+                let commutation = op.map(|op| op.qubit_commutation()).unwrap_or_default();
+                for (i, &(q, port)) in qubits.iter().enumerate() {
+                    let pauli = frame.get(q);
+                    let commutes = commutation
+                        .iter()
+                        .any(|&(idx, p)| idx == i && p.commutes_with(pauli));
+                    if pauli == Pauli::I || commutes {
+                        continue;
+                    }
+                    insert_1q_gate_before(circ, node, port, pauli_gate(pauli).unwrap());
+                    frame.set(q, Pauli::I);
+                }
+            }
+        }
+    }
+
+    // Removing the Paulis from the circuit may change its global phase.
+    discard_global_phase(circ);
+    frame
+}
+
+/// The Pauli operator implemented by a Pauli gate.
+fn pauli_from_op(op: Tk2Op) -> Pauli {
+    match op {
+        Tk2Op::X => Pauli::X,
+        Tk2Op::Y => Pauli::Y,
+        Tk2Op::Z => Pauli::Z,
+        _ => Pauli::I,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::build_simple_circuit;
+    use hugr::Hugr;
+    use rstest::rstest;
+
+    fn gates(circ: &Hugr) -> Vec<Tk2Op> {
+        circ.commands()
+            .map(|cmd| cmd.optype().try_into().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn x_through_cx_control() {
+        let mut circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::X, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let frame = track_paulis(&mut circ);
+
+        assert_eq!(gates(&circ), vec![Tk2Op::CX]);
+        assert_eq!(frame.paulis(), &[Pauli::X, Pauli::X]);
+    }
+
+    #[rstest]
+    #[case(Tk2Op::X, Tk2Op::H, Pauli::Z)]
+    #[case(Tk2Op::Z, Tk2Op::H, Pauli::X)]
+    #[case(Tk2Op::X, Tk2Op::S, Pauli::Y)]
+    #[case(Tk2Op::Y, Tk2Op::S, Pauli::X)]
+    fn clifford_conjugation(#[case] pauli: Tk2Op, #[case] gate: Tk2Op, #[case] expected: Pauli) {
+        let mut circ = build_simple_circuit(1, |circ| {
+            circ.append(pauli, [0])?;
+            circ.append(gate, [0])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let frame = track_paulis(&mut circ);
+
+        assert_eq!(gates(&circ), vec![gate]);
+        assert_eq!(frame.paulis(), &[expected]);
+    }
+
+    #[test]
+    fn x_flips_t() {
+        let mut circ = build_simple_circuit(1, |circ| {
+            circ.append(Tk2Op::X, [0])?;
+            circ.append(Tk2Op::T, [0])?;
+            circ.append(Tk2Op::X, [0])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let frame = track_paulis(&mut circ);
+
+        assert_eq!(gates(&circ), vec![Tk2Op::Tdg]);
+        assert!(frame.is_identity());
+    }
+
+    #[test]
+    fn flush_before_measure() {
+        let mut circ = build_simple_circuit(1, |circ| {
+            circ.append(Tk2Op::Z, [0])?;
+            circ.append(Tk2Op::X, [0])?;
+            circ.append(Tk2Op::Measure, [0])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let frame = track_paulis(&mut circ);
+
+        assert_eq!(gates(&circ), vec![Tk2Op::Y, Tk2Op::Measure]);
+        assert!(frame.is_identity());
+    }
+}
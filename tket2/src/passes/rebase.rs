@@ -0,0 +1,453 @@
+//! Rebasing circuits to a target gate set.
+
+use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+use hugr::ops::{OpName, OpTrait, OpType};
+use hugr::std_extensions::arithmetic::float_types::ConstF64;
+use hugr::{Hugr, Node, Wire};
+use itertools::Itertools;
+use smol_str::SmolStr;
+use thiserror::Error;
+
+use crate::circuit::Circuit;
+use crate::extension::REGISTRY;
+use crate::json::discard_global_phase;
+use crate::ops::is_barrier;
+use crate::rewrite::Subcircuit;
+use crate::Tk2Op;
+
+/// Rebase a circuit to the Nam gate set {[`Tk2Op::CX`], [`Tk2Op::RzF64`],
+/// [`Tk2Op::H`]}.
+///
+/// Every other quantum gate is replaced by a fixed decomposition into the
+/// target gate set, equal to the original gate up to a global phase. Gates
+/// already in the target set, non-unitary operations (measurements, resets,
+/// allocations), and barriers are left unchanged. If any gate is replaced,
+/// the recorded global phase of the circuit is discarded, see
+/// [`crate::json::circuit_global_phase`].
+///
+/// Returns an error without modifying the circuit if it contains an
+/// operation acting on qubits that is not a [`Tk2Op`].
+pub fn rebase_to_nam(circ: &mut Hugr) -> Result<(), RebaseError> {
+    let mut to_rebase = Vec::new();
+    for cmd in circ.commands() {
+        let optype = cmd.optype();
+        match Tk2Op::try_from(optype) {
+            Ok(op) => {
+                if let Some(repl) = nam_decomposition(op) {
+                    to_rebase.push((cmd.node(), repl));
+                }
+            }
+            Err(_) if is_barrier(optype) || cmd.input_qubits().next().is_none() => {}
+            Err(_) => {
+                return Err(RebaseError::UnsupportedOp {
+                    op: optype.name(),
+                    node: cmd.node(),
+                })
+            }
+        }
+    }
+
+    if !to_rebase.is_empty() {
+        discard_global_phase(circ);
+    }
+    for (node, repl) in to_rebase {
+        replace_gate(circ, node, repl);
+    }
+    Ok(())
+}
+
+/// Decompose every [`Tk2Op::TK1`] gate into the Nam gate set.
+///
+/// Each `TK1(a, b, c) = Rz(a) Rx(b) Rz(c)` is replaced by the sequence
+/// `Rz(c) H Rz(b) H Rz(a)`, applied in that order. The rotations reuse the
+/// angle inputs of the original gate, so symbolic angles are supported.
+///
+/// Returns the number of gates decomposed.
+pub fn decompose_tk1(circ: &mut Hugr) -> usize {
+    let tk1_gates = circ
+        .commands()
+        .filter(|cmd| Tk2Op::try_from(cmd.optype()) == Ok(Tk2Op::TK1))
+        .map(|cmd| cmd.node())
+        .collect_vec();
+    for &node in &tk1_gates {
+        let repl = nam_decomposition(Tk2Op::TK1).expect("TK1 gates are rebased.");
+        replace_gate(circ, node, repl);
+    }
+    tk1_gates.len()
+}
+
+/// Replace a single gate by a circuit with the same signature.
+fn replace_gate(circ: &mut Hugr, node: Node, repl: Hugr) {
+    let subcirc =
+        Subcircuit::try_from_nodes([node], circ).expect("A single gate is a valid subcircuit.");
+    subcirc
+        .create_rewrite(circ, repl)
+        .expect("The decomposition has the same signature as the gate.")
+        .apply(circ)
+        .expect("Could not apply the decomposition.");
+}
+
+/// The decomposition of a gate into the Nam gate set, as a circuit with the
+/// same signature as the gate.
+///
+/// Returns `None` for gates that are not rebased.
+fn nam_decomposition(op: Tk2Op) -> Option<Hugr> {
+    use Tk2Op::*;
+    if matches!(op, H | CX | RzF64) || !op.is_quantum() {
+        return None;
+    }
+
+    let mut b = NamBuilder::new(op);
+    match op {
+        T => b.rz_const(0, 0.25),
+        Tdg => b.rz_const(0, -0.25),
+        S => b.rz_const(0, 0.5),
+        Sdg => b.rz_const(0, -0.5),
+        Z => b.rz_const(0, 1.),
+        X => b.x(0),
+        // Y = iXZ
+        Y => {
+            b.rz_const(0, 1.);
+            b.x(0);
+        }
+        RxF64 => {
+            b.h(0);
+            b.rz(0, b.params[0]);
+            b.h(0);
+        }
+        CZ => {
+            b.h(1);
+            b.cx(0, 1);
+            b.h(1);
+        }
+        CY => {
+            b.rz_const(1, -0.5);
+            b.cx(0, 1);
+            b.rz_const(1, 0.5);
+        }
+        SWAP => {
+            b.cx(0, 1);
+            b.cx(1, 0);
+            b.cx(0, 1);
+        }
+        ZZMax => {
+            b.cx(0, 1);
+            b.rz_const(1, 0.5);
+            b.cx(0, 1);
+        }
+        ZZPhase => {
+            b.cx(0, 1);
+            b.rz(1, b.params[0]);
+            b.cx(0, 1);
+        }
+        // PhasedX(a, b) = Rz(b) Rx(a) Rz(-b), with Rz(-b) = X Rz(b) X
+        PhasedX => {
+            b.x(0);
+            b.rz(0, b.params[1]);
+            b.x(0);
+            b.h(0);
+            b.rz(0, b.params[0]);
+            b.h(0);
+            b.rz(0, b.params[1]);
+        }
+        // TK1(a, b, c) = Rz(a) Rx(b) Rz(c)
+        TK1 => {
+            b.rz(0, b.params[2]);
+            b.h(0);
+            b.rz(0, b.params[1]);
+            b.h(0);
+            b.rz(0, b.params[0]);
+        }
+        _ => return None,
+    }
+    Some(b.finish())
+}
+
+/// Helper for building gate decompositions in the Nam gate set.
+struct NamBuilder {
+    builder: DFGBuilder<Hugr>,
+    /// The current wire of each qubit.
+    qubits: Vec<Wire>,
+    /// The parameter inputs of the decomposed gate.
+    params: Vec<Wire>,
+}
+
+impl NamBuilder {
+    /// Start a decomposition with the signature of `op`.
+    fn new(op: Tk2Op) -> Self {
+        let sig = OpType::from(op)
+            .dataflow_signature()
+            .expect("Tk2Ops are dataflow operations.");
+        let builder = DFGBuilder::new(sig).unwrap();
+        let mut inputs = builder.input_wires().collect_vec();
+        let params = inputs.split_off(op.n_qubits());
+        Self {
+            builder,
+            qubits: inputs,
+            params,
+        }
+    }
+
+    fn h(&mut self, q: usize) {
+        let h = self.builder.add_dataflow_op(Tk2Op::H, [self.qubits[q]]);
+        self.qubits[q] = h.unwrap().out_wire(0);
+    }
+
+    fn rz(&mut self, q: usize, angle: Wire) {
+        let rz = self
+            .builder
+            .add_dataflow_op(Tk2Op::RzF64, [self.qubits[q], angle]);
+        self.qubits[q] = rz.unwrap().out_wire(0);
+    }
+
+    fn rz_const(&mut self, q: usize, angle: f64) {
+        let angle = self.builder.add_load_const(ConstF64::new(angle)).unwrap();
+        self.rz(q, angle);
+    }
+
+    /// X = H Rz(π) H
+    fn x(&mut self, q: usize) {
+        self.h(q);
+        self.rz_const(q, 1.);
+        self.h(q);
+    }
+
+    fn cx(&mut self, c: usize, t: usize) {
+        let cx = self
+            .builder
+            .add_dataflow_op(Tk2Op::CX, [self.qubits[c], self.qubits[t]]);
+        [self.qubits[c], self.qubits[t]] = cx.unwrap().outputs_arr();
+    }
+
+    fn finish(self) -> Hugr {
+        self.builder
+            .finish_hugr_with_outputs(self.qubits, &REGISTRY)
+            .unwrap()
+    }
+}
+
+/// Error type for [`rebase_to_nam`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum RebaseError {
+    /// The circuit contains a quantum operation that is not a [`Tk2Op`].
+    #[error("Cannot rebase the {op} operation at node {node:?}.")]
+    UnsupportedOp {
+        /// The name of the operation.
+        op: SmolStr,
+        /// The node of the operation.
+        node: Node,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::circuit::unitary::{rx_matrix, rz_matrix, Unitary, ZERO};
+    use crate::utils::{build_simple_circuit, constant_f64_input};
+    use hugr::extension::prelude::QB_T;
+    use hugr::types::FunctionType;
+    use hugr::IncomingPort;
+    use num_complex::Complex64;
+    use rstest::rstest;
+
+    /// The gates of a circuit and the qubits they act on.
+    fn gates(circ: &Hugr) -> Vec<(Node, Tk2Op, Vec<usize>)> {
+        circ.commands()
+            .filter_map(|cmd| {
+                let op = Tk2Op::try_from(cmd.optype()).ok()?;
+                let qubits = cmd.linear_inputs().map(|(u, _, _)| u.index()).collect();
+                Some((cmd.node(), op, qubits))
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case::single_qubit(&[(Tk2Op::T, &[0]), (Tk2Op::S, &[1]), (Tk2Op::X, &[0]), (Tk2Op::Y, &[1]), (Tk2Op::Sdg, &[0])])]
+    #[case::two_qubit(&[(Tk2Op::CZ, &[0, 1]), (Tk2Op::T, &[1]), (Tk2Op::SWAP, &[0, 1]), (Tk2Op::CY, &[1, 0]), (Tk2Op::ZZMax, &[0, 1])])]
+    fn rebase_fixed_gates(#[case] ops: &[(Tk2Op, &[usize])]) {
+        let mut circ = build_simple_circuit(2, |circ| {
+            for &(op, qbs) in ops {
+                circ.append(op, qbs.iter().copied())?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        rebase_to_nam(&mut circ).unwrap();
+
+        let rebased = gates(&circ);
+        assert!(rebased
+            .iter()
+            .all(|(_, op, _)| matches!(op, Tk2Op::CX | Tk2Op::RzF64 | Tk2Op::H)));
+
+        // Apply the rebased circuit followed by the inverse of the original,
+        // simulating each Rz(k/4) rotation as k T gates.
+        let mut u = Unitary::identity(2);
+        for (node, op, qubits) in rebased {
+            if op == Tk2Op::RzF64 {
+                let angle = constant_f64_input(&circ, node, IncomingPort::from(1)).unwrap();
+                let n_t = (angle * 4.).round().rem_euclid(8.) as usize;
+                for _ in 0..n_t {
+                    assert!(u.apply_op(Tk2Op::T, &qubits));
+                }
+            } else {
+                assert!(u.apply_op(op, &qubits));
+            }
+        }
+        for &(op, qbs) in ops.iter().rev() {
+            let inv = if op == Tk2Op::ZZMax {
+                // ZZMax^3 is the inverse of ZZMax, up to Z gates.
+                assert!(u.apply_op(Tk2Op::Z, &[0]));
+                assert!(u.apply_op(Tk2Op::Z, &[1]));
+                Tk2Op::ZZMax
+            } else {
+                op.dagger().unwrap()
+            };
+            assert!(u.apply_op(inv, qbs));
+        }
+        assert!(u.is_identity_up_to_phase(1e-8));
+    }
+
+    #[test]
+    fn decompose_tk1_gate() {
+        let angles = [0.3, 0.7, -0.2];
+        let mut h = DFGBuilder::new(FunctionType::new_endo(vec![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let [a, b, c] = angles.map(|angle| h.add_load_const(ConstF64::new(angle)).unwrap());
+        let tk1 = h.add_dataflow_op(Tk2Op::TK1, [q, a, b, c]).unwrap();
+        let mut circ = h
+            .finish_hugr_with_outputs(tk1.outputs(), &REGISTRY)
+            .unwrap();
+
+        assert_eq!(decompose_tk1(&mut circ), 1);
+        circ.update_validate(&REGISTRY).unwrap();
+        let decomposed = gates(&circ);
+        assert_eq!(
+            decomposed.iter().map(|(_, op, _)| *op).collect_vec(),
+            [Tk2Op::RzF64, Tk2Op::H, Tk2Op::RzF64, Tk2Op::H, Tk2Op::RzF64]
+        );
+
+        // Apply the decomposition followed by the inverse of
+        // TK1(a, b, c) = Rz(a) Rx(b) Rz(c).
+        let mut u = Unitary::identity(1);
+        for (node, op, qubits) in decomposed {
+            if op == Tk2Op::RzF64 {
+                let angle = constant_f64_input(&circ, node, IncomingPort::from(1)).unwrap();
+                assert!(u.apply_matrix(&rz_matrix(angle), &qubits));
+            } else {
+                assert!(u.apply_op(op, &qubits));
+            }
+        }
+        let [a, b, c] = angles;
+        assert!(u.apply_matrix(&rz_matrix(-a), &[0]));
+        assert!(u.apply_matrix(&rx_matrix(-b), &[0]));
+        assert!(u.apply_matrix(&rz_matrix(-c), &[0]));
+        assert!(u.is_identity_up_to_phase(1e-8));
+
+        assert_eq!(decompose_tk1(&mut circ), 0);
+    }
+
+    /// The row-major matrix of a ZZPhase gate, with the angle in half-turns.
+    fn zz_phase_matrix(angle: f64) -> Vec<Complex64> {
+        let phase = Complex64::from_polar(1., angle * std::f64::consts::FRAC_PI_2);
+        let diag = [phase.conj(), phase, phase, phase.conj()];
+        (0..16)
+            .map(|i| match i % 5 {
+                0 => diag[i / 5],
+                _ => ZERO,
+            })
+            .collect()
+    }
+
+    #[rstest]
+    #[case::rx(Tk2Op::RxF64, &[0.3], &[Tk2Op::H, Tk2Op::RzF64, Tk2Op::H], &[0.3])]
+    #[case::phased_x(
+        Tk2Op::PhasedX,
+        &[0.3, 0.7],
+        &[
+            Tk2Op::H, Tk2Op::RzF64, Tk2Op::H, Tk2Op::RzF64, Tk2Op::H, Tk2Op::RzF64,
+            Tk2Op::H, Tk2Op::H, Tk2Op::RzF64, Tk2Op::H, Tk2Op::RzF64,
+        ],
+        &[1., 0.7, 1., 0.3, 0.7],
+    )]
+    #[case::tk1(
+        Tk2Op::TK1,
+        &[0.3, 0.7, -0.2],
+        &[Tk2Op::RzF64, Tk2Op::H, Tk2Op::RzF64, Tk2Op::H, Tk2Op::RzF64],
+        &[-0.2, 0.7, 0.3],
+    )]
+    #[case::zz_phase(Tk2Op::ZZPhase, &[0.3], &[Tk2Op::CX, Tk2Op::RzF64, Tk2Op::CX], &[0.3])]
+    fn rebase_parametric_gates(
+        #[case] op: Tk2Op,
+        #[case] angles: &[f64],
+        #[case] expected_ops: &[Tk2Op],
+        #[case] expected_angles: &[f64],
+    ) {
+        let n_qubits = op.n_qubits();
+        let mut h = DFGBuilder::new(FunctionType::new_endo(vec![QB_T; n_qubits])).unwrap();
+        let mut inputs = h.input_wires().collect_vec();
+        for &angle in angles {
+            inputs.push(h.add_load_const(ConstF64::new(angle)).unwrap());
+        }
+        let gate = h.add_dataflow_op(op, inputs).unwrap();
+        let mut circ = h
+            .finish_hugr_with_outputs(gate.outputs(), &REGISTRY)
+            .unwrap();
+
+        rebase_to_nam(&mut circ).unwrap();
+        circ.update_validate(&REGISTRY).unwrap();
+
+        let rebased = gates(&circ);
+        assert_eq!(
+            rebased.iter().map(|(_, op, _)| *op).collect_vec(),
+            expected_ops
+        );
+        let rz_angles = rebased
+            .iter()
+            .filter(|(_, op, _)| *op == Tk2Op::RzF64)
+            .map(|(node, _, _)| constant_f64_input(&circ, *node, IncomingPort::from(1)).unwrap())
+            .collect_vec();
+        assert_eq!(rz_angles, expected_angles);
+
+        // Apply the rebased circuit followed by the inverse of the gate.
+        let mut u = Unitary::identity(n_qubits);
+        for (node, op, qubits) in rebased {
+            if op == Tk2Op::RzF64 {
+                let angle = constant_f64_input(&circ, node, IncomingPort::from(1)).unwrap();
+                assert!(u.apply_matrix(&rz_matrix(angle), &qubits));
+            } else {
+                assert!(u.apply_op(op, &qubits));
+            }
+        }
+        let inverse = match (op, angles) {
+            (Tk2Op::RxF64, &[a]) => vec![rx_matrix(-a)],
+            // PhasedX(a, b) = Rz(b) Rx(a) Rz(-b)
+            (Tk2Op::PhasedX, &[a, b]) => vec![rz_matrix(-b), rx_matrix(-a), rz_matrix(b)],
+            // TK1(a, b, c) = Rz(a) Rx(b) Rz(c)
+            (Tk2Op::TK1, &[a, b, c]) => vec![rz_matrix(-a), rx_matrix(-b), rz_matrix(-c)],
+            (Tk2Op::ZZPhase, &[a]) => vec![zz_phase_matrix(-a)],
+            _ => unreachable!(),
+        };
+        let qubits = (0..n_qubits).collect_vec();
+        for mat in inverse {
+            assert!(u.apply_matrix(&mat, &qubits));
+        }
+        assert!(u.is_identity_up_to_phase(1e-8));
+    }
+
+    #[test]
+    fn rebase_keeps_target_gates() {
+        let mut circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        let before = gates(&circ);
+
+        rebase_to_nam(&mut circ).unwrap();
+
+        assert_eq!(gates(&circ), before);
+    }
+}
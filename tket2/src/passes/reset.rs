@@ -0,0 +1,84 @@
+//! Simplification of gates made redundant by qubit resets.
+
+use hugr::{Hugr, HugrView, IncomingPort, Node};
+use itertools::Itertools;
+
+use super::identities::remove_gate_with_params;
+use crate::circuit::Circuit;
+use crate::Tk2Op;
+
+/// Remove the single-qubit gates immediately preceding a [`Tk2Op::Reset`] on
+/// the same qubit.
+///
+/// A reset discards the state of its qubit, so the unitary gates applied just
+/// before it have no observable effect. Chains of such gates are removed up to
+/// the first operation that is not a single-qubit unitary [`Tk2Op`], such as a
+/// multi-qubit gate, a measurement, or a barrier. The constants feeding the
+/// removed gates are also removed if they become unused.
+///
+/// Returns the number of gates removed.
+pub fn simplify_before_reset(circ: &mut Hugr) -> usize {
+    let resets = circ
+        .commands()
+        .filter(|cmd| Tk2Op::try_from(cmd.optype()) == Ok(Tk2Op::Reset))
+        .map(|cmd| cmd.node())
+        .collect_vec();
+
+    let mut removed = 0;
+    for reset in resets {
+        while let Some(gate) = redundant_predecessor(circ, reset) {
+            remove_gate_with_params(circ, gate);
+            removed += 1;
+        }
+    }
+    removed
+}
+
+/// The single-qubit unitary gate acting on the qubit of `reset` just before
+/// it, if any.
+fn redundant_predecessor(circ: &Hugr, reset: Node) -> Option<Node> {
+    let (pred, _) = circ
+        .linked_outputs(reset, IncomingPort::from(0))
+        .exactly_one()
+        .ok()?;
+    let op = Tk2Op::try_from(circ.get_optype(pred)).ok()?;
+    (op.is_quantum() && op.n_qubits() == 1).then_some(pred)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::build_simple_circuit;
+    use crate::utils::test::gates;
+
+    #[test]
+    fn remove_before_reset() {
+        let mut circ = build_simple_circuit(1, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::X, [0])?;
+            circ.append(Tk2Op::Reset, [0])?;
+            circ.append(Tk2Op::T, [0])?;
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(simplify_before_reset(&mut circ), 2);
+        assert_eq!(gates(&circ), [Tk2Op::Reset, Tk2Op::T]);
+    }
+
+    #[test]
+    fn blocked_by_two_qubit_gates() {
+        let mut circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::Reset, [1])?;
+            circ.append(Tk2Op::Measure, [0])?;
+            circ.append(Tk2Op::Reset, [0])?;
+            Ok(())
+        })
+        .unwrap();
+        let before = gates(&circ);
+
+        assert_eq!(simplify_before_reset(&mut circ), 0);
+        assert_eq!(gates(&circ), before);
+    }
+}
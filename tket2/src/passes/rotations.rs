@@ -0,0 +1,243 @@
+//! Passes merging single-qubit rotations.
+
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::{Hugr, HugrView, IncomingPort, Node, OutgoingPort, Port};
+use itertools::Itertools;
+
+use super::fold::fold_additions;
+use crate::circuit::Circuit;
+use crate::utils::remove_1q_gate;
+use crate::Tk2Op;
+
+/// Merge [`Tk2Op::RzF64`] rotations separated only by [`Tk2Op::SWAP`] gates.
+///
+/// Single-qubit gates commute through a SWAP, emerging on the other qubit.
+/// Each Rz rotation is followed through any sequence of SWAPs, tracking the
+/// wire its qubit is moved to. If it then reaches another Rz, the first
+/// rotation is removed and its angle is added to the second one using a
+/// [`Tk2Op::AngleAdd`] operation.
+///
+/// Returns the number of rotations removed from the circuit.
+pub fn merge_rotations_through_swaps(circ: &mut Hugr) -> usize {
+    let rotations = circ
+        .commands()
+        .filter(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(Tk2Op::RzF64))
+        .map(|cmd| cmd.node())
+        .collect_vec();
+
+    let mut n_merged = 0;
+    for rz in rotations {
+        let Some(next_rz) = rotation_after_swaps(circ, rz) else {
+            continue;
+        };
+        merge_rotation_into(circ, rz, next_rz);
+        n_merged += 1;
+    }
+    n_merged
+}
+
+/// Merge runs of consecutive [`Tk2Op::RzF64`] rotations on the same qubit.
+///
+/// Each run of rotations uninterrupted by other gates is replaced by a single
+/// rotation, whose angle is the sum of the angles in the run computed with
+/// [`Tk2Op::AngleAdd`] operations. The additions inserted by this pass whose
+/// inputs are constant are then folded into a single constant; other
+/// additions in the circuit are left untouched.
+///
+/// Returns the number of merges, i.e. the number of rotations removed from
+/// the circuit.
+pub fn merge_rz_rotations(circ: &mut Hugr) -> usize {
+    let rotations = circ
+        .commands()
+        .filter(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(Tk2Op::RzF64))
+        .map(|cmd| cmd.node())
+        .collect_vec();
+
+    let mut additions = Vec::new();
+    for rz in rotations {
+        let Some(next_rz) = next_rotation(circ, rz) else {
+            continue;
+        };
+        additions.push(merge_rotation_into(circ, rz, next_rz));
+    }
+    let n_merged = additions.len();
+    // Each addition feeds the next one in a run, so they are in topological
+    // order.
+    fold_additions(circ, additions);
+    n_merged
+}
+
+/// Returns the rotation directly following `rz` on its qubit, if any.
+fn next_rotation(circ: &Hugr, rz: Node) -> Option<Node> {
+    let (next, next_port) = circ
+        .linked_inputs(rz, OutgoingPort::from(0))
+        .exactly_one()
+        .ok()?;
+    let is_rz = Tk2Op::try_from(circ.get_optype(next)).ok()? == Tk2Op::RzF64;
+    (is_rz && next_port.index() == 0).then_some(next)
+}
+
+/// Follow the qubit output of a rotation through a non-empty sequence of
+/// SWAP gates, and return the next rotation acting on it, if any.
+fn rotation_after_swaps(circ: &Hugr, rz: Node) -> Option<Node> {
+    let mut node = rz;
+    let mut port = OutgoingPort::from(0);
+    let mut n_swaps = 0;
+    loop {
+        let (next, next_port) = circ.linked_inputs(node, port).exactly_one().ok()?;
+        match Tk2Op::try_from(circ.get_optype(next)).ok()? {
+            Tk2Op::SWAP => {
+                // The qubit emerges on the other wire.
+                node = next;
+                port = OutgoingPort::from(1 - next_port.index());
+                n_swaps += 1;
+            }
+            Tk2Op::RzF64 if n_swaps > 0 && next_port.index() == 0 => return Some(next),
+            _ => return None,
+        }
+    }
+}
+
+/// Remove the rotation `rz`, adding its angle to the rotation `target`.
+///
+/// Returns the inserted [`Tk2Op::AngleAdd`] node.
+fn merge_rotation_into(circ: &mut Hugr, rz: Node, target: Node) -> Node {
+    let angle_port = IncomingPort::from(1);
+    let (a, a_port) = circ
+        .linked_outputs(rz, angle_port)
+        .exactly_one()
+        .ok()
+        .expect("Invalid circuit: angle input is not connected.");
+    let (b, b_port) = circ
+        .linked_outputs(target, angle_port)
+        .exactly_one()
+        .ok()
+        .expect("Invalid circuit: angle input is not connected.");
+
+    let parent = circ.get_parent(target).expect("Command has no parent.");
+    let add = circ
+        .add_op_with_parent(parent, Tk2Op::AngleAdd)
+        .expect("Invalid circuit: could not insert angle addition.");
+    circ.connect(a, a_port.index(), add, 0)
+        .expect("Invalid circuit: could not connect angle.");
+    circ.connect(b, b_port.index(), add, 1)
+        .expect("Invalid circuit: could not connect angle.");
+    circ.disconnect(target, Port::from(angle_port))
+        .expect("Invalid circuit: could not disconnect angle.");
+    circ.connect(add, 0, target, angle_port.index())
+        .expect("Invalid circuit: could not connect angle.");
+
+    remove_1q_gate(circ, rz);
+    add
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::REGISTRY;
+    use crate::utils::constant_f64_input;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::QB_T;
+    use hugr::std_extensions::arithmetic::float_types::ConstF64;
+    use hugr::type_row;
+    use hugr::types::FunctionType;
+    use rstest::rstest;
+
+    /// Build a circuit applying `Rz(0.25)` on qubit 0, a number of SWAPs, and
+    /// `Rz(0.5)` on the qubit `last_qb`.
+    fn rz_swaps_rz(n_swaps: usize, last_qb: usize) -> Hugr {
+        let mut h = DFGBuilder::new(FunctionType::new(
+            type_row![QB_T, QB_T],
+            type_row![QB_T, QB_T],
+        ))
+        .unwrap();
+        let [q0, q1] = h.input_wires_arr();
+        let a = h.add_load_const(ConstF64::new(0.25)).unwrap();
+        let b = h.add_load_const(ConstF64::new(0.5)).unwrap();
+
+        let rz = h.add_dataflow_op(Tk2Op::RzF64, [q0, a]).unwrap();
+        let mut qbs = [rz.out_wire(0), q1];
+        for _ in 0..n_swaps {
+            qbs = h.add_dataflow_op(Tk2Op::SWAP, qbs).unwrap().outputs_arr();
+        }
+        let rz = h.add_dataflow_op(Tk2Op::RzF64, [qbs[last_qb], b]).unwrap();
+        qbs[last_qb] = rz.out_wire(0);
+
+        h.finish_hugr_with_outputs(qbs, &REGISTRY).unwrap()
+    }
+
+    fn count_op(circ: &Hugr, op: Tk2Op) -> usize {
+        circ.commands()
+            .filter(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(op))
+            .count()
+    }
+
+    #[rstest]
+    #[case::swapped_wire(1, 1, 1)]
+    #[case::same_wire(1, 0, 0)]
+    #[case::two_swaps(2, 0, 1)]
+    #[case::no_swap(0, 0, 0)]
+    fn merge_through_swaps(
+        #[case] n_swaps: usize,
+        #[case] last_qb: usize,
+        #[case] expected: usize,
+    ) {
+        let mut circ = rz_swaps_rz(n_swaps, last_qb);
+
+        assert_eq!(merge_rotations_through_swaps(&mut circ), expected);
+        assert_eq!(count_op(&circ, Tk2Op::RzF64), 2 - expected);
+        assert_eq!(count_op(&circ, Tk2Op::AngleAdd), expected);
+        assert_eq!(count_op(&circ, Tk2Op::SWAP), n_swaps);
+    }
+
+    #[rstest]
+    #[case::pair(vec![0.1, 0.2], 1, 0.3)]
+    #[case::run(vec![0.25, 0.5, 0.75], 2, 1.5)]
+    #[case::single(vec![0.5], 0, 0.5)]
+    fn merge_rz(#[case] angles: Vec<f64>, #[case] expected: usize, #[case] total: f64) {
+        let mut h = DFGBuilder::new(FunctionType::new(type_row![QB_T], type_row![QB_T])).unwrap();
+        let [mut q] = h.input_wires_arr();
+        for angle in angles {
+            let a = h.add_load_const(ConstF64::new(angle)).unwrap();
+            q = h.add_dataflow_op(Tk2Op::RzF64, [q, a]).unwrap().out_wire(0);
+        }
+        let mut circ = h.finish_hugr_with_outputs([q], &REGISTRY).unwrap();
+
+        assert_eq!(merge_rz_rotations(&mut circ), expected);
+        assert_eq!(count_op(&circ, Tk2Op::RzF64), 1);
+        assert_eq!(count_op(&circ, Tk2Op::AngleAdd), 0);
+
+        let rz = circ
+            .commands()
+            .find(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(Tk2Op::RzF64))
+            .unwrap()
+            .node();
+        let angle = constant_f64_input(&circ, rz, IncomingPort::from(1)).unwrap();
+        assert!((angle - total).abs() < 1e-12);
+    }
+
+    #[test]
+    fn merge_rz_keeps_other_additions() {
+        let mut h = DFGBuilder::new(FunctionType::new(type_row![QB_T], type_row![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let a = h.add_load_const(ConstF64::new(0.25)).unwrap();
+        let b = h.add_load_const(ConstF64::new(0.5)).unwrap();
+        let sum = h
+            .add_dataflow_op(Tk2Op::AngleAdd, [a, b])
+            .unwrap()
+            .out_wire(0);
+        let q = h
+            .add_dataflow_op(Tk2Op::RzF64, [q, sum])
+            .unwrap()
+            .out_wire(0);
+        let c = h.add_load_const(ConstF64::new(0.125)).unwrap();
+        let q = h.add_dataflow_op(Tk2Op::RzF64, [q, c]).unwrap().out_wire(0);
+        let mut circ = h.finish_hugr_with_outputs([q], &REGISTRY).unwrap();
+
+        assert_eq!(merge_rz_rotations(&mut circ), 1);
+        assert_eq!(count_op(&circ, Tk2Op::RzF64), 1);
+        // The addition in the input circuit is not folded, so neither is the
+        // one inserted by the pass.
+        assert_eq!(count_op(&circ, Tk2Op::AngleAdd), 2);
+    }
+}
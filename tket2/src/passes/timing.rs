@@ -0,0 +1,135 @@
+//! Utilities for timing sequences of passes.
+
+use std::time::{Duration, Instant};
+
+use hugr::Hugr;
+
+/// A circuit pass, returning the number of changes it made to the circuit.
+pub type PassFn<'a> = Box<dyn FnMut(&mut Hugr) -> usize + 'a>;
+
+/// A sequence of named passes, recording the wall-clock time taken by each
+/// one when run.
+#[derive(Default)]
+pub struct PassTimer<'a> {
+    passes: Vec<(String, PassFn<'a>)>,
+}
+
+impl<'a> PassTimer<'a> {
+    /// Create a new empty sequence of passes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a named pass to the sequence.
+    pub fn with_pass(
+        mut self,
+        name: impl Into<String>,
+        pass: impl FnMut(&mut Hugr) -> usize + 'a,
+    ) -> Self {
+        self.passes.push((name.into(), Box::new(pass)));
+        self
+    }
+
+    /// The number of passes in the sequence.
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    /// Returns `true` if the sequence has no passes.
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// Run the passes in order on a circuit.
+    ///
+    /// Returns the name of each pass along with the time it took to run.
+    pub fn run(&mut self, circ: &mut Hugr) -> Vec<(String, Duration)> {
+        self.passes
+            .iter_mut()
+            .map(|(name, pass)| (name.clone(), time_pass(circ, pass)))
+            .collect()
+    }
+}
+
+impl std::fmt::Debug for PassTimer<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PassTimer")
+            .field(
+                "passes",
+                &self.passes.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Run a sequence of named passes on a circuit, in order.
+///
+/// Returns the name of each pass along with the wall-clock time it took to run.
+pub fn run_passes_timed(
+    circ: &mut Hugr,
+    passes: &mut [(&str, PassFn<'_>)],
+) -> Vec<(String, Duration)> {
+    passes
+        .iter_mut()
+        .map(|(name, pass)| (name.to_string(), time_pass(circ, pass)))
+        .collect()
+}
+
+/// Time a single pass.
+fn time_pass(circ: &mut Hugr, pass: &mut PassFn<'_>) -> Duration {
+    let start = Instant::now();
+    pass(circ);
+    start.elapsed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::passes::apply_greedy_commutation;
+    use crate::passes::track_paulis;
+    use crate::utils::build_simple_circuit;
+    use crate::Tk2Op;
+
+    #[test]
+    fn two_passes() {
+        let mut circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::X, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let mut passes: Vec<(&str, PassFn)> = vec![
+            (
+                "pauli",
+                Box::new(|circ: &mut Hugr| track_paulis(circ).paulis().len()),
+            ),
+            (
+                "commutation",
+                Box::new(|circ: &mut Hugr| apply_greedy_commutation(circ).unwrap() as usize),
+            ),
+        ];
+        let timings = run_passes_timed(&mut circ, &mut passes);
+
+        let names: Vec<_> = timings.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["pauli", "commutation"]);
+    }
+
+    #[test]
+    fn pass_timer() {
+        let mut circ = build_simple_circuit(1, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            Ok(())
+        })
+        .unwrap();
+
+        let mut timer = PassTimer::new()
+            .with_pass("first", |_: &mut Hugr| 0)
+            .with_pass("second", |_: &mut Hugr| 1);
+        assert_eq!(timer.len(), 2);
+
+        let timings = timer.run(&mut circ);
+        let names: Vec<_> = timings.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["first", "second"]);
+    }
+}
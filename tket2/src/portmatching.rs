@@ -58,11 +58,13 @@ pub mod pattern;
 
 use hugr::OutgoingPort;
 use itertools::Itertools;
-pub use matcher::{PatternMatch, PatternMatcher};
+pub use matcher::{
+    match_overlap_graph, MatchCache, MatchCheck, MatchTrace, PatternMatch, PatternMatcher,
+};
 pub use pattern::CircuitPattern;
 
 use hugr::{
-    ops::{OpTag, OpTrait},
+    ops::{OpTag, OpTrait, OpType},
     Node, Port,
 };
 use matcher::MatchOp;
@@ -70,7 +72,27 @@ use thiserror::Error;
 
 use crate::{circuit::Circuit, utils::type_is_linear};
 
-type PNode = MatchOp;
+/// A node property in a circuit pattern.
+///
+/// The enum is serialised untagged, so that operation properties keep the
+/// same encoding as in previously serialised matchers.
+#[derive(
+    Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(untagged)]
+enum PNode {
+    /// An operation of the circuit.
+    Op(MatchOp),
+    /// A hidden copy node, for an outgoing port whose value is consumed by
+    /// several nodes of the pattern.
+    Copy,
+}
+
+impl From<OpType> for PNode {
+    fn from(op: OpType) -> Self {
+        Self::Op(op.into())
+    }
+}
 
 /// An edge property in a circuit pattern.
 ///
@@ -97,6 +119,15 @@ enum PEdge {
     InputEdge { src: Port },
 }
 
+impl PEdge {
+    /// The port the edge starts from.
+    fn src_port(&self) -> Port {
+        match *self {
+            PEdge::InternalEdge { src, .. } | PEdge::InputEdge { src } => src,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Error)]
 enum InvalidEdgeProperty {
     /// The port is linked to multiple edges.
@@ -177,7 +208,8 @@ impl portmatching::EdgeProperty for PEdge {
 /// that is identified by its node and outgoing port.
 ///
 /// A NodeID::CopyNode can only be found as a target of a PEdge::InputEdge
-/// property. Furthermore, a NodeID::CopyNode never has a node property.
+/// property. A NodeID::CopyNode only has a node property, [`PNode::Copy`],
+/// when the copied value fans out to several nodes of the pattern.
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
 )]
@@ -192,6 +224,13 @@ impl NodeID {
         let port: OutgoingPort = port.into();
         Self::CopyNode(node, port.into())
     }
+
+    /// The HUGR node of the node ID.
+    fn node(&self) -> Node {
+        match *self {
+            NodeID::HugrNode(node) | NodeID::CopyNode(node, _) => node,
+        }
+    }
 }
 
 impl From<Node> for NodeID {
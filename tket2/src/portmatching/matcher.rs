@@ -1,25 +1,35 @@
 //! Pattern and matcher objects for circuit matching
 
 use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
     fmt::Debug,
     fs::File,
+    hash::{Hash, Hasher},
     io,
     path::{Path, PathBuf},
 };
 
-use super::{CircuitPattern, NodeID, PEdge, PNode};
+use fxhash::FxHasher64;
+
+use super::{CircuitPattern, InvalidPattern, NodeID, PEdge, PNode};
 use hugr::hugr::views::sibling_subgraph::{
-    InvalidReplacement, InvalidSubgraph, InvalidSubgraphBoundary, TopoConvexChecker,
+    InvalidSubgraph, InvalidSubgraphBoundary, TopoConvexChecker,
 };
 use hugr::hugr::views::SiblingSubgraph;
 use hugr::ops::{OpName, OpType};
+use hugr::std_extensions::arithmetic::float_types::ConstF64;
+use hugr::values::Value;
 use hugr::{Hugr, IncomingPort, Node, OutgoingPort, Port, PortIndex};
 use itertools::Itertools;
+use petgraph::graph::UnGraph;
 use portgraph::algorithms::ConvexChecker;
 use portmatching::{
     automaton::{LineBuilder, ScopeAutomaton},
+    patterns::LinePattern,
     EdgeProperty, PatternID,
 };
+use rayon::prelude::*;
 use smol_str::SmolStr;
 use thiserror::Error;
 
@@ -27,8 +37,9 @@ use thiserror::Error;
 use pyo3::prelude::*;
 
 use crate::{
-    circuit::Circuit,
-    rewrite::{CircuitRewrite, Subcircuit},
+    circuit::{angle::AngleValue, check_convex_with_checker, units::filter, Circuit},
+    ops::is_barrier,
+    rewrite::{CircuitRewrite, InvalidReplacement, Subcircuit},
 };
 
 /// Matchable operations in a circuit.
@@ -60,12 +71,35 @@ impl From<OpType> for MatchOp {
             {
                 None
             }
+            OpType::Const(ref const_op) => match const_op.value() {
+                Value::Extension { c: (val,) } => match val.downcast_ref::<ConstF64>() {
+                    Some(f) => Some(encode_f64(f.value())),
+                    None => rmp_serde::encode::to_vec(&op).ok(),
+                },
+                _ => rmp_serde::encode::to_vec(&op).ok(),
+            },
             _ => rmp_serde::encode::to_vec(&op).ok(),
         };
         Self { op_name, encoded }
     }
 }
 
+impl MatchOp {
+    /// The name of the matched operation.
+    pub(crate) fn name(&self) -> &SmolStr {
+        &self.op_name
+    }
+}
+
+/// Encode a float constant by its canonical key, so that equal constants
+/// compare equal.
+///
+/// This uses the same key as the circuit hash, see
+/// [`AngleValue::canonical_key`].
+fn encode_f64(value: f64) -> Vec<u8> {
+    format!("f64:{:?}", AngleValue::F64(value).canonical_key()).into_bytes()
+}
+
 /// A convex pattern match in a circuit.
 ///
 /// The pattern is identified by a [`PatternID`] that can be used to retrieve the
@@ -102,6 +136,38 @@ impl PatternMatch {
         self.position.nodes()
     }
 
+    /// Returns the input ports of the matched subcircuit.
+    ///
+    /// Each entry corresponds to an input of the pattern, and lists the
+    /// ports of the subcircuit nodes connected to it.
+    pub fn incoming_boundary(&self) -> &[Vec<(Node, IncomingPort)>] {
+        self.position.subgraph.incoming_ports()
+    }
+
+    /// Returns the output ports of the matched subcircuit.
+    ///
+    /// Each entry corresponds to an output of the pattern.
+    pub fn outgoing_boundary(&self) -> &[(Node, OutgoingPort)] {
+        self.position.subgraph.outgoing_ports()
+    }
+
+    /// Returns the sorted indices of the circuit qubits acted on by the
+    /// matched subcircuit.
+    pub fn qubits(&self, circ: &impl Circuit) -> Vec<usize> {
+        let nodes: HashSet<Node> = self.nodes().iter().copied().collect();
+        circ.commands()
+            .filter(|cmd| nodes.contains(&cmd.node()))
+            .flat_map(|cmd| {
+                cmd.inputs()
+                    .filter_map(filter::filter_qubit)
+                    .map(|(unit, _, _)| unit.index())
+                    .collect_vec()
+            })
+            .sorted()
+            .dedup()
+            .collect()
+    }
+
     /// Create a pattern match from the image of a pattern root.
     ///
     /// This checks at construction time that the match is convex. This will
@@ -235,6 +301,9 @@ impl Debug for PatternMatch {
 pub struct PatternMatcher {
     automaton: ScopeAutomaton<PNode, PEdge, Port>,
     patterns: Vec<CircuitPattern>,
+    /// The maximum number of matches returned for each pattern.
+    #[serde(default)]
+    max_matches_per_pattern: Option<usize>,
 }
 
 impl Debug for PatternMatcher {
@@ -247,34 +316,213 @@ impl Debug for PatternMatcher {
 
 impl PatternMatcher {
     /// Construct a matcher from a set of patterns
+    ///
+    /// # Panics
+    ///
+    /// Panics if a pattern cannot be expressed as a line pattern. See
+    /// [`PatternMatcher::try_from_patterns`] for a fallible version.
     pub fn from_patterns(patterns: impl Into<Vec<CircuitPattern>>) -> Self {
+        Self::try_from_patterns(patterns).expect("Failed to express pattern as line pattern")
+    }
+
+    /// Construct a matcher from a set of patterns.
+    ///
+    /// Returns an error naming the first pattern that cannot be expressed as
+    /// a line pattern, e.g. because it is not connected.
+    pub fn try_from_patterns(
+        patterns: impl Into<Vec<CircuitPattern>>,
+    ) -> Result<Self, InvalidPattern> {
         let patterns = patterns.into();
         let line_patterns = patterns
             .iter()
-            .map(|p| {
-                p.pattern
-                    .clone()
-                    .try_into_line_pattern(compatible_offsets)
-                    .expect("Failed to express pattern as line pattern")
-            })
-            .collect_vec();
+            .enumerate()
+            .map(|(index, p)| to_line_pattern(index, p))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_line_patterns(patterns, line_patterns))
+    }
+
+    /// Construct a matcher from a set of patterns, converting them to line
+    /// patterns in parallel.
+    ///
+    /// Produces the same matcher as [`PatternMatcher::from_patterns`], with
+    /// the pattern IDs in input order. The conversions are distributed over
+    /// the rayon thread pool, which speeds up the construction of matchers
+    /// with many patterns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a pattern cannot be expressed as a line pattern. See
+    /// [`PatternMatcher::try_from_patterns_parallel`] for a fallible version.
+    pub fn from_patterns_parallel(patterns: impl Into<Vec<CircuitPattern>>) -> Self {
+        Self::try_from_patterns_parallel(patterns)
+            .expect("Failed to express pattern as line pattern")
+    }
+
+    /// Construct a matcher from a set of patterns, converting them to line
+    /// patterns in parallel.
+    ///
+    /// Returns an error naming the first pattern that cannot be expressed as
+    /// a line pattern, as [`PatternMatcher::try_from_patterns`] does.
+    pub fn try_from_patterns_parallel(
+        patterns: impl Into<Vec<CircuitPattern>>,
+    ) -> Result<Self, InvalidPattern> {
+        let patterns = patterns.into();
+        let line_patterns = patterns
+            .par_iter()
+            .enumerate()
+            .map(|(index, p)| to_line_pattern(index, p))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::from_line_patterns(patterns, line_patterns))
+    }
+
+    /// Build the matching automaton from the line patterns of `patterns`.
+    fn from_line_patterns(
+        patterns: Vec<CircuitPattern>,
+        line_patterns: Vec<LinePattern<NodeID, PNode, PEdge>>,
+    ) -> Self {
         let builder = LineBuilder::from_patterns(line_patterns);
         let automaton = builder.build();
         Self {
             automaton,
             patterns,
+            max_matches_per_pattern: None,
         }
     }
 
+    /// Add a pattern to the matcher, returning its ID.
+    ///
+    /// The matching automaton of `portmatching` cannot be extended in place,
+    /// so it is rebuilt from the line patterns of all the patterns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the pattern cannot be expressed as a line pattern. See
+    /// [`PatternMatcher::try_add_pattern`] for a fallible version.
+    pub fn add_pattern(&mut self, pattern: CircuitPattern) -> PatternID {
+        self.try_add_pattern(pattern)
+            .expect("Failed to express pattern as line pattern")
+    }
+
+    /// Add a pattern to the matcher, returning its ID.
+    ///
+    /// Returns an error if the pattern cannot be expressed as a line pattern,
+    /// in which case the matcher is left unchanged. See
+    /// [`PatternMatcher::add_pattern`].
+    pub fn try_add_pattern(
+        &mut self,
+        pattern: CircuitPattern,
+    ) -> Result<PatternID, InvalidPattern> {
+        let index = self.patterns.len();
+        let new_line_pattern = to_line_pattern(index, &pattern)?;
+        let line_patterns = self
+            .patterns
+            .iter()
+            .enumerate()
+            .map(|(index, p)| to_line_pattern(index, p))
+            .chain([Ok(new_line_pattern)])
+            .collect::<Result<Vec<_>, _>>()?;
+        self.automaton = LineBuilder::from_patterns(line_patterns).build();
+        self.patterns.push(pattern);
+        Ok(PatternID(index))
+    }
+
+    /// Limit the number of matches returned for each pattern.
+    ///
+    /// Once `limit` matches of a pattern have been found, the `find_*`
+    /// methods skip any further match of that pattern, while still returning
+    /// the matches of the other patterns, and
+    /// [`PatternMatcher::count_matches`] reports at most `limit` matches per
+    /// pattern. This bounds the number of matches on highly symmetric
+    /// circuits. The limit is kept when the matcher is serialised.
+    pub fn with_max_matches_per_pattern(mut self, limit: usize) -> Self {
+        self.max_matches_per_pattern = Some(limit);
+        self
+    }
+
     /// Find all convex pattern matches in a circuit.
+    ///
+    /// Every command of the circuit is used as a root for the search, so a
+    /// circuit without commands has no matches. A single-gate pattern matches
+    /// once for every command with the same operation.
     pub fn find_matches_iter<'a, 'c: 'a, C: Circuit + Clone>(
         &'a self,
         circuit: &'c C,
     ) -> impl Iterator<Item = PatternMatch> + 'a {
         let checker = TopoConvexChecker::new(circuit);
-        circuit
-            .commands()
-            .flat_map(move |cmd| self.find_rooted_matches(circuit, cmd.node(), &checker))
+        self.rooted_candidates(circuit)
+            .filter_map(move |(root, pattern_id)| {
+                handle_match_error(
+                    PatternMatch::try_from_root_match_with_checker(
+                        root, pattern_id, circuit, self, &checker,
+                    ),
+                    root,
+                )
+            })
+            .filter(self.match_limiter())
+    }
+
+    /// Find all convex pattern matches in a circuit, reusing the automaton
+    /// runs of previous calls.
+    ///
+    /// The patterns found at each root are stored in `cache`, along with a
+    /// hash of the operation and links of every node of the circuit. In later
+    /// calls, the automaton is only run at the roots within the maximum
+    /// number of edges in a pattern of a node whose hash changed. Convexity is
+    /// checked again on every call, so this returns the same matches as
+    /// [`PatternMatcher::find_matches`].
+    ///
+    /// A cache should only be used with a single matcher.
+    pub fn find_matches_with_cache<C: Circuit + Clone>(
+        &self,
+        circ: &C,
+        cache: &mut MatchCache,
+    ) -> Vec<PatternMatch> {
+        let checker = TopoConvexChecker::new(circ);
+        let node_hashes: HashMap<Node, u64> = circ
+            .children(circ.root())
+            .map(|node| (node, node_hash(circ, node)))
+            .collect();
+        let changed = node_hashes
+            .iter()
+            .filter(|&(node, hash)| cache.node_hashes.get(node) != Some(hash))
+            .map(|(&node, _)| node)
+            .collect_vec();
+        let stale = nodes_within(circ, changed, self.max_pattern_edges());
+        cache.node_hashes = node_hashes;
+
+        let roots = circ.commands().map(|cmd| cmd.node()).collect_vec();
+        let root_set: HashSet<Node> = roots.iter().copied().collect();
+        cache
+            .runs
+            .retain(|root, _| root_set.contains(root) && !stale.contains(root));
+        roots
+            .into_iter()
+            .flat_map(|root| {
+                let pattern_ids = cache
+                    .runs
+                    .entry(root)
+                    .or_insert_with(|| self.run_automaton(circ, root))
+                    .clone();
+                self.matches_from_pattern_ids(circ, root, pattern_ids, &checker)
+            })
+            .filter(self.match_limiter())
+            .collect()
+    }
+
+    /// A stateful filter keeping at most `max_matches_per_pattern` matches of
+    /// each pattern.
+    fn match_limiter(&self) -> impl FnMut(&PatternMatch) -> bool + '_ {
+        let mut n_matches: HashMap<PatternID, usize> = HashMap::new();
+        move |m| {
+            let Some(limit) = self.max_matches_per_pattern else {
+                return true;
+            };
+            let count = n_matches.entry(m.pattern_id()).or_default();
+            *count += 1;
+            *count <= limit
+        }
     }
 
     /// Find all convex pattern matches in a circuit.and collect in to a vector
@@ -282,6 +530,162 @@ impl PatternMatcher {
         self.find_matches_iter(circuit).collect()
     }
 
+    /// Find the first convex match of any pattern in a circuit, if any.
+    ///
+    /// The search stops at the first match found, which makes this cheaper
+    /// than [`PatternMatcher::find_matches`] to check that a circuit does not
+    /// contain any of a set of forbidden subcircuits.
+    pub fn contains_any<C: Circuit + Clone>(&self, circuit: &C) -> Option<PatternMatch> {
+        self.find_matches_iter(circuit).next()
+    }
+
+    /// Run the automaton from `root` and record the checks it performs.
+    ///
+    /// This is a diagnostic tool to find out why a pattern does not match at
+    /// a given position. The returned trace lists the node and edge checks in
+    /// the order they were performed, and the patterns recognised at `root`.
+    /// The convexity of the matches is not checked.
+    pub fn explain_no_match(&self, circ: &impl Circuit, root: Node) -> MatchTrace {
+        let checks = RefCell::new(Vec::new());
+        let validate_node = validate_circuit_node(circ);
+        let validate_edge = validate_circuit_edge(circ);
+        let patterns = self
+            .automaton
+            .run(
+                root.into(),
+                |node: NodeID, prop: &PNode| {
+                    let passed = validate_node(node, prop);
+                    checks.borrow_mut().push(MatchCheck::Node {
+                        node: node.node(),
+                        passed,
+                    });
+                    passed
+                },
+                |src: NodeID, prop: &PEdge| {
+                    let target = validate_edge(src, prop);
+                    checks.borrow_mut().push(MatchCheck::Edge {
+                        node: src.node(),
+                        port: prop.src_port(),
+                        target: target.map(|t| t.node()),
+                    });
+                    target
+                },
+            )
+            .collect();
+        MatchTrace {
+            checks: checks.into_inner(),
+            patterns,
+        }
+    }
+
+    /// Find all convex pattern matches in a circuit, grouped by their root
+    /// node.
+    pub fn find_matches_by_root<C: Circuit + Clone>(
+        &self,
+        circuit: &C,
+    ) -> HashMap<Node, Vec<PatternMatch>> {
+        let mut groups: HashMap<Node, Vec<PatternMatch>> = HashMap::new();
+        for m in self.find_matches_iter(circuit) {
+            groups.entry(m.root()).or_default().push(m);
+        }
+        groups
+    }
+
+    /// Find all convex pattern matches in a circuit, in parallel.
+    ///
+    /// The search is rooted at every command of the circuit, and the roots
+    /// are distributed over the rayon thread pool. Each thread owns its own
+    /// convexity checker.
+    ///
+    /// Returns the same matches as [`PatternMatcher::find_matches`], in an
+    /// arbitrary order. If the number of matches per pattern is limited, the
+    /// matches kept for each pattern are also arbitrary.
+    pub fn find_par_matches<C: Circuit + Clone + Sync>(&self, circ: &C) -> Vec<PatternMatch> {
+        let roots = circ.commands().map(|cmd| cmd.node()).collect_vec();
+        let matches: Vec<_> = roots
+            .into_par_iter()
+            .map_init(
+                || TopoConvexChecker::new(circ),
+                |checker, root| self.find_rooted_matches(circ, root, checker),
+            )
+            .flatten_iter()
+            .collect();
+        matches.into_iter().filter(self.match_limiter()).collect()
+    }
+
+    /// Count the convex pattern matches in a circuit, per pattern ID.
+    ///
+    /// This is equivalent to grouping the results of
+    /// [`PatternMatcher::find_matches`] by pattern ID, but does not construct
+    /// a [`PatternMatch`] for each match. The matched nodes are only used for
+    /// the convexity check and discarded immediately.
+    pub fn count_matches<C: Circuit + Clone>(&self, circ: &C) -> Vec<usize> {
+        let checker = TopoConvexChecker::new(circ);
+        let mut counts = vec![0; self.patterns.len()];
+        for (root, pattern_id) in self.rooted_candidates(circ) {
+            let Some(map) = self.patterns[pattern_id.0].get_match_map(root, circ) else {
+                continue;
+            };
+            let nodes = map.into_values().collect_vec();
+            if check_convex_with_checker(circ, &nodes, &checker) {
+                counts[pattern_id.0] += 1;
+            }
+        }
+        if let Some(limit) = self.max_matches_per_pattern {
+            counts.iter_mut().for_each(|c| *c = (*c).min(limit));
+        }
+        counts
+    }
+
+    /// Find the convex pattern matches that may have appeared after a local
+    /// change to the circuit.
+    ///
+    /// `changed_nodes` are the nodes of the circuit whose connections changed,
+    /// e.g. the nodes inserted by a rewrite along with the nodes on its
+    /// boundary. Only the commands within the pattern radius of the changed
+    /// nodes are used as roots, where the radius is the maximum number of
+    /// edges in a pattern.
+    ///
+    /// Returns every match containing a changed node. Any previously found
+    /// match containing a changed or removed node should be discarded by the
+    /// caller.
+    pub fn find_matches_near<C: Circuit + Clone>(
+        &self,
+        circ: &C,
+        changed_nodes: &[Node],
+        checker: &impl ConvexChecker,
+    ) -> Vec<PatternMatch> {
+        let (inp, out) = (circ.input(), circ.output());
+        let is_command = |n: &Node| *n != inp && *n != out;
+
+        let touched: HashSet<Node> = changed_nodes.iter().copied().filter(is_command).collect();
+
+        // All the possible roots of a match containing a touched node.
+        let mut roots = touched.iter().copied().collect_vec();
+        let mut seen = touched.clone();
+        let mut frontier = roots.clone();
+        for _ in 0..self.max_pattern_edges() {
+            frontier = frontier
+                .into_iter()
+                .flat_map(|n| circ.all_neighbours(n))
+                .filter(|n| is_command(n) && seen.insert(*n))
+                .collect();
+            roots.extend(frontier.iter().copied());
+        }
+
+        roots
+            .into_iter()
+            .flat_map(|root| self.find_rooted_matches(circ, root, checker))
+            .filter(|m| m.nodes().iter().any(|n| touched.contains(n)))
+            .filter(self.match_limiter())
+            .collect()
+    }
+
+    /// The maximum number of edges in the matcher's patterns.
+    fn max_pattern_edges(&self) -> usize {
+        self.patterns.iter().map(|p| p.n_edges()).max().unwrap_or(0)
+    }
+
     /// Find all convex pattern matches in a circuit rooted at a given node.
     fn find_rooted_matches<C: Circuit + Clone>(
         &self,
@@ -289,6 +693,30 @@ impl PatternMatcher {
         root: Node,
         checker: &impl ConvexChecker,
     ) -> Vec<PatternMatch> {
+        let pattern_ids = self.run_automaton(circ, root);
+        self.matches_from_pattern_ids(circ, root, pattern_ids, checker)
+    }
+
+    /// The patterns recognised by the automaton at every command of the
+    /// circuit, along with the root they were found at.
+    ///
+    /// The convexity of the matches is not checked.
+    fn rooted_candidates<'a>(
+        &'a self,
+        circ: &'a impl Circuit,
+    ) -> impl Iterator<Item = (Node, PatternID)> + 'a {
+        circ.commands().flat_map(move |cmd| {
+            let root = cmd.node();
+            self.run_automaton(circ, root)
+                .into_iter()
+                .map(move |pattern_id| (root, pattern_id))
+        })
+    }
+
+    /// The patterns recognised by the automaton at a given root.
+    ///
+    /// The convexity of the matches is not checked.
+    fn run_automaton(&self, circ: &impl Circuit, root: Node) -> Vec<PatternID> {
         self.automaton
             .run(
                 root.into(),
@@ -297,6 +725,19 @@ impl PatternMatcher {
                 // Check edge exist
                 validate_circuit_edge(circ),
             )
+            .collect()
+    }
+
+    /// Construct the convex matches of the patterns recognised at a root.
+    fn matches_from_pattern_ids<C: Circuit + Clone>(
+        &self,
+        circ: &C,
+        root: Node,
+        pattern_ids: impl IntoIterator<Item = PatternID>,
+        checker: &impl ConvexChecker,
+    ) -> Vec<PatternMatch> {
+        pattern_ids
+            .into_iter()
             .filter_map(|pattern_id| {
                 handle_match_error(
                     PatternMatch::try_from_root_match_with_checker(
@@ -366,6 +807,30 @@ impl PatternMatcher {
     }
 }
 
+/// The overlap graph of a set of matches.
+///
+/// The graph has a node for each match, with index (and weight) equal to the
+/// position of the match in `matches`. Two matches are connected by an edge
+/// if their subcircuits share a circuit node, so the matches in an
+/// independent set of the graph are pairwise disjoint.
+pub fn match_overlap_graph(matches: &[PatternMatch]) -> UnGraph<usize, ()> {
+    let mut graph = UnGraph::with_capacity(matches.len(), 0);
+    let match_nodes = (0..matches.len()).map(|i| graph.add_node(i)).collect_vec();
+
+    let mut node_matches: HashMap<Node, Vec<usize>> = HashMap::new();
+    for (i, m) in matches.iter().enumerate() {
+        for &node in m.nodes() {
+            node_matches.entry(node).or_default().push(i);
+        }
+    }
+    for ms in node_matches.values() {
+        for (&a, &b) in ms.iter().tuple_combinations() {
+            graph.update_edge(match_nodes[a], match_nodes[b], ());
+        }
+    }
+    graph
+}
+
 /// Errors that can occur when constructing matches.
 #[derive(Debug, Clone, PartialEq, Eq, Error)]
 pub enum InvalidPatternMatch {
@@ -419,6 +884,137 @@ impl From<InvalidSubgraph> for InvalidPatternMatch {
     }
 }
 
+/// Cached automaton runs for [`PatternMatcher::find_matches_with_cache`].
+#[derive(Debug, Clone, Default)]
+pub struct MatchCache {
+    /// The patterns recognised at each root.
+    runs: HashMap<Node, Vec<PatternID>>,
+    /// The hash of the operation and links of each node of the circuit.
+    node_hashes: HashMap<Node, u64>,
+}
+
+impl MatchCache {
+    /// Create an empty cache.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of roots with a cached automaton run.
+    pub fn len(&self) -> usize {
+        self.runs.len()
+    }
+
+    /// Returns `true` if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.runs.is_empty()
+    }
+
+    /// Discard all the cached runs.
+    pub fn clear(&mut self) {
+        self.runs.clear();
+        self.node_hashes.clear();
+    }
+}
+
+/// The checks performed by the automaton of a [`PatternMatcher`] from a root.
+///
+/// See [`PatternMatcher::explain_no_match`].
+#[derive(Debug, Clone)]
+pub struct MatchTrace {
+    /// The checks performed, in order.
+    pub checks: Vec<MatchCheck>,
+    /// The patterns recognised at the root.
+    pub patterns: Vec<PatternID>,
+}
+
+impl MatchTrace {
+    /// The first check that failed, if any.
+    ///
+    /// The automaton explores the patterns simultaneously, so a failed check
+    /// only rules out the patterns that required it.
+    pub fn first_failure(&self) -> Option<&MatchCheck> {
+        self.checks.iter().find(|check| !check.passed())
+    }
+}
+
+/// A check performed by the automaton of a [`PatternMatcher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchCheck {
+    /// Check that a circuit node matches an operation of the pattern.
+    Node {
+        /// The circuit node.
+        node: Node,
+        /// Whether the node matched.
+        passed: bool,
+    },
+    /// Check that an edge of the pattern exists in the circuit.
+    Edge {
+        /// The circuit node the edge starts from.
+        node: Node,
+        /// The port of `node` the edge starts from.
+        port: Port,
+        /// The node at the other end of the edge, if the edge exists.
+        target: Option<Node>,
+    },
+}
+
+impl MatchCheck {
+    /// Whether the check succeeded.
+    pub fn passed(&self) -> bool {
+        match self {
+            MatchCheck::Node { passed, .. } => *passed,
+            MatchCheck::Edge { target, .. } => target.is_some(),
+        }
+    }
+}
+
+/// A hash of the operation and links of a node.
+fn node_hash(circ: &impl Circuit, node: Node) -> u64 {
+    let mut hasher = FxHasher64::default();
+    MatchOp::from(circ.get_optype(node).clone()).hash(&mut hasher);
+    for port in circ.all_node_ports(node) {
+        for (linked, linked_port) in circ.linked_ports(node, port) {
+            (port, linked, linked_port).hash(&mut hasher);
+        }
+    }
+    hasher.finish()
+}
+
+/// The nodes within `radius` edges of any of the `sources`.
+fn nodes_within(circ: &impl Circuit, sources: Vec<Node>, radius: usize) -> HashSet<Node> {
+    let mut seen: HashSet<Node> = sources.iter().copied().collect();
+    let mut frontier = sources;
+    for _ in 0..radius {
+        let mut next = Vec::new();
+        for node in frontier {
+            for port in circ.all_node_ports(node) {
+                for (linked, _) in circ.linked_ports(node, port) {
+                    if seen.insert(linked) {
+                        next.push(linked);
+                    }
+                }
+            }
+        }
+        frontier = next;
+    }
+    seen
+}
+
+/// Express the `index`-th pattern of a matcher as a line pattern.
+fn to_line_pattern(
+    index: usize,
+    pattern: &CircuitPattern,
+) -> Result<LinePattern<NodeID, PNode, PEdge>, InvalidPattern> {
+    pattern
+        .pattern
+        .clone()
+        .try_into_line_pattern(compatible_offsets)
+        .map_err(|e| InvalidPattern::NotLinePattern {
+            index,
+            source: Box::new(e.into()),
+        })
+}
+
 fn compatible_offsets(e1: &PEdge, e2: &PEdge) -> bool {
     let PEdge::InternalEdge { dst: dst1, .. } = e1 else {
         return false;
@@ -428,6 +1024,13 @@ fn compatible_offsets(e1: &PEdge, e2: &PEdge) -> bool {
 }
 
 /// Returns a predicate checking that an edge at `src` satisfies `prop` in `circ`.
+///
+/// Classical outputs may be copied to several consumers. Pattern edges are
+/// followed from the consumer to the producer, where the link is unique, so
+/// each consumer of a copied output is matched separately. An edge leaving a
+/// copied output towards consumers that share the same input port index is
+/// ambiguous and is never followed: patterns needing such an edge have no
+/// valid root and are rejected by [`CircuitPattern::try_from_circuit`].
 pub(super) fn validate_circuit_edge(
     circ: &impl Circuit,
 ) -> impl for<'a> Fn(NodeID, &'a PEdge) -> Option<NodeID> + '_ {
@@ -441,8 +1044,15 @@ pub(super) fn validate_circuit_edge(
                 dst: dst_port,
                 ..
             } => {
-                let (next_node, next_port) = circ.linked_ports(src, src_port).exactly_one().ok()?;
-                (dst_port == next_port).then_some(NodeID::HugrNode(next_node))
+                // Classical outputs, such as the BOOL output of a measurement,
+                // may be copied to several consumers. Follow the one linked to
+                // `dst_port`, and fail if it is ambiguous.
+                let (next_node, _) = circ
+                    .linked_ports(src, src_port)
+                    .filter(|&(_, next_port)| next_port == dst_port)
+                    .exactly_one()
+                    .ok()?;
+                Some(NodeID::HugrNode(next_node))
             }
             PEdge::InputEdge { src: src_port } => {
                 let (next_node, next_port) = circ.linked_ports(src, src_port).exactly_one().ok()?;
@@ -456,11 +1066,18 @@ pub(super) fn validate_circuit_edge(
 pub(crate) fn validate_circuit_node(
     circ: &impl Circuit,
 ) -> impl for<'a> Fn(NodeID, &PNode) -> bool + '_ {
-    move |node, prop| {
-        let NodeID::HugrNode(node) = node else {
-            return false;
-        };
-        &MatchOp::from(circ.get_optype(node).clone()) == prop
+    move |node, prop| match (node, prop) {
+        (NodeID::HugrNode(node), PNode::Op(match_op)) => {
+            let op = circ.get_optype(node);
+            // Barriers are ordering fences that no rewrite may cross.
+            !is_barrier(op) && &MatchOp::from(op.clone()) == match_op
+        }
+        // A copy node is an outgoing port whose value is copied to several
+        // consumers.
+        (NodeID::CopyNode(node, port), PNode::Copy) => {
+            port.as_outgoing().is_ok() && circ.linked_ports(node, port).nth(1).is_some()
+        }
+        _ => false,
     }
 }
 
@@ -483,14 +1100,29 @@ fn handle_match_error<T>(match_res: Result<T, InvalidPatternMatch>, root: Node)
 
 #[cfg(test)]
 mod tests {
+    use hugr::ops::{Const, OpType};
+    use hugr::std_extensions::arithmetic::float_types::{ConstF64, FLOAT64_TYPE};
     use hugr::Hugr;
     use itertools::Itertools;
     use rstest::{fixture, rstest};
 
-    use crate::utils::build_simple_circuit;
-    use crate::Tk2Op;
+    use crate::circuit::Circuit;
+    use crate::extension::REGISTRY;
+    use crate::utils::{build_simple_circuit, remove_1q_gate};
+    use crate::{barrier_op, Tk2Op};
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::extension::prelude::{BOOL_T, QB_T};
+    use hugr::hugr::views::sibling_subgraph::TopoConvexChecker;
+    use hugr::type_row;
+    use hugr::types::FunctionType;
+    use hugr::{HugrView, OutgoingPort};
 
-    use super::{CircuitPattern, PatternMatcher};
+    use super::{
+        match_overlap_graph, CircuitPattern, InvalidPattern, MatchCache, MatchCheck, MatchOp,
+        PatternMatcher,
+    };
+    use crate::rewrite::InvalidReplacement;
+    use portmatching::{Pattern, PatternID};
 
     fn h_cx() -> Hugr {
         build_simple_circuit(2, |circ| {
@@ -561,6 +1193,653 @@ mod tests {
         assert_eq!(buf, buf2);
     }
 
+    #[test]
+    fn disconnected_pattern() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0]).unwrap();
+            circ.append(Tk2Op::H, [1]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+        // Bypass the connectivity check of `CircuitPattern::try_from_circuit`.
+        let mut pattern = Pattern::new();
+        for cmd in circ.commands() {
+            pattern.require(cmd.node().into(), cmd.optype().clone().into());
+        }
+        let disconnected = CircuitPattern {
+            pattern,
+            inputs: vec![],
+            outputs: vec![],
+        };
+        let valid = CircuitPattern::try_from_circuit(&h_cx()).unwrap();
+
+        let res = PatternMatcher::try_from_patterns(vec![valid, disconnected]);
+        assert_eq!(
+            res.unwrap_err(),
+            InvalidPattern::NotLinePattern {
+                index: 1,
+                source: Box::new(InvalidPattern::NotConnected)
+            }
+        );
+    }
+
+    #[rstest]
+    fn no_match_across_barrier(cx_cx: Hugr) {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(barrier_op(2), [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+        let p = CircuitPattern::try_from_circuit(&cx_cx).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+
+        assert_eq!(m.find_matches(&cx_cx).len(), 1);
+        assert_eq!(m.find_matches(&circ).len(), 0);
+    }
+
+    #[rstest]
+    fn matches_near_local_edit(cx_cx: Hugr) {
+        let p = CircuitPattern::try_from_circuit(&cx_cx).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+
+        let mut circ = build_simple_circuit(3, |circ| {
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::H, [1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [1, 2]).unwrap();
+            circ.append(Tk2Op::CX, [1, 2]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(m.find_matches(&circ).len(), 1);
+
+        // Remove the H gate, creating a new match.
+        let h = circ
+            .commands()
+            .find(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(Tk2Op::H))
+            .unwrap()
+            .node();
+        let neighbours = circ.all_neighbours(h).collect_vec();
+        remove_1q_gate(&mut circ, h);
+
+        let checker = TopoConvexChecker::new(&circ);
+        let near = m.find_matches_near(&circ, &neighbours, &checker);
+        let full = m.find_matches(&circ);
+        assert_eq!(full.len(), 2);
+        assert_eq!(near.len(), 1);
+        let new_match = full
+            .iter()
+            .find(|fm| fm.nodes().iter().any(|n| neighbours.contains(n)))
+            .unwrap();
+        assert_eq!(near[0].root(), new_match.root());
+        assert_eq!(near[0].pattern_id(), new_match.pattern_id());
+    }
+
+    #[test]
+    fn empty_circuit_matches() {
+        let p = CircuitPattern::try_from_circuit(&h_cx()).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+        let empty = build_simple_circuit(2, |_| Ok(())).unwrap();
+
+        assert!(m.find_matches(&empty).is_empty());
+        assert!(m.find_par_matches(&empty).is_empty());
+        assert_eq!(m.count_matches(&empty), [0]);
+    }
+
+    #[test]
+    fn single_command_matches() {
+        let single = |op: Tk2Op| {
+            build_simple_circuit(1, |circ| {
+                circ.append(op, [0]).unwrap();
+                Ok(())
+            })
+            .unwrap()
+        };
+        let h = single(Tk2Op::H);
+        let p = CircuitPattern::try_from_circuit(&h).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+
+        let matches = m.find_matches(&h);
+        assert_eq!(matches.len(), 1);
+        let h_node = h.commands().next().unwrap().node();
+        assert_eq!(matches[0].nodes(), [h_node]);
+        assert_eq!(matches[0].root(), h_node);
+
+        assert!(m.find_matches(&single(Tk2Op::X)).is_empty());
+    }
+
+    #[test]
+    fn cx_ladder_par_matches() {
+        let circ = build_simple_circuit(3, |circ| {
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::H, [0]).unwrap();
+            circ.append(Tk2Op::CX, [2, 1]).unwrap();
+            circ.append(Tk2Op::CX, [1, 2]).unwrap();
+            circ.append(Tk2Op::H, [1]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+        let patterns = [h_cx(), cx_cx_3()]
+            .iter()
+            .map(|p| CircuitPattern::try_from_circuit(p).unwrap())
+            .collect_vec();
+        let m = PatternMatcher::from_patterns(patterns);
+
+        let key = |pm: &super::PatternMatch| (pm.root(), pm.pattern_id().0);
+        let seq = m.find_matches(&circ).iter().map(key).sorted().collect_vec();
+        let par = m
+            .find_par_matches(&circ)
+            .iter()
+            .map(key)
+            .sorted()
+            .collect_vec();
+
+        assert_eq!(seq.len(), 3);
+        assert_eq!(par, seq);
+    }
+
+    #[test]
+    fn parallel_construction() {
+        let patterns = [h_cx(), cx_xc(), cx_cx(), cx_cx_3()]
+            .iter()
+            .map(|p| CircuitPattern::try_from_circuit(p).unwrap())
+            .collect_vec();
+        let seq = PatternMatcher::from_patterns(patterns.clone());
+        let par = PatternMatcher::from_patterns_parallel(patterns);
+        let circ = build_simple_circuit(3, |circ| {
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [1, 0]).unwrap();
+            circ.append(Tk2Op::H, [0]).unwrap();
+            circ.append(Tk2Op::CX, [2, 1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let key = |pm: &super::PatternMatch| (pm.root(), pm.pattern_id().0);
+        let seq_matches = seq
+            .find_matches(&circ)
+            .iter()
+            .map(key)
+            .sorted()
+            .collect_vec();
+        let par_matches = par
+            .find_matches(&circ)
+            .iter()
+            .map(key)
+            .sorted()
+            .collect_vec();
+        assert!(!seq_matches.is_empty());
+        assert_eq!(par_matches, seq_matches);
+        assert_eq!(par.n_patterns(), seq.n_patterns());
+    }
+
+    #[test]
+    fn add_pattern() {
+        let patterns = [h_cx(), cx_xc(), cx_cx()]
+            .iter()
+            .map(|p| CircuitPattern::try_from_circuit(p).unwrap())
+            .collect_vec();
+        let full = PatternMatcher::from_patterns(patterns.clone());
+        let mut incremental = PatternMatcher::from_patterns(patterns[..1].to_vec());
+        for (index, p) in patterns.into_iter().enumerate().skip(1) {
+            assert_eq!(incremental.add_pattern(p), PatternID(index));
+        }
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::H, [0]).unwrap();
+            circ.append(Tk2Op::CX, [1, 0]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let key = |pm: &super::PatternMatch| (pm.root(), pm.pattern_id().0);
+        let sorted_matches =
+            |m: &PatternMatcher| m.find_matches(&circ).iter().map(key).sorted().collect_vec();
+        assert_eq!(incremental.n_patterns(), full.n_patterns());
+        assert!(!sorted_matches(&full).is_empty());
+        assert_eq!(sorted_matches(&incremental), sorted_matches(&full));
+    }
+
+    #[test]
+    fn cached_matches() {
+        let patterns = [h_cx(), cx_xc(), cx_cx()]
+            .iter()
+            .map(|p| CircuitPattern::try_from_circuit(p).unwrap())
+            .collect_vec();
+        let m = PatternMatcher::from_patterns(patterns);
+        let mut circ = build_simple_circuit(3, |circ| {
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::H, [0]).unwrap();
+            circ.append(Tk2Op::CX, [1, 0]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::H, [2]).unwrap();
+            circ.append(Tk2Op::H, [2]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let key = |pm: &super::PatternMatch| (pm.root(), pm.pattern_id().0);
+        let sorted = |ms: Vec<super::PatternMatch>| ms.iter().map(key).sorted().collect_vec();
+        let mut cache = MatchCache::new();
+        let cached = sorted(m.find_matches_with_cache(&circ, &mut cache));
+        assert!(!cached.is_empty());
+        assert_eq!(cached, sorted(m.find_matches(&circ)));
+        assert_eq!(cache.len(), circ.num_gates());
+
+        // Edit the circuit and match again with the same cache.
+        let h = circ
+            .commands()
+            .find(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(Tk2Op::H))
+            .unwrap()
+            .node();
+        remove_1q_gate(&mut circ, h);
+        let cached = sorted(m.find_matches_with_cache(&circ, &mut cache));
+        assert_eq!(cached, sorted(m.find_matches(&circ)));
+        assert_eq!(cache.len(), circ.num_gates());
+    }
+
+    #[rstest]
+    fn match_qubits(cx_cx: Hugr) {
+        let p = CircuitPattern::try_from_circuit(&cx_cx).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+        let circ = build_simple_circuit(3, |circ| {
+            circ.append(Tk2Op::H, [1]).unwrap();
+            circ.append(Tk2Op::CX, [2, 0]).unwrap();
+            circ.append(Tk2Op::CX, [2, 0]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let pm = m
+            .find_matches(&circ)
+            .into_iter()
+            .exactly_one()
+            .ok()
+            .unwrap();
+        assert_eq!(pm.qubits(&circ), [0, 2]);
+    }
+
+    #[rstest]
+    fn match_boundary(cx_cx_3: Hugr) {
+        let p = CircuitPattern::try_from_circuit(&cx_cx_3).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+        let circ = build_simple_circuit(3, |circ| {
+            circ.append(Tk2Op::H, [0]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [2, 1]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let pm = m
+            .find_matches(&circ)
+            .into_iter()
+            .exactly_one()
+            .ok()
+            .unwrap();
+        let signature = cx_cx_3.circuit_signature();
+        assert_eq!(pm.incoming_boundary().len(), signature.input_count());
+        assert_eq!(pm.outgoing_boundary().len(), signature.output_count());
+        assert!(pm
+            .incoming_boundary()
+            .iter()
+            .flatten()
+            .all(|(n, _)| pm.nodes().contains(n)));
+    }
+
+    #[test]
+    fn match_measure() {
+        let mut h =
+            DFGBuilder::new(FunctionType::new(type_row![QB_T], type_row![QB_T, BOOL_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let [q] = h.add_dataflow_op(Tk2Op::H, [q]).unwrap().outputs_arr();
+        let measure = h.add_dataflow_op(Tk2Op::Measure, [q]).unwrap();
+        let pattern = h
+            .finish_hugr_with_outputs(measure.outputs(), &REGISTRY)
+            .unwrap();
+        let p = CircuitPattern::try_from_circuit(&pattern).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+
+        // The measurement result is copied to two outputs.
+        let mut h = DFGBuilder::new(FunctionType::new(
+            type_row![QB_T, QB_T],
+            type_row![QB_T, QB_T, BOOL_T, BOOL_T],
+        ))
+        .unwrap();
+        let [q0, q1] = h.input_wires_arr();
+        let [q1] = h.add_dataflow_op(Tk2Op::X, [q1]).unwrap().outputs_arr();
+        let [q0] = h.add_dataflow_op(Tk2Op::H, [q0]).unwrap().outputs_arr();
+        let measure = h.add_dataflow_op(Tk2Op::Measure, [q0]).unwrap();
+        let [q0, bit] = measure.outputs_arr();
+        let [q0, q1] = h
+            .add_dataflow_op(Tk2Op::CX, [q0, q1])
+            .unwrap()
+            .outputs_arr();
+        let circ = h
+            .finish_hugr_with_outputs([q0, q1, bit, bit], &REGISTRY)
+            .unwrap();
+
+        let pm = m
+            .find_matches(&circ)
+            .into_iter()
+            .exactly_one()
+            .ok()
+            .unwrap();
+        assert!(pm.nodes().contains(&measure.node()));
+        assert!(pm
+            .outgoing_boundary()
+            .contains(&(measure.node(), OutgoingPort::from(1))));
+    }
+
+    #[test]
+    fn match_measure_consumer() {
+        let bool_noop = || hugr::ops::LeafOp::Noop { ty: BOOL_T };
+
+        // A measurement whose result is consumed inside the pattern.
+        let mut h =
+            DFGBuilder::new(FunctionType::new(type_row![QB_T], type_row![QB_T, BOOL_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let [q, bit] = h
+            .add_dataflow_op(Tk2Op::Measure, [q])
+            .unwrap()
+            .outputs_arr();
+        let [bit] = h.add_dataflow_op(bool_noop(), [bit]).unwrap().outputs_arr();
+        let pattern = h.finish_hugr_with_outputs([q, bit], &REGISTRY).unwrap();
+        let p = CircuitPattern::try_from_circuit(&pattern).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+
+        // The measurement result is copied to the consumer and to an output.
+        let mut h = DFGBuilder::new(FunctionType::new(
+            type_row![QB_T],
+            type_row![QB_T, BOOL_T, BOOL_T],
+        ))
+        .unwrap();
+        let [q] = h.input_wires_arr();
+        let measure = h.add_dataflow_op(Tk2Op::Measure, [q]).unwrap();
+        let [q, bit] = measure.outputs_arr();
+        let noop = h.add_dataflow_op(bool_noop(), [bit]).unwrap();
+        let [noop_bit] = noop.outputs_arr();
+        let circ = h
+            .finish_hugr_with_outputs([q, bit, noop_bit], &REGISTRY)
+            .unwrap();
+
+        let pm = m
+            .find_matches(&circ)
+            .into_iter()
+            .exactly_one()
+            .ok()
+            .unwrap();
+        assert_eq!(
+            pm.nodes().iter().copied().sorted().collect_vec(),
+            [measure.node(), noop.node()]
+                .into_iter()
+                .sorted()
+                .collect_vec()
+        );
+        // The copy of the result leaving the match is on the boundary.
+        assert!(pm
+            .outgoing_boundary()
+            .contains(&(measure.node(), OutgoingPort::from(1))));
+
+        // The consumer reads the result of another measurement.
+        let mut h = DFGBuilder::new(FunctionType::new(
+            type_row![QB_T, QB_T],
+            type_row![QB_T, QB_T, BOOL_T, BOOL_T],
+        ))
+        .unwrap();
+        let [q0, q1] = h.input_wires_arr();
+        let [q0, bit0] = h
+            .add_dataflow_op(Tk2Op::Measure, [q0])
+            .unwrap()
+            .outputs_arr();
+        let [q1, bit1] = h
+            .add_dataflow_op(Tk2Op::Measure, [q1])
+            .unwrap()
+            .outputs_arr();
+        let [bit1] = h
+            .add_dataflow_op(bool_noop(), [bit1])
+            .unwrap()
+            .outputs_arr();
+        let circ = h
+            .finish_hugr_with_outputs([q0, q1, bit0, bit1], &REGISTRY)
+            .unwrap();
+        assert_eq!(m.find_matches(&circ).len(), 1);
+    }
+
+    #[test]
+    fn match_measure_fan_out() {
+        let bool_noop = || hugr::ops::LeafOp::Noop { ty: BOOL_T };
+
+        // A measurement whose result is copied to two consumers, both reading
+        // it at input port 0.
+        let mut h = DFGBuilder::new(FunctionType::new(
+            type_row![QB_T],
+            type_row![QB_T, BOOL_T, BOOL_T],
+        ))
+        .unwrap();
+        let [q] = h.input_wires_arr();
+        let measure = h.add_dataflow_op(Tk2Op::Measure, [q]).unwrap();
+        let [q, bit] = measure.outputs_arr();
+        let noop0 = h.add_dataflow_op(bool_noop(), [bit]).unwrap();
+        let noop1 = h.add_dataflow_op(bool_noop(), [bit]).unwrap();
+        let fan_out = h
+            .finish_hugr_with_outputs([q, noop0.out_wire(0), noop1.out_wire(0)], &REGISTRY)
+            .unwrap();
+
+        // Telling the two consumers apart would require following the edge
+        // out of the copied result, which is ambiguous.
+        assert_eq!(
+            CircuitPattern::try_from_circuit(&fan_out).err(),
+            Some(InvalidPattern::NotConnected)
+        );
+
+        // A pattern with a single consumer matches each of them separately.
+        let mut h =
+            DFGBuilder::new(FunctionType::new(type_row![QB_T], type_row![QB_T, BOOL_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let [q, bit] = h
+            .add_dataflow_op(Tk2Op::Measure, [q])
+            .unwrap()
+            .outputs_arr();
+        let [bit] = h.add_dataflow_op(bool_noop(), [bit]).unwrap().outputs_arr();
+        let pattern = h.finish_hugr_with_outputs([q, bit], &REGISTRY).unwrap();
+        let p = CircuitPattern::try_from_circuit(&pattern).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+
+        let matched = m
+            .find_matches(&fan_out)
+            .into_iter()
+            .map(|pm| pm.nodes().iter().copied().sorted().collect_vec())
+            .sorted()
+            .collect_vec();
+        let expected = [noop0.node(), noop1.node()]
+            .into_iter()
+            .map(|n| [measure.node(), n].into_iter().sorted().collect_vec())
+            .sorted()
+            .collect_vec();
+        assert_eq!(matched, expected);
+    }
+
+    #[rstest]
+    fn explain_no_match(cx_cx: Hugr) {
+        let p = CircuitPattern::try_from_circuit(&cx_cx).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+
+        let roots = |circ: &Hugr| circ.commands().map(|cmd| cmd.node()).collect_vec();
+        assert!(roots(&cx_cx)
+            .into_iter()
+            .any(|root| !m.explain_no_match(&cx_cx, root).patterns.is_empty()));
+
+        // The second CX has its qubits swapped.
+        let near_miss = cx_xc();
+        let traces = roots(&near_miss)
+            .into_iter()
+            .map(|root| m.explain_no_match(&near_miss, root))
+            .collect_vec();
+        assert!(traces.iter().all(|trace| trace.patterns.is_empty()));
+        assert!(traces.iter().any(|trace| matches!(
+            trace.first_failure(),
+            Some(MatchCheck::Edge { target: None, .. })
+        )));
+    }
+
+    #[rstest]
+    fn contains_any(cx_cx: Hugr) {
+        let p = CircuitPattern::try_from_circuit(&cx_xc()).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+
+        let found = m.contains_any(&cx_xc()).unwrap();
+        assert_eq!(found.pattern_id().0, 0);
+        assert!(m.contains_any(&cx_cx).is_none());
+    }
+
+    #[rstest]
+    fn matches_by_root(cx_cx: Hugr) {
+        let patterns = [cx_cx.clone(), cx_cx.clone()]
+            .iter()
+            .map(|p| CircuitPattern::try_from_circuit(p).unwrap())
+            .collect_vec();
+        let m = PatternMatcher::from_patterns(patterns);
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let groups = m.find_matches_by_root(&circ);
+        assert_eq!(groups.len(), 1);
+        let (&root, matches) = groups.iter().exactly_one().unwrap();
+        assert_eq!(matches.len(), 2);
+        assert!(matches.iter().all(|pm| pm.root() == root));
+        let ids = matches
+            .iter()
+            .map(|pm| pm.pattern_id().0)
+            .sorted()
+            .collect_vec();
+        assert_eq!(ids, [0, 1]);
+    }
+
+    #[test]
+    fn max_matches_per_pattern() {
+        let patterns = [h_cx(), cx_cx()]
+            .iter()
+            .map(|p| CircuitPattern::try_from_circuit(p).unwrap())
+            .collect_vec();
+        let m = PatternMatcher::from_patterns(patterns).with_max_matches_per_pattern(3);
+        let circ = build_simple_circuit(2, |circ| {
+            for _ in 0..8 {
+                circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            }
+            circ.append(Tk2Op::H, [0]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let counts = m
+            .find_matches(&circ)
+            .into_iter()
+            .counts_by(|pm| pm.pattern_id().0);
+        assert_eq!(counts[&0], 1);
+        assert_eq!(counts[&1], 3);
+        assert_eq!(m.count_matches(&circ), [1, 3]);
+        let par_counts = m
+            .find_par_matches(&circ)
+            .into_iter()
+            .counts_by(|pm| pm.pattern_id().0);
+        assert_eq!(par_counts, counts);
+
+        // The limit is kept through serialisation.
+        let mut buf = Vec::new();
+        m.save_binary_io(&mut buf).unwrap();
+        let loaded = PatternMatcher::load_binary_io(&mut buf.as_slice()).unwrap();
+        assert_eq!(loaded.count_matches(&circ), [1, 3]);
+    }
+
+    #[test]
+    fn count_matches() {
+        let patterns = [h_cx(), cx_xc(), cx_cx()]
+            .iter()
+            .map(|p| CircuitPattern::try_from_circuit(p).unwrap())
+            .collect_vec();
+        let m = PatternMatcher::from_patterns(patterns);
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::H, [0]).unwrap();
+            circ.append(Tk2Op::CX, [1, 0]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+
+        let counts = m.count_matches(&circ);
+        let expected = m
+            .find_matches(&circ)
+            .into_iter()
+            .counts_by(|pm| pm.pattern_id().0);
+        assert_eq!(counts.len(), 3);
+        for (id, &count) in counts.iter().enumerate() {
+            assert_eq!(count, expected.get(&id).copied().unwrap_or(0));
+        }
+        assert!(counts.iter().sum::<usize>() > 0);
+    }
+
+    #[rstest]
+    fn overlap_graph(cx_cx: Hugr) {
+        let p = CircuitPattern::try_from_circuit(&cx_cx).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::H, [0]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            circ.append(Tk2Op::CX, [0, 1]).unwrap();
+            Ok(())
+        })
+        .unwrap();
+        let cxs = circ.commands().map(|cmd| cmd.node()).collect_vec();
+        let matches = m
+            .find_matches(&circ)
+            .into_iter()
+            .sorted_by_key(|pm| cxs.iter().position(|&n| n == pm.root()))
+            .collect_vec();
+        assert_eq!(matches.len(), 3);
+
+        let graph = match_overlap_graph(&matches);
+        let edge = |a: usize, b: usize| graph.find_edge(a.into(), b.into()).is_some();
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.edge_count(), 1);
+        // The first two matches share the second CX.
+        assert!(edge(0, 1));
+        assert!(!edge(0, 2));
+        assert!(!edge(1, 2));
+    }
+
+    #[rstest]
+    fn boundary_mismatch(cx_cx: Hugr, cx_cx_3: Hugr) {
+        let p = CircuitPattern::try_from_circuit(&cx_cx_3).unwrap();
+        let m = PatternMatcher::from_patterns(vec![p]);
+        let matches = m.find_matches(&cx_cx_3);
+        assert_eq!(matches.len(), 1);
+
+        let err = matches[0].to_rewrite(&cx_cx_3, cx_cx).unwrap_err();
+        let InvalidReplacement::BoundaryMismatch { expected, found } = err else {
+            panic!("Expected a boundary mismatch, got {err:?}.");
+        };
+        assert_eq!(expected.input.len(), 3);
+        assert_eq!(found.input.len(), 2);
+    }
+
     #[rstest]
     fn cx_cx_replace_to_id(cx_cx: Hugr, cx_cx_3: Hugr) {
         let p = CircuitPattern::try_from_circuit(&cx_cx_3).unwrap();
@@ -569,4 +1848,92 @@ mod tests {
         let matches = m.find_matches(&cx_cx);
         assert_eq!(matches.len(), 0);
     }
+
+    /// A circuit applying two Rz rotations by the same angle input.
+    fn rz_fan_out(shared_angle: bool) -> Hugr {
+        let mut h = DFGBuilder::new(FunctionType::new(
+            vec![QB_T, FLOAT64_TYPE, FLOAT64_TYPE],
+            vec![QB_T],
+        ))
+        .unwrap();
+        let [q, a, b] = h.input_wires_arr();
+        let b = if shared_angle { a } else { b };
+        let q = h.add_dataflow_op(Tk2Op::H, [q]).unwrap().out_wire(0);
+        let q = h.add_dataflow_op(Tk2Op::RzF64, [q, a]).unwrap().out_wire(0);
+        let q = h.add_dataflow_op(Tk2Op::RzF64, [q, b]).unwrap().out_wire(0);
+        h.finish_hugr_with_outputs([q], &REGISTRY).unwrap()
+    }
+
+    #[test]
+    fn fan_out_match() {
+        let mut p =
+            DFGBuilder::new(FunctionType::new(vec![QB_T, FLOAT64_TYPE], vec![QB_T])).unwrap();
+        let [q, a] = p.input_wires_arr();
+        let q = p.add_dataflow_op(Tk2Op::RzF64, [q, a]).unwrap().out_wire(0);
+        let q = p.add_dataflow_op(Tk2Op::RzF64, [q, a]).unwrap().out_wire(0);
+        let p = p.finish_hugr_with_outputs([q], &REGISTRY).unwrap();
+
+        let m = PatternMatcher::from_patterns(vec![CircuitPattern::try_from_circuit(&p).unwrap()]);
+
+        assert_eq!(m.find_matches(&p).len(), 1);
+        assert_eq!(m.find_matches(&rz_fan_out(true)).len(), 1);
+        assert_eq!(m.find_matches(&rz_fan_out(false)).len(), 0);
+    }
+
+    fn float_const(value: f64) -> MatchOp {
+        let op: OpType = Const::new(ConstF64::new(value).into(), FLOAT64_TYPE)
+            .unwrap()
+            .into();
+        op.into()
+    }
+
+    #[rstest]
+    #[case(0.5, 0.5, true)]
+    #[case(0.5, 0.25, false)]
+    #[case(f64::NAN, -f64::NAN, true)]
+    #[case(0.0, -0.0, true)]
+    fn match_float_consts(#[case] a: f64, #[case] b: f64, #[case] expected: bool) {
+        assert_eq!(float_const(a) == float_const(b), expected);
+    }
+
+    /// A single-qubit circuit applying an Rz rotation for each angle.
+    fn rz_chain(angles: &[f64]) -> Hugr {
+        let mut h = DFGBuilder::new(FunctionType::new(type_row![QB_T], type_row![QB_T])).unwrap();
+        let [mut q] = h.input_wires_arr();
+        for &angle in angles {
+            let a = h.add_load_const(ConstF64::new(angle)).unwrap();
+            q = h.add_dataflow_op(Tk2Op::RzF64, [q, a]).unwrap().out_wire(0);
+        }
+        h.finish_hugr_with_outputs([q], &REGISTRY).unwrap()
+    }
+
+    #[test]
+    fn match_float_const_in_circuit() {
+        let pattern_circ = rz_chain(&[0.5]);
+        let const_cmd = pattern_circ
+            .commands()
+            .find(|cmd| matches!(cmd.optype(), OpType::Const(_)))
+            .unwrap();
+        // A pattern made of the constant alone.
+        let mut pattern = Pattern::new();
+        pattern.require(const_cmd.node().into(), const_cmd.optype().clone().into());
+        pattern.set_any_root().unwrap();
+        let pattern = CircuitPattern {
+            pattern,
+            inputs: vec![],
+            outputs: vec![],
+        };
+        let matcher = PatternMatcher::from_patterns(vec![pattern]);
+
+        let circ = rz_chain(&[0.25, 0.5, -0.5]);
+        let roots = matcher
+            .rooted_candidates(&circ)
+            .map(|(root, _)| root)
+            .collect_vec();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(
+            MatchOp::from(circ.get_optype(roots[0]).clone()),
+            float_const(0.5)
+        );
+    }
 }
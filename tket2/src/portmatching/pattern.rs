@@ -1,17 +1,23 @@
 //! Circuit Patterns for pattern matching
 
+use hugr::hugr::views::sibling_subgraph::InvalidSubgraph;
+use hugr::hugr::views::SiblingSubgraph;
 use hugr::IncomingPort;
-use hugr::{Node, Port};
+use hugr::{Direction, Node, Port};
 use itertools::Itertools;
 use portmatching::{patterns::NoRootFound, HashMap, Pattern, SinglePatternMatcher};
+use smol_str::SmolStr;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
+use std::str::FromStr;
 use thiserror::Error;
 
 use super::{
     matcher::{validate_circuit_edge, validate_circuit_node},
     PEdge, PNode,
 };
-use crate::{circuit::Circuit, portmatching::NodeID};
+use crate::extension::TKET2_EXTENSION_ID;
+use crate::{circuit::Circuit, portmatching::NodeID, Tk2Op};
 
 /// A pattern that match a circuit exactly
 #[derive(Clone, serde::Serialize, serde::Deserialize)]
@@ -29,12 +35,97 @@ impl CircuitPattern {
         self.pattern.n_edges()
     }
 
+    /// The number of gates in the pattern.
+    pub fn n_gates(&self) -> usize {
+        self.gates().len()
+    }
+
+    /// The number of qubits the pattern acts on.
+    ///
+    /// Only the qubits of [`Tk2Op`] gates are counted.
+    pub fn n_qubits(&self) -> usize {
+        let prefix = format!("{TKET2_EXTENSION_ID}.");
+        let gate_qubits: usize = self
+            .gates()
+            .iter()
+            .filter_map(|op| op.strip_prefix(&prefix))
+            .filter_map(|name| Tk2Op::from_str(name).ok())
+            .map(|op| op.n_qubits())
+            .sum();
+        let linear_edges = self
+            .pattern
+            .edges()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|e| {
+                matches!(
+                    e.edge_prop,
+                    PEdge::InternalEdge {
+                        is_reversible: true,
+                        ..
+                    }
+                )
+            })
+            .count();
+        gate_qubits.saturating_sub(linear_edges)
+    }
+
+    /// The names of the operations in the pattern, in topological order.
+    pub fn gates(&self) -> Vec<SmolStr> {
+        let edges = self.pattern.edges().unwrap_or_default();
+        let mut ops: BTreeMap<Node, SmolStr> = BTreeMap::new();
+        let mut predecessors: BTreeMap<Node, BTreeSet<Node>> = BTreeMap::new();
+        for e in &edges {
+            for (node, prop) in [(e.source, &e.source_prop), (e.target, &e.target_prop)] {
+                if let (Some(NodeID::HugrNode(node)), Some(PNode::Op(op))) = (node, prop) {
+                    ops.insert(node, op.name().clone());
+                }
+            }
+            if let (
+                Some(NodeID::HugrNode(source)),
+                Some(NodeID::HugrNode(target)),
+                PEdge::InternalEdge { src, .. },
+            ) = (e.source, e.target, e.edge_prop)
+            {
+                // Edges point from the port at `src` to the linked port.
+                let (pred, succ) = match src.direction() {
+                    Direction::Incoming => (target, source),
+                    Direction::Outgoing => (source, target),
+                };
+                predecessors.entry(succ).or_default().insert(pred);
+            }
+        }
+
+        // Kahn's algorithm, visiting the nodes in index order for determinism.
+        let mut gates = Vec::with_capacity(ops.len());
+        while !ops.is_empty() {
+            let ready = ops
+                .keys()
+                .copied()
+                .filter(|n| predecessors.get(n).map_or(true, |preds| preds.is_empty()))
+                .collect_vec();
+            if ready.is_empty() {
+                // Patterns are built from the DAG of a circuit's commands, so
+                // their edges cannot form a cycle.
+                unreachable!("Pattern contains a cycle");
+            }
+            for node in ready {
+                gates.push(ops.remove(&node).unwrap());
+                predecessors.values_mut().for_each(|preds| {
+                    preds.remove(&node);
+                });
+            }
+        }
+        gates
+    }
+
     /// Construct a pattern from a circuit.
     pub fn try_from_circuit(circuit: &impl Circuit) -> Result<Self, InvalidPattern> {
         if circuit.num_gates() == 0 {
             return Err(InvalidPattern::EmptyCircuit);
         }
         let mut pattern = Pattern::new();
+        let mut copies = Vec::new();
         for cmd in circuit.commands() {
             let op = cmd.optype().clone();
             pattern.require(cmd.node().into(), op.into());
@@ -49,11 +140,16 @@ impl CircuitPattern {
                     .expect("invalid HUGR");
                 let prev_node = match edge_prop {
                     PEdge::InternalEdge { .. } => NodeID::HugrNode(prev_node),
-                    PEdge::InputEdge { .. } => NodeID::new_copy(prev_node, prev_port),
+                    PEdge::InputEdge { .. } => {
+                        let copy = NodeID::new_copy(prev_node, prev_port);
+                        copies.push(copy);
+                        copy
+                    }
                 };
                 pattern.add_edge(cmd.node().into(), prev_node, edge_prop);
             }
         }
+        require_fan_out_copies(&mut pattern, copies);
         pattern.set_any_root()?;
         if !pattern.is_valid() {
             return Err(InvalidPattern::NotConnected);
@@ -86,6 +182,75 @@ impl CircuitPattern {
         })
     }
 
+    /// Construct a pattern from a subset of the nodes of a circuit.
+    ///
+    /// The nodes must form a convex and connected region of the circuit.
+    /// Edges coming from outside the region become inputs of the pattern, and
+    /// edges leaving it become outputs.
+    pub fn try_from_subcircuit(
+        circuit: &impl Circuit,
+        nodes: &[Node],
+    ) -> Result<Self, InvalidPattern> {
+        if nodes.is_empty() {
+            return Err(InvalidPattern::EmptyCircuit);
+        }
+        if nodes.contains(&circuit.input()) || nodes.contains(&circuit.output()) {
+            return Err(InvalidPattern::InvalidSubcircuit);
+        }
+        let subgraph = SiblingSubgraph::try_from_nodes(nodes.to_vec(), circuit)?;
+
+        let mut pattern = Pattern::new();
+        let mut copies = Vec::new();
+        for cmd in circuit.commands() {
+            let node = cmd.node();
+            if !nodes.contains(&node) {
+                continue;
+            }
+            pattern.require(node.into(), cmd.optype().clone().into());
+            for in_offset in 0..cmd.input_count() {
+                let in_offset: IncomingPort = in_offset.into();
+                let (prev_node, prev_port) = circuit
+                    .linked_outputs(node, in_offset)
+                    .exactly_one()
+                    .ok()
+                    .expect("invalid HUGR");
+                let (prev_node, edge_prop) = if nodes.contains(&prev_node) {
+                    let edge_prop = PEdge::try_from_port(node, in_offset.into(), circuit)
+                        .expect("Invalid HUGR");
+                    (NodeID::HugrNode(prev_node), edge_prop)
+                } else {
+                    let edge_prop = PEdge::InputEdge {
+                        src: in_offset.into(),
+                    };
+                    let copy = NodeID::new_copy(prev_node, prev_port);
+                    copies.push(copy);
+                    (copy, edge_prop)
+                };
+                pattern.add_edge(node.into(), prev_node, edge_prop);
+            }
+        }
+        require_fan_out_copies(&mut pattern, copies);
+        pattern.set_any_root()?;
+        if !pattern.is_valid() {
+            return Err(InvalidPattern::NotConnected);
+        }
+        let inputs = subgraph
+            .incoming_ports()
+            .iter()
+            .map(|ps| ps.iter().map(|&(n, p)| (n, p.into())).collect())
+            .collect();
+        let outputs = subgraph
+            .outgoing_ports()
+            .iter()
+            .map(|&(n, p)| (n, p.into()))
+            .collect();
+        Ok(Self {
+            pattern,
+            inputs,
+            outputs,
+        })
+    }
+
     /// Compute the map from pattern nodes to circuit nodes in `circ`.
     pub fn get_match_map(&self, root: Node, circ: &impl Circuit) -> Option<HashMap<Node, Node>> {
         let single_matcher = SinglePatternMatcher::from_pattern(self.pattern.clone());
@@ -109,6 +274,19 @@ impl CircuitPattern {
     }
 }
 
+/// Mark the copy nodes reached by several input edges of a pattern.
+///
+/// Such a copy node stands for a value that fans out to several nodes of the
+/// pattern, and must be matched to a port with multiple consumers.
+fn require_fan_out_copies(
+    pattern: &mut Pattern<NodeID, PNode, PEdge>,
+    copies: impl IntoIterator<Item = NodeID>,
+) {
+    for copy in copies.into_iter().duplicates() {
+        pattern.require(copy, PNode::Copy);
+    }
+}
+
 impl Debug for CircuitPattern {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         self.pattern.fmt(f)?;
@@ -125,6 +303,21 @@ pub enum InvalidPattern {
     /// Patterns must be connected circuits.
     #[error("pattern is not connected")]
     NotConnected,
+    /// The nodes of a subcircuit pattern do not form a convex region.
+    #[error("pattern is not convex")]
+    NotConvex,
+    /// The nodes of a subcircuit pattern are not a valid circuit region.
+    #[error("pattern nodes are not a valid subcircuit")]
+    InvalidSubcircuit,
+    /// A pattern passed to a matcher cannot be expressed as a line pattern.
+    #[error("pattern {index} cannot be expressed as a line pattern")]
+    NotLinePattern {
+        /// The index of the offending pattern.
+        index: usize,
+        /// The reason the pattern could not be converted.
+        #[source]
+        source: Box<InvalidPattern>,
+    },
 }
 
 impl From<NoRootFound> for InvalidPattern {
@@ -133,6 +326,16 @@ impl From<NoRootFound> for InvalidPattern {
     }
 }
 
+impl From<InvalidSubgraph> for InvalidPattern {
+    fn from(value: InvalidSubgraph) -> Self {
+        match value {
+            InvalidSubgraph::NotConvex => InvalidPattern::NotConvex,
+            InvalidSubgraph::EmptySubgraph => InvalidPattern::EmptyCircuit,
+            _ => InvalidPattern::InvalidSubcircuit,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -146,6 +349,7 @@ mod tests {
     use hugr::Hugr;
 
     use crate::extension::REGISTRY;
+    use crate::portmatching::PatternMatcher;
     use crate::utils::build_simple_circuit;
     use crate::Tk2Op;
 
@@ -225,6 +429,18 @@ mod tests {
         )
     }
 
+    #[test]
+    fn pattern_gates() {
+        let p = CircuitPattern::try_from_circuit(&h_cx()).unwrap();
+
+        assert_eq!(
+            p.gates(),
+            [Tk2Op::CX.exposed_name(), Tk2Op::H.exposed_name()]
+        );
+        assert_eq!(p.n_gates(), 2);
+        assert_eq!(p.n_qubits(), 2);
+    }
+
     #[test]
     fn disconnected_pattern() {
         let circ = build_simple_circuit(2, |circ| {
@@ -252,6 +468,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn pattern_from_subcircuit() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::T, [1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::H, [1])?;
+            Ok(())
+        })
+        .unwrap();
+        let cxs = get_nodes_by_tk2op(&circ, Tk2Op::CX);
+        let (first_cx, second_cx) = circ
+            .commands()
+            .map(|cmd| cmd.node())
+            .filter(|n| cxs.contains(n))
+            .collect_tuple()
+            .unwrap();
+        let t = get_nodes_by_tk2op(&circ, Tk2Op::T)[0];
+
+        let pattern = CircuitPattern::try_from_subcircuit(&circ, &[first_cx, t]).unwrap();
+        let matcher = PatternMatcher::from_patterns(vec![pattern]);
+        let matches = matcher.find_matches(&circ);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(
+            matches[0].nodes().iter().copied().sorted().collect_vec(),
+            [first_cx, t].into_iter().sorted().collect_vec()
+        );
+
+        assert_eq!(
+            CircuitPattern::try_from_subcircuit(&circ, &[first_cx, second_cx]).unwrap_err(),
+            InvalidPattern::NotConvex
+        );
+    }
+
     fn get_nodes_by_tk2op(circ: &impl Circuit, t2_op: Tk2Op) -> Vec<Node> {
         circ.nodes()
             .filter(|n| {
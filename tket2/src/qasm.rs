@@ -0,0 +1,206 @@
+//! OpenQASM 2.0 export.
+//!
+//! Only circuits made of the gates supported by QASM's `qelib1.inc` can be
+//! exported. Qubit registers are named after the TKET1 registers stored in
+//! the circuit metadata, if present.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write;
+
+use hugr::extension::prelude::QB_T;
+use hugr::ops::{OpName, OpType};
+use hugr::{IncomingPort, Node};
+use smol_str::SmolStr;
+use thiserror::Error;
+use tket_json_rs::circuit_json::Register;
+
+use crate::circuit::command::CircuitUnit;
+use crate::circuit::Circuit;
+use crate::json::{METADATA_B_REGISTERS, METADATA_Q_REGISTERS};
+use crate::utils::constant_f64_input;
+use crate::Tk2Op;
+
+/// Export a circuit as an OpenQASM 2.0 string.
+///
+/// Supports the operations [`Tk2Op::H`], [`Tk2Op::CX`], [`Tk2Op::X`],
+/// [`Tk2Op::Y`], [`Tk2Op::Z`], [`Tk2Op::S`], [`Tk2Op::Sdg`], [`Tk2Op::T`],
+/// [`Tk2Op::Tdg`], [`Tk2Op::RzF64`] with a constant angle, and
+/// [`Tk2Op::Measure`]. Each measurement writes to a new classical bit.
+pub fn to_qasm(circ: &impl Circuit) -> Result<String, QasmExportError> {
+    let root = circ.root();
+    let qubit_registers: Vec<Register> = circ
+        .get_metadata(root, METADATA_Q_REGISTERS)
+        .and_then(|regs| serde_json::from_value(regs.clone()).ok())
+        .unwrap_or_default();
+    let bit_registers: Vec<Register> = circ
+        .get_metadata(root, METADATA_B_REGISTERS)
+        .and_then(|regs| serde_json::from_value(regs.clone()).ok())
+        .unwrap_or_default();
+
+    // Map the qubits to registers, defaulting to sequential `q` indices.
+    let qubit_to_reg: HashMap<CircuitUnit, Register> = circ
+        .units()
+        .filter(|(_, _, ty)| ty == &QB_T)
+        .enumerate()
+        .map(|(i, (unit, _, _))| {
+            let reg = qubit_registers
+                .get(i)
+                .cloned()
+                .unwrap_or_else(|| Register("q".to_string(), vec![i as i64]));
+            (unit, reg)
+        })
+        .collect();
+
+    let mut body = String::new();
+    let mut bits: Vec<Register> = Vec::new();
+    for cmd in circ.commands() {
+        let optype = cmd.optype();
+        if matches!(optype, OpType::Const(_) | OpType::LoadConstant(_)) {
+            continue;
+        }
+        let op =
+            Tk2Op::try_from(optype).map_err(|_| QasmExportError::UnsupportedOp(optype.name()))?;
+        let qubits = cmd
+            .inputs()
+            .filter_map(|(unit, _, _)| qubit_to_reg.get(&unit))
+            .map(register_str)
+            .collect::<Vec<_>>()
+            .join(",");
+        let gate = match op {
+            Tk2Op::H => "h",
+            Tk2Op::CX => "cx",
+            Tk2Op::X => "x",
+            Tk2Op::Y => "y",
+            Tk2Op::Z => "z",
+            Tk2Op::S => "s",
+            Tk2Op::Sdg => "sdg",
+            Tk2Op::T => "t",
+            Tk2Op::Tdg => "tdg",
+            Tk2Op::RzF64 => {
+                let angle = constant_f64_input(circ, cmd.node(), IncomingPort::from(1))
+                    .ok_or(QasmExportError::NonConstantParameter(op, cmd.node()))?;
+                writeln!(body, "rz({angle}*pi) {qubits};").unwrap();
+                continue;
+            }
+            Tk2Op::Measure => {
+                let index = bits.len();
+                let bit = bit_registers
+                    .get(index)
+                    .cloned()
+                    .unwrap_or_else(|| Register("c".to_string(), vec![index as i64]));
+                writeln!(body, "measure {qubits} -> {};", register_str(&bit)).unwrap();
+                bits.push(bit);
+                continue;
+            }
+            _ => return Err(QasmExportError::UnsupportedOp(optype.name())),
+        };
+        writeln!(body, "{gate} {qubits};").unwrap();
+    }
+
+    let mut qasm = String::from("OPENQASM 2.0;\ninclude \"qelib1.inc\";\n\n");
+    for (name, size) in register_sizes(qubit_to_reg.values()) {
+        writeln!(qasm, "qreg {name}[{size}];").unwrap();
+    }
+    for (name, size) in register_sizes(&bits) {
+        writeln!(qasm, "creg {name}[{size}];").unwrap();
+    }
+    qasm.push_str(&body);
+    Ok(qasm)
+}
+
+/// The QASM name of a register element.
+fn register_str(reg: &Register) -> String {
+    let indices = reg.1.iter().map(|i| format!("[{i}]")).collect::<String>();
+    format!("{}{indices}", reg.0)
+}
+
+/// The size of each named register, sorted by name.
+fn register_sizes<'a>(regs: impl IntoIterator<Item = &'a Register>) -> BTreeMap<&'a str, i64> {
+    let mut sizes = BTreeMap::new();
+    for reg in regs {
+        let index = reg.1.first().copied().unwrap_or(0);
+        let size = sizes.entry(reg.0.as_str()).or_insert(0);
+        *size = (*size).max(index + 1);
+    }
+    sizes
+}
+
+/// Error type for exporting circuits to QASM.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum QasmExportError {
+    /// The operation has no QASM equivalent.
+    #[error("Cannot export operation {0} to QASM.")]
+    UnsupportedOp(SmolStr),
+    /// The parameter of an operation is not a constant.
+    #[error("The parameter of the {0:?} operation at node {1:?} is not a constant.")]
+    NonConstantParameter(Tk2Op, Node),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::extension::REGISTRY;
+    use crate::ops::test::t2_bell_circuit;
+    use crate::utils::build_simple_circuit;
+    use hugr::builder::{DFGBuilder, Dataflow, DataflowHugr};
+    use hugr::std_extensions::arithmetic::float_types::{ConstF64, FLOAT64_TYPE};
+    use hugr::types::FunctionType;
+    use hugr::Hugr;
+    use rstest::rstest;
+
+    #[rstest]
+    fn bell_circuit(t2_bell_circuit: Hugr) {
+        let qasm = to_qasm(&t2_bell_circuit).unwrap();
+
+        assert!(qasm.starts_with("OPENQASM 2.0;"));
+        assert!(qasm.contains("qreg q[2];"));
+        assert!(qasm.contains("h q[0];"));
+        assert!(qasm.contains("cx q[0],q[1];"));
+    }
+
+    #[test]
+    fn rz_and_measure() {
+        let mut h = DFGBuilder::new(FunctionType::new(vec![QB_T], vec![QB_T])).unwrap();
+        let [q] = h.input_wires_arr();
+        let angle = h.add_load_const(ConstF64::new(0.25)).unwrap();
+        let rz = h.add_dataflow_op(Tk2Op::RzF64, [q, angle]).unwrap();
+        let [q] = rz.outputs_arr();
+        let measure = h.add_dataflow_op(Tk2Op::Measure, [q]).unwrap();
+        let [q, _] = measure.outputs_arr();
+        let circ = h.finish_hugr_with_outputs([q], &REGISTRY).unwrap();
+
+        let qasm = to_qasm(&circ).unwrap();
+        assert!(qasm.contains("creg c[1];"));
+        assert!(qasm.contains("rz(0.25*pi) q[0];"));
+        assert!(qasm.contains("measure q[0] -> c[0];"));
+    }
+
+    #[test]
+    fn non_constant_angle() {
+        let mut h =
+            DFGBuilder::new(FunctionType::new(vec![QB_T, FLOAT64_TYPE], vec![QB_T])).unwrap();
+        let [q, angle] = h.input_wires_arr();
+        let rz = h.add_dataflow_op(Tk2Op::RzF64, [q, angle]).unwrap();
+        let circ = h.finish_hugr_with_outputs(rz.outputs(), &REGISTRY).unwrap();
+
+        assert!(matches!(
+            to_qasm(&circ),
+            Err(QasmExportError::NonConstantParameter(Tk2Op::RzF64, _))
+        ));
+    }
+
+    #[test]
+    fn unsupported_op() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::ZZMax, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+
+        assert_eq!(
+            to_qasm(&circ),
+            Err(QasmExportError::UnsupportedOp(Tk2Op::ZZMax.exposed_name()))
+        );
+    }
+}
@@ -2,20 +2,34 @@
 
 #[cfg(feature = "portmatching")]
 pub mod ecc_rewriter;
+#[cfg(feature = "portmatching")]
+pub mod rule_rewriter;
 pub mod strategy;
 pub mod trace;
 
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+use std::sync::Arc;
+
 use bytemuck::TransparentWrapper;
 #[cfg(feature = "portmatching")]
 pub use ecc_rewriter::ECCRewriter;
+#[cfg(feature = "portmatching")]
+pub use rule_rewriter::{InvalidRule, RuleRewriter};
 
 use derive_more::{From, Into};
-use hugr::hugr::views::sibling_subgraph::{InvalidReplacement, InvalidSubgraph};
-use hugr::Node;
+use hugr::hugr::views::sibling_subgraph::{
+    InvalidReplacement as HugrInvalidReplacement, InvalidSubgraph, TopoConvexChecker,
+};
+use hugr::ops::OpType;
+use hugr::types::FunctionType;
 use hugr::{
     hugr::{hugrmut::HugrMut, views::SiblingSubgraph, Rewrite, SimpleReplacementError},
     Hugr, SimpleReplacement,
 };
+use hugr::{HugrView, Node};
+use itertools::Itertools;
+use thiserror::Error;
 
 use crate::circuit::Circuit;
 
@@ -56,27 +70,114 @@ impl Subcircuit {
         source: &Hugr,
         target: Hugr,
     ) -> Result<CircuitRewrite, InvalidReplacement> {
-        Ok(CircuitRewrite(
-            self.subgraph.create_simple_replacement(source, target)?,
-        ))
+        CircuitRewrite::try_new(self, source, target)
     }
 }
 
+/// Enumerate the convex connected subcircuits of a circuit with `size`
+/// operations.
+///
+/// Constant definitions and loads are not counted as operations, and are
+/// never part of the returned subcircuits. Each set of nodes is returned
+/// exactly once, and the subcircuits are computed lazily.
+///
+/// The number of connected subcircuits grows exponentially with `size` in the
+/// worst case, so this should only be used with small sizes.
+pub fn enumerate_convex_subcircuits(
+    circ: &Hugr,
+    size: usize,
+) -> impl Iterator<Item = Subcircuit> + '_ {
+    let nodes = circ
+        .commands()
+        .filter(|cmd| !matches!(cmd.optype(), OpType::Const(_) | OpType::LoadConstant(_)))
+        .map(|cmd| cmd.node())
+        .collect_vec();
+    let index: HashMap<Node, usize> = nodes.iter().enumerate().map(|(i, &n)| (n, i)).collect();
+    let adjacency = nodes
+        .iter()
+        .map(|&n| {
+            circ.all_neighbours(n)
+                .filter_map(|m| index.get(&m).copied())
+                .unique()
+                .collect_vec()
+        })
+        .collect_vec();
+    let checker = TopoConvexChecker::new(circ);
+
+    // Enumerate the connected sets of nodes with the ESU algorithm. Each set
+    // is generated from its smallest node, `root`, by only extending it with
+    // larger nodes that are not adjacent to the nodes already in the set.
+    let mut stack = match size {
+        0 => Vec::new(),
+        _ => (0..nodes.len())
+            .rev()
+            .map(|root| {
+                let ext = adjacency[root].iter().copied().filter(|&u| u > root);
+                (vec![root], ext.collect_vec(), root)
+            })
+            .collect_vec(),
+    };
+    std::iter::from_fn(move || {
+        while let Some((sub, ext, root)) = stack.pop() {
+            if sub.len() == size {
+                let sub_nodes = sub.iter().map(|&i| nodes[i]).collect_vec();
+                // Fails if the nodes are not convex.
+                if let Ok(subgraph) =
+                    SiblingSubgraph::try_from_nodes_with_checker(sub_nodes, circ, &checker)
+                {
+                    return Some(Subcircuit { subgraph });
+                }
+                continue;
+            }
+            let neighbourhood: HashSet<usize> = sub
+                .iter()
+                .flat_map(|&v| adjacency[v].iter().copied())
+                .chain(sub.iter().copied())
+                .collect();
+            for (i, &w) in ext.iter().enumerate().rev() {
+                let mut new_ext = ext[i + 1..].to_vec();
+                new_ext.extend(
+                    adjacency[w]
+                        .iter()
+                        .copied()
+                        .filter(|&u| u > root && !neighbourhood.contains(&u)),
+                );
+                let mut new_sub = sub.clone();
+                new_sub.push(w);
+                stack.push((new_sub, new_ext, root));
+            }
+        }
+        None
+    })
+}
+
 /// A rewrite rule for circuits.
 #[derive(Debug, Clone, From, Into)]
 pub struct CircuitRewrite(SimpleReplacement);
 
 impl CircuitRewrite {
     /// Create a new rewrite rule.
+    ///
+    /// Returns [`InvalidReplacement::BoundaryMismatch`] if the inputs and
+    /// outputs of the replacement do not match the boundary of the replaced
+    /// subcircuit, and [`InvalidReplacement::MissingSignature`] if the
+    /// replacement is not a dataflow graph with a function type.
     pub fn try_new(
         source_position: &Subcircuit,
         source: &Hugr,
         target: Hugr,
     ) -> Result<Self, InvalidReplacement> {
-        source_position
+        let expected = source_position.subgraph.signature(source);
+        let found = target
+            .get_function_type()
+            .ok_or(InvalidReplacement::MissingSignature)?;
+        if expected.input != found.input || expected.output != found.output {
+            return Err(InvalidReplacement::BoundaryMismatch { expected, found });
+        }
+        let replacement = source_position
             .subgraph
-            .create_simple_replacement(source, target)
-            .map(Self)
+            .create_simple_replacement(source, target)?;
+        Ok(Self(replacement))
     }
 
     /// Number of nodes added or removed by the rewrite.
@@ -89,6 +190,20 @@ impl CircuitRewrite {
         new_count - old_count
     }
 
+    /// Difference in cost between the replacement and the replaced
+    /// subcircuit, for a cost function on whole circuits.
+    ///
+    /// A negative number is an improvement. The replaced subcircuit is
+    /// extracted from `circ`, the circuit the rewrite applies to, which is
+    /// not modified.
+    ///
+    /// Returns `None` if the replaced subcircuit cannot be extracted from
+    /// `circ`, e.g. if the rewrite was not built for that circuit.
+    pub fn cost_delta(&self, circ: &impl HugrView, cost: impl Fn(&Hugr) -> usize) -> Option<isize> {
+        let source = self.0.subgraph().extract_subgraph(circ, "Source").ok()?;
+        Some(cost(self.replacement()) as isize - cost(&source) as isize)
+    }
+
     /// The subcircuit that is replaced.
     pub fn subcircuit(&self) -> &Subcircuit {
         Subcircuit::wrap_ref(self.0.subgraph())
@@ -116,6 +231,55 @@ impl CircuitRewrite {
         self.0.apply(circ)
     }
 
+    /// Apply the rewrite rule to a circuit, returning the nodes added and
+    /// removed by the rewrite.
+    ///
+    /// The changed nodes are found by exploring the neighbourhood of the
+    /// replaced subcircuit, without traversing the whole circuit. This can be
+    /// used to update a set of pattern matches incrementally.
+    pub fn apply_notify(self, circ: &mut Hugr) -> Result<RewriteResult, SimpleReplacementError> {
+        let removed_nodes = self.subcircuit().nodes().to_vec();
+        let removed: HashSet<Node> = removed_nodes.iter().copied().collect();
+
+        // The nodes connected to the subcircuit, and their other neighbours.
+        let boundary: HashMap<Node, HashSet<Node>> = removed_nodes
+            .iter()
+            .flat_map(|&n| circ.all_neighbours(n))
+            .filter(|n| !removed.contains(n))
+            .unique()
+            .map(|b| {
+                let neighbours = circ
+                    .all_neighbours(b)
+                    .filter(|n| !removed.contains(n))
+                    .collect();
+                (b, neighbours)
+            })
+            .collect();
+
+        self.apply(circ)?;
+
+        // The inserted nodes are only connected to each other and to the
+        // boundary, so we can find them starting from the new boundary edges.
+        let mut queue = boundary
+            .iter()
+            .flat_map(|(&b, old)| circ.all_neighbours(b).filter(|n| !old.contains(n)))
+            .collect_vec();
+        let mut seen = HashSet::new();
+        let mut added_nodes = Vec::new();
+        while let Some(node) = queue.pop() {
+            if boundary.contains_key(&node) || !seen.insert(node) {
+                continue;
+            }
+            added_nodes.push(node);
+            queue.extend(circ.all_neighbours(node));
+        }
+
+        Ok(RewriteResult {
+            added_nodes,
+            removed_nodes,
+        })
+    }
+
     /// Apply the rewrite rule to a circuit, without registering it in the rewrite trace.
     #[inline]
     pub fn apply_notrace(self, circ: &mut impl HugrMut) -> Result<(), SimpleReplacementError> {
@@ -123,8 +287,194 @@ impl CircuitRewrite {
     }
 }
 
+/// The nodes changed by applying a [`CircuitRewrite`].
+///
+/// See [`CircuitRewrite::apply_notify`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RewriteResult {
+    /// The nodes inserted in the circuit.
+    pub added_nodes: Vec<Node>,
+    /// The nodes removed from the circuit.
+    ///
+    /// These node indices may be reused by the added nodes.
+    pub removed_nodes: Vec<Node>,
+}
+
+/// Errors that can occur when creating a [`CircuitRewrite`].
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum InvalidReplacement {
+    /// The inputs or outputs of the replacement do not match the boundary of
+    /// the replaced subcircuit.
+    #[error("Replacement with signature {found} does not match the boundary of the replaced subcircuit, {expected}.")]
+    BoundaryMismatch {
+        /// The signature of the replaced subcircuit.
+        expected: FunctionType,
+        /// The signature of the replacement.
+        found: FunctionType,
+    },
+    /// The replacement does not have a function type.
+    #[error("Replacement has no function type.")]
+    MissingSignature,
+    /// The replacement could not be created.
+    #[error(transparent)]
+    Hugr(#[from] HugrInvalidReplacement),
+}
+
 /// Generate rewrite rules for circuits.
 pub trait Rewriter {
     /// Get the rewrite rules for a circuit.
     fn get_rewrites<C: Circuit + Clone>(&self, circ: &C) -> Vec<CircuitRewrite>;
 }
+
+/// An object-safe version of [`Rewriter`], implemented for every rewriter.
+///
+/// See [`BoxedRewriter`].
+pub trait DynRewriter {
+    /// Get the rewrite rules for a circuit.
+    fn get_rewrites_dyn(&self, circ: &Hugr) -> Vec<CircuitRewrite>;
+}
+
+impl<R: Rewriter> DynRewriter for R {
+    fn get_rewrites_dyn(&self, circ: &Hugr) -> Vec<CircuitRewrite> {
+        self.get_rewrites(circ)
+    }
+}
+
+/// A rewriter chosen at runtime.
+///
+/// Wraps any [`Rewriter`] behind a shared trait object, so that optimisers
+/// can be constructed without knowing the rewriter type statically.
+///
+/// The rewrites are computed on the [`HugrView::base_hugr`] of the circuits.
+#[derive(Clone)]
+pub struct BoxedRewriter(Arc<dyn DynRewriter + Send + Sync>);
+
+impl BoxedRewriter {
+    /// Wrap a rewriter.
+    pub fn new(rewriter: impl Rewriter + Send + Sync + 'static) -> Self {
+        Self(Arc::new(rewriter))
+    }
+}
+
+impl Debug for BoxedRewriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxedRewriter").finish_non_exhaustive()
+    }
+}
+
+impl Rewriter for BoxedRewriter {
+    fn get_rewrites<C: Circuit + Clone>(&self, circ: &C) -> Vec<CircuitRewrite> {
+        self.0.get_rewrites_dyn(circ.base_hugr())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::build_simple_circuit;
+    use crate::Tk2Op;
+
+    #[test]
+    fn rewrite_cost_delta() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        let cxs = circ
+            .commands()
+            .filter(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(Tk2Op::CX))
+            .map(|cmd| cmd.node())
+            .collect_vec();
+        let identity = build_simple_circuit(2, |_| Ok(())).unwrap();
+        let rewrite = Subcircuit::try_from_nodes(cxs, &circ)
+            .unwrap()
+            .create_rewrite(&circ, identity)
+            .unwrap();
+
+        let cx_count = |c: &Hugr| {
+            c.commands()
+                .filter(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(Tk2Op::CX))
+                .count()
+        };
+        assert_eq!(rewrite.cost_delta(&circ, cx_count), Some(-2));
+        assert_eq!(
+            rewrite.cost_delta(&circ, |c: &Hugr| c.num_gates()),
+            Some(-2)
+        );
+    }
+
+    #[test]
+    fn convex_subcircuits() {
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::T, [1])?;
+            Ok(())
+        })
+        .unwrap();
+        let count = |size| enumerate_convex_subcircuits(&circ, size).count();
+
+        // The pair of CX gates is connected but not convex.
+        assert_eq!(count(2), 3);
+        assert_eq!(count(3), 2);
+        assert_eq!(count(1), 4);
+        assert_eq!(count(4), 1);
+        assert_eq!(count(0), 0);
+
+        let pairs = enumerate_convex_subcircuits(&circ, 2)
+            .map(|s| s.nodes().iter().copied().sorted().collect_vec())
+            .collect_vec();
+        assert_eq!(pairs.iter().unique().count(), pairs.len());
+    }
+
+    #[test]
+    fn apply_notify() {
+        let mut circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::H, [1])?;
+            Ok(())
+        })
+        .unwrap();
+        let cxs = circ
+            .commands()
+            .filter(|cmd| Tk2Op::try_from(cmd.optype()).ok() == Some(Tk2Op::CX))
+            .map(|cmd| cmd.node())
+            .collect_vec();
+        let subcirc = Subcircuit::try_from_nodes(cxs.clone(), &circ).unwrap();
+
+        // Replace CX-CX by the identity.
+        let identity = build_simple_circuit(2, |_| Ok(())).unwrap();
+        let rewrite = subcirc.create_rewrite(&circ, identity).unwrap();
+        let result = rewrite.apply_notify(&mut circ).unwrap();
+
+        assert_eq!(
+            result.removed_nodes.iter().sorted().collect_vec(),
+            cxs.iter().sorted().collect_vec()
+        );
+        assert!(result.added_nodes.is_empty());
+        assert_eq!(circ.num_gates(), 2);
+
+        // Replace an H gate by an X gate.
+        let h = circ.commands().next().unwrap().node();
+        let subcirc = Subcircuit::try_from_nodes([h], &circ).unwrap();
+        let x = build_simple_circuit(1, |circ| {
+            circ.append(Tk2Op::X, [0])?;
+            Ok(())
+        })
+        .unwrap();
+        let rewrite = subcirc.create_rewrite(&circ, x).unwrap();
+        let result = rewrite.apply_notify(&mut circ).unwrap();
+
+        assert_eq!(result.removed_nodes, vec![h]);
+        assert_eq!(result.added_nodes.len(), 1);
+        let added = circ.get_optype(result.added_nodes[0]);
+        assert_eq!(Tk2Op::try_from(added).ok(), Some(Tk2Op::X));
+    }
+}
@@ -71,6 +71,30 @@ impl ECCRewriter {
         Ok(Self::from_eccs(eccs))
     }
 
+    /// Create a new rewriter from equivalent circuit classes in JSON file,
+    /// ignoring the circuits with more than `max_gates` operations.
+    ///
+    /// Equivalence classes left with a single circuit are discarded. This
+    /// trades optimisation power for a smaller matcher and faster matching.
+    ///
+    /// See [`ECCRewriter::try_from_eccs_json_file`] for the file format.
+    pub fn try_from_eccs_json_file_filtered(
+        path: impl AsRef<Path>,
+        max_gates: usize,
+    ) -> io::Result<Self> {
+        let eccs = load_eccs_json_file(path)?
+            .into_iter()
+            .filter_map(|ecc| {
+                let circs = ecc
+                    .into_circuits()
+                    .filter(|circ| circ.num_gates() <= max_gates)
+                    .collect_vec();
+                (circs.len() > 1).then(|| EqCircClass::from_circuits(circs).unwrap())
+            })
+            .collect_vec();
+        Ok(Self::from_eccs(eccs))
+    }
+
     /// Create a new rewriter from a list of equivalent circuit classes.
     ///
     /// Equivalence classes are represented as [`EqCircClass`]s, lists of
@@ -366,6 +390,15 @@ mod tests {
         assert_eq!(n_eccs_of_len, exp_n_eccs_of_len);
     }
 
+    #[test]
+    fn ecc_rewriter_from_file_filtered() {
+        let test_file = "../test_files/small_eccs.json";
+        let rewriter = ECCRewriter::try_from_eccs_json_file(test_file).unwrap();
+        let filtered = ECCRewriter::try_from_eccs_json_file_filtered(test_file, 2).unwrap();
+        assert!(filtered.matcher.n_patterns() < rewriter.matcher.n_patterns());
+        assert!(filtered.targets.iter().all(|circ| circ.num_gates() <= 2));
+    }
+
     /// Some inputs are left untouched: these parameters should be removed to
     /// obtain convex patterns
     #[test]
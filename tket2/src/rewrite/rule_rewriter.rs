@@ -0,0 +1,165 @@
+//! A rewriter based on user-supplied rewrite rules.
+//!
+//! Each rule is a pair of circuits with the same signature. Occurrences of
+//! the left-hand side circuit are found using the [`PatternMatcher`], and
+//! replaced by the right-hand side circuit.
+
+use hugr::{Hugr, HugrView};
+use thiserror::Error;
+
+use crate::circuit::Circuit;
+use crate::portmatching::pattern::InvalidPattern;
+use crate::portmatching::{CircuitPattern, PatternMatcher};
+
+use super::{CircuitRewrite, Rewriter};
+
+/// A rewriter applying a fixed set of rewrite rules.
+///
+/// Unlike the [`ECCRewriter`], rules are only applied from left to right.
+///
+/// [`ECCRewriter`]: super::ECCRewriter
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct RuleRewriter {
+    /// Matcher for the left-hand sides of the rules.
+    matcher: PatternMatcher,
+    /// The right-hand side of each rule, indexed by pattern ID.
+    replacements: Vec<Hugr>,
+}
+
+impl RuleRewriter {
+    /// Create a rewriter from a matcher and the replacement for each of its
+    /// patterns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of replacements differs from the number of
+    /// patterns in the matcher.
+    pub fn new(matcher: PatternMatcher, replacements: impl Into<Vec<Hugr>>) -> Self {
+        let replacements = replacements.into();
+        assert_eq!(
+            matcher.n_patterns(),
+            replacements.len(),
+            "Each pattern must have a replacement."
+        );
+        Self {
+            matcher,
+            replacements,
+        }
+    }
+
+    /// Create a rewriter from a list of `(left, right)` rewrite rules.
+    ///
+    /// Returns an error if a left-hand side circuit is not a valid pattern,
+    /// or if the two sides of a rule have different signatures.
+    pub fn try_from_rules(
+        rules: impl IntoIterator<Item = (Hugr, Hugr)>,
+    ) -> Result<Self, InvalidRule> {
+        let (lefts, replacements): (Vec<_>, Vec<_>) = rules.into_iter().unzip();
+        for (index, (left, right)) in lefts.iter().zip(&replacements).enumerate() {
+            let left_sig = left.get_function_type();
+            if left_sig.is_none() || left_sig != right.get_function_type() {
+                return Err(InvalidRule::SignatureMismatch { index });
+            }
+        }
+        let patterns = lefts
+            .iter()
+            .map(CircuitPattern::try_from_circuit)
+            .collect::<Result<Vec<_>, _>>()?;
+        let matcher = PatternMatcher::try_from_patterns(patterns)?;
+        Ok(Self::new(matcher, replacements))
+    }
+
+    /// The number of rules in the rewriter.
+    pub fn n_rules(&self) -> usize {
+        self.replacements.len()
+    }
+}
+
+impl Rewriter for RuleRewriter {
+    fn get_rewrites<C: Circuit + Clone>(&self, circ: &C) -> Vec<CircuitRewrite> {
+        self.matcher
+            .find_matches(circ)
+            .into_iter()
+            .filter_map(|m| {
+                let repl = self.replacements[m.pattern_id().0].clone();
+                m.to_rewrite(circ.base_hugr(), repl).ok()
+            })
+            .collect()
+    }
+}
+
+/// Errors that can occur when building a [`RuleRewriter`] from rules.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum InvalidRule {
+    /// The left-hand side of a rule is not a valid pattern.
+    #[error(transparent)]
+    Pattern(#[from] InvalidPattern),
+    /// The two sides of a rule do not have the same signature.
+    #[error("the two sides of rule {index} have different signatures")]
+    SignatureMismatch {
+        /// The index of the offending rule.
+        index: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimiser::badger::BadgerOptions;
+    use crate::optimiser::BadgerOptimiser;
+    use crate::rewrite::strategy::LexicographicCostFunction;
+    use crate::utils::build_simple_circuit;
+    use crate::Tk2Op;
+
+    #[test]
+    fn cx_cx_rule() {
+        let cx_cx = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        let identity = build_simple_circuit(2, |_| Ok(())).unwrap();
+        let rewriter = RuleRewriter::try_from_rules([(cx_cx, identity)]).unwrap();
+        assert_eq!(rewriter.n_rules(), 1);
+
+        let circ = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::H, [0])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::CX, [0, 1])?;
+            circ.append(Tk2Op::H, [1])?;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(rewriter.get_rewrites(&circ).len(), 1);
+
+        let optimiser = BadgerOptimiser::new(rewriter, LexicographicCostFunction::default_cx());
+        let opt = optimiser.optimise(
+            &circ,
+            BadgerOptions {
+                queue_size: 4,
+                ..Default::default()
+            },
+        );
+        let gates = opt
+            .commands()
+            .map(|cmd| Tk2Op::try_from(cmd.optype()).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(gates, [Tk2Op::H, Tk2Op::H]);
+    }
+
+    #[test]
+    fn signature_mismatch() {
+        let cx = build_simple_circuit(2, |circ| {
+            circ.append(Tk2Op::CX, [0, 1])?;
+            Ok(())
+        })
+        .unwrap();
+        let one_qubit = build_simple_circuit(1, |_| Ok(())).unwrap();
+        assert!(matches!(
+            RuleRewriter::try_from_rules([(cx, one_qubit)]),
+            Err(InvalidRule::SignatureMismatch { index: 0 })
+        ));
+    }
+}
@@ -3,7 +3,9 @@
 //! This module contains the [`RewriteStrategy`] trait, which is currently
 //! implemented by
 //! - [`GreedyRewriteStrategy`], which applies as many rewrites as possible on
-//!   one circuit, and
+//!   one circuit,
+//! - [`BestRewriteStrategy`], which applies the single best rewrite to one
+//!   circuit, and
 //! - Exhaustive strategies, which clone the original circuit and explore every
 //!   possible rewrite (with some pruning strategy):
 //!    - [`ExhaustiveGreedyStrategy`], which applies multiple combinations of
@@ -19,8 +21,14 @@
 //!      [`LexicographicCostFunction::default_cx`]) for a default implementation.
 //!    - [`GammaStrategyCost`] ignores rewrites that increase the cost
 //!      function beyond a percentage given by a f64 parameter gamma.
+//!    - [`MaxIncreaseStrategyCost`] ignores rewrites that increase the cost
+//!      function by more than a fixed amount.
+//!
+//! Finally, [`CircuitCostStrategy`] wraps any other strategy to rank circuits
+//! using an arbitrary cost function on whole circuits.
 
 use std::iter;
+use std::sync::Arc;
 use std::{collections::HashSet, fmt::Debug};
 
 use derive_more::From;
@@ -149,6 +157,63 @@ impl RewriteStrategy for GreedyRewriteStrategy {
     }
 }
 
+/// A hill-climbing strategy applying the single best rewrite to a circuit.
+///
+/// The cost delta of every rewrite is computed with the operation cost
+/// function `op_cost`, and only the rewrite with the lowest delta is applied.
+/// Unlike [`GreedyRewriteStrategy`], non-overlapping rewrites are not
+/// combined.
+///
+/// At most one circuit is returned, and only if the best rewrite strictly
+/// decreases the cost. This is fast, but the optimisation may get stuck in
+/// a local minimum.
+#[derive(Debug, Copy, Clone)]
+pub struct BestRewriteStrategy<F> {
+    /// The cost function of each operation.
+    pub op_cost: F,
+}
+
+impl<F: Fn(&OpType) -> usize> BestRewriteStrategy<F> {
+    /// Create a new strategy with the given operation cost function.
+    pub fn new(op_cost: F) -> Self {
+        Self { op_cost }
+    }
+}
+
+impl<F: Fn(&OpType) -> usize> RewriteStrategy for BestRewriteStrategy<F> {
+    type Cost = usize;
+
+    #[tracing::instrument(skip_all)]
+    fn apply_rewrites(
+        &self,
+        rewrites: impl IntoIterator<Item = CircuitRewrite>,
+        circ: &Hugr,
+    ) -> impl Iterator<Item = RewriteResult<Self::Cost>> {
+        let best = rewrites
+            .into_iter()
+            .map(|rw| {
+                let pattern_cost = self.pre_rewrite_cost(&rw, circ);
+                let target_cost = self.post_rewrite_cost(&rw);
+                let delta = target_cost.sub_cost(&pattern_cost);
+                (rw, delta)
+            })
+            .min_by_key(|(_, delta)| *delta)
+            .filter(|(_, delta)| *delta < 0);
+        best.map(|(rw, delta)| {
+            let mut circ = circ.clone();
+            rw.apply(&mut circ)
+                .expect("Could not perform rewrite in best rewrite strategy");
+            (circ, delta).into()
+        })
+        .into_iter()
+    }
+
+    #[inline]
+    fn op_cost(&self, op: &OpType) -> Self::Cost {
+        (self.op_cost)(op)
+    }
+}
+
 /// Exhaustive strategies based on cost functions and thresholds.
 ///
 /// Every possible rewrite is applied to a copy of the input circuit. In
@@ -282,6 +347,180 @@ impl<T: StrategyCost> RewriteStrategy for ExhaustiveThresholdStrategy<T> {
     }
 }
 
+/// A rewrite strategy ranking circuits with a cost function on whole circuits.
+///
+/// The rewrites are selected and applied by the wrapped strategy, but the cost
+/// of every resulting circuit is computed by calling `cost_fn` on the full
+/// circuit. This allows optimising for cost functions that cannot be
+/// decomposed into a sum of per-operation costs, at the price of evaluating
+/// the cost function once for every new circuit.
+///
+/// The per-operation costs of the wrapped strategy are still used where
+/// an operation cost is needed, e.g. when splitting a circuit into chunks.
+#[derive(Debug, Clone)]
+pub struct CircuitCostStrategy<S, C> {
+    /// The strategy used to select and apply rewrites.
+    pub strategy: S,
+    /// The cost function evaluated on whole circuits.
+    pub cost_fn: C,
+}
+
+impl<S, C> CircuitCostStrategy<S, C> {
+    /// Wrap a rewrite strategy, ranking circuits using the given cost function.
+    pub fn new(strategy: S, cost_fn: C) -> Self {
+        Self { strategy, cost_fn }
+    }
+}
+
+impl<S, C> RewriteStrategy for CircuitCostStrategy<S, C>
+where
+    S: RewriteStrategy,
+    C: Fn(&Hugr) -> usize,
+{
+    type Cost = usize;
+
+    #[tracing::instrument(skip_all)]
+    fn apply_rewrites(
+        &self,
+        rewrites: impl IntoIterator<Item = CircuitRewrite>,
+        circ: &Hugr,
+    ) -> impl Iterator<Item = RewriteResult<Self::Cost>> {
+        let circ_cost = (self.cost_fn)(circ);
+        self.strategy
+            .apply_rewrites(rewrites, circ)
+            .map(move |RewriteResult { circ, .. }| {
+                let cost_delta = (self.cost_fn)(&circ).sub_cost(&circ_cost);
+                (circ, cost_delta).into()
+            })
+    }
+
+    #[inline]
+    fn op_cost(&self, op: &OpType) -> Self::Cost {
+        self.strategy.op_cost(op).as_usize()
+    }
+
+    #[inline]
+    fn circuit_cost(&self, circ: &Hugr) -> Self::Cost {
+        (self.cost_fn)(circ)
+    }
+}
+
+/// An object-safe version of [`RewriteStrategy`], implemented for every
+/// strategy.
+///
+/// See [`BoxedStrategy`].
+pub trait DynRewriteStrategy {
+    /// The circuit cost to be minimised.
+    type Cost: CircuitCost;
+
+    /// Apply a set of rewrites to a circuit, collecting the results.
+    fn apply_rewrites_dyn(
+        &self,
+        rewrites: Vec<CircuitRewrite>,
+        circ: &Hugr,
+    ) -> Vec<RewriteResult<Self::Cost>>;
+
+    /// The cost of a single operation.
+    fn op_cost_dyn(&self, op: &OpType) -> Self::Cost;
+
+    /// The cost of a circuit.
+    fn circuit_cost_dyn(&self, circ: &Hugr) -> Self::Cost;
+
+    /// The cost of a rewrite's matched subcircuit before replacing it.
+    fn pre_rewrite_cost_dyn(&self, rw: &CircuitRewrite, circ: &Hugr) -> Self::Cost;
+
+    /// The expected cost of a rewrite's matched subcircuit after replacing it.
+    fn post_rewrite_cost_dyn(&self, rw: &CircuitRewrite) -> Self::Cost;
+}
+
+impl<S: RewriteStrategy> DynRewriteStrategy for S {
+    type Cost = S::Cost;
+
+    fn apply_rewrites_dyn(
+        &self,
+        rewrites: Vec<CircuitRewrite>,
+        circ: &Hugr,
+    ) -> Vec<RewriteResult<Self::Cost>> {
+        self.apply_rewrites(rewrites, circ).collect()
+    }
+
+    fn op_cost_dyn(&self, op: &OpType) -> Self::Cost {
+        self.op_cost(op)
+    }
+
+    fn circuit_cost_dyn(&self, circ: &Hugr) -> Self::Cost {
+        self.circuit_cost(circ)
+    }
+
+    fn pre_rewrite_cost_dyn(&self, rw: &CircuitRewrite, circ: &Hugr) -> Self::Cost {
+        self.pre_rewrite_cost(rw, circ)
+    }
+
+    fn post_rewrite_cost_dyn(&self, rw: &CircuitRewrite) -> Self::Cost {
+        self.post_rewrite_cost(rw)
+    }
+}
+
+/// A rewrite strategy chosen at runtime.
+///
+/// Wraps any [`RewriteStrategy`] with cost `C` behind a shared trait object,
+/// so that optimisers can be constructed without knowing the strategy type
+/// statically. The rewritten circuits are computed eagerly.
+pub struct BoxedStrategy<C>(Arc<dyn DynRewriteStrategy<Cost = C> + Send + Sync>);
+
+impl<C: CircuitCost> BoxedStrategy<C> {
+    /// Wrap a rewrite strategy.
+    pub fn new(strategy: impl RewriteStrategy<Cost = C> + Send + Sync + 'static) -> Self {
+        Self(Arc::new(strategy))
+    }
+}
+
+impl<C> Clone for BoxedStrategy<C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<C> Debug for BoxedStrategy<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BoxedStrategy").finish_non_exhaustive()
+    }
+}
+
+impl<C: CircuitCost> RewriteStrategy for BoxedStrategy<C> {
+    type Cost = C;
+
+    fn apply_rewrites(
+        &self,
+        rewrites: impl IntoIterator<Item = CircuitRewrite>,
+        circ: &Hugr,
+    ) -> impl Iterator<Item = RewriteResult<Self::Cost>> {
+        self.0
+            .apply_rewrites_dyn(rewrites.into_iter().collect(), circ)
+            .into_iter()
+    }
+
+    #[inline]
+    fn op_cost(&self, op: &OpType) -> Self::Cost {
+        self.0.op_cost_dyn(op)
+    }
+
+    #[inline]
+    fn circuit_cost(&self, circ: &Hugr) -> Self::Cost {
+        self.0.circuit_cost_dyn(circ)
+    }
+
+    #[inline]
+    fn pre_rewrite_cost(&self, rw: &CircuitRewrite, circ: &Hugr) -> Self::Cost {
+        self.0.pre_rewrite_cost_dyn(rw, circ)
+    }
+
+    #[inline]
+    fn post_rewrite_cost(&self, rw: &CircuitRewrite) -> Self::Cost {
+        self.0.post_rewrite_cost_dyn(rw)
+    }
+}
+
 /// Cost function definitions required in exhaustive strategies.
 ///
 /// See [`ExhaustiveThresholdStrategy`], [`ExhaustiveGreedyStrategy`].
@@ -426,6 +665,50 @@ impl GammaStrategyCost<fn(&OpType) -> usize> {
     }
 }
 
+/// Rewrite strategy cost allowing rewrites with a bounded absolute cost increase.
+///
+/// A rewrite C1 -> C2 is allowed if
+///
+/// $cost(C2) <= cost(C1) + max_cost_increase$
+///
+/// where the cost is the sum of the cost of each operation. Discarding the
+/// rewrites that increase the cost too much reduces the number of circuits
+/// explored by exhaustive strategies, at the price of a less thorough search.
+#[derive(Debug, Clone)]
+pub struct MaxIncreaseStrategyCost<C> {
+    /// The maximum cost increase allowed for a single rewrite.
+    pub max_cost_increase: usize,
+    /// A cost function for each operation.
+    pub op_cost: C,
+}
+
+impl<C: Fn(&OpType) -> usize> StrategyCost for MaxIncreaseStrategyCost<C> {
+    type OpCost = usize;
+
+    #[inline]
+    fn under_threshold(&self, &pattern_cost: &Self::OpCost, &target_cost: &Self::OpCost) -> bool {
+        target_cost <= pattern_cost + self.max_cost_increase
+    }
+
+    #[inline]
+    fn op_cost(&self, op: &OpType) -> Self::OpCost {
+        (self.op_cost)(op)
+    }
+}
+
+impl<C> MaxIncreaseStrategyCost<C> {
+    /// New exhaustive rewrite strategy with provided maximum cost increase
+    /// and cost function.
+    #[inline]
+    pub fn new(max_cost_increase: usize, op_cost: C) -> ExhaustiveThresholdStrategy<Self> {
+        Self {
+            max_cost_increase,
+            op_cost,
+        }
+        .into()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -492,6 +775,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_best_rewrite_strategy() {
+        let circ = n_cx(10);
+        let cx_gates = circ.commands().map(|cmd| cmd.node()).collect_vec();
+
+        let rws = [
+            rw_to_empty(&circ, cx_gates[0..2].to_vec()),
+            rw_to_full(&circ, cx_gates[4..7].to_vec()),
+            rw_to_empty(&circ, cx_gates[4..8].to_vec()),
+            rw_to_empty(&circ, cx_gates[9..10].to_vec()),
+        ];
+
+        let strategy = BestRewriteStrategy::new(|op: &OpType| is_cx(op) as usize);
+        let rewritten = strategy.apply_rewrites(rws, &circ).collect_vec();
+        assert_eq!(rewritten.len(), 1);
+        assert_eq!(rewritten[0].circ.num_gates(), 6);
+        assert_eq!(rewritten[0].cost_delta, -4);
+
+        // Rewrites that do not decrease the cost are not applied.
+        let rws = [rw_to_full(&circ, cx_gates[4..7].to_vec())];
+        assert_eq!(strategy.apply_rewrites(rws, &circ).count(), 0);
+    }
+
     #[test]
     fn test_exhaustive_default_strategy() {
         let mut circ = n_cx(10);
@@ -548,6 +854,27 @@ mod tests {
         assert_eq!(circ_lens, exp_circ_lens);
     }
 
+    #[test]
+    fn test_exhaustive_max_increase_strategy() {
+        let circ = n_cx(10);
+        let cx_gates = circ.commands().map(|cmd| cmd.node()).collect_vec();
+
+        let rws = [
+            rw_to_empty(&circ, cx_gates[0..2].to_vec()),
+            // Increases the CX count by 7.
+            rw_to_full(&circ, cx_gates[4..7].to_vec()),
+            // Increases the CX count by 1.
+            rw_to_full(&circ, cx_gates[0..9].to_vec()),
+        ];
+
+        let strategy = MaxIncreaseStrategyCost::new(5, |op: &OpType| is_cx(op) as usize);
+        let deltas: HashSet<_> = strategy
+            .apply_rewrites(rws, &circ)
+            .map(|r| r.cost_delta)
+            .collect();
+        assert_eq!(deltas, HashSet::from_iter([-2, 1]));
+    }
+
     #[test]
     fn test_exhaustive_default_cx_cost() {
         let strat = LexicographicCostFunction::default_cx();
@@ -563,6 +890,31 @@ mod tests {
         assert_eq!(strat.circuit_cost(&circ), (1, 3).into());
     }
 
+    #[test]
+    fn test_circuit_cost_strategy() {
+        let circ = n_cx(10);
+        let cx_gates = circ.commands().map(|cmd| cmd.node()).collect_vec();
+
+        let rws = [
+            rw_to_empty(&circ, cx_gates[0..2].to_vec()),
+            rw_to_full(&circ, cx_gates[4..7].to_vec()),
+        ];
+
+        let strategy = CircuitCostStrategy::new(
+            GammaStrategyCost::exhaustive_cx_with_gamma(10.),
+            |circ: &Hugr| 2 * circ.num_gates(),
+        );
+        assert_eq!(strategy.circuit_cost(&circ), 20);
+        let deltas: HashSet<_> = strategy
+            .apply_rewrites(rws, &circ)
+            .map(|r| {
+                assert_eq!(strategy.circuit_cost(&r.circ), (20 + r.cost_delta) as usize);
+                r.cost_delta
+            })
+            .collect();
+        assert_eq!(deltas, HashSet::from_iter([-4, 14]));
+    }
+
     #[test]
     fn test_exhaustive_default_cx_threshold() {
         let strat = LexicographicCostFunction::default_cx().strat_cost;
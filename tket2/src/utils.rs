@@ -1,13 +1,21 @@
 //! Utility functions for the library.
 
 use hugr::extension::PRELUDE_REGISTRY;
+use hugr::hugr::hugrmut::HugrMut;
+use hugr::ops::OpType;
+use hugr::std_extensions::arithmetic::float_types::ConstF64;
 use hugr::types::{Type, TypeBound};
+use hugr::values::Value;
 use hugr::{
     builder::{BuildError, CircuitBuilder, DFGBuilder, Dataflow, DataflowHugr},
     extension::prelude::QB_T,
     types::FunctionType,
     Hugr,
 };
+use hugr::{HugrView, IncomingPort, Node, OutgoingPort, Port};
+use itertools::Itertools;
+
+use crate::Tk2Op;
 
 pub(crate) fn type_is_linear(typ: &Type) -> bool {
     !TypeBound::Copyable.contains(typ.least_upper_bound())
@@ -32,13 +40,133 @@ pub(crate) fn build_simple_circuit(
     h.finish_hugr_with_outputs(qbs, &PRELUDE_REGISTRY)
 }
 
+/// Remove a single-qubit gate from the circuit, connecting its predecessor to
+/// its successor.
+pub(crate) fn remove_1q_gate(circ: &mut Hugr, node: Node) {
+    let in_port = Port::from(IncomingPort::from(0));
+    let out_port = Port::from(OutgoingPort::from(0));
+    let (src, src_port) = circ
+        .linked_ports(node, in_port)
+        .exactly_one()
+        .ok()
+        .expect("Invalid circuit: qubit input is not connected.");
+    let (dst, dst_port) = circ
+        .linked_ports(node, out_port)
+        .exactly_one()
+        .ok()
+        .expect("Invalid circuit: qubit output is not connected.");
+    circ.remove_node(node)
+        .expect("Invalid circuit: could not remove node.");
+    circ.connect(src, src_port.index(), dst, dst_port.index())
+        .expect("Invalid circuit: could not reconnect qubit.");
+}
+
+/// Insert a single-qubit gate on the wire connected to the given input port.
+pub(crate) fn insert_1q_gate_before(circ: &mut Hugr, node: Node, port: IncomingPort, op: Tk2Op) {
+    let in_port = Port::from(port);
+    let (src, src_port) = circ
+        .linked_ports(node, in_port)
+        .exactly_one()
+        .ok()
+        .expect("Invalid circuit: qubit input is not connected.");
+    let parent = circ.get_parent(node).expect("Command has no parent.");
+    let new_node = circ
+        .add_op_with_parent(parent, op)
+        .expect("Invalid circuit: could not insert gate.");
+    circ.disconnect(node, in_port)
+        .expect("Invalid circuit: could not disconnect qubit.");
+    circ.connect(src, src_port.index(), new_node, 0)
+        .expect("Invalid circuit: could not insert gate.");
+    circ.connect(new_node, 0, node, port.index())
+        .expect("Invalid circuit: could not insert gate.");
+}
+
+/// Returns the value of a float constant connected to the given input port,
+/// if any.
+///
+/// The port must be connected to a [`OpType::LoadConstant`] node loading a
+/// [`ConstF64`] constant.
+pub(crate) fn constant_f64_input(
+    circ: &impl HugrView,
+    node: Node,
+    port: IncomingPort,
+) -> Option<f64> {
+    let (load, _) = circ.linked_outputs(node, port).exactly_one().ok()?;
+    if !matches!(circ.get_optype(load), OpType::LoadConstant(_)) {
+        return None;
+    }
+    let const_node = circ.input_neighbours(load).exactly_one().ok()?;
+    let OpType::Const(const_op) = circ.get_optype(const_node) else {
+        return None;
+    };
+    match const_op.value() {
+        Value::Extension { c: (val,) } => val.downcast_ref::<ConstF64>().map(|f| f.value()),
+        _ => None,
+    }
+}
+
 // Test only utils
 #[allow(dead_code)]
 #[cfg(test)]
 pub(crate) mod test {
+    use std::path::{Path, PathBuf};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use hugr::Hugr;
     #[allow(unused_imports)]
     use hugr::HugrView;
 
+    use super::build_simple_circuit;
+    use crate::circuit::Circuit;
+    use crate::Tk2Op;
+
+    /// Counter used to create unique temporary file names.
+    static TEMP_FILE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    /// A unique path in the temporary directory, whose file is removed when
+    /// the path is dropped.
+    pub(crate) struct TempPath(PathBuf);
+
+    impl TempPath {
+        /// Create a new path, ending with `name`.
+        pub(crate) fn new(name: &str) -> Self {
+            let id = TEMP_FILE_COUNT.fetch_add(1, Ordering::Relaxed);
+            Self(
+                std::env::temp_dir().join(format!("tket2_test_{}_{id}_{name}", std::process::id())),
+            )
+        }
+    }
+
+    impl AsRef<Path> for TempPath {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    /// The [`Tk2Op`] gates of a circuit, in command order.
+    pub(crate) fn gates(circ: &Hugr) -> Vec<Tk2Op> {
+        circ.commands()
+            .filter_map(|cmd| Tk2Op::try_from(cmd.optype()).ok())
+            .collect()
+    }
+
+    /// A two-qubit circuit made of `n_gates` CX gates.
+    pub(crate) fn n_cx(n_gates: usize) -> Hugr {
+        build_simple_circuit(2, |circ| {
+            for _ in 0..n_gates {
+                circ.append(Tk2Op::CX, [0, 1])?;
+            }
+            Ok(())
+        })
+        .unwrap()
+    }
+
     /// Open a browser page to render a dot string graph.
     ///
     /// This can be used directly on the output of `Hugr::dot_string`